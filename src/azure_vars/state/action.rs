@@ -1,22 +1,201 @@
+use std::time::SystemTime;
+
+use super::{
+    audit::AuditEvent,
+    checks::Check,
+    error::ClientError,
+    permissions::{PermissionChanges, Pipeline, PipelinePermissions},
+    state::VarGroup,
+};
+
 #[derive(Debug, Clone)]
 pub enum Action {
     RefreshVarGroups,
+    CancelRefresh,
+
+    // Sent by the refresh task; `generation` tells apart cancelled refreshes.
+    VarGroupsPageLoaded {
+        generation: u64,
+        groups: Vec<VarGroup>,
+        done: bool,
+    },
+    VarGroupsFetchFailed {
+        generation: u64,
+        error: ClientError,
+    },
+    VarGroupDetailsLoaded {
+        id: i32,
+        group: VarGroup,
+    },
+    VarGroupDetailsFailed {
+        id: i32,
+        error: ClientError,
+    },
+    VarGroupUpdated {
+        group: VarGroup,
+    },
+    /// Staged variables were changed by someone else; `group` is as it is now.
+    SaveConflicted {
+        group: VarGroup,
+    },
+    VarGroupUpdateFailed {
+        name: String,
+        error: ClientError,
+    },
+    /// The Library events of the organization up to `until`, for the audit
+    /// view of `group`.
+    AuditEventsLoaded {
+        group: String,
+        events: Vec<AuditEvent>,
+        until: SystemTime,
+    },
+    AuditEventsFailed {
+        group: String,
+        error: ClientError,
+    },
+    SharingLoaded {
+        group: String,
+        projects: Vec<String>,
+        shared: Vec<String>,
+    },
+    SharingLoadFailed {
+        group: String,
+        error: ClientError,
+    },
+    SharingUpdated {
+        group: String,
+        share: Vec<String>,
+        unshare: Vec<String>,
+    },
+    SharingUpdateFailed {
+        group: String,
+        error: ClientError,
+    },
+    PipelinesLoaded {
+        group: String,
+        pipelines: Vec<Pipeline>,
+        permissions: PipelinePermissions,
+    },
+    PipelinesLoadFailed {
+        group: String,
+        error: ClientError,
+    },
+    PipelinePermissionsUpdated {
+        group: String,
+        changes: PermissionChanges,
+    },
+    PipelinePermissionsUpdateFailed {
+        group: String,
+        error: ClientError,
+    },
+    ChecksLoaded {
+        group: String,
+        checks: Vec<Check>,
+    },
+    ChecksLoadFailed {
+        group: String,
+        error: ClientError,
+    },
+    CheckAdded {
+        group: String,
+        check: Check,
+    },
+    CheckRemoved {
+        group: String,
+        check: Check,
+    },
+    CheckUpdateFailed {
+        group: String,
+        error: ClientError,
+    },
+    GroupCopied {
+        group: String,
+        profile: String,
+        copy: VarGroup,
+        /// Secrets created without a value, which must be entered again.
+        missing_secrets: Vec<String>,
+    },
+    GroupCopyFailed {
+        group: String,
+        profile: String,
+        error: ClientError,
+    },
 
     // Search
     EnterSearchMode,
     ExitSearchMode,
-    SearchInsertChar { ch: char },
+    SearchInsertChar {
+        ch: char,
+    },
     SearchBackspace,
     SubmitSearch,
 
     // View Toggle
-    EnterViewVarGroup { index: usize },
+    EnterViewVarGroup {
+        index: usize,
+    },
     ExitViewVarGroup,
+    EnterHistory,
+    ExitHistory,
+    EnterAudit,
+    ExitAudit,
 
     // Actions
     ToggleTheme,
+    ToggleLogPane,
     CopySelectedVar,
     ExportCurrentGroup,
+    RestoreSelectedVersion,
+    RequestQuit,
+
+    // Editing
+    StartEditVar,
+    StartAddVar,
+    RemoveSelectedVar,
+    PromptInsertChar {
+        ch: char,
+    },
+    PromptBackspace,
+    SubmitPrompt,
+    CancelPrompt,
+    UndoEdit,
+    RedoEdit,
+    EnterReview,
+    ExitReview,
+    SaveChanges,
+
+    // Promotion
+    StartPromote,
+    PickPromoteTarget,
+    TogglePromoteKey,
+    StartOverrideValue,
+    ApplyPromotion,
+    /// Goes back to picking the target, or leaves once picking.
+    ExitPromote,
+
+    // Sharing with other projects
+    EnterShare,
+    ToggleShareProject,
+    ApplySharing,
+    ExitShare,
+
+    // Pipeline permissions
+    EnterPipelines,
+    TogglePipeline,
+    ApplyPipelinePermissions,
+    ExitPipelines,
+
+    // Approvals and checks
+    EnterChecks,
+    StartAddApproval,
+    /// Asks for confirmation first.
+    RemoveSelectedCheck,
+    ExitChecks,
+
+    // Copying to a profile
+    StartCopy,
+    PickCopyProfile,
+    ExitCopy,
 
     // Navigation
     MoveSelectionUp,
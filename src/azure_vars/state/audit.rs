@@ -0,0 +1,398 @@
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use azure_core::time::{OffsetDateTime, parse_rfc3339, to_rfc3339};
+use azure_devops_rust_api::audit::models::DecoratedAuditLogEntry;
+use log::{debug, warn};
+use serde::{Serialize, Serializer};
+use serde_json::Value;
+use tokio::sync::mpsc::Sender;
+
+use super::{
+    action::Action,
+    error::ClientError,
+    state::{AuditView, Overlay, State, StatusMessage, VarGroup},
+    state_store::VariableGroupsClient,
+};
+use crate::config::duration::{CALENDAR_UNITS, DurationError, parse_duration};
+
+/// The audit log area of variable groups, secure files and service
+/// connections.
+const LIBRARY_AREA: &str = "Library";
+
+/// Azure DevOps keeps audit events this long.
+pub const AUDIT_RETENTION: Duration = Duration::from_secs(90 * 86400);
+
+/// One change recorded in the audit log of an organization.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AuditEvent {
+    #[serde(serialize_with = "serialize_time")]
+    pub at: Option<SystemTime>,
+    pub actor: String,
+    /// Such as `Library.VariableGroupModified`.
+    pub action: String,
+    /// Such as `create`, `modify` or `remove`.
+    pub category: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+    pub details: String,
+    /// What the action was applied to, as the audit service reports it.
+    #[serde(skip_serializing_if = "Value::is_null")]
+    pub data: Value,
+    #[serde(skip)]
+    area: Option<String>,
+}
+
+impl From<DecoratedAuditLogEntry> for AuditEvent {
+    fn from(entry: DecoratedAuditLogEntry) -> Self {
+        Self {
+            at: entry.timestamp.map(SystemTime::from),
+            actor: entry
+                .actor_display_name
+                .or(entry.actor_upn)
+                .unwrap_or_else(|| "unknown".to_string()),
+            action: entry.action_id.unwrap_or_default(),
+            category: entry
+                .category_display_name
+                .or_else(|| {
+                    entry
+                        .category
+                        .and_then(|category| serde_json::to_value(category).ok())
+                        .and_then(|category| category.as_str().map(str::to_string))
+                })
+                .unwrap_or_default(),
+            project: entry.project_name,
+            details: entry.details.unwrap_or_default(),
+            data: entry.data.unwrap_or_default(),
+            area: entry.area,
+        }
+    }
+}
+
+impl AuditEvent {
+    pub fn is_library(&self) -> bool {
+        self.area.as_deref() == Some(LIBRARY_AREA)
+            || self.action.starts_with(&format!("{LIBRARY_AREA}."))
+    }
+
+    /// Events name groups in their data, or only in their details.
+    pub fn concerns(&self, group: &VarGroup) -> bool {
+        mentions_group(&self.data, group) || mentions(&self.details, &group.name)
+    }
+
+    /// The action without its area, e.g. `VariableGroupModified`.
+    pub fn short_action(&self) -> &str {
+        self.action
+            .split_once('.')
+            .map_or(self.action.as_str(), |(_, action)| action)
+    }
+}
+
+fn mentions_group(data: &Value, group: &VarGroup) -> bool {
+    match data {
+        Value::Object(map) => map.iter().any(|(key, value)| {
+            let is_id = key.to_ascii_lowercase().ends_with("id");
+            let matches_id = group.id.is_some_and(|id| match value {
+                Value::Number(number) => number.as_i64() == Some(i64::from(id)),
+                Value::String(text) => text == &id.to_string(),
+                _ => false,
+            });
+            (is_id && matches_id) || mentions_group(value, group)
+        }),
+        Value::Array(items) => items.iter().any(|item| mentions_group(item, group)),
+        Value::String(text) => text == &group.name,
+        _ => false,
+    }
+}
+
+/// Whether `text` names `name` as a whole, so that `app` is not found in
+/// `app-prod`.
+fn mentions(text: &str, name: &str) -> bool {
+    let is_name_char = |c: char| c.is_alphanumeric() || "-_.".contains(c);
+    text.match_indices(name).any(|(idx, _)| {
+        let before = text[..idx].chars().next_back();
+        let after = text[idx + name.len()..].chars().next();
+        // A trailing full stop ends the sentence rather than the name.
+        let after =
+            after.filter(|c| *c != '.' || text[idx + name.len() + 1..].starts_with(is_name_char));
+        !before.is_some_and(is_name_char) && !after.is_some_and(is_name_char)
+    })
+}
+
+/// Formats a time as RFC 3339, in UTC.
+pub fn format_time(time: SystemTime) -> String {
+    to_rfc3339(&OffsetDateTime::from(time))
+}
+
+fn serialize_time<S: Serializer>(
+    time: &Option<SystemTime>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match time {
+        Some(time) => serializer.serialize_str(&format_time(*time)),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// Parses an RFC 3339 time, a date such as `2025-03-31` (midnight UTC), or an
+/// age such as `90m`, `12h`, `7d` or `2w` before `now`.
+pub fn parse_time(value: &str, now: SystemTime) -> Result<SystemTime, String> {
+    let value = value.trim();
+    match parse_duration(value, CALENDAR_UNITS) {
+        Ok(age) => {
+            return now
+                .checked_sub(age)
+                .ok_or_else(|| format!("`{value}` is too long ago"));
+        }
+        Err(DurationError::TooLong(_)) => return Err(format!("`{value}` is too long ago")),
+        // Not an age, maybe a date.
+        Err(_) => {}
+    }
+    let timestamp = if value.len() == 10 {
+        format!("{value}T00:00:00Z")
+    } else {
+        value.to_string()
+    };
+    parse_rfc3339(&timestamp)
+        .map(SystemTime::from)
+        .map_err(|_| "expected a time like 2025-03-31T12:00:00Z, a date, or an age like 7d".into())
+}
+
+/// The Library audit events loaded so far, oldest first, and the time they
+/// were loaded up to. Opening the audit view again only asks for the events
+/// since.
+#[derive(Debug, Default)]
+pub struct AuditLog {
+    loaded: Option<(SystemTime, Vec<AuditEvent>)>,
+}
+
+impl AuditLog {
+    /// The events concerning `group`, newest first.
+    fn events_of(&self, group: &VarGroup) -> Vec<AuditEvent> {
+        let Some((_, events)) = &self.loaded else {
+            return Vec::new();
+        };
+        events
+            .iter()
+            .rev()
+            .filter(|event| event.concerns(group))
+            .cloned()
+            .collect()
+    }
+
+    /// The time to load events from: where the last load ended, or as far
+    /// back as they are kept.
+    fn loaded_until(&self, now: SystemTime) -> SystemTime {
+        match &self.loaded {
+            Some((until, _)) => *until,
+            None => now
+                .checked_sub(AUDIT_RETENTION)
+                .unwrap_or(SystemTime::UNIX_EPOCH),
+        }
+    }
+
+    /// Adds events loaded up to `until` to those loaded before. Events that
+    /// are already known, from a load that overlapped, are left out.
+    fn add(&mut self, events: Vec<AuditEvent>, until: SystemTime) {
+        let Some((loaded_until, known)) = &mut self.loaded else {
+            self.loaded = Some((until, events));
+            return;
+        };
+        let since = *loaded_until;
+        let new = events
+            .into_iter()
+            .filter(|event| event.at.is_some_and(|at| at >= since))
+            .filter(|event| !known.contains(event))
+            .collect::<Vec<_>>();
+        known.extend(new);
+        *loaded_until = since.max(until);
+    }
+}
+
+/// Opens the audit events of the selected group, which are loaded from the
+/// audit log of the organization.
+pub(super) fn enter<C: VariableGroupsClient + 'static>(
+    state: &mut State,
+    log: &AuditLog,
+    client: &Arc<C>,
+    fetch_tx: &Sender<Action>,
+) {
+    if state.is_offline() {
+        state.ui.report_offline("load audit events");
+        return;
+    }
+    let Some(viewed) = state.current_group() else {
+        return;
+    };
+    let group = viewed.name.clone();
+    // Events loaded before are shown while the newer ones load.
+    let events = log.events_of(viewed);
+    state.ui.search.deactivate();
+    state.ui.open(Overlay::Audit(AuditView {
+        events,
+        group: group.clone(),
+        loading: true,
+        ..AuditView::default()
+    }));
+    let end = SystemTime::now();
+    let start = log.loaded_until(end);
+    debug!("Loading audit events of {group}");
+    tokio::spawn(fetch_events(
+        Arc::clone(client),
+        state.organization().to_string(),
+        state.project().to_string(),
+        group,
+        (start, end),
+        fetch_tx.clone(),
+    ));
+}
+
+/// Keeps the events loaded for `group`, and shows them if its audit view is
+/// still open. Returns whether it is.
+pub(super) fn loaded(
+    state: &mut State,
+    log: &mut AuditLog,
+    group: &str,
+    events: Vec<AuditEvent>,
+    until: SystemTime,
+) -> bool {
+    log.add(events, until);
+    let events = state
+        .groups()
+        .iter()
+        .find(|g| g.name == group)
+        .map(|viewed| log.events_of(viewed))
+        .unwrap_or_default();
+    let Some(audit) = state
+        .ui
+        .opened_mut::<AuditView>()
+        .filter(|audit| audit.group == group)
+    else {
+        return false;
+    };
+    audit.loading = false;
+    audit.events = events;
+    if audit.events.is_empty() {
+        let message = format!("No audit events for {group} in the last 90 days");
+        state.ui.set_status(StatusMessage::info(message));
+    }
+    true
+}
+
+pub(super) fn load_failed(state: &mut State, group: &str, error: ClientError) {
+    warn!("Failed to load audit events of {group}: {error}");
+    if let Some(audit) = state.ui.opened_mut::<AuditView>() {
+        audit.loading = false;
+    }
+    state.ui.set_status(StatusMessage::error(format!(
+        "Failed to load audit events of {group}: {error}"
+    )));
+}
+
+async fn fetch_events<C: VariableGroupsClient>(
+    client: Arc<C>,
+    organization: String,
+    project: String,
+    group: String,
+    (start, end): (SystemTime, SystemTime),
+    fetch_tx: Sender<Action>,
+) {
+    let action = match client
+        .get_library_audit_events(&organization, &project, start, end)
+        .await
+    {
+        Ok(events) => Action::AuditEventsLoaded {
+            group,
+            events,
+            until: end,
+        },
+        Err(error) => Action::AuditEventsFailed { group, error },
+    };
+    let _ = fetch_tx.send(action).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn event(details: &str, data: Value) -> AuditEvent {
+        AuditEvent {
+            at: None,
+            actor: "Alice".into(),
+            action: "Library.VariableGroupModified".into(),
+            category: "modify".into(),
+            project: Some("Web".into()),
+            details: details.into(),
+            data,
+            area: Some(LIBRARY_AREA.into()),
+        }
+    }
+
+    #[test]
+    fn events_are_matched_to_groups_by_id_or_whole_name() {
+        let group = VarGroup {
+            id: Some(7),
+            name: "app".into(),
+            ..VarGroup::default()
+        };
+        assert!(event("", json!({ "VariableGroupId": 7 })).concerns(&group));
+        assert!(event("", json!({ "Group": { "Name": "app" } })).concerns(&group));
+        assert!(event("Modified variable group app.", Value::Null).concerns(&group));
+        assert!(!event("Modified variable group app-prod.", Value::Null).concerns(&group));
+        assert!(!event("", json!({ "VariableGroupId": 8, "Count": 7 })).concerns(&group));
+        assert_eq!(
+            event("", Value::Null).short_action(),
+            "VariableGroupModified"
+        );
+    }
+
+    #[test]
+    fn times_are_parsed_as_ages_dates_or_timestamps() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(30 * 86400);
+        assert_eq!(
+            parse_time("7d", now),
+            Ok(SystemTime::UNIX_EPOCH + Duration::from_secs(23 * 86400))
+        );
+        assert_eq!(
+            parse_time("1970-01-02", now),
+            Ok(SystemTime::UNIX_EPOCH + Duration::from_secs(86400))
+        );
+        assert_eq!(
+            parse_time("1970-01-01T01:00:00Z", now),
+            Ok(SystemTime::UNIX_EPOCH + Duration::from_secs(3600))
+        );
+        assert!(parse_time("yesterday", now).is_err());
+        assert!(parse_time("99999999999999999d", now).is_err());
+        assert_eq!(format_time(SystemTime::UNIX_EPOCH), "1970-01-01T00:00:00Z");
+    }
+
+    #[test]
+    fn events_loaded_again_are_added_once() {
+        let at = |secs| Some(SystemTime::UNIX_EPOCH + Duration::from_secs(secs));
+        let group = VarGroup {
+            id: Some(7),
+            name: "app".into(),
+            ..VarGroup::default()
+        };
+        let first = AuditEvent {
+            at: at(10),
+            ..event("", json!({ "VariableGroupId": 7 }))
+        };
+        let second = AuditEvent {
+            at: at(30),
+            ..event("", json!({ "VariableGroupId": 7 }))
+        };
+        let mut log = AuditLog::default();
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(20);
+        log.add(vec![first.clone()], now);
+        assert_eq!(log.loaded_until(now), now);
+
+        log.add(vec![first.clone(), second.clone()], at(40).unwrap());
+        assert_eq!(log.events_of(&group), vec![second, first]);
+        assert_eq!(log.loaded_until(now), at(40).unwrap());
+    }
+}
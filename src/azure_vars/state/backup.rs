@@ -0,0 +1,364 @@
+use std::{fs, path::Path, time::SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    audit::format_time,
+    cache::{GroupCache, SECRET_PLACEHOLDER},
+    diff::{VarChange, var_changes},
+    error::{StoreError, StoreFile},
+    state::VarGroup,
+    state_store::fingerprint,
+};
+
+/// Tells backup archives apart from other JSON files.
+const BACKUP_FORMAT: &str = "azure-devtools-library-backup";
+
+/// The layout of the archives written by this build. Older layouts are
+/// still read.
+pub const BACKUP_VERSION: u32 = 1;
+
+/// Every variable group of a project, with secrets recorded by name only.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Backup {
+    format: String,
+    pub version: u32,
+    /// RFC 3339, in UTC.
+    pub created_at: String,
+    pub organization: String,
+    pub project: String,
+    /// The version of the tool that wrote the archive.
+    pub created_by: String,
+    /// FNV-1a of `groups` as JSON, to tell a damaged or edited archive.
+    checksum: String,
+    pub groups: Vec<VarGroup>,
+}
+
+impl Backup {
+    /// Backs up `groups`, whose variables must be loaded.
+    pub fn new(organization: &str, project: &str, groups: &[VarGroup], at: SystemTime) -> Self {
+        // The cache already knows how to leave out secret values.
+        let groups = GroupCache::new(groups, at)
+            .groups
+            .into_iter()
+            .map(|group| VarGroup {
+                id: group.id,
+                name: group.name,
+                variables: group.variables,
+                modified_by: group.modified_by,
+                description: group.description,
+                group_type: group.group_type,
+                ..VarGroup::default()
+            })
+            .collect::<Vec<_>>();
+        Self {
+            format: BACKUP_FORMAT.to_string(),
+            version: BACKUP_VERSION,
+            created_at: format_time(at),
+            organization: organization.to_string(),
+            project: project.to_string(),
+            created_by: format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")),
+            checksum: checksum(&groups),
+            groups,
+        }
+    }
+
+    /// Reads an archive, refusing one whose checksum does not match or that
+    /// a newer version wrote.
+    pub fn read(path: &Path) -> Result<Self, StoreError> {
+        let invalid = |message: String| StoreError::Invalid {
+            file: StoreFile::Backup,
+            path: path.to_path_buf(),
+            message,
+        };
+        let text = fs::read_to_string(path).map_err(|source| StoreError::Io {
+            file: StoreFile::Backup,
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let backup = serde_json::from_str::<Self>(&text)
+            .ok()
+            .filter(|backup| backup.format == BACKUP_FORMAT)
+            .ok_or_else(|| invalid("expected a Library backup".into()))?;
+        if backup.version > BACKUP_VERSION {
+            return Err(invalid(format!(
+                "the backup has version {}, this build reads up to {BACKUP_VERSION}",
+                backup.version
+            )));
+        }
+        if backup.checksum != checksum(&backup.groups) {
+            return Err(invalid(
+                "the checksum does not match, the backup is damaged or was edited".into(),
+            ));
+        }
+        Ok(backup)
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), StoreError> {
+        let io_error = |source| StoreError::Io {
+            file: StoreFile::Backup,
+            path: path.to_path_buf(),
+            source,
+        };
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            fs::create_dir_all(parent).map_err(io_error)?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(|error| StoreError::Invalid {
+            file: StoreFile::Backup,
+            path: path.to_path_buf(),
+            message: error.to_string(),
+        })?;
+        // Write then rename so that a crash never leaves a truncated backup.
+        let draft = path.with_extension("json.tmp");
+        fs::write(&draft, json).map_err(io_error)?;
+        fs::rename(&draft, path).map_err(io_error)
+    }
+}
+
+fn checksum(groups: &[VarGroup]) -> String {
+    let groups = serde_json::to_value(groups).unwrap_or_default();
+    format!("fnv1a64:{:016x}", fingerprint(&groups))
+}
+
+/// What restoring one group of a backup does.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RestoreStep {
+    /// The group no longer exists and is created again.
+    Create(VarGroup),
+    /// The group exists with other variables or another description, which
+    /// are replaced by those of the backup. `group` carries the id of the
+    /// existing group.
+    Update {
+        group: VarGroup,
+        changes: Vec<VarChange>,
+        /// The description before the restore, if it changes.
+        old_description: Option<Option<String>>,
+    },
+}
+
+impl RestoreStep {
+    pub fn group(&self) -> &VarGroup {
+        match self {
+            RestoreStep::Create(group) | RestoreStep::Update { group, .. } => group,
+        }
+    }
+
+    /// Secret variables that are restored without a value, which must be
+    /// entered again. Secrets left in place keep their values.
+    pub fn secrets_to_reenter(&self) -> Vec<&str> {
+        let is_new_secret = |var: &&_| match self {
+            RestoreStep::Create(_) => true,
+            RestoreStep::Update { changes, .. } => changes.iter().any(|change| match change {
+                VarChange::Added(added) => added == *var,
+                VarChange::Changed { old, new } => new == *var && !old.is_secret,
+                VarChange::Removed(_) => false,
+            }),
+        };
+        self.group()
+            .variables
+            .iter()
+            .filter(|var| var.is_secret && var.value == SECRET_PLACEHOLDER)
+            .filter(is_new_secret)
+            .map(|var| var.name.as_str())
+            .collect()
+    }
+}
+
+/// Groups of a backup that are linked to Azure Key Vault and differ from
+/// the project. Their variables point at the vault, whose connection the
+/// backup does not hold.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error(
+    "Cannot restore {}: linked to Azure Key Vault. Link the vault again in Azure DevOps, or restore the other groups with --group",
+    .names.join(", ")
+)]
+pub struct LinkedGroups {
+    pub names: Vec<String>,
+}
+
+/// The steps that bring `current` back to the groups of `backup`, matched
+/// by name. `current` must hold the variables of every group it has in
+/// common with the backup. Groups that are not in the backup are left
+/// alone, and so are secret values, which the backup does not know.
+pub fn plan_restore(
+    backup: &[VarGroup],
+    current: &[VarGroup],
+) -> Result<Vec<RestoreStep>, LinkedGroups> {
+    let steps = backup
+        .iter()
+        .filter_map(|saved| {
+            let restored = VarGroup {
+                name: saved.name.clone(),
+                variables: saved.variables.clone(),
+                description: saved.description.clone(),
+                group_type: saved.group_type.clone(),
+                ..VarGroup::default()
+            };
+            let Some(existing) = current.iter().find(|group| group.name == saved.name) else {
+                return Some(RestoreStep::Create(restored));
+            };
+            let changes = var_changes(&existing.variables, &saved.variables);
+            // Backups written before descriptions were kept have none, which
+            // leaves the description alone.
+            let old_description = (saved.description.is_some()
+                && saved.description != existing.description)
+                .then(|| existing.description.clone());
+            if changes.is_empty() && old_description.is_none() {
+                return None;
+            }
+            Some(RestoreStep::Update {
+                group: VarGroup {
+                    id: existing.id,
+                    ..restored
+                },
+                changes,
+                old_description,
+            })
+        })
+        .collect::<Vec<_>>();
+    let names = steps
+        .iter()
+        .map(RestoreStep::group)
+        .filter(|group| group.is_linked())
+        .map(|group| group.name.clone())
+        .collect::<Vec<_>>();
+    if !names.is_empty() {
+        return Err(LinkedGroups { names });
+    }
+    Ok(steps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::azure_vars::state::state::VarEntry;
+
+    fn var(name: &str, value: &str, is_secret: bool) -> VarEntry {
+        VarEntry {
+            name: name.into(),
+            value: value.into(),
+            is_secret,
+        }
+    }
+
+    fn group(name: &str, variables: Vec<VarEntry>) -> VarGroup {
+        VarGroup {
+            name: name.into(),
+            variables,
+            ..VarGroup::default()
+        }
+    }
+
+    #[test]
+    fn backups_keep_secret_names_only_and_detect_edits() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("backup.json");
+        let groups = [group(
+            "app",
+            vec![
+                var("URL", "https://example.com", false),
+                var("TOKEN", "hunter2", true),
+            ],
+        )];
+
+        Backup::new("org", "web", &groups, SystemTime::UNIX_EPOCH)
+            .write(&path)
+            .unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        assert!(!text.contains("hunter2"));
+
+        let backup = Backup::read(&path).unwrap();
+        assert_eq!(backup.created_at, "1970-01-01T00:00:00Z");
+        assert_eq!(backup.groups[0].variables[1].name, "TOKEN");
+        assert_eq!(backup.groups[0].variables[1].value, SECRET_PLACEHOLDER);
+
+        fs::write(&path, text.replace("example.com", "example.org")).unwrap();
+        let error = Backup::read(&path).unwrap_err();
+        assert!(
+            error.to_string().starts_with("Invalid backup "),
+            "unexpected error: {error}"
+        );
+    }
+
+    #[test]
+    fn restoring_recreates_missing_groups_and_reconciles_the_others() {
+        let backup = Backup::new(
+            "org",
+            "web",
+            &[
+                group("deleted", vec![var("TOKEN", "", true)]),
+                group(
+                    "edited",
+                    vec![var("URL", "one", false), var("KEY", "", true)],
+                ),
+                group("same", vec![var("URL", "one", false)]),
+            ],
+            SystemTime::UNIX_EPOCH,
+        );
+        let current = [
+            VarGroup {
+                id: Some(7),
+                ..group(
+                    "edited",
+                    vec![var("URL", "two", false), var("NEW", "x", false)],
+                )
+            },
+            group("same", vec![var("URL", "one", false)]),
+            group("other", vec![]),
+        ];
+
+        let steps = plan_restore(&backup.groups, &current).unwrap();
+        assert_eq!(steps.len(), 2);
+        assert!(matches!(&steps[0], RestoreStep::Create(group) if group.name == "deleted"));
+        assert_eq!(steps[0].secrets_to_reenter(), ["TOKEN"]);
+        let RestoreStep::Update { group, changes, .. } = &steps[1] else {
+            panic!("expected an update, got {:?}", steps[1]);
+        };
+        assert_eq!(group.id, Some(7));
+        assert_eq!(changes.len(), 3);
+        assert_eq!(steps[1].secrets_to_reenter(), ["KEY"]);
+    }
+
+    #[test]
+    fn restoring_brings_back_descriptions_and_refuses_key_vault_groups() {
+        let described = |description: &str| VarGroup {
+            description: Some(description.into()),
+            group_type: Some("Vsts".into()),
+            ..group("app", vec![var("URL", "one", false)])
+        };
+        let vault = VarGroup {
+            group_type: Some("AzureKeyVault".into()),
+            ..group("vault", vec![var("db-password", "", true)])
+        };
+        let backup = Backup::new(
+            "org",
+            "web",
+            &[described("Front end"), vault],
+            SystemTime::UNIX_EPOCH,
+        );
+        assert_eq!(backup.groups[0].description.as_deref(), Some("Front end"));
+        assert!(backup.groups[1].is_linked());
+
+        let current = [described("Old"), backup.groups[1].clone()];
+        let steps = plan_restore(&backup.groups, &current).unwrap();
+        let [
+            RestoreStep::Update {
+                group,
+                changes,
+                old_description,
+            },
+        ] = steps.as_slice()
+        else {
+            panic!("expected one update, got {steps:?}");
+        };
+        assert_eq!(group.description.as_deref(), Some("Front end"));
+        assert!(changes.is_empty());
+        assert_eq!(old_description, &Some(Some("Old".to_string())));
+
+        let error = plan_restore(&backup.groups, &current[..1]).unwrap_err();
+        assert_eq!(error.names, ["vault"]);
+    }
+}
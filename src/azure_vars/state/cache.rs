@@ -0,0 +1,338 @@
+use std::{
+    fs,
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    error::{StoreError, StoreFile},
+    state::{VarEntry, VarGroup},
+};
+
+/// Stored in place of secret values, which never reach the disk.
+pub const SECRET_PLACEHOLDER: &str = "<secret value hidden>";
+
+/// The last groups fetched for one organization and project.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GroupCache {
+    /// Seconds since the Unix epoch.
+    fetched_at: u64,
+    pub groups: Vec<VarGroup>,
+}
+
+impl GroupCache {
+    pub fn new(groups: &[VarGroup], fetched_at: SystemTime) -> Self {
+        let groups = groups
+            .iter()
+            .map(|group| VarGroup {
+                variables: group
+                    .variables
+                    .iter()
+                    .map(|var| VarEntry {
+                        value: if var.is_secret {
+                            SECRET_PLACEHOLDER.to_string()
+                        } else {
+                            var.value.clone()
+                        },
+                        ..var.clone()
+                    })
+                    .collect(),
+                ..group.clone()
+            })
+            .collect();
+        Self {
+            fetched_at: fetched_at
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            groups,
+        }
+    }
+
+    pub fn fetched_at(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(self.fetched_at)
+    }
+
+    /// Keeps the variables of a group fetched on its own, so that it can be
+    /// shown offline. The list keeps its age.
+    pub fn store_details(&mut self, group: &VarGroup) {
+        let Some(group) = Self::new(std::slice::from_ref(group), UNIX_EPOCH)
+            .groups
+            .pop()
+        else {
+            return;
+        };
+        let cached = self.groups.iter_mut().find(|cached| match group.id {
+            Some(id) => cached.id == Some(id),
+            None => cached.name == group.name,
+        });
+        match cached {
+            Some(cached) => *cached = group,
+            None => self.groups.push(group),
+        }
+    }
+
+    /// Reads the cache. A missing, unreadable or outdated cache is ignored.
+    pub fn read(path: &Path) -> Option<Self> {
+        let text = fs::read_to_string(path).ok()?;
+        serde_json::from_str(&text).ok()
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), StoreError> {
+        let io_error = |source| StoreError::Io {
+            file: StoreFile::Cache,
+            path: path.to_path_buf(),
+            source,
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(io_error)?;
+        }
+        let json = serde_json::to_string(self).map_err(|error| StoreError::Invalid {
+            file: StoreFile::Cache,
+            path: path.to_path_buf(),
+            message: error.to_string(),
+        })?;
+        // Write then rename so that a crash never leaves a truncated cache.
+        let draft = path.with_extension("json.tmp");
+        fs::write(&draft, json).map_err(io_error)?;
+        fs::rename(&draft, path).map_err(io_error)
+    }
+}
+
+/// The files `--snapshot` accepts: a cache file, or groups exported as JSON.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Snapshot {
+    Cache(GroupCache),
+    Groups(Vec<VarGroup>),
+    Group(VarGroup),
+}
+
+impl GroupCache {
+    /// Reads a cache file or exported groups. Exports carry no timestamp, so
+    /// their age is taken from the file.
+    pub fn read_snapshot(path: &Path) -> Result<Self, StoreError> {
+        let io_error = |source| StoreError::Io {
+            file: StoreFile::Snapshot,
+            path: path.to_path_buf(),
+            source,
+        };
+        let text = fs::read_to_string(path).map_err(io_error)?;
+        let snapshot = serde_json::from_str(&text).map_err(|error| StoreError::Invalid {
+            file: StoreFile::Snapshot,
+            path: path.to_path_buf(),
+            message: format!(
+                "expected a variable group cache or exported variable groups (line {}, column {})",
+                error.line(),
+                error.column()
+            ),
+        })?;
+        let groups = match snapshot {
+            Snapshot::Cache(cache) => return Ok(cache),
+            Snapshot::Groups(groups) => groups,
+            Snapshot::Group(group) => vec![group],
+        };
+        let modified = fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .map_err(io_error)?;
+        Ok(Self::new(&groups, modified))
+    }
+}
+
+/// Matches a group name the way the server's `groupName` filter does:
+/// ignoring case, with `*` standing for any run of characters.
+pub fn name_matches(pattern: &str, name: &str) -> bool {
+    let name = name.to_lowercase();
+    let pattern = pattern.to_lowercase();
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+    let mut parts = parts.collect::<Vec<_>>();
+    let Some(last) = parts.pop() else {
+        return rest.is_empty();
+    };
+    for part in parts {
+        match rest.find(part) {
+            Some(idx) => rest = &rest[idx + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+/// The groups a name given on the command line refers to, matched like
+/// [`name_matches`]. A group with exactly that name wins over the others, so
+/// `app` still finds `app` next to `App`.
+pub fn find_groups<'a>(groups: &'a [VarGroup], name: &str) -> Vec<&'a VarGroup> {
+    if let Some(group) = groups.iter().find(|group| group.name == name) {
+        return vec![group];
+    }
+    groups
+        .iter()
+        .filter(|group| name_matches(name, &group.name))
+        .collect()
+}
+
+/// A short, human readable age such as `42s`, `5m`, `3h` or `2d`.
+pub fn format_age(age: Duration) -> String {
+    let seconds = age.as_secs();
+    match seconds {
+        0..60 => format!("{seconds}s"),
+        60..3600 => format!("{}m", seconds / 60),
+        3600..86400 => format!("{}h", seconds / 3600),
+        _ => format!("{}d", seconds / 86400),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn group() -> VarGroup {
+        VarGroup {
+            name: "Group".into(),
+            variables: vec![
+                VarEntry {
+                    name: "URL".into(),
+                    value: "https://example.com".into(),
+                    is_secret: false,
+                },
+                VarEntry {
+                    name: "TOKEN".into(),
+                    value: "hunter2".into(),
+                    is_secret: true,
+                },
+            ],
+            ..VarGroup::default()
+        }
+    }
+
+    #[test]
+    fn cache_round_trips_without_secret_values() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("groups").join("org--project.json");
+        let fetched_at = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+
+        GroupCache::new(&[group()], fetched_at)
+            .write(&path)
+            .unwrap();
+        assert!(!fs::read_to_string(&path).unwrap().contains("hunter2"));
+
+        let cache = GroupCache::read(&path).unwrap();
+        assert_eq!(cache.fetched_at(), fetched_at);
+        assert_eq!(cache.groups[0].variables[0].value, "https://example.com");
+        assert_eq!(cache.groups[0].variables[1].value, SECRET_PLACEHOLDER);
+    }
+
+    #[test]
+    fn groups_shown_after_a_lazy_listing_are_cached() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("org--project.json");
+        let fetched_at = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let summary = VarGroup {
+            id: Some(7),
+            summary_only: true,
+            variable_count: 2,
+            variables: Vec::new(),
+            ..group()
+        };
+        GroupCache::new(&[summary], fetched_at)
+            .write(&path)
+            .unwrap();
+
+        let mut cache = GroupCache::read(&path).unwrap();
+        cache.store_details(&VarGroup {
+            id: Some(7),
+            ..group()
+        });
+        cache.write(&path).unwrap();
+
+        let cache = GroupCache::read(&path).unwrap();
+        assert_eq!(cache.fetched_at(), fetched_at);
+        let found = find_groups(&cache.groups, "group");
+        assert_eq!(found.len(), 1);
+        assert!(!found[0].summary_only);
+        assert_eq!(found[0].variables[1].value, SECRET_PLACEHOLDER);
+    }
+
+    #[test]
+    fn unreadable_caches_are_ignored() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache.json");
+        assert_eq!(GroupCache::read(&path), None);
+
+        fs::write(&path, "{\"groups\": 3}").unwrap();
+        assert_eq!(GroupCache::read(&path), None);
+    }
+
+    #[test]
+    fn snapshots_accept_caches_and_exported_groups() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache_path = dir.path().join("cache.json");
+        let cache = GroupCache::new(&[group()], UNIX_EPOCH);
+        cache.write(&cache_path).unwrap();
+        assert_eq!(GroupCache::read_snapshot(&cache_path).unwrap(), cache);
+
+        let export_path = dir.path().join("Group_variables.json");
+        fs::write(
+            &export_path,
+            serde_json::to_string_pretty(&group()).unwrap(),
+        )
+        .unwrap();
+        let snapshot = GroupCache::read_snapshot(&export_path).unwrap();
+        assert_eq!(snapshot.groups.len(), 1);
+        assert_eq!(snapshot.groups[0].variables[1].value, SECRET_PLACEHOLDER);
+
+        fs::write(&export_path, "[{\"name\": 3}]").unwrap();
+        assert!(matches!(
+            GroupCache::read_snapshot(&export_path),
+            Err(StoreError::Invalid {
+                file: StoreFile::Snapshot,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn names_match_like_the_server_filter() {
+        assert!(name_matches("app-prod", "App-Prod"));
+        assert!(!name_matches("app", "app-prod"));
+        assert!(name_matches("app-*", "app-prod"));
+        assert!(name_matches("*-prod", "app-prod"));
+        assert!(name_matches("a*p*d", "app-prod"));
+        assert!(!name_matches("*-prod*-prod", "app-prod"));
+        assert!(name_matches("*", ""));
+    }
+
+    #[test]
+    fn groups_are_found_ignoring_case_unless_the_name_is_exact() {
+        let named = |name: &str| VarGroup {
+            name: name.into(),
+            ..VarGroup::default()
+        };
+        let groups = [named("app"), named("App"), named("API-prod")];
+        let names = |name| {
+            find_groups(&groups, name)
+                .into_iter()
+                .map(|group| group.name.as_str())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names("App"), ["App"]);
+        assert_eq!(names("api-PROD"), ["API-prod"]);
+        assert_eq!(names("APP"), ["app", "App"]);
+        assert_eq!(names("a*"), ["app", "App", "API-prod"]);
+        assert!(names("web").is_empty());
+    }
+
+    #[test]
+    fn formats_ages() {
+        assert_eq!(format_age(Duration::from_secs(42)), "42s");
+        assert_eq!(format_age(Duration::from_secs(5 * 60 + 3)), "5m");
+        assert_eq!(format_age(Duration::from_secs(3 * 3600)), "3h");
+        assert_eq!(format_age(Duration::from_secs(2 * 86400)), "2d");
+    }
+}
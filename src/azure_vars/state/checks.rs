@@ -0,0 +1,561 @@
+use std::sync::Arc;
+
+use azure_devops_rust_api::approvals_and_checks::models::{
+    CheckType, GenericCheckConfiguration, Resource,
+};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::mpsc::Sender;
+
+use super::{
+    action::Action,
+    error::ClientError,
+    permissions::VARIABLE_GROUP_RESOURCE,
+    state::{ChecksView, Overlay, Prompt, PromptKind, State, StatusMessage, VarGroup},
+    state_store::VariableGroupsClient,
+};
+
+/// The check type of approvals.
+pub const APPROVAL_CHECK: &str = "8c6f20a7-a545-4486-9777-f762fafe0d4d";
+/// The check type of checks running a task, such as branch control and
+/// business hours.
+const TASK_CHECK: &str = "fe1de3ee-a436-41b4-bb20-f6eb4cb879a7";
+const BRANCH_CONTROL_TASK: &str = "evaluatebranchprotection";
+const BUSINESS_HOURS_TASK: &str = "evaluatebusinesshours";
+/// How long a new approval waits, as in the web UI.
+const APPROVAL_TIMEOUT_MINUTES: i32 = 30 * 24 * 60;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CheckKind {
+    /// `min_approvers` of the approvers must approve; all of them when none.
+    Approval {
+        approvers: Vec<String>,
+        min_approvers: Option<i32>,
+    },
+    /// Only runs of the branches listed, such as `refs/heads/main`, may use
+    /// the group.
+    BranchControl {
+        branches: String,
+        protected_only: bool,
+    },
+    /// Runs may only use the group on `days` between `start` and `end`.
+    BusinessHours {
+        days: String,
+        start: String,
+        end: String,
+        time_zone: String,
+    },
+    /// Any other check, by its name.
+    Other(String),
+}
+
+/// A check runs must pass before they may use a group.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Check {
+    pub id: i32,
+    pub kind: CheckKind,
+    pub disabled: bool,
+}
+
+impl Check {
+    pub fn name(&self) -> &str {
+        match &self.kind {
+            CheckKind::Approval { .. } => "Approval",
+            CheckKind::BranchControl { .. } => "Branch control",
+            CheckKind::BusinessHours { .. } => "Business hours",
+            CheckKind::Other(name) => name,
+        }
+    }
+
+    pub fn is_approval(&self) -> bool {
+        matches!(self.kind, CheckKind::Approval { .. })
+    }
+
+    /// What the check requires, in a line.
+    pub fn summary(&self) -> String {
+        let summary = match &self.kind {
+            CheckKind::Approval {
+                approvers,
+                min_approvers,
+            } => {
+                let required = match min_approvers {
+                    Some(min) if *min > 0 && (*min as usize) < approvers.len() => {
+                        format!("{min} of {}", approvers.len())
+                    }
+                    _ => "all".to_string(),
+                };
+                format!("{} ({required})", approvers.join(", "))
+            }
+            CheckKind::BranchControl {
+                branches,
+                protected_only,
+            } => {
+                if *protected_only {
+                    format!("{branches}, protected branches only")
+                } else {
+                    branches.clone()
+                }
+            }
+            CheckKind::BusinessHours {
+                days,
+                start,
+                end,
+                time_zone,
+            } => format!("{days} {start}-{end} {time_zone}"),
+            CheckKind::Other(_) => String::new(),
+        };
+        if self.disabled {
+            format!("{summary} (disabled)").trim_start().to_string()
+        } else {
+            summary
+        }
+    }
+}
+
+impl From<GenericCheckConfiguration> for Check {
+    fn from(configuration: GenericCheckConfiguration) -> Self {
+        let check = configuration.check_configuration;
+        let reference = check.check_configuration_ref;
+        let check_type = reference.type_.unwrap_or_default();
+        let settings = configuration.settings.unwrap_or_default();
+        let text = |value: &Value, key: &str| match &value[key] {
+            Value::String(text) => text.clone(),
+            Value::Null => String::new(),
+            other => other.to_string(),
+        };
+        let is_type = |id: &str| {
+            check_type
+                .id
+                .as_deref()
+                .is_some_and(|type_id| type_id.eq_ignore_ascii_case(id))
+        };
+        let task = text(&settings["definitionRef"], "name").to_lowercase();
+        let inputs = &settings["inputs"];
+        let kind = if is_type(APPROVAL_CHECK) {
+            CheckKind::Approval {
+                approvers: settings["approvers"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .map(|approver| {
+                        let name = text(approver, "displayName");
+                        if name.is_empty() {
+                            text(approver, "uniqueName")
+                        } else {
+                            name
+                        }
+                    })
+                    .collect(),
+                min_approvers: settings["minRequiredApprovers"]
+                    .as_i64()
+                    .map(|min| min as i32),
+            }
+        } else if is_type(TASK_CHECK) && task == BRANCH_CONTROL_TASK {
+            CheckKind::BranchControl {
+                branches: text(inputs, "allowedBranches"),
+                protected_only: text(inputs, "ensureProtectionOfBranch") == "true",
+            }
+        } else if is_type(TASK_CHECK) && task == BUSINESS_HOURS_TASK {
+            CheckKind::BusinessHours {
+                days: text(inputs, "businessDays"),
+                start: text(inputs, "startTime"),
+                end: text(inputs, "endTime"),
+                time_zone: text(inputs, "timeZone"),
+            }
+        } else {
+            let name = text(&settings, "displayName");
+            CheckKind::Other(if name.is_empty() {
+                check_type
+                    .name
+                    .unwrap_or_else(|| "Unknown check".to_string())
+            } else {
+                name
+            })
+        };
+        Self {
+            id: reference.id.unwrap_or_default(),
+            kind,
+            disabled: check.is_disabled.unwrap_or(false),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct CheckList {
+    #[serde(default)]
+    value: Vec<GenericCheckConfiguration>,
+}
+
+/// The checks of a list response, fetched with their settings.
+pub fn read_checks(body: &[u8]) -> serde_json::Result<Vec<Check>> {
+    let list = serde_json::from_slice::<CheckList>(body)?;
+    Ok(list.value.into_iter().map(Check::from).collect())
+}
+
+/// The body of a request adding a check. The generated model has no
+/// `settings`.
+#[derive(Debug, Serialize)]
+pub struct NewCheck {
+    #[serde(rename = "type")]
+    check_type: CheckType,
+    resource: Resource,
+    settings: ApprovalSettings,
+    timeout: i32,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ApprovalSettings {
+    approvers: Vec<Approver>,
+    execution_order: &'static str,
+    min_required_approvers: i32,
+    instructions: String,
+    blocked_approvers: Vec<Approver>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Approver {
+    id: String,
+    display_name: String,
+}
+
+/// A new approval check on the group `group_id`, which every one of
+/// `approvers`, given as identity id and name, must approve.
+pub fn approval_check(group_id: i32, approvers: &[(String, String)]) -> NewCheck {
+    NewCheck {
+        check_type: CheckType {
+            id: Some(APPROVAL_CHECK.to_string()),
+            name: Some("Approval".to_string()),
+        },
+        resource: Resource {
+            id: Some(group_id.to_string()),
+            type_: Some(VARIABLE_GROUP_RESOURCE.to_string()),
+            ..Resource::default()
+        },
+        settings: ApprovalSettings {
+            approvers: approvers
+                .iter()
+                .map(|(id, name)| Approver {
+                    id: id.clone(),
+                    display_name: name.clone(),
+                })
+                .collect(),
+            execution_order: "anyOrder",
+            min_required_approvers: 0,
+            instructions: String::new(),
+            blocked_approvers: Vec::new(),
+        },
+        timeout: APPROVAL_TIMEOUT_MINUTES,
+    }
+}
+
+/// Lists the approvals and checks protecting the selected group.
+pub(super) fn enter<C: VariableGroupsClient + 'static>(
+    state: &mut State,
+    client: &Arc<C>,
+    fetch_tx: &Sender<Action>,
+) {
+    if state.is_offline() {
+        state.ui.report_offline("list approvals and checks");
+        return;
+    }
+    let Some(group) = state.current_group().filter(|g| g.id.is_some()).cloned() else {
+        return;
+    };
+    state.ui.search.deactivate();
+    state.ui.open(Overlay::Checks(ChecksView {
+        group: group.name.clone(),
+        loading: true,
+        ..ChecksView::default()
+    }));
+    tokio::spawn(fetch_checks(
+        Arc::clone(client),
+        state.organization().to_string(),
+        state.project().to_string(),
+        group,
+        fetch_tx.clone(),
+    ));
+}
+
+/// Asks for the approvers of a new approval.
+pub(super) fn start_add_approval(state: &mut State) {
+    if let Some(view) = state.ui.opened::<ChecksView>().filter(|v| !v.loading) {
+        state.ui.prompt = Some(Prompt {
+            kind: PromptKind::Approvers {
+                group: view.group.clone(),
+            },
+            text: String::new(),
+        });
+    }
+}
+
+/// Adds an approval by the comma separated `approvers` to `group`. Returns
+/// false when none is named, for the prompt to be corrected.
+pub(super) fn add_approval<C: VariableGroupsClient + 'static>(
+    state: &mut State,
+    group: &str,
+    approvers: &str,
+    client: &Arc<C>,
+    fetch_tx: &Sender<Action>,
+) -> bool {
+    let approvers = approvers
+        .split(',')
+        .map(str::trim)
+        .filter(|approver| !approver.is_empty())
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+    if approvers.is_empty() {
+        state
+            .ui
+            .set_status(StatusMessage::error("Name at least one approver"));
+        return false;
+    }
+    let Some(group) = state.groups().iter().find(|g| g.name == group).cloned() else {
+        return true;
+    };
+    if let Some(view) = state.ui.opened_mut::<ChecksView>() {
+        view.loading = true;
+    }
+    tokio::spawn(post_approval(
+        Arc::clone(client),
+        state.organization().to_string(),
+        state.project().to_string(),
+        group,
+        approvers,
+        fetch_tx.clone(),
+    ));
+    true
+}
+
+/// Asks for confirmation, then removes the selected approval. Other checks
+/// are left to the web UI.
+pub(super) fn remove_selected<C: VariableGroupsClient + 'static>(
+    state: &mut State,
+    client: &Arc<C>,
+    fetch_tx: &Sender<Action>,
+) {
+    let Some(view) = state
+        .ui
+        .opened_mut::<ChecksView>()
+        .filter(|view| !view.loading)
+    else {
+        return;
+    };
+    let Some(check) = view.selected_check().cloned() else {
+        return;
+    };
+    if !check.is_approval() {
+        let message = format!("Only approvals can be removed here, not {}", check.name());
+        state.ui.set_status(StatusMessage::error(message));
+        return;
+    }
+    if !view.confirm_remove {
+        view.confirm_remove = true;
+        let message = format!(
+            "Remove the approval by {} from {}? Press remove again to confirm",
+            check.summary(),
+            view.group
+        );
+        state.ui.set_status(StatusMessage::info(message));
+        return;
+    }
+    view.confirm_remove = false;
+    view.loading = true;
+    let group = view.group.clone();
+    tokio::spawn(remove_check(
+        Arc::clone(client),
+        state.organization().to_string(),
+        state.project().to_string(),
+        group,
+        check,
+        fetch_tx.clone(),
+    ));
+}
+
+/// Shows the checks of `group`, if its view is still open. Returns whether
+/// it is.
+pub(super) fn loaded(state: &mut State, group: &str, checks: Vec<Check>) -> bool {
+    let Some(view) = state
+        .ui
+        .opened_mut::<ChecksView>()
+        .filter(|view| view.group == group)
+    else {
+        return false;
+    };
+    view.checks = checks;
+    view.loading = false;
+    if view.checks.is_empty() {
+        let message = format!("No approvals or checks protect {group}");
+        state.ui.set_status(StatusMessage::info(message));
+    }
+    true
+}
+
+pub(super) fn load_failed(state: &mut State, group: &str, error: ClientError) {
+    warn!("Failed to load the checks of {group}: {error}");
+    state.ui.close::<ChecksView>();
+    state.ui.set_status(StatusMessage::error(format!(
+        "Failed to load the checks of {group}: {error}"
+    )));
+}
+
+pub(super) fn added(state: &mut State, group: &str, check: Check) {
+    let message = format!("Added an approval by {} to {group}", check.summary());
+    if let Some(view) = state.ui.opened_mut::<ChecksView>()
+        && view.group == group
+    {
+        view.checks.push(check);
+        view.selected = view.checks.len() - 1;
+        view.loading = false;
+    }
+    info!("{message}");
+    state.ui.set_status(StatusMessage::info(message));
+}
+
+pub(super) fn removed(state: &mut State, group: &str, check: Check) {
+    if let Some(view) = state.ui.opened_mut::<ChecksView>()
+        && view.group == group
+    {
+        view.checks.retain(|c| c.id != check.id);
+        view.selected = view.selected.min(view.checks.len().saturating_sub(1));
+        view.loading = false;
+    }
+    let message = format!("Removed the approval by {} from {group}", check.summary());
+    info!("{message}");
+    state.ui.set_status(StatusMessage::info(message));
+}
+
+pub(super) fn update_failed(state: &mut State, group: &str, error: ClientError) {
+    warn!("Failed to change the checks of {group}: {error}");
+    if let Some(view) = state.ui.opened_mut::<ChecksView>() {
+        view.loading = false;
+    }
+    state.ui.set_status(StatusMessage::error(format!(
+        "Failed to change the checks of {group}: {error}"
+    )));
+}
+
+async fn fetch_checks<C: VariableGroupsClient>(
+    client: Arc<C>,
+    organization: String,
+    project: String,
+    group: VarGroup,
+    fetch_tx: Sender<Action>,
+) {
+    let group_id = group.id.unwrap_or_default();
+    let action = match client.get_checks(&organization, &project, group_id).await {
+        Ok(checks) => Action::ChecksLoaded {
+            group: group.name,
+            checks,
+        },
+        Err(error) => Action::ChecksLoadFailed {
+            group: group.name,
+            error,
+        },
+    };
+    let _ = fetch_tx.send(action).await;
+}
+
+async fn post_approval<C: VariableGroupsClient>(
+    client: Arc<C>,
+    organization: String,
+    project: String,
+    group: VarGroup,
+    approvers: Vec<String>,
+    fetch_tx: Sender<Action>,
+) {
+    let group_id = group.id.unwrap_or_default();
+    let action = match client
+        .add_approval_check(&organization, &project, group_id, approvers)
+        .await
+    {
+        Ok(check) => Action::CheckAdded {
+            group: group.name,
+            check,
+        },
+        Err(error) => Action::CheckUpdateFailed {
+            group: group.name,
+            error,
+        },
+    };
+    let _ = fetch_tx.send(action).await;
+}
+
+async fn remove_check<C: VariableGroupsClient>(
+    client: Arc<C>,
+    organization: String,
+    project: String,
+    group: String,
+    check: Check,
+    fetch_tx: Sender<Action>,
+) {
+    let action = match client.remove_check(&organization, &project, check.id).await {
+        Ok(()) => Action::CheckRemoved { group, check },
+        Err(error) => Action::CheckUpdateFailed { group, error },
+    };
+    let _ = fetch_tx.send(action).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(json: &str) -> Check {
+        serde_json::from_str::<GenericCheckConfiguration>(json)
+            .unwrap()
+            .into()
+    }
+
+    #[test]
+    fn approvals_branch_control_and_business_hours_are_summarized() {
+        let approval = check(
+            r#"{"id": 4, "type": {"id": "8C6F20A7-A545-4486-9777-F762FAFE0D4D", "name": "Approval"},
+                "settings": {"approvers": [{"displayName": "Ana"}, {"uniqueName": "bo@contoso.com"}],
+                             "minRequiredApprovers": 1}}"#,
+        );
+        assert_eq!(approval.id, 4);
+        assert!(approval.is_approval());
+        assert_eq!(approval.summary(), "Ana, bo@contoso.com (1 of 2)");
+
+        let branches = check(
+            r#"{"id": 5, "type": {"id": "fe1de3ee-a436-41b4-bb20-f6eb4cb879a7", "name": "Task Check"},
+                "isDisabled": true,
+                "settings": {"definitionRef": {"name": "evaluatebranchProtection"},
+                             "inputs": {"allowedBranches": "refs/heads/main", "ensureProtectionOfBranch": "true"}}}"#,
+        );
+        assert_eq!(branches.name(), "Branch control");
+        assert_eq!(
+            branches.summary(),
+            "refs/heads/main, protected branches only (disabled)"
+        );
+
+        let hours = check(
+            r#"{"id": 6, "type": {"id": "fe1de3ee-a436-41b4-bb20-f6eb4cb879a7", "name": "Task Check"},
+                "settings": {"displayName": "Business Hours", "definitionRef": {"name": "evaluatebusinesshours"},
+                             "inputs": {"businessDays": "Monday,Friday", "startTime": "09:00",
+                                        "endTime": "17:00", "timeZone": "UTC"}}}"#,
+        );
+        assert_eq!(hours.summary(), "Monday,Friday 09:00-17:00 UTC");
+
+        let other = check(
+            r#"{"id": 7, "type": {"id": "fe1de3ee-a436-41b4-bb20-f6eb4cb879a7", "name": "Task Check"},
+                "settings": {"displayName": "Invoke REST API", "definitionRef": {"name": "invokerestapi"}}}"#,
+        );
+        assert_eq!(other.name(), "Invoke REST API");
+    }
+
+    #[test]
+    fn new_approvals_send_their_settings() {
+        let check = approval_check(7, &[("1f2e".to_string(), "Ana".to_string())]);
+        let sent = serde_json::to_value(&check).unwrap();
+        assert_eq!(sent["settings"]["approvers"][0]["id"], "1f2e");
+        assert_eq!(sent["settings"]["approvers"][0]["displayName"], "Ana");
+        assert_eq!(sent["settings"]["minRequiredApprovers"], 0);
+        assert_eq!(sent["type"]["id"], APPROVAL_CHECK);
+        assert_eq!(sent["resource"]["type"], "variablegroup");
+        assert_eq!(sent["resource"]["id"], "7");
+        assert_eq!(sent["timeout"], APPROVAL_TIMEOUT_MINUTES);
+    }
+}
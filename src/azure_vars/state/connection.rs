@@ -0,0 +1,327 @@
+use std::{fs, sync::Arc};
+
+use async_trait::async_trait;
+use azure_core::{
+    base64,
+    http::{
+        BufResponse, ClientOptions, Context, ExponentialRetryOptions, HttpClient, Method, Pipeline,
+        Request, RetryOptions, TransportOptions, Url,
+        headers::{AUTHORIZATION, query_param::API_VERSION},
+        policies::{Policy, PolicyResult},
+    },
+};
+use azure_devops_rust_api::{
+    ADO_SCOPE, Credential,
+    approvals_and_checks::{self as checks_api, check_configurations, pipeline_permissions},
+    audit::{self as audit_api, audit_log},
+    core::{self as projects_api, projects},
+    distributed_task::{ClientBuilder, variablegroups},
+    ims::{self as ims_api, identities},
+    pipelines::{self as pipelines_api, pipelines},
+};
+
+use serde::Serialize;
+
+use super::fixtures::{FixtureMode, RecordingClient, ReplayClient};
+use crate::config::{
+    config::{ConfigKey, ServerConfig},
+    error::ConfigError,
+};
+
+/// The generated clients the Azure DevOps backend talks to.
+pub struct AzureClients {
+    pub variable_groups: variablegroups::Client,
+    pub projects: projects::Client,
+    pub audit_log: audit_log::Client,
+    pub pipelines: pipelines::Client,
+    pub pipeline_permissions: pipeline_permissions::Client,
+    pub check_configurations: check_configurations::Client,
+    pub identities: identities::Client,
+    pub raw: RawClient,
+}
+
+/// Sends the requests the generated clients cannot build, such as a check
+/// configuration with its settings, with the same policies, retries and
+/// transport.
+pub struct RawClient {
+    endpoint: String,
+    credential: Credential,
+    pipeline: Pipeline,
+}
+
+impl RawClient {
+    /// POSTs `body` as JSON to `path`, which starts with the organization.
+    pub async fn post(&self, path: &str, body: &impl Serialize) -> azure_core::Result<BufResponse> {
+        let mut url = Url::parse(&format!("{}/{path}", self.endpoint))?;
+        url.query_pairs_mut()
+            .append_pair(API_VERSION, RAW_API_VERSION);
+        let mut request = Request::new(url, Method::Post);
+        if let Some(authorization) = self.authorization().await? {
+            request.insert_header(AUTHORIZATION, authorization);
+        }
+        request.insert_header("content-type", "application/json");
+        request.set_body(azure_core::json::to_json(body)?);
+        self.pipeline.send(&Context::new(), &mut request).await
+    }
+
+    /// The header the generated clients send for the credential.
+    async fn authorization(&self) -> azure_core::Result<Option<String>> {
+        Ok(match &self.credential {
+            Credential::Unauthenticated => None,
+            Credential::Pat(pat) => Some(format!("Basic {}", base64::encode(format!(":{pat}")))),
+            Credential::TokenCredential(credential) => {
+                let token = credential.get_token(&[ADO_SCOPE], None).await?;
+                Some(format!("Bearer {}", token.token.secret()))
+            }
+        })
+    }
+}
+
+/// The API version the generated clients are built for.
+const RAW_API_VERSION: &str = "7.1-preview";
+const DEFAULT_ENDPOINT: &str = "https://dev.azure.com";
+
+/// The generated client builders share their methods but not a trait.
+macro_rules! configure {
+    ($builder:expr, $endpoint:expr, $policies:expr, $transport:expr) => {{
+        let mut builder = $builder.retry(retry_options());
+        if let Some(endpoint) = $endpoint.clone() {
+            builder = builder.endpoint(endpoint);
+        }
+        if !$policies.is_empty() {
+            builder = builder.per_call_policies($policies.clone());
+        }
+        if let Some(transport) = $transport.clone() {
+            builder = builder.transport(TransportOptions::new(transport));
+        }
+        builder.build()
+    }};
+}
+
+/// Builds the clients for dev.azure.com or the server in `server`. With
+/// `fixtures`, responses are recorded to or replayed from a fixture file.
+pub fn azure_clients(
+    credential: Credential,
+    server: &ServerConfig,
+    fixtures: Option<&FixtureMode>,
+) -> Result<AzureClients, ConfigError> {
+    // The generated clients append `/{organization}` themselves.
+    let endpoint = match &server.endpoint {
+        Some(endpoint) => Some(
+            Url::parse(endpoint.trim_end_matches('/'))
+                .map_err(|error| invalid(ConfigKey::Endpoint, endpoint, error))?,
+        ),
+        None => None,
+    };
+    let policies: Vec<Arc<dyn Policy>> = match &server.api_version {
+        Some(version) => vec![Arc::new(ApiVersionPolicy(version.clone()))],
+        None => Vec::new(),
+    };
+    let transport: Option<Arc<dyn HttpClient>> = match fixtures {
+        Some(FixtureMode::Replay(path)) => Some(Arc::new(ReplayClient::load(path)?)),
+        Some(FixtureMode::Record(path)) => Some(Arc::new(RecordingClient::new(
+            Arc::new(http_client(server)?),
+            path.clone(),
+        ))),
+        None if server.ca_bundle.is_some() || server.proxy.is_some() => {
+            Some(Arc::new(http_client(server)?))
+        }
+        None => None,
+    };
+    Ok(AzureClients {
+        variable_groups: configure!(
+            ClientBuilder::new(credential.clone()),
+            endpoint,
+            policies,
+            transport
+        )
+        .variablegroups_client(),
+        projects: configure!(
+            projects_api::ClientBuilder::new(credential.clone()),
+            endpoint,
+            policies,
+            transport
+        )
+        .projects_client(),
+        pipelines: configure!(
+            pipelines_api::ClientBuilder::new(credential.clone()),
+            endpoint,
+            policies,
+            transport
+        )
+        .pipelines_client(),
+        pipeline_permissions: configure!(
+            checks_api::ClientBuilder::new(credential.clone()),
+            endpoint,
+            policies,
+            transport
+        )
+        .pipeline_permissions_client(),
+        check_configurations: configure!(
+            checks_api::ClientBuilder::new(credential.clone()),
+            endpoint,
+            policies,
+            transport
+        )
+        .check_configurations_client(),
+        // Without an endpoint this defaults to vssps.dev.azure.com.
+        identities: configure!(
+            ims_api::ClientBuilder::new(credential.clone()),
+            endpoint,
+            policies,
+            transport
+        )
+        .identities_client(),
+        raw: RawClient {
+            endpoint: endpoint
+                .as_ref()
+                .map_or(DEFAULT_ENDPOINT, Url::as_str)
+                .trim_end_matches('/')
+                .to_string(),
+            credential: credential.clone(),
+            pipeline: Pipeline::new(
+                option_env!("CARGO_PKG_NAME"),
+                option_env!("CARGO_PKG_VERSION"),
+                ClientOptions {
+                    per_call_policies: policies.clone(),
+                    retry: Some(retry_options()),
+                    transport: transport.clone().map(TransportOptions::new),
+                    ..ClientOptions::default()
+                },
+                Vec::new(),
+                Vec::new(),
+                None,
+            ),
+        },
+        // Without an endpoint this defaults to auditservice.dev.azure.com.
+        audit_log: configure!(
+            audit_api::ClientBuilder::new(credential),
+            endpoint,
+            policies,
+            transport
+        )
+        .audit_log_client(),
+    })
+}
+
+/// Retries throttled, timed out and failed requests with exponential
+/// backoff. A `Retry-After` from the server takes precedence over the backoff.
+pub fn retry_options() -> RetryOptions {
+    RetryOptions::exponential(ExponentialRetryOptions {
+        initial_delay: azure_core::time::Duration::milliseconds(500),
+        max_retries: 4,
+        max_total_elapsed: azure_core::time::Duration::seconds(60),
+        max_delay: azure_core::time::Duration::seconds(30),
+    })
+}
+
+/// An HTTP client trusting the extra certificates of `ca_bundle` and sending
+/// every request through `proxy`. Without a proxy setting the system proxy
+/// is used.
+fn http_client(server: &ServerConfig) -> Result<reqwest::Client, ConfigError> {
+    let mut builder = reqwest::Client::builder();
+    if let Some(path) = &server.ca_bundle {
+        let pem = fs::read(path).map_err(|source| ConfigError::Io {
+            path: path.clone(),
+            source,
+        })?;
+        let value = path.display().to_string();
+        let certificates = reqwest::Certificate::from_pem_bundle(&pem)
+            .map_err(|error| invalid(ConfigKey::CaBundle, &value, error))?;
+        if certificates.is_empty() {
+            return Err(invalid(
+                ConfigKey::CaBundle,
+                &value,
+                "no PEM certificates found",
+            ));
+        }
+        for certificate in certificates {
+            builder = builder.add_root_certificate(certificate);
+        }
+    }
+    if let Some(proxy) = &server.proxy {
+        let proxy =
+            reqwest::Proxy::all(proxy).map_err(|error| invalid(ConfigKey::Proxy, proxy, error))?;
+        builder = builder.proxy(proxy);
+    }
+    builder
+        .build()
+        .map_err(|error| match (&server.proxy, &server.ca_bundle) {
+            (Some(proxy), _) => invalid(ConfigKey::Proxy, proxy, error),
+            (None, path) => invalid(ConfigKey::CaBundle, &format!("{path:?}"), error),
+        })
+}
+
+fn invalid(key: ConfigKey, value: &str, error: impl ToString) -> ConfigError {
+    ConfigError::InvalidValue {
+        key: key.name(),
+        value: value.to_string(),
+        reason: error.to_string(),
+        origin: "the configuration".into(),
+    }
+}
+
+/// Sends every request with the configured API version instead of the one
+/// the generated client was built for, which older servers reject.
+#[derive(Debug)]
+struct ApiVersionPolicy(String);
+
+#[async_trait]
+impl Policy for ApiVersionPolicy {
+    async fn send(
+        &self,
+        ctx: &Context,
+        request: &mut Request,
+        next: &[Arc<dyn Policy>],
+    ) -> PolicyResult {
+        pin_api_version(request.url_mut(), &self.0);
+        next[0].send(ctx, request, &next[1..]).await
+    }
+}
+
+fn pin_api_version(url: &mut Url, version: &str) {
+    let query = url
+        .query_pairs()
+        .filter(|(name, _)| name != API_VERSION)
+        .map(|(name, value)| (name.into_owned(), value.into_owned()))
+        .collect::<Vec<_>>();
+    url.query_pairs_mut()
+        .clear()
+        .extend_pairs(query)
+        .append_pair(API_VERSION, version);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn api_version_replaces_the_generated_one() {
+        let mut url = Url::parse(
+            "https://tfs.example.com/tfs/DefaultCollection/Web/_apis/distributedtask/variablegroups?groupName=app&api-version=7.1-preview",
+        )
+        .unwrap();
+        pin_api_version(&mut url, "6.0-preview.2");
+        assert_eq!(url.query(), Some("groupName=app&api-version=6.0-preview.2"));
+    }
+
+    #[test]
+    fn unreadable_ca_bundles_are_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ca.pem");
+        let server = ServerConfig {
+            ca_bundle: Some(path.clone()),
+            ..ServerConfig::default()
+        };
+        assert!(matches!(http_client(&server), Err(ConfigError::Io { .. })));
+
+        fs::write(&path, "not a certificate").unwrap();
+        assert!(matches!(
+            http_client(&server),
+            Err(ConfigError::InvalidValue {
+                key: "ca_bundle",
+                ..
+            })
+        ));
+    }
+}
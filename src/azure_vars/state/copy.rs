@@ -0,0 +1,428 @@
+use std::{collections::HashMap, fmt, str::FromStr, sync::Arc};
+
+use log::{debug, info, warn};
+use tokio::sync::mpsc::Sender;
+
+use super::{
+    action::Action,
+    cache::SECRET_PLACEHOLDER,
+    error::ClientError,
+    state::{
+        CopyProfile, CopyView, Overlay, Prompt, PromptKind, State, StatusMessage, VarEntry,
+        VarGroup,
+    },
+    state_store::VariableGroupsClient,
+};
+
+/// A project given as `organization/project`, optionally followed by
+/// `/group`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupPath {
+    pub organization: String,
+    pub project: String,
+    pub group: Option<String>,
+}
+
+impl FromStr for GroupPath {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // Group names may contain slashes, project names may not.
+        let mut parts = s.splitn(3, '/');
+        let organization = parts.next().unwrap_or_default().trim();
+        let project = parts.next().unwrap_or_default().trim();
+        let group = parts.next().map(str::trim);
+        if organization.is_empty() || project.is_empty() || group == Some("") {
+            return Err("expected organization/project or organization/project/group".into());
+        }
+        Ok(Self {
+            organization: organization.to_string(),
+            project: project.to_string(),
+            group: group.map(str::to_string),
+        })
+    }
+}
+
+impl fmt::Display for GroupPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.organization, self.project)?;
+        match &self.group {
+            Some(group) => write!(f, "/{group}"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Values for secret variables, which cannot be read from the source group.
+/// Read from lines of `NAME=value`, or `group:NAME=value` for the secrets
+/// of one group only.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SecretValues {
+    values: HashMap<(Option<String>, String), String>,
+}
+
+impl SecretValues {
+    /// Blank lines and lines starting with `#` are skipped.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut values = HashMap::new();
+        for (idx, line) in text.lines().enumerate() {
+            let line = line.trim_start();
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(format!("line {}: expected NAME=value", idx + 1));
+            };
+            // Variable names cannot contain colons, group names can.
+            let (group, name) = match key.rsplit_once(':') {
+                Some((group, name)) => (Some(group.trim().to_string()), name.trim()),
+                None => (None, key.trim()),
+            };
+            if name.is_empty() {
+                return Err(format!("line {}: the variable name is empty", idx + 1));
+            }
+            values.insert((group, name.to_string()), value.to_string());
+        }
+        Ok(Self { values })
+    }
+
+    /// The value for `name` in `group`, preferring one given for the group.
+    pub fn get(&self, group: &str, name: &str) -> Option<&str> {
+        self.values
+            .get(&(Some(group.to_string()), name.to_string()))
+            .or_else(|| self.values.get(&(None, name.to_string())))
+            .map(String::as_str)
+    }
+}
+
+/// A new group named `name` with the description and variables of
+/// `source`, whose variables must be loaded. Secrets take their value from
+/// `secrets`, and are otherwise created empty. Groups linked to Azure Key
+/// Vault are refused, as their copy would be a plain group of empty secrets.
+pub fn copy_of(source: &VarGroup, name: &str, secrets: &SecretValues) -> Result<VarGroup, String> {
+    if source.is_linked() {
+        return Err(linked_group(&source.name));
+    }
+    let variables = source
+        .variables
+        .iter()
+        .map(|var| match secrets.get(&source.name, &var.name) {
+            Some(value) if var.is_secret => VarEntry {
+                value: value.to_string(),
+                ..var.clone()
+            },
+            _ => var.clone(),
+        })
+        .collect();
+    Ok(VarGroup {
+        name: name.to_string(),
+        variables,
+        description: source.description.clone(),
+        ..VarGroup::default()
+    })
+}
+
+/// Why a group linked to Azure Key Vault is not copied.
+pub fn linked_group(name: &str) -> String {
+    format!(
+        "`{name}` is linked to Azure Key Vault and cannot be copied, link the vault to a new group instead"
+    )
+}
+
+/// The secret variables of `group` that have no value, which must be entered
+/// once it is copied.
+pub fn secrets_without_value(group: &VarGroup) -> Vec<&str> {
+    group
+        .variables
+        .iter()
+        .filter(|var| var.is_secret && var.value == SECRET_PLACEHOLDER)
+        .map(|var| var.name.as_str())
+        .collect()
+}
+
+/// Creates `group` in `organization/project` unless a group of that name
+/// already exists there.
+pub async fn copy_group<C: VariableGroupsClient + ?Sized>(
+    client: &C,
+    organization: &str,
+    project: &str,
+    group: VarGroup,
+) -> Result<VarGroup, ClientError> {
+    let taken = client
+        .get_variable_groups(organization, project, Some(&group.name))
+        .await?
+        .iter()
+        .any(|existing| existing.name == group.name);
+    if taken {
+        return Err(ClientError::Unexpected {
+            message: format!(
+                "a variable group named `{}` already exists in {organization}/{project}",
+                group.name
+            ),
+        });
+    }
+    client
+        .add_variable_group(organization, project, group)
+        .await
+}
+
+/// A project groups can be copied to, with the client that reaches it.
+#[derive(Clone)]
+pub struct CopyTarget {
+    pub profile: CopyProfile,
+    pub client: Arc<dyn VariableGroupsClient>,
+}
+
+/// Opens the profiles the selected group can be copied to.
+pub(super) fn start(state: &mut State, targets: &[CopyTarget]) {
+    if state.is_offline() {
+        state.ui.report_offline("copy groups");
+        return;
+    }
+    if targets.is_empty() {
+        state.ui.set_status(StatusMessage::error(
+            "No profiles to copy to, add them under `profiles` in the config file",
+        ));
+        return;
+    }
+    let Some(group) = state.current_group() else {
+        return;
+    };
+    if group.is_linked() {
+        let message = linked_group(&group.name);
+        state.ui.set_status(StatusMessage::error(message));
+        return;
+    }
+    let group = group.name.clone();
+    state.ui.search.deactivate();
+    state
+        .ui
+        .open(Overlay::Copy(CopyView { group, selected: 0 }));
+}
+
+/// Asks for the name of the copy in the highlighted profile. Returns false
+/// when no profile is being picked.
+pub(super) fn pick_profile(state: &mut State) -> bool {
+    let Some(copy) = state.ui.close::<CopyView>() else {
+        return false;
+    };
+    let Some(profile) = state.copy_profiles().get(copy.selected) else {
+        return false;
+    };
+    state.ui.prompt = Some(Prompt {
+        kind: PromptKind::CopyName {
+            group: copy.group.clone(),
+            profile: profile.name.clone(),
+        },
+        text: copy.group,
+    });
+    true
+}
+
+/// Copies `group` as last fetched, without its unsaved changes, to the
+/// project of `profile` as `name`. Returns false when the name is empty, for
+/// the prompt to be corrected.
+pub(super) fn copy_to<C: VariableGroupsClient + 'static>(
+    state: &mut State,
+    targets: &[CopyTarget],
+    group: &str,
+    profile: &str,
+    name: &str,
+    client: &Arc<C>,
+    fetch_tx: &Sender<Action>,
+) -> bool {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        state
+            .ui
+            .set_status(StatusMessage::error("The name must not be empty"));
+        return false;
+    }
+    let source = state.groups().iter().find(|g| g.name == group);
+    let target = targets.iter().find(|t| t.profile.name == profile);
+    let (Some(source), Some(target)) = (source, target) else {
+        return true;
+    };
+    debug!("Copying {group} to {profile} as {name}");
+    tokio::spawn(copy_to_profile(
+        Arc::clone(client),
+        state.organization().to_string(),
+        state.project().to_string(),
+        source.clone(),
+        name,
+        target.clone(),
+        fetch_tx.clone(),
+    ));
+    state.ui.set_status(StatusMessage::info(format!(
+        "Copying {group} to {profile}..."
+    )));
+    true
+}
+
+pub(super) fn copied(
+    state: &mut State,
+    group: &str,
+    profile: &str,
+    copy: &VarGroup,
+    missing_secrets: &[String],
+) {
+    info!("Copied {group} to {profile} as {}", copy.name);
+    let mut message = format!("Copied {group} to {profile} as {}", copy.name);
+    if !missing_secrets.is_empty() {
+        message.push_str(&format!(
+            ", enter its secrets there: {}",
+            missing_secrets.join(", ")
+        ));
+    }
+    state.ui.set_status(StatusMessage::info(message));
+}
+
+pub(super) fn failed(state: &mut State, group: &str, profile: &str, error: ClientError) {
+    warn!("Failed to copy {group} to {profile}: {error}");
+    state.ui.set_status(StatusMessage::error(format!(
+        "Failed to copy {group} to {profile}: {error}"
+    )));
+}
+
+/// Loads the variables of `source` if needed, then creates the copy in the
+/// project of `profile`. Secrets are created empty.
+async fn copy_to_profile<C: VariableGroupsClient>(
+    client: Arc<C>,
+    organization: String,
+    project: String,
+    source: VarGroup,
+    name: String,
+    target: CopyTarget,
+    fetch_tx: Sender<Action>,
+) {
+    let CopyTarget {
+        profile,
+        client: target,
+    } = target;
+    let group = source.name.clone();
+    let copied = async {
+        let source = match source.id.filter(|_| source.summary_only) {
+            Some(id) => {
+                client
+                    .get_variable_group(&organization, &project, id)
+                    .await?
+            }
+            None => source,
+        };
+        let copy = copy_of(&source, &name, &SecretValues::default())
+            .map_err(|message| ClientError::Unexpected { message })?;
+        let missing_secrets = secrets_without_value(&copy)
+            .into_iter()
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        let copy = copy_group(
+            target.as_ref(),
+            &profile.organization,
+            &profile.project,
+            copy,
+        )
+        .await?;
+        Ok::<_, ClientError>((copy, missing_secrets))
+    }
+    .await;
+    let action = match copied {
+        Ok((copy, missing_secrets)) => Action::GroupCopied {
+            group,
+            profile: profile.name,
+            copy,
+            missing_secrets,
+        },
+        Err(error) => Action::GroupCopyFailed {
+            group,
+            profile: profile.name,
+            error,
+        },
+    };
+    let _ = fetch_tx.send(action).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::azure_vars::state::directory::DirectoryVariableGroupsClient;
+
+    fn var(name: &str, value: &str, is_secret: bool) -> VarEntry {
+        VarEntry {
+            name: name.into(),
+            value: value.into(),
+            is_secret,
+        }
+    }
+
+    #[test]
+    fn paths_and_secret_files_are_parsed() {
+        let path = "contoso/Web/app/prod".parse::<GroupPath>().unwrap();
+        assert_eq!(path.project, "Web");
+        assert_eq!(path.group.as_deref(), Some("app/prod"));
+        assert_eq!(path.to_string(), "contoso/Web/app/prod");
+        assert_eq!("contoso/Web".parse::<GroupPath>().unwrap().group, None);
+        assert!("contoso".parse::<GroupPath>().is_err());
+        assert!("contoso/Web/".parse::<GroupPath>().is_err());
+
+        let secrets = SecretValues::parse("# shared\nTOKEN=abc=\n\napp-prod:TOKEN=xyz\n").unwrap();
+        assert_eq!(secrets.get("app-dev", "TOKEN"), Some("abc="));
+        assert_eq!(secrets.get("app-prod", "TOKEN"), Some("xyz"));
+        assert_eq!(secrets.get("app-prod", "KEY"), None);
+        assert!(SecretValues::parse("TOKEN\n").is_err());
+    }
+
+    #[tokio::test]
+    async fn groups_are_copied_with_the_secrets_given() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("New")).unwrap();
+        fs::write(dir.path().join("New").join("taken.yaml"), "variables: {}\n").unwrap();
+        let client = DirectoryVariableGroupsClient::new(dir.path().to_path_buf());
+        let source = VarGroup {
+            id: Some(3),
+            name: "app".into(),
+            variables: vec![
+                var("URL", "https://example.com", false),
+                var("TOKEN", SECRET_PLACEHOLDER, true),
+                var("KEY", SECRET_PLACEHOLDER, true),
+            ],
+            ..VarGroup::default()
+        };
+        let secrets = SecretValues::parse("TOKEN=hunter2\n").unwrap();
+
+        let copy = copy_of(&source, "app-copy", &secrets).unwrap();
+        assert_eq!(secrets_without_value(&copy), ["KEY"]);
+        let created = copy_group(&client, "org", "New", copy).await.unwrap();
+        assert_eq!(created.name, "app-copy");
+        let stored = fs::read_to_string(dir.path().join("New").join("app-copy.yaml")).unwrap();
+        assert!(stored.contains("hunter2"));
+
+        let taken = copy_of(&source, "taken", &secrets).unwrap();
+        let error = copy_group(&client, "org", "New", taken).await.unwrap_err();
+        assert!(error.to_string().contains("already exists in org/New"));
+    }
+
+    #[test]
+    fn groups_linked_to_key_vault_are_not_copied() {
+        let vault = VarGroup {
+            name: "kv-prod".into(),
+            group_type: Some("AzureKeyVault".into()),
+            variables: vec![var("db-password", SECRET_PLACEHOLDER, true)],
+            ..VarGroup::default()
+        };
+        assert_eq!(
+            copy_of(&vault, "kv-copy", &SecretValues::default()),
+            Err(linked_group("kv-prod"))
+        );
+
+        let described = VarGroup {
+            name: "app".into(),
+            description: Some("Front end".into()),
+            group_type: Some("Vsts".into()),
+            ..VarGroup::default()
+        };
+        let copy = copy_of(&described, "app-copy", &SecretValues::default()).unwrap();
+        assert_eq!(copy.description.as_deref(), Some("Front end"));
+    }
+}
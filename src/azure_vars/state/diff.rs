@@ -0,0 +1,322 @@
+use std::collections::HashMap;
+
+use super::state::{VarEntry, VarGroup};
+
+/// At most this many groups are named in a change summary.
+const SUMMARY_GROUPS: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Modified,
+}
+
+/// How one group differs between two fetches.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupChange {
+    pub group: String,
+    pub kind: ChangeKind,
+    /// Variables that were added or given a new value. Removed variables are
+    /// only counted, as there is no row left to highlight.
+    pub changed_vars: Vec<String>,
+    pub removed_vars: usize,
+    pub modified_by: Option<String>,
+}
+
+impl GroupChange {
+    pub fn summary(&self) -> String {
+        let by = self
+            .modified_by
+            .as_ref()
+            .map(|name| format!(" by {name}"))
+            .unwrap_or_default();
+        match self.kind {
+            ChangeKind::Added => format!("{}: group added{by}", self.group),
+            ChangeKind::Removed => format!("{}: group removed", self.group),
+            ChangeKind::Modified if self.changed_vars.is_empty() && self.removed_vars == 0 => {
+                format!("{}: variables changed{by}", self.group)
+            }
+            ChangeKind::Modified => {
+                let count = self.changed_vars.len() + self.removed_vars;
+                let noun = if count == 1 { "variable" } else { "variables" };
+                format!("{}: {count} {noun} changed{by}", self.group)
+            }
+        }
+    }
+}
+
+/// Compares groups by name. Secret values are hidden in both fetches, so
+/// changes to them go unnoticed. Groups whose variables were not loaded on
+/// both sides are compared by fingerprint, which tells that something
+/// changed but not what.
+pub fn diff_groups(old: &[VarGroup], new: &[VarGroup]) -> Vec<GroupChange> {
+    let old_by_name = old
+        .iter()
+        .map(|group| (group.name.as_str(), group))
+        .collect::<HashMap<_, _>>();
+    let mut changes = Vec::new();
+
+    for group in new {
+        match old_by_name.get(group.name.as_str()) {
+            None => changes.push(GroupChange {
+                group: group.name.clone(),
+                kind: ChangeKind::Added,
+                changed_vars: loaded_vars(group).map(|v| v.name.clone()).collect(),
+                removed_vars: 0,
+                modified_by: group.modified_by.clone(),
+            }),
+            Some(previous) if previous.summary_only || group.summary_only => {
+                if previous.fingerprint.is_some()
+                    && group.fingerprint.is_some()
+                    && previous.fingerprint != group.fingerprint
+                {
+                    changes.push(GroupChange {
+                        group: group.name.clone(),
+                        kind: ChangeKind::Modified,
+                        changed_vars: Vec::new(),
+                        removed_vars: 0,
+                        modified_by: group.modified_by.clone(),
+                    });
+                }
+            }
+            Some(previous) => {
+                let (changed_vars, removed_vars) = diff_vars(&previous.variables, &group.variables);
+                if !changed_vars.is_empty() || removed_vars > 0 {
+                    changes.push(GroupChange {
+                        group: group.name.clone(),
+                        kind: ChangeKind::Modified,
+                        changed_vars,
+                        removed_vars,
+                        modified_by: group.modified_by.clone(),
+                    });
+                }
+            }
+        }
+    }
+
+    let new_names = new.iter().map(|g| g.name.as_str()).collect::<Vec<_>>();
+    changes.extend(
+        old.iter()
+            .filter(|group| !new_names.contains(&group.name.as_str()))
+            .map(|group| GroupChange {
+                group: group.name.clone(),
+                kind: ChangeKind::Removed,
+                changed_vars: Vec::new(),
+                removed_vars: group.var_count(),
+                modified_by: None,
+            }),
+    );
+    changes
+}
+
+fn loaded_vars(group: &VarGroup) -> impl Iterator<Item = &VarEntry> {
+    group.variables.iter().filter(|_| !group.summary_only)
+}
+
+/// Keeps the variables already loaded for groups that have not changed
+/// since, so that a refresh does not send the user back to a loading screen.
+pub fn keep_loaded_details(previous: &[VarGroup], groups: &mut [VarGroup]) {
+    for group in groups.iter_mut().filter(|group| group.summary_only) {
+        let loaded = previous.iter().find(|old| {
+            !old.summary_only
+                && old.name == group.name
+                && old.fingerprint.is_some()
+                && old.fingerprint == group.fingerprint
+        });
+        if let Some(loaded) = loaded {
+            *group = loaded.clone();
+        }
+    }
+}
+
+fn diff_vars(old: &[VarEntry], new: &[VarEntry]) -> (Vec<String>, usize) {
+    let changed = new
+        .iter()
+        .filter(|var| !old.contains(var))
+        .map(|var| var.name.clone())
+        .collect();
+    let removed = old
+        .iter()
+        .filter(|var| !new.iter().any(|v| v.name == var.name))
+        .count();
+    (changed, removed)
+}
+
+/// How one variable differs between two versions of a group.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VarChange {
+    Added(VarEntry),
+    Removed(VarEntry),
+    Changed { old: VarEntry, new: VarEntry },
+}
+
+/// The variables of `new` that were added or changed since `old`, then the
+/// ones removed, each in the order of its version.
+pub fn var_changes(old: &[VarEntry], new: &[VarEntry]) -> Vec<VarChange> {
+    let mut changes = new
+        .iter()
+        .filter_map(|var| match old.iter().find(|o| o.name == var.name) {
+            None => Some(VarChange::Added(var.clone())),
+            Some(previous) if previous != var => Some(VarChange::Changed {
+                old: previous.clone(),
+                new: var.clone(),
+            }),
+            Some(_) => None,
+        })
+        .collect::<Vec<_>>();
+    changes.extend(
+        old.iter()
+            .filter(|var| !new.iter().any(|n| n.name == var.name))
+            .map(|var| VarChange::Removed(var.clone())),
+    );
+    changes
+}
+
+/// One status line for all changes, e.g. `app-prod: 2 variables changed by alice`.
+pub fn summarize(changes: &[GroupChange]) -> Option<String> {
+    if changes.is_empty() {
+        return None;
+    }
+    let mut summary = changes
+        .iter()
+        .take(SUMMARY_GROUPS)
+        .map(GroupChange::summary)
+        .collect::<Vec<_>>()
+        .join("; ");
+    if changes.len() > SUMMARY_GROUPS {
+        summary.push_str(&format!(
+            "; {} more groups changed",
+            changes.len() - SUMMARY_GROUPS
+        ));
+    }
+    Some(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn var(name: &str, value: &str) -> VarEntry {
+        VarEntry {
+            name: name.into(),
+            value: value.into(),
+            is_secret: false,
+        }
+    }
+
+    fn group(name: &str, variables: Vec<VarEntry>, modified_by: Option<&str>) -> VarGroup {
+        VarGroup {
+            name: name.into(),
+            variables,
+            modified_by: modified_by.map(Into::into),
+            ..VarGroup::default()
+        }
+    }
+
+    #[test]
+    fn reports_changed_added_and_removed_variables() {
+        let old = vec![
+            group(
+                "app-prod",
+                vec![var("A", "1"), var("B", "2"), var("C", "3")],
+                None,
+            ),
+            group("unchanged", vec![var("X", "1")], None),
+        ];
+        let new = vec![
+            group(
+                "app-prod",
+                vec![var("A", "1"), var("B", "20"), var("D", "4")],
+                Some("alice"),
+            ),
+            group("unchanged", vec![var("X", "1")], Some("bob")),
+        ];
+
+        let changes = diff_groups(&old, &new);
+        assert_eq!(
+            changes,
+            vec![GroupChange {
+                group: "app-prod".into(),
+                kind: ChangeKind::Modified,
+                changed_vars: vec!["B".into(), "D".into()],
+                removed_vars: 1,
+                modified_by: Some("alice".into()),
+            }]
+        );
+        assert_eq!(
+            summarize(&changes).unwrap(),
+            "app-prod: 3 variables changed by alice"
+        );
+    }
+
+    #[test]
+    fn reports_added_and_removed_groups() {
+        let old = vec![group("old", vec![var("A", "1")], None)];
+        let new = vec![group("new", vec![var("A", "1")], Some("carol"))];
+
+        let changes = diff_groups(&old, &new);
+        assert_eq!(
+            summarize(&changes).unwrap(),
+            "new: group added by carol; old: group removed"
+        );
+        assert_eq!(summarize(&diff_groups(&new, &new)), None);
+    }
+
+    #[test]
+    fn unloaded_groups_are_compared_by_fingerprint() {
+        let summary = |fingerprint| VarGroup {
+            fingerprint: Some(fingerprint),
+            summary_only: true,
+            variable_count: 1,
+            ..group("app-prod", vec![], Some("alice"))
+        };
+        let loaded = VarGroup {
+            fingerprint: Some(1),
+            ..group("app-prod", vec![var("A", "1")], None)
+        };
+
+        let previous = std::slice::from_ref(&loaded);
+        assert_eq!(diff_groups(previous, &[summary(1)]), vec![]);
+        assert_eq!(
+            summarize(&diff_groups(previous, &[summary(2)])).unwrap(),
+            "app-prod: variables changed by alice"
+        );
+
+        let mut groups = vec![summary(1)];
+        keep_loaded_details(previous, &mut groups);
+        assert_eq!(groups, previous);
+        let mut groups = vec![summary(2)];
+        keep_loaded_details(previous, &mut groups);
+        assert!(groups[0].summary_only);
+    }
+
+    #[test]
+    fn lists_variable_changes_between_versions() {
+        let old = [var("A", "1"), var("B", "2"), var("C", "3")];
+        let new = [var("A", "1"), var("B", "20"), var("D", "4")];
+
+        assert_eq!(
+            var_changes(&old, &new),
+            vec![
+                VarChange::Changed {
+                    old: var("B", "2"),
+                    new: var("B", "20"),
+                },
+                VarChange::Added(var("D", "4")),
+                VarChange::Removed(var("C", "3")),
+            ]
+        );
+        assert_eq!(var_changes(&new, &new), vec![]);
+    }
+
+    #[test]
+    fn long_summaries_are_shortened() {
+        let new = (0..5)
+            .map(|i| group(&format!("g{i}"), vec![], None))
+            .collect::<Vec<_>>();
+
+        let summary = summarize(&diff_groups(&[], &new)).unwrap();
+        assert!(summary.ends_with("; 2 more groups changed"), "{summary}");
+    }
+}
@@ -0,0 +1,687 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+
+use super::{
+    audit::AuditEvent,
+    cache::{SECRET_PLACEHOLDER, name_matches},
+    checks::Check,
+    error::ClientError,
+    permissions::{PermissionChanges, Pipeline, PipelinePermissions},
+    state::{VarEntry, VarGroup},
+    state_store::{VarGroupPage, VariableGroupsClient, fingerprint},
+};
+
+/// Extensions of group files. New groups are written as the first.
+const EXTENSIONS: [&str; 3] = ["yaml", "yml", "json"];
+/// Ids derived from group names start here, above the ids written by hand.
+const DERIVED_IDS: i32 = 1 << 24;
+
+/// Serves a directory as an organization: each project is a subdirectory
+/// and each group a YAML or JSON file in it, named after the group.
+///
+/// ```yaml
+/// # demo/app/app-prod.yaml
+/// id: 3
+/// modified_by: alice
+/// variables:
+///   API_URL: https://api.example.com
+///   PORT: 8080
+///   TOKEN:
+///     value: hunter2
+///     secret: true
+/// ```
+///
+/// Groups without an id get one derived from their name, so that adding a
+/// file never renumbers the others.
+/// Secret values are hidden like Azure DevOps hides them.
+pub struct DirectoryVariableGroupsClient {
+    root: PathBuf,
+}
+
+impl DirectoryVariableGroupsClient {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    fn project_dir(&self, organization: &str, project: &str) -> Result<PathBuf, ClientError> {
+        let dir = self.root.join(project);
+        if !dir.is_dir() {
+            return Err(ClientError::NotFound {
+                what: format!("Project `{organization}/{project}`"),
+            });
+        }
+        Ok(dir)
+    }
+
+    /// Every group file of the project with its id, sorted by name.
+    fn read_project(
+        &self,
+        organization: &str,
+        project: &str,
+    ) -> Result<Vec<StoredGroup>, ClientError> {
+        let dir = self.project_dir(organization, project)?;
+        let entries = fs::read_dir(&dir).map_err(|error| io_error(&dir, error))?;
+        let mut paths = entries
+            .filter_map(|entry| Some(entry.ok()?.path()))
+            .filter(|path| {
+                path.is_file()
+                    && path
+                        .extension()
+                        .is_some_and(|ext| EXTENSIONS.iter().any(|known| ext == *known))
+            })
+            .collect::<Vec<_>>();
+        paths.sort();
+
+        let mut groups = paths
+            .into_iter()
+            .map(StoredGroup::read)
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut taken = groups
+            .iter()
+            .filter_map(|g| g.file.id)
+            .collect::<HashSet<_>>();
+        for group in groups.iter_mut().filter(|g| g.file.id.is_none()) {
+            let id = derived_id(&group.name, &taken);
+            taken.insert(id);
+            group.file.id = Some(id);
+        }
+        Ok(groups)
+    }
+
+    fn find(
+        &self,
+        organization: &str,
+        project: &str,
+        group_id: i32,
+    ) -> Result<StoredGroup, ClientError> {
+        self.read_project(organization, project)?
+            .into_iter()
+            .find(|group| group.file.id == Some(group_id))
+            .ok_or_else(|| ClientError::NotFound {
+                what: format!("Variable group {group_id}"),
+            })
+    }
+}
+
+#[async_trait]
+impl VariableGroupsClient for DirectoryVariableGroupsClient {
+    async fn get_variable_groups_page(
+        &self,
+        organization: &str,
+        project: &str,
+        group_name: Option<String>,
+        _continuation_token: Option<i32>,
+    ) -> Result<VarGroupPage, ClientError> {
+        let groups = self
+            .read_project(organization, project)?
+            .into_iter()
+            .filter(|group| {
+                group_name
+                    .as_ref()
+                    .is_none_or(|pattern| name_matches(pattern, &group.name))
+            })
+            .map(|group| {
+                let variable_count = group.file.variables.len();
+                VarGroup {
+                    variables: Vec::new(),
+                    summary_only: true,
+                    variable_count,
+                    ..group.to_var_group()
+                }
+            })
+            .collect::<Vec<_>>();
+        Ok(VarGroupPage {
+            groups,
+            continuation_token: None,
+        })
+    }
+
+    async fn get_variable_group(
+        &self,
+        organization: &str,
+        project: &str,
+        group_id: i32,
+    ) -> Result<VarGroup, ClientError> {
+        Ok(self.find(organization, project, group_id)?.to_var_group())
+    }
+
+    async fn add_variable_group(
+        &self,
+        organization: &str,
+        project: &str,
+        group: VarGroup,
+    ) -> Result<VarGroup, ClientError> {
+        let existing = self.read_project(organization, project)?;
+        if existing.iter().any(|stored| stored.name == group.name) {
+            return Err(already_exists(&group.name));
+        }
+        let dir = self.project_dir(organization, project)?;
+        let taken = existing
+            .iter()
+            .filter_map(|g| g.file.id)
+            .collect::<HashSet<_>>();
+        let id = derived_id(&group.name, &taken);
+        let stored = StoredGroup {
+            path: dir.join(file_name(&group.name, EXTENSIONS[0])?),
+            name: group.name.clone(),
+            file: GroupFile {
+                id: Some(id),
+                modified_by: group.modified_by.clone(),
+                variables: BTreeMap::new(),
+            }
+            .with_variables(&group.variables, &BTreeMap::new()),
+        };
+        stored.write()?;
+        Ok(stored.to_var_group())
+    }
+
+    async fn update_variable_group(
+        &self,
+        organization: &str,
+        project: &str,
+        group: VarGroup,
+    ) -> Result<VarGroup, ClientError> {
+        let group_id = group.id.ok_or_else(|| ClientError::NotFound {
+            what: format!("Variable group `{}`", group.name),
+        })?;
+        let groups = self.read_project(organization, project)?;
+        if groups
+            .iter()
+            .any(|other| other.name == group.name && other.file.id != Some(group_id))
+        {
+            return Err(already_exists(&group.name));
+        }
+        let current = groups
+            .into_iter()
+            .find(|stored| stored.file.id == Some(group_id))
+            .ok_or_else(|| ClientError::NotFound {
+                what: format!("Variable group {group_id}"),
+            })?;
+        let extension = current
+            .path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or(EXTENSIONS[0]);
+        let updated = StoredGroup {
+            path: current
+                .path
+                .with_file_name(file_name(&group.name, extension)?),
+            name: group.name.clone(),
+            file: GroupFile {
+                id: Some(group_id),
+                modified_by: group.modified_by.clone(),
+                variables: BTreeMap::new(),
+            }
+            .with_variables(&group.variables, &current.file.variables),
+        };
+        updated.write()?;
+        if updated.path != current.path {
+            fs::remove_file(&current.path).map_err(|error| io_error(&current.path, error))?;
+        }
+        Ok(updated.to_var_group())
+    }
+
+    async fn delete_variable_group(
+        &self,
+        organization: &str,
+        project: &str,
+        group_id: i32,
+    ) -> Result<(), ClientError> {
+        let group = self.find(organization, project, group_id)?;
+        fs::remove_file(&group.path).map_err(|error| io_error(&group.path, error))
+    }
+
+    async fn get_library_audit_events(
+        &self,
+        _organization: &str,
+        _project: &str,
+        _start: SystemTime,
+        _end: SystemTime,
+    ) -> Result<Vec<AuditEvent>, ClientError> {
+        Err(ClientError::Unsupported {
+            what: "The audit log".to_string(),
+        })
+    }
+
+    /// The subdirectories of the root.
+    async fn get_projects(&self, _organization: &str) -> Result<Vec<String>, ClientError> {
+        let entries = fs::read_dir(&self.root).map_err(|error| io_error(&self.root, error))?;
+        let mut names = entries
+            .filter_map(Result::ok)
+            .filter(|entry| entry.path().is_dir())
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        names.sort();
+        Ok(names)
+    }
+
+    async fn get_shared_projects(
+        &self,
+        _organization: &str,
+        _project: &str,
+        _group_id: i32,
+    ) -> Result<Vec<String>, ClientError> {
+        Err(sharing_unsupported())
+    }
+
+    async fn share_variable_group(
+        &self,
+        _organization: &str,
+        _project: &str,
+        _group_id: i32,
+        _share: Vec<String>,
+        _unshare: Vec<String>,
+    ) -> Result<(), ClientError> {
+        Err(sharing_unsupported())
+    }
+
+    async fn get_pipelines(
+        &self,
+        _organization: &str,
+        _project: &str,
+    ) -> Result<Vec<Pipeline>, ClientError> {
+        Err(pipelines_unsupported())
+    }
+
+    async fn get_pipeline_permissions(
+        &self,
+        _organization: &str,
+        _project: &str,
+        _group_id: i32,
+    ) -> Result<PipelinePermissions, ClientError> {
+        Err(pipelines_unsupported())
+    }
+
+    async fn set_pipeline_permissions(
+        &self,
+        _organization: &str,
+        _project: &str,
+        _group_id: i32,
+        _changes: PermissionChanges,
+    ) -> Result<(), ClientError> {
+        Err(pipelines_unsupported())
+    }
+
+    async fn get_checks(
+        &self,
+        _organization: &str,
+        _project: &str,
+        _group_id: i32,
+    ) -> Result<Vec<Check>, ClientError> {
+        Err(checks_unsupported())
+    }
+
+    async fn add_approval_check(
+        &self,
+        _organization: &str,
+        _project: &str,
+        _group_id: i32,
+        _approvers: Vec<String>,
+    ) -> Result<Check, ClientError> {
+        Err(checks_unsupported())
+    }
+
+    async fn remove_check(
+        &self,
+        _organization: &str,
+        _project: &str,
+        _check_id: i32,
+    ) -> Result<(), ClientError> {
+        Err(checks_unsupported())
+    }
+}
+
+/// A group file as written on disk. The name of the group is the file name.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct GroupFile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    modified_by: Option<String>,
+    #[serde(default)]
+    variables: BTreeMap<String, StoredVar>,
+}
+
+/// A variable is either just its value, or a map that can mark it secret.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+enum StoredVar {
+    Detailed {
+        #[serde(default)]
+        value: Value,
+        #[serde(default)]
+        secret: bool,
+    },
+    Plain(Value),
+}
+
+impl StoredVar {
+    fn value(&self) -> &Value {
+        match self {
+            Self::Detailed { value, .. } | Self::Plain(value) => value,
+        }
+    }
+
+    fn is_secret(&self) -> bool {
+        matches!(self, Self::Detailed { secret: true, .. })
+    }
+}
+
+impl GroupFile {
+    /// Sets the variables. Secrets still holding the placeholder keep the
+    /// value stored in `previous`.
+    fn with_variables(
+        mut self,
+        variables: &[VarEntry],
+        previous: &BTreeMap<String, StoredVar>,
+    ) -> Self {
+        self.variables = variables
+            .iter()
+            .map(|var| {
+                let stored = if !var.is_secret {
+                    StoredVar::Plain(Value::String(var.value.clone()))
+                } else if var.value == SECRET_PLACEHOLDER {
+                    StoredVar::Detailed {
+                        value: previous
+                            .get(&var.name)
+                            .map(|old| old.value().clone())
+                            .unwrap_or_default(),
+                        secret: true,
+                    }
+                } else {
+                    StoredVar::Detailed {
+                        value: Value::String(var.value.clone()),
+                        secret: true,
+                    }
+                };
+                (var.name.clone(), stored)
+            })
+            .collect();
+        self
+    }
+}
+
+struct StoredGroup {
+    path: PathBuf,
+    name: String,
+    file: GroupFile,
+}
+
+impl StoredGroup {
+    fn read(path: PathBuf) -> Result<Self, ClientError> {
+        let text = fs::read_to_string(&path).map_err(|error| io_error(&path, error))?;
+        // JSON is valid YAML, so one parser reads both.
+        let file =
+            serde_yaml::from_str::<GroupFile>(&text).map_err(|error| ClientError::Unexpected {
+                message: format!("{}: {error}", path.display()),
+            })?;
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Ok(Self { path, name, file })
+    }
+
+    fn write(&self) -> Result<(), ClientError> {
+        let text = if self.path.extension().is_some_and(|ext| ext == "json") {
+            serde_json::to_string_pretty(&self.file).map_err(ClientError::from)
+        } else {
+            serde_yaml::to_string(&self.file).map_err(|error| ClientError::Unexpected {
+                message: error.to_string(),
+            })
+        }?;
+        // Write then rename so that a crash never leaves a truncated group.
+        let draft = self.path.with_extension("tmp");
+        fs::write(&draft, text).map_err(|error| io_error(&draft, error))?;
+        fs::rename(&draft, &self.path).map_err(|error| io_error(&self.path, error))
+    }
+
+    fn to_var_group(&self) -> VarGroup {
+        let variables = self
+            .file
+            .variables
+            .iter()
+            .map(|(name, var)| VarEntry {
+                name: name.clone(),
+                value: if var.is_secret() {
+                    SECRET_PLACEHOLDER.to_string()
+                } else {
+                    scalar_text(var.value())
+                },
+                is_secret: var.is_secret(),
+            })
+            .collect();
+        VarGroup {
+            id: self.file.id,
+            name: self.name.clone(),
+            variables,
+            modified_by: self.file.modified_by.clone(),
+            fingerprint: serde_json::to_value(&self.file.variables)
+                .ok()
+                .as_ref()
+                .map(fingerprint),
+            ..VarGroup::default()
+        }
+    }
+}
+
+/// Numbers and booleans are accepted unquoted, as values are strings anyway.
+fn scalar_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        Value::Number(number) => number.to_string(),
+        Value::Bool(flag) => flag.to_string(),
+        Value::Null => String::new(),
+        other => serde_yaml::to_string(other)
+            .unwrap_or_default()
+            .trim_end()
+            .to_string(),
+    }
+}
+
+fn file_name(group: &str, extension: &str) -> Result<String, ClientError> {
+    if group.is_empty() || group.contains(['/', '\\']) || group.starts_with('.') {
+        return Err(ClientError::Unexpected {
+            message: format!("`{group}` cannot be used as a file name"),
+        });
+    }
+    Ok(format!("{group}.{extension}"))
+}
+
+fn sharing_unsupported() -> ClientError {
+    ClientError::Unsupported {
+        what: "Sharing groups with other projects".to_string(),
+    }
+}
+
+fn pipelines_unsupported() -> ClientError {
+    ClientError::Unsupported {
+        what: "Pipeline permissions".to_string(),
+    }
+}
+
+fn checks_unsupported() -> ClientError {
+    ClientError::Unsupported {
+        what: "Approvals and checks".to_string(),
+    }
+}
+
+/// A stable id for the group `name`, unless another group has it already.
+fn derived_id(name: &str, taken: &HashSet<i32>) -> i32 {
+    let hash = name.bytes().fold(0x811c_9dc5_u32, |hash, byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193)
+    });
+    let range = (i32::MAX - DERIVED_IDS) as u32;
+    let mut offset = hash % range;
+    while taken.contains(&(DERIVED_IDS + offset as i32)) {
+        offset = (offset + 1) % range;
+    }
+    DERIVED_IDS + offset as i32
+}
+
+fn already_exists(group: &str) -> ClientError {
+    ClientError::Unexpected {
+        message: format!("a variable group named `{group}` already exists"),
+    }
+}
+
+fn io_error(path: &Path, error: std::io::Error) -> ClientError {
+    ClientError::Unexpected {
+        message: format!("{}: {error}", path.display()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client() -> (tempfile::TempDir, DirectoryVariableGroupsClient) {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("app");
+        fs::create_dir(&project).unwrap();
+        fs::write(
+            project.join("app-prod.yaml"),
+            "id: 3\nmodified_by: alice\nvariables:\n  PORT: 8080\n  TOKEN:\n    value: hunter2\n    secret: true\n",
+        )
+        .unwrap();
+        fs::write(
+            project.join("app-dev.json"),
+            r#"{"variables": {"DEBUG": true}}"#,
+        )
+        .unwrap();
+        let client = DirectoryVariableGroupsClient::new(dir.path().to_path_buf());
+        (dir, client)
+    }
+
+    #[tokio::test]
+    async fn files_are_read_as_groups() {
+        let (_dir, client) = client();
+
+        let groups = client
+            .get_variable_groups("org", "app", None)
+            .await
+            .unwrap();
+        let dev_id = derived_id("app-dev", &HashSet::new());
+        let names = groups
+            .iter()
+            .map(|g| (g.name.as_str(), g.id))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec![("app-dev", Some(dev_id)), ("app-prod", Some(3))]
+        );
+        assert!(groups.iter().all(|g| g.summary_only));
+        assert_eq!(groups[1].var_count(), 2);
+
+        let prod = client.get_variable_group("org", "app", 3).await.unwrap();
+        assert_eq!(prod.variables[0].value, "8080");
+        assert_eq!(prod.variables[1].value, SECRET_PLACEHOLDER);
+        assert_eq!(prod.modified_by.as_deref(), Some("alice"));
+
+        let filtered = client
+            .get_variable_groups("org", "app", Some("*-dev"))
+            .await
+            .unwrap();
+        assert_eq!(filtered.len(), 1);
+        assert!(matches!(
+            client.get_variable_groups("org", "web", None).await,
+            Err(ClientError::NotFound { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn ids_without_a_file_id_stay_put_as_files_are_added() {
+        let (dir, client) = client();
+        let ids = || async {
+            client
+                .get_variable_groups("org", "app", None)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|g| (g.name, g.id.unwrap()))
+                .collect::<BTreeMap<_, _>>()
+        };
+        let before = ids().await;
+        fs::write(
+            dir.path().join("app").join("app-ci.yaml"),
+            "variables:\n  CI: true\n",
+        )
+        .unwrap();
+        let after = ids().await;
+        assert_eq!(after["app-dev"], before["app-dev"]);
+        assert_eq!(after["app-prod"], 3);
+        assert_ne!(after["app-ci"], after["app-dev"]);
+
+        let taken = HashSet::from([derived_id("app-ci", &HashSet::new())]);
+        assert_ne!(derived_id("app-ci", &taken), after["app-ci"]);
+    }
+
+    #[tokio::test]
+    async fn groups_are_added_updated_and_deleted() {
+        let (dir, client) = client();
+
+        let mut prod = client.get_variable_group("org", "app", 3).await.unwrap();
+        prod.name = "app-live".into();
+        prod.variables[0].value = "443".into();
+        client
+            .update_variable_group("org", "app", prod)
+            .await
+            .unwrap();
+        let text = fs::read_to_string(dir.path().join("app").join("app-live.yaml")).unwrap();
+        assert!(text.contains("hunter2"), "{text}");
+        assert!(!dir.path().join("app").join("app-prod.yaml").exists());
+
+        let added = client
+            .add_variable_group(
+                "org",
+                "app",
+                VarGroup {
+                    name: "app-test".into(),
+                    variables: vec![VarEntry {
+                        name: "URL".into(),
+                        value: "https://test.example.com".into(),
+                        is_secret: false,
+                    }],
+                    ..VarGroup::default()
+                },
+            )
+            .await
+            .unwrap();
+        let test_id = derived_id("app-test", &HashSet::new());
+        assert_eq!(added.id, Some(test_id));
+        assert!(matches!(
+            client
+                .add_variable_group(
+                    "org",
+                    "app",
+                    VarGroup {
+                        name: "app-test".into(),
+                        ..VarGroup::default()
+                    }
+                )
+                .await,
+            Err(ClientError::Unexpected { .. })
+        ));
+
+        client
+            .delete_variable_group("org", "app", test_id)
+            .await
+            .unwrap();
+        let names = client
+            .get_variable_groups("org", "app", None)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|g| g.name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["app-dev", "app-live"]);
+    }
+}
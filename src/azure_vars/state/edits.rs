@@ -0,0 +1,233 @@
+use super::{
+    diff::{VarChange, var_changes},
+    state::VarEntry,
+};
+
+/// One staged change to the variables of a group.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EditOp {
+    /// Adds the variable, or replaces the one with the same name.
+    Set(VarEntry),
+    Remove(String),
+}
+
+impl EditOp {
+    fn apply(&self, variables: &mut Vec<VarEntry>) {
+        match self {
+            EditOp::Set(var) => match variables.iter_mut().find(|v| v.name == var.name) {
+                Some(existing) => *existing = var.clone(),
+                None => variables.push(var.clone()),
+            },
+            EditOp::Remove(name) => variables.retain(|v| &v.name != name),
+        }
+    }
+
+    /// The name of the variable the operation changes.
+    pub fn name(&self) -> &str {
+        match self {
+            EditOp::Set(var) => &var.name,
+            EditOp::Remove(name) => name,
+        }
+    }
+
+    /// Describes the operation for the status bar, e.g. `set API_URL`.
+    pub fn describe(&self) -> String {
+        match self {
+            EditOp::Set(var) => format!("set {}", var.name),
+            EditOp::Remove(name) => format!("remove {name}"),
+        }
+    }
+}
+
+/// An operation with the variable it changes as fetched when it was staged.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct StagedOp {
+    op: EditOp,
+    base: Option<VarEntry>,
+}
+
+/// A staged change to a variable that someone else changed since.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+    pub name: String,
+    /// The variable as last fetched, `None` if it was removed.
+    pub theirs: Option<VarEntry>,
+    /// The variable as staged, `None` if it is removed.
+    pub ours: Option<VarEntry>,
+}
+
+/// Changes to the variables of a group that are not saved yet. They are kept
+/// as operations on top of the variables as last fetched, so that a refresh
+/// which brings a newer version keeps them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StagedEdits {
+    base: Vec<VarEntry>,
+    ops: Vec<StagedOp>,
+    /// Undone operations, most recent last, until another one is staged.
+    undone: Vec<StagedOp>,
+    /// `base` with `ops` applied.
+    variables: Vec<VarEntry>,
+}
+
+impl StagedEdits {
+    pub fn new(base: &[VarEntry]) -> Self {
+        Self {
+            base: base.to_vec(),
+            variables: base.to_vec(),
+            ..Self::default()
+        }
+    }
+
+    /// The variables with every staged operation applied.
+    pub fn variables(&self) -> &[VarEntry] {
+        &self.variables
+    }
+
+    pub fn changes(&self) -> Vec<VarChange> {
+        var_changes(&self.base, &self.variables)
+    }
+
+    pub fn has_changes(&self) -> bool {
+        self.base != self.variables
+    }
+
+    pub fn push(&mut self, op: EditOp) {
+        op.apply(&mut self.variables);
+        let base = find(&self.base, op.name());
+        self.ops.push(StagedOp { op, base });
+        self.undone.clear();
+    }
+
+    /// Takes back the last operation, returning it.
+    pub(super) fn undo(&mut self) -> Option<&EditOp> {
+        let staged = self.ops.pop()?;
+        self.undone.push(staged);
+        self.reapply();
+        self.undone.last().map(|staged| &staged.op)
+    }
+
+    /// Stages the last undone operation again, returning it.
+    pub(super) fn redo(&mut self) -> Option<&EditOp> {
+        let staged = self.undone.pop()?;
+        staged.op.apply(&mut self.variables);
+        self.ops.push(staged);
+        self.ops.last().map(|staged| &staged.op)
+    }
+
+    /// The staged variables that someone else changed, or removed, since
+    /// they were staged, unless to what is staged.
+    pub fn conflicts(&self) -> Vec<Conflict> {
+        let mut conflicts = Vec::new();
+        for staged in self.ops.iter().rev() {
+            let name = staged.op.name();
+            if conflicts.iter().any(|c: &Conflict| c.name == name) {
+                continue;
+            }
+            let theirs = find(&self.base, name);
+            let ours = find(&self.variables, name);
+            if theirs != staged.base && theirs != ours {
+                conflicts.push(Conflict {
+                    name: name.to_string(),
+                    theirs,
+                    ours,
+                });
+            }
+        }
+        conflicts.reverse();
+        conflicts
+    }
+
+    /// Keeps the staged changes over the ones that conflict with them, so
+    /// that saving overwrites those.
+    pub(super) fn accept_conflicts(&mut self) {
+        for staged in &mut self.ops {
+            staged.base = find(&self.base, staged.op.name());
+        }
+    }
+
+    /// Applies the operations to a newer version of the variables.
+    pub(super) fn rebase(&mut self, base: &[VarEntry]) {
+        if self.base != base {
+            self.base = base.to_vec();
+            self.reapply();
+        }
+    }
+
+    fn reapply(&mut self) {
+        self.variables = self.base.clone();
+        for staged in &self.ops {
+            staged.op.apply(&mut self.variables);
+        }
+    }
+}
+
+fn find(variables: &[VarEntry], name: &str) -> Option<VarEntry> {
+    variables.iter().find(|var| var.name == name).cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn var(name: &str, value: &str) -> VarEntry {
+        VarEntry {
+            name: name.into(),
+            value: value.into(),
+            is_secret: false,
+        }
+    }
+
+    #[test]
+    fn undo_and_redo_walk_the_staged_operations() {
+        let mut edits = StagedEdits::new(&[var("URL", "one"), var("OLD", "x")]);
+        edits.push(EditOp::Set(var("URL", "two")));
+        edits.push(EditOp::Remove("OLD".into()));
+        edits.push(EditOp::Set(var("NEW", "y")));
+        assert_eq!(edits.variables(), [var("URL", "two"), var("NEW", "y")]);
+
+        assert_eq!(edits.undo(), Some(&EditOp::Set(var("NEW", "y"))));
+        assert_eq!(edits.undo(), Some(&EditOp::Remove("OLD".into())));
+        assert_eq!(edits.variables(), [var("URL", "two"), var("OLD", "x")]);
+        assert_eq!(edits.redo(), Some(&EditOp::Remove("OLD".into())));
+        assert_eq!(edits.variables(), [var("URL", "two")]);
+
+        // A new operation forgets what was undone.
+        edits.push(EditOp::Set(var("URL", "three")));
+        assert_eq!(edits.redo(), None);
+        while edits.undo().is_some() {}
+        assert!(!edits.has_changes());
+    }
+
+    #[test]
+    fn operations_survive_a_newer_version() {
+        let mut edits = StagedEdits::new(&[var("URL", "one")]);
+        edits.push(EditOp::Set(var("URL", "two")));
+        edits.rebase(&[var("URL", "one"), var("ADDED", "z")]);
+        assert_eq!(edits.variables(), [var("URL", "two"), var("ADDED", "z")]);
+        assert_eq!(edits.changes().len(), 1);
+        assert!(edits.conflicts().is_empty());
+    }
+
+    #[test]
+    fn changes_made_since_staging_conflict() {
+        let mut edits = StagedEdits::new(&[var("URL", "one"), var("PORT", "80"), var("OLD", "x")]);
+        edits.push(EditOp::Set(var("URL", "two")));
+        edits.push(EditOp::Set(var("PORT", "443")));
+        edits.push(EditOp::Remove("OLD".into()));
+
+        // Someone else changed URL, set PORT to the same value and removed OLD.
+        edits.rebase(&[var("URL", "three"), var("PORT", "443")]);
+        assert_eq!(
+            edits.conflicts(),
+            [Conflict {
+                name: "URL".into(),
+                theirs: Some(var("URL", "three")),
+                ours: Some(var("URL", "two")),
+            }]
+        );
+
+        edits.accept_conflicts();
+        assert!(edits.conflicts().is_empty());
+        assert_eq!(edits.variables(), [var("URL", "two"), var("PORT", "443")]);
+    }
+}
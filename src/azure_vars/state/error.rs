@@ -0,0 +1,260 @@
+use std::{
+    fmt, io,
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
+
+use azure_core::error::{Error as AzureError, ErrorKind};
+use azure_core::time::parse_rfc7231;
+
+/// Why a request to Azure DevOps failed, after any retries.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ClientError {
+    #[error("Not signed in to Azure DevOps ({message}). Run `az login` and try again")]
+    Auth { message: String },
+
+    #[error("{what} not found")]
+    NotFound { what: String },
+
+    #[error("Permission denied ({message}). Ask a project administrator for access to the library")]
+    PermissionDenied { message: String },
+
+    #[error("Azure DevOps is throttling requests{}", retry_hint(*retry_after))]
+    Throttled { retry_after: Option<Duration> },
+
+    #[error("Could not reach Azure DevOps ({message}). Check the network connection")]
+    Network { message: String },
+
+    #[error("Azure DevOps failed with status {status} ({message}). Try again later")]
+    Server { status: u16, message: String },
+
+    #[error("Unexpected response from Azure DevOps: {message}")]
+    Unexpected { message: String },
+
+    #[error("Azure DevOps is not contacted in offline mode")]
+    Offline,
+
+    #[error("{what} is not available with this backend")]
+    Unsupported { what: String },
+}
+
+fn retry_hint(retry_after: Option<Duration>) -> String {
+    match retry_after {
+        Some(wait) => format!(", try again in {}s", wait.as_secs().max(1)),
+        None => ", try again later".to_string(),
+    }
+}
+
+impl ClientError {
+    /// The exit code of the command line, stable across releases so that
+    /// scripts can tell failures apart. 2 is taken by usage errors.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::Auth { .. } => 3,
+            Self::PermissionDenied { .. } => 4,
+            Self::NotFound { .. } => 5,
+            Self::Throttled { .. } => 6,
+            Self::Network { .. } => 7,
+            Self::Server { .. } | Self::Unexpected { .. } => 8,
+            Self::Offline | Self::Unsupported { .. } => 1,
+        }
+    }
+
+    /// Classifies an error status. `message` is the server's explanation if
+    /// it gave one.
+    pub fn from_status(
+        status: u16,
+        retry_after: Option<Duration>,
+        what: &str,
+        message: Option<String>,
+    ) -> Self {
+        let message = message.unwrap_or_else(|| format!("status {status}"));
+        match status {
+            // Azure DevOps answers requests it cannot authenticate with a
+            // sign-in page and 203.
+            401 | 203 => Self::Auth { message },
+            403 => Self::PermissionDenied { message },
+            404 => Self::NotFound {
+                what: what.to_string(),
+            },
+            429 => Self::Throttled { retry_after },
+            500..=599 => Self::Server { status, message },
+            _ => Self::Unexpected { message },
+        }
+    }
+
+    /// Classifies an error response from its status, `Retry-After` header
+    /// and body, which holds the server's explanation as JSON.
+    pub fn from_response(
+        status: u16,
+        retry_after: Option<&str>,
+        body: &[u8],
+        what: &str,
+        now: SystemTime,
+    ) -> Self {
+        let retry_after = retry_after.and_then(|value| parse_retry_after(value, now));
+        let message = serde_json::from_slice::<serde_json::Value>(body)
+            .ok()
+            .and_then(|error| Some(error.get("message")?.as_str()?.to_string()));
+        Self::from_status(status, retry_after, what, message)
+    }
+}
+
+/// The wait a `Retry-After` header asks for, given in seconds or as an HTTP
+/// date. A date in the past asks for no wait.
+pub fn parse_retry_after(value: &str, now: SystemTime) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let until = SystemTime::from(parse_rfc7231(value).ok()?);
+    Some(until.duration_since(now).unwrap_or_default())
+}
+
+impl From<AzureError> for ClientError {
+    fn from(error: AzureError) -> Self {
+        match error.kind() {
+            ErrorKind::HttpResponse {
+                status, error_code, ..
+            } => Self::from_status(u16::from(*status), None, "Resource", error_code.clone()),
+            ErrorKind::Credential => Self::Auth {
+                message: error.to_string(),
+            },
+            ErrorKind::Io => Self::Network {
+                message: error.to_string(),
+            },
+            _ => Self::Unexpected {
+                message: error.to_string(),
+            },
+        }
+    }
+}
+
+impl From<serde_json::Error> for ClientError {
+    fn from(error: serde_json::Error) -> Self {
+        Self::Unexpected {
+            message: error.to_string(),
+        }
+    }
+}
+
+/// The files kept besides the configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StoreFile {
+    Cache,
+    Snapshot,
+    Backup,
+    Journal,
+}
+
+impl fmt::Display for StoreFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Cache => "cache",
+            Self::Snapshot => "snapshot",
+            Self::Backup => "backup",
+            Self::Journal => "history journal",
+        })
+    }
+}
+
+/// Why a cache, snapshot, backup or history journal could not be read or
+/// written.
+#[derive(Debug, thiserror::Error)]
+pub enum StoreError {
+    #[error("Failed to access the {file} {}: {source}", path.display())]
+    Io {
+        file: StoreFile,
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    #[error("Invalid {file} {}: {message}", path.display())]
+    Invalid {
+        file: StoreFile,
+        path: PathBuf,
+        message: String,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn statuses_map_to_variants_and_exit_codes() {
+        let error = |status| ClientError::from_status(status, None, "Variable group 7", None);
+
+        assert!(matches!(error(401), ClientError::Auth { .. }));
+        assert!(matches!(error(203), ClientError::Auth { .. }));
+        assert_eq!(error(403).exit_code(), 4);
+        assert_eq!(error(404).to_string(), "Variable group 7 not found");
+        assert_eq!(error(503).exit_code(), 8);
+        assert_eq!(error(400).exit_code(), 8);
+
+        let throttled =
+            ClientError::from_status(429, Some(Duration::from_secs(30)), "Variable groups", None);
+        assert_eq!(throttled.exit_code(), 6);
+        assert_eq!(
+            throttled.to_string(),
+            "Azure DevOps is throttling requests, try again in 30s"
+        );
+    }
+
+    #[test]
+    fn responses_map_to_exit_codes() {
+        let now = SystemTime::UNIX_EPOCH;
+        let response = |status, body: &str| {
+            ClientError::from_response(status, None, body.as_bytes(), "Variable group 7", now)
+        };
+        let codes = [401, 203, 403, 404, 429, 500, 503, 400, 409]
+            .map(|status| response(status, "").exit_code());
+        assert_eq!(codes, [3, 3, 4, 5, 6, 8, 8, 8, 8]);
+        let network = AzureError::message(ErrorKind::Io, "connection reset");
+        assert_eq!(ClientError::from(network).exit_code(), 7);
+
+        assert_eq!(
+            response(403, r#"{"message": "TF401019: no access", "typeKey": "x"}"#),
+            ClientError::PermissionDenied {
+                message: "TF401019: no access".into()
+            }
+        );
+        assert_eq!(
+            response(500, "<html>oops</html>"),
+            ClientError::Server {
+                status: 500,
+                message: "status 500".into()
+            }
+        );
+    }
+
+    #[test]
+    fn retry_after_is_read_as_seconds_or_a_date() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(784111777);
+        let throttled = |retry_after| {
+            ClientError::from_response(429, Some(retry_after), b"", "Variable groups", now)
+        };
+        assert_eq!(
+            throttled(" 120 "),
+            ClientError::Throttled {
+                retry_after: Some(Duration::from_secs(120))
+            }
+        );
+        // 1994-11-06T08:49:37Z, 20 seconds after `now`.
+        assert_eq!(
+            throttled("Sun, 06 Nov 1994 08:49:57 GMT"),
+            ClientError::Throttled {
+                retry_after: Some(Duration::from_secs(20))
+            }
+        );
+        assert_eq!(
+            parse_retry_after("Sun, 06 Nov 1994 08:49:00 GMT", now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(
+            throttled("soon"),
+            ClientError::Throttled { retry_after: None }
+        );
+    }
+}
@@ -0,0 +1,347 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use azure_core::{
+    Bytes,
+    error::{Error as AzureError, ErrorKind},
+    http::{BufResponse, HttpClient, Request, StatusCode, Url, headers::Headers},
+};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::cache::SECRET_PLACEHOLDER;
+use crate::config::error::ConfigError;
+
+/// Response headers kept in fixtures. The others describe the session or
+/// the server rather than the answer.
+const KEPT_HEADERS: [&str; 3] = ["content-type", "x-ms-continuationtoken", "retry-after"];
+
+/// Stored in place of names and ids of people.
+const REDACTED: &str = "<redacted>";
+
+/// Stored in place of the scheme and host of the server.
+const REDACTED_ORIGIN: &str = "https://azure-devops.invalid";
+
+/// Where the HTTP transport takes its responses from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FixtureMode {
+    /// Sends requests to Azure DevOps and saves the redacted responses.
+    Record(PathBuf),
+    /// Answers requests from a fixture file without contacting Azure DevOps.
+    Replay(PathBuf),
+}
+
+/// One recorded response.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Fixture {
+    pub method: String,
+    /// The request path from `_apis/` on, with its query. The server,
+    /// organization and project are left out so that fixtures replay
+    /// against any of them.
+    pub request: String,
+    pub status: u16,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    /// The body as JSON, or as a string if it is not JSON.
+    #[serde(default)]
+    pub body: Value,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct FixtureFile {
+    responses: Vec<Fixture>,
+}
+
+impl Fixture {
+    fn record(request: &Request, status: StatusCode, headers: &Headers, body: &[u8]) -> Self {
+        let origin = request.url().origin().ascii_serialization();
+        let mut body = serde_json::from_slice(body)
+            .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(body).into_owned()));
+        redact(&mut body, &origin);
+        Self {
+            method: request.method().as_str().to_string(),
+            request: api_path(request.url()),
+            status: u16::from(status),
+            headers: headers
+                .iter()
+                .filter(|(name, _)| KEPT_HEADERS.contains(&name.as_str()))
+                .map(|(name, value)| (name.as_str().to_string(), value.as_str().to_string()))
+                .collect(),
+            body,
+        }
+    }
+
+    fn response(&self) -> BufResponse {
+        let mut headers = Headers::new();
+        for (name, value) in &self.headers {
+            headers.insert(name.clone(), value.clone());
+        }
+        let body = match &self.body {
+            Value::Null => Vec::new(),
+            Value::String(text) => text.clone().into_bytes(),
+            json => json.to_string().into_bytes(),
+        };
+        BufResponse::from_bytes(StatusCode::from(self.status), headers, Bytes::from(body))
+    }
+}
+
+/// The path from `_apis/` on, with the query decoded and sorted so that
+/// hand-written fixtures need not follow the generated client's order.
+fn api_path(url: &Url) -> String {
+    let path = url.path();
+    let path = path.find("/_apis/").map_or(path, |idx| &path[idx + 1..]);
+    let mut query = url
+        .query_pairs()
+        .map(|(name, value)| format!("{name}={value}"))
+        .collect::<Vec<_>>();
+    if query.is_empty() {
+        return path.to_string();
+    }
+    query.sort();
+    format!("{path}?{}", query.join("&"))
+}
+
+/// Hides secret values, the people who changed a group and the server
+/// address. Organization and project names inside URLs are kept.
+fn redact(value: &mut Value, origin: &str) {
+    match value {
+        Value::Object(map) => {
+            let is_secret = map.get("isSecret").and_then(Value::as_bool) == Some(true);
+            let is_identity = map.contains_key("uniqueName") || map.contains_key("descriptor");
+            for (key, field) in map.iter_mut() {
+                match field {
+                    Value::String(_) if is_secret && key == "value" => {
+                        *field = Value::String(SECRET_PLACEHOLDER.to_string());
+                    }
+                    Value::String(_) if is_identity => {
+                        *field = Value::String(REDACTED.to_string());
+                    }
+                    _ => redact(field, origin),
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(|item| redact(item, origin)),
+        Value::String(text) if text.contains(origin) => {
+            *text = text.replace(origin, REDACTED_ORIGIN);
+        }
+        _ => {}
+    }
+}
+
+fn write_fixtures(path: &Path, responses: &[Fixture]) -> Result<(), ConfigError> {
+    let io_error = |source| ConfigError::Io {
+        path: path.to_path_buf(),
+        source,
+    };
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        fs::create_dir_all(parent).map_err(io_error)?;
+    }
+    let file = FixtureFile {
+        responses: responses.to_vec(),
+    };
+    let json = serde_json::to_string_pretty(&file).map_err(|error| ConfigError::Parse {
+        path: path.to_path_buf(),
+        line: None,
+        column: None,
+        message: error.to_string(),
+    })?;
+    let draft = path.with_extension("json.tmp");
+    fs::write(&draft, json).map_err(io_error)?;
+    fs::rename(&draft, path).map_err(io_error)
+}
+
+/// Passes requests on to `inner` and saves every response to a fixture
+/// file, rewritten after each one so that an interrupted session still
+/// leaves a usable file.
+#[derive(Debug)]
+pub struct RecordingClient {
+    inner: Arc<dyn HttpClient>,
+    path: PathBuf,
+    responses: Mutex<Vec<Fixture>>,
+}
+
+impl RecordingClient {
+    pub fn new(inner: Arc<dyn HttpClient>, path: PathBuf) -> Self {
+        Self {
+            inner,
+            path,
+            responses: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+#[async_trait]
+impl HttpClient for RecordingClient {
+    async fn execute_request(&self, request: &Request) -> azure_core::Result<BufResponse> {
+        let (status, headers, body) = self.inner.execute_request(request).await?.deconstruct();
+        let body = body.collect().await?;
+        let fixture = Fixture::record(request, status, &headers, &body);
+        let mut responses = self.responses.lock().unwrap();
+        responses.push(fixture);
+        if let Err(error) = write_fixtures(&self.path, &responses) {
+            warn!("Failed to record the response: {error}");
+        }
+        Ok(BufResponse::from_bytes(status, headers, body))
+    }
+}
+
+/// Answers requests from a fixture file. A request recorded several times
+/// gets the responses in recorded order, then the last one again.
+#[derive(Debug)]
+pub struct ReplayClient {
+    responses: Vec<Fixture>,
+    served: Mutex<HashMap<(String, String), usize>>,
+}
+
+impl ReplayClient {
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let text = fs::read_to_string(path).map_err(|source| ConfigError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let file: FixtureFile =
+            serde_json::from_str(&text).map_err(|error| ConfigError::Parse {
+                path: path.to_path_buf(),
+                line: Some(error.line()),
+                column: Some(error.column()),
+                message: "expected recorded Azure DevOps responses".into(),
+            })?;
+        Ok(Self::new(file.responses))
+    }
+
+    pub fn new(responses: Vec<Fixture>) -> Self {
+        Self {
+            responses,
+            served: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn find(&self, method: &str, request: &str) -> Option<&Fixture> {
+        let matching = self
+            .responses
+            .iter()
+            .filter(|fixture| fixture.method == method && fixture.request == request)
+            .collect::<Vec<_>>();
+        let mut served = self.served.lock().unwrap();
+        let count = served
+            .entry((method.to_string(), request.to_string()))
+            .or_default();
+        let fixture = matching.get(*count).or(matching.last()).copied();
+        *count += 1;
+        fixture
+    }
+}
+
+#[async_trait]
+impl HttpClient for ReplayClient {
+    async fn execute_request(&self, request: &Request) -> azure_core::Result<BufResponse> {
+        let method = request.method().as_str();
+        let path = api_path(request.url());
+        self.find(method, &path)
+            .map(Fixture::response)
+            .ok_or_else(|| {
+                AzureError::message(
+                    ErrorKind::Other,
+                    format!("No recorded response for {method} {path}"),
+                )
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn recorded_bodies_hide_secrets_people_and_the_server() {
+        let mut body = json!({
+            "value": [{
+                "name": "app-prod",
+                "createdBy": {
+                    "displayName": "Alice",
+                    "uniqueName": "alice@contoso.com",
+                    "id": "8f3c"
+                },
+                "variables": {
+                    "TOKEN": { "value": "hunter2", "isSecret": true },
+                    "URL": { "value": "https://dev.azure.com/contoso" }
+                }
+            }]
+        });
+        redact(&mut body, "https://dev.azure.com");
+
+        let group = &body["value"][0];
+        assert_eq!(group["createdBy"]["displayName"], REDACTED);
+        assert_eq!(group["createdBy"]["id"], REDACTED);
+        assert_eq!(group["variables"]["TOKEN"]["value"], SECRET_PLACEHOLDER);
+        assert_eq!(
+            group["variables"]["URL"]["value"],
+            "https://azure-devops.invalid/contoso"
+        );
+        assert_eq!(group["name"], "app-prod");
+    }
+
+    #[test]
+    fn requests_are_matched_without_server_or_project() {
+        let url = Url::parse(
+            "https://tfs.example.com/tfs/DefaultCollection/Web/_apis/distributedtask/variablegroups?groupName=app&api-version=7.1-preview&%24top=100",
+        )
+        .unwrap();
+        assert_eq!(
+            api_path(&url),
+            "_apis/distributedtask/variablegroups?$top=100&api-version=7.1-preview&groupName=app"
+        );
+
+        let fixture = |status| Fixture {
+            method: "GET".into(),
+            request: "_apis/distributedtask/variablegroups/7".into(),
+            status,
+            headers: BTreeMap::new(),
+            body: Value::Null,
+        };
+        let client = ReplayClient::new(vec![fixture(503), fixture(200)]);
+        let find = || client.find("GET", "_apis/distributedtask/variablegroups/7");
+        assert_eq!(find().unwrap().status, 503);
+        assert_eq!(find().unwrap().status, 200);
+        assert_eq!(find().unwrap().status, 200);
+        assert!(
+            client
+                .find("DELETE", "_apis/distributedtask/variablegroups/7")
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn fixture_files_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("fixtures").join("groups.json");
+        let fixture = Fixture {
+            method: "GET".into(),
+            request: "_apis/distributedtask/variablegroups".into(),
+            status: 200,
+            headers: BTreeMap::from([("content-type".into(), "application/json".into())]),
+            body: json!({ "count": 0, "value": [] }),
+        };
+        write_fixtures(&path, std::slice::from_ref(&fixture)).unwrap();
+
+        let client = ReplayClient::load(&path).unwrap();
+        assert_eq!(client.responses, vec![fixture]);
+
+        fs::write(&path, "[]").unwrap();
+        assert!(matches!(
+            ReplayClient::load(&path),
+            Err(ConfigError::Parse { .. })
+        ));
+    }
+}
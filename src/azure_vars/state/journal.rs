@@ -0,0 +1,309 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    io::{Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    cache::GroupCache,
+    error::{StoreError, StoreFile},
+    state::VarGroup,
+};
+
+/// Past this size the journal is moved aside to `<file>.1`, replacing the
+/// one moved aside before, so at most about twice as much is kept.
+const MAX_JOURNAL_BYTES: u64 = 8 * 1024 * 1024;
+
+/// One version of a group, as fetched.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    /// Seconds since the Unix epoch.
+    recorded_at: u64,
+    pub group: VarGroup,
+}
+
+impl JournalEntry {
+    pub fn recorded_at(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(self.recorded_at)
+    }
+}
+
+/// Every version of the groups of one organization and project fetched so
+/// far, one JSON entry per line, without secret values. Azure DevOps keeps no
+/// history of variable values itself.
+#[derive(Debug)]
+pub struct Journal {
+    path: PathBuf,
+    /// The last version recorded of each group, read on first use.
+    latest: Option<HashMap<String, VarGroup>>,
+    max_bytes: u64,
+}
+
+impl Journal {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            latest: None,
+            max_bytes: MAX_JOURNAL_BYTES,
+        }
+    }
+
+    /// Appends the groups that differ from their last recorded version.
+    /// Groups whose variables were not loaded are skipped. Returns how many
+    /// versions were recorded.
+    pub fn record(&mut self, groups: &[VarGroup], at: SystemTime) -> Result<usize, StoreError> {
+        let latest = match &mut self.latest {
+            Some(latest) => latest,
+            None => self.latest.insert(
+                read_entries(&self.path)
+                    .into_iter()
+                    .map(|entry| (key(&entry.group), entry.group))
+                    .collect(),
+            ),
+        };
+        let recorded_at = at.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        // The cache already knows how to leave out secret values.
+        let changed = GroupCache::new(groups, at)
+            .groups
+            .into_iter()
+            .filter(|group| !group.summary_only)
+            .filter(|group| {
+                latest
+                    .get(&key(group))
+                    .is_none_or(|last| last.name != group.name || last.variables != group.variables)
+            })
+            .map(|group| JournalEntry { recorded_at, group })
+            .collect::<Vec<_>>();
+        if changed.is_empty() {
+            return Ok(0);
+        }
+
+        let io_error = |source| StoreError::Io {
+            file: StoreFile::Journal,
+            path: self.path.clone(),
+            source,
+        };
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).map_err(io_error)?;
+        }
+        // Start on a new line if the last write was cut short.
+        let mut lines = if ends_mid_line(&self.path) {
+            String::from("\n")
+        } else {
+            String::new()
+        };
+        for entry in &changed {
+            let line = serde_json::to_string(entry).map_err(|error| StoreError::Invalid {
+                file: StoreFile::Journal,
+                path: self.path.clone(),
+                message: error.to_string(),
+            })?;
+            lines.push_str(&line);
+            lines.push('\n');
+        }
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| file.write_all(lines.as_bytes()))
+            .map_err(io_error)?;
+        if fs::metadata(&self.path).is_ok_and(|meta| meta.len() > self.max_bytes) {
+            fs::rename(&self.path, rotated(&self.path)).map_err(io_error)?;
+        }
+
+        for entry in &changed {
+            latest.insert(key(&entry.group), entry.group.clone());
+        }
+        Ok(changed.len())
+    }
+
+    /// The recorded versions of `group`, newest first, including those moved
+    /// aside. Groups are matched by id, so versions from before a rename are
+    /// included.
+    pub fn history(&self, group: &VarGroup) -> Vec<JournalEntry> {
+        let key = key(group);
+        let mut entries = read_entries(&self.path)
+            .into_iter()
+            .filter(|entry| self::key(&entry.group) == key)
+            .collect::<Vec<_>>();
+        entries.reverse();
+        entries
+    }
+}
+
+fn ends_mid_line(path: &Path) -> bool {
+    let mut last = *b"\n";
+    File::open(path)
+        .and_then(|mut file| {
+            file.seek(SeekFrom::End(-1))?;
+            file.read_exact(&mut last)
+        })
+        .is_ok_and(|()| last[0] != b'\n')
+}
+
+fn key(group: &VarGroup) -> String {
+    match group.id {
+        Some(id) => format!("#{id}"),
+        None => group.name.clone(),
+    }
+}
+
+fn rotated(path: &Path) -> PathBuf {
+    let mut rotated = path.as_os_str().to_owned();
+    rotated.push(".1");
+    rotated.into()
+}
+
+/// Reads every entry, oldest first, starting with those moved aside.
+fn read_entries(path: &Path) -> Vec<JournalEntry> {
+    let mut entries = read_file(&rotated(path));
+    entries.extend(read_file(path));
+    entries
+}
+
+/// Reads the entries of one file. Lines that cannot be read, such as one cut
+/// short by a crash, are skipped.
+fn read_file(path: &Path) -> Vec<JournalEntry> {
+    let Ok(text) = fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .filter_map(|(idx, line)| match serde_json::from_str(line) {
+            Ok(entry) => Some(entry),
+            Err(error) => {
+                warn!("Skipping line {} of {}: {error}", idx + 1, path.display());
+                None
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::azure_vars::state::{cache::SECRET_PLACEHOLDER, state::VarEntry};
+
+    fn group(name: &str, value: &str) -> VarGroup {
+        VarGroup {
+            id: Some(7),
+            name: name.into(),
+            variables: vec![
+                VarEntry {
+                    name: "URL".into(),
+                    value: value.into(),
+                    is_secret: false,
+                },
+                VarEntry {
+                    name: "TOKEN".into(),
+                    value: "hunter2".into(),
+                    is_secret: true,
+                },
+            ],
+            ..VarGroup::default()
+        }
+    }
+
+    #[test]
+    fn only_changed_versions_are_recorded() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history").join("org--project.jsonl");
+        let at = |secs| UNIX_EPOCH + Duration::from_secs(secs);
+        let summary = VarGroup {
+            id: Some(8),
+            name: "other".into(),
+            summary_only: true,
+            ..VarGroup::default()
+        };
+
+        let mut journal = Journal::new(path.clone());
+        let v1 = group("app", "https://one.example.com");
+        assert_eq!(journal.record(&[v1.clone(), summary], at(100)).unwrap(), 1);
+        assert_eq!(
+            journal.record(std::slice::from_ref(&v1), at(200)).unwrap(),
+            0
+        );
+        let renamed = group("app-prod", "https://two.example.com");
+        assert_eq!(
+            journal
+                .record(std::slice::from_ref(&renamed), at(300))
+                .unwrap(),
+            1
+        );
+        assert!(!fs::read_to_string(&path).unwrap().contains("hunter2"));
+
+        // A new journal reads what the last one recorded.
+        let mut journal = Journal::new(path);
+        assert_eq!(
+            journal
+                .record(std::slice::from_ref(&renamed), at(400))
+                .unwrap(),
+            0
+        );
+
+        let history = journal.history(&renamed);
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].group.name, "app-prod");
+        assert_eq!(history[0].recorded_at(), at(300));
+        assert_eq!(history[1].group.variables[1].value, SECRET_PLACEHOLDER);
+    }
+
+    #[test]
+    fn damaged_lines_are_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("org--project.jsonl");
+        let entry = JournalEntry {
+            recorded_at: 100,
+            group: group("app", "https://one.example.com"),
+        };
+        fs::write(
+            &path,
+            format!(
+                "{}\n{{\"recorded_at\": 2",
+                serde_json::to_string(&entry).unwrap()
+            ),
+        )
+        .unwrap();
+
+        let mut journal = Journal::new(path);
+        let next = group("app", "https://two.example.com");
+        assert_eq!(
+            journal
+                .record(std::slice::from_ref(&next), UNIX_EPOCH)
+                .unwrap(),
+            1
+        );
+        let history = journal.history(&entry.group);
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[1], entry);
+    }
+
+    #[test]
+    fn large_journals_are_moved_aside() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("org--project.jsonl");
+        let at = |secs| UNIX_EPOCH + Duration::from_secs(secs);
+        let version = |value: &str| group("app", &format!("https://{value}.example.com"));
+        let mut journal = Journal::new(path.clone());
+        journal.record(&[version("one")], at(100)).unwrap();
+        // Room for exactly one version.
+        journal.max_bytes = fs::metadata(&path).unwrap().len();
+
+        journal.record(&[version("two")], at(200)).unwrap();
+        assert!(!path.exists());
+        journal.record(&[version("six")], at(300)).unwrap();
+        assert_eq!(journal.history(&version("six")).len(), 3);
+
+        // Moving the journal aside again drops the oldest versions.
+        journal.record(&[version("ten")], at(400)).unwrap();
+        let history = journal.history(&version("ten"));
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[1].recorded_at(), at(300));
+    }
+}
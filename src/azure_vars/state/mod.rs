@@ -1,3 +1,21 @@
 pub mod action;
+pub mod audit;
+pub mod backup;
+pub mod cache;
+pub mod checks;
+pub mod connection;
+pub mod copy;
+pub mod diff;
+pub mod directory;
+pub mod edits;
+pub mod error;
+pub mod fixtures;
+pub mod journal;
+pub mod paging;
+pub mod permissions;
+pub mod promote;
+pub mod session;
+pub mod share;
 pub mod state;
 pub mod state_store;
+pub mod theme;
@@ -0,0 +1,89 @@
+use std::fmt;
+
+use log::warn;
+
+/// Follows the continuation tokens of a paged list. Paging ends when the
+/// server returns no token, returns the token it was given, or after the
+/// most pages allowed.
+#[derive(Debug)]
+pub struct Pages<T> {
+    /// Names the list in warnings.
+    what: String,
+    token: Option<T>,
+    read: usize,
+    max: Option<usize>,
+}
+
+impl<T: Clone + PartialEq + fmt::Display> Pages<T> {
+    pub fn new(what: impl Into<String>) -> Self {
+        Self {
+            what: what.into(),
+            token: None,
+            read: 0,
+            max: None,
+        }
+    }
+
+    /// Stops after `max` pages, with a warning.
+    pub fn at_most(self, max: usize) -> Self {
+        Self {
+            max: Some(max),
+            ..self
+        }
+    }
+
+    /// The token to request the next page with, `None` for the first.
+    pub fn token(&self) -> Option<T> {
+        self.token.clone()
+    }
+
+    /// Takes the token the page just read came with. Returns whether it was
+    /// the last page to read.
+    pub fn advance(&mut self, returned: Option<T>) -> bool {
+        self.read += 1;
+        match returned {
+            None => true,
+            Some(next) if self.token.as_ref() == Some(&next) => {
+                warn!(
+                    "Server returned the same continuation token {next} twice for {}",
+                    self.what
+                );
+                true
+            }
+            Some(_) if self.max == Some(self.read) => {
+                warn!("Stopped reading {} after {} pages", self.what, self.read);
+                true
+            }
+            next => {
+                self.token = next;
+                false
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paging_follows_tokens_until_none_is_returned() {
+        let mut pages = Pages::new("groups");
+        assert_eq!(pages.token(), None);
+        assert!(!pages.advance(Some(7)));
+        assert_eq!(pages.token(), Some(7));
+        assert!(!pages.advance(Some(3)));
+        assert!(pages.advance(None));
+    }
+
+    #[test]
+    fn paging_stops_on_a_repeated_token_or_at_the_limit() {
+        let mut pages = Pages::new("groups");
+        assert!(!pages.advance(Some(7)));
+        assert!(pages.advance(Some(7)));
+
+        let mut pages = Pages::new("the audit log").at_most(2);
+        assert!(!pages.advance(Some("a".to_string())));
+        assert!(pages.advance(Some("b".to_string())));
+    }
+}
@@ -0,0 +1,433 @@
+use std::{collections::BTreeSet, sync::Arc};
+
+use azure_devops_rust_api::approvals_and_checks::models::{
+    Permission, PipelinePermission, Resource, ResourcePipelinePermissions,
+};
+use log::{info, warn};
+use tokio::sync::mpsc::Sender;
+
+use super::{
+    action::Action,
+    error::ClientError,
+    state::{Overlay, PipelinesView, State, StatusMessage, VarGroup},
+    state_store::VariableGroupsClient,
+};
+
+/// The resource type pipeline permissions use for variable groups.
+pub const VARIABLE_GROUP_RESOURCE: &str = "variablegroup";
+
+/// A pipeline of the project.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pipeline {
+    pub id: i32,
+    pub name: String,
+    /// Such as `\` or `\deploy`.
+    pub folder: String,
+}
+
+impl Pipeline {
+    /// A pipeline known only by id, such as a deleted one still authorized.
+    pub fn unknown(id: i32) -> Self {
+        Self {
+            id,
+            name: format!("pipeline {id}"),
+            folder: String::new(),
+        }
+    }
+
+    /// The folder and name, as `deploy/web`.
+    pub fn path(&self) -> String {
+        let folder = self.folder.trim_matches('\\').replace('\\', "/");
+        if folder.is_empty() {
+            self.name.clone()
+        } else {
+            format!("{folder}/{}", self.name)
+        }
+    }
+}
+
+/// The pipelines allowed to use a group.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PipelinePermissions {
+    /// Every pipeline of the project may use the group.
+    pub all_pipelines: bool,
+    /// The ids of the pipelines authorized one by one.
+    pub authorized: BTreeSet<i32>,
+}
+
+impl From<ResourcePipelinePermissions> for PipelinePermissions {
+    fn from(permissions: ResourcePipelinePermissions) -> Self {
+        Self {
+            all_pipelines: permissions
+                .all_pipelines
+                .and_then(|permission| permission.authorized)
+                .unwrap_or(false),
+            authorized: permissions
+                .pipelines
+                .into_iter()
+                .filter(|pipeline| pipeline.permission.authorized == Some(true))
+                .filter_map(|pipeline| pipeline.id)
+                .collect(),
+        }
+    }
+}
+
+/// Changes to the pipelines allowed to use a group.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PermissionChanges {
+    /// Opens the group to every pipeline, or restricts it again.
+    pub all_pipelines: Option<bool>,
+    pub grant: Vec<i32>,
+    pub revoke: Vec<i32>,
+}
+
+impl PermissionChanges {
+    /// What it takes to go from `current` to `wanted`.
+    pub fn between(current: &PipelinePermissions, wanted: &PipelinePermissions) -> Self {
+        Self {
+            all_pipelines: (current.all_pipelines != wanted.all_pipelines)
+                .then_some(wanted.all_pipelines),
+            grant: wanted
+                .authorized
+                .difference(&current.authorized)
+                .copied()
+                .collect(),
+            revoke: current
+                .authorized
+                .difference(&wanted.authorized)
+                .copied()
+                .collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.all_pipelines.is_none() && self.grant.is_empty() && self.revoke.is_empty()
+    }
+
+    /// `permissions` once the changes are applied.
+    pub fn apply(&self, permissions: &mut PipelinePermissions) {
+        if let Some(all_pipelines) = self.all_pipelines {
+            permissions.all_pipelines = all_pipelines;
+        }
+        permissions.authorized.extend(&self.grant);
+        permissions
+            .authorized
+            .retain(|id| !self.revoke.contains(id));
+    }
+
+    /// The request body for the group `group_id`. Pipelines left out keep
+    /// their permission.
+    pub fn to_request(&self, group_id: i32) -> ResourcePipelinePermissions {
+        let permission = |authorized| Permission {
+            authorized: Some(authorized),
+            ..Permission::default()
+        };
+        let pipelines = self
+            .grant
+            .iter()
+            .map(|id| (*id, true))
+            .chain(self.revoke.iter().map(|id| (*id, false)))
+            .map(|(id, authorized)| PipelinePermission {
+                permission: permission(authorized),
+                id: Some(id),
+            })
+            .collect();
+        ResourcePipelinePermissions {
+            all_pipelines: self.all_pipelines.map(permission),
+            pipelines,
+            resource: Some(Resource {
+                id: Some(group_id.to_string()),
+                type_: Some(VARIABLE_GROUP_RESOURCE.to_string()),
+                ..Resource::default()
+            }),
+        }
+    }
+}
+
+/// Lists the pipelines of the project, checked where they may use the
+/// selected group.
+pub(super) fn enter<C: VariableGroupsClient + 'static>(
+    state: &mut State,
+    client: &Arc<C>,
+    fetch_tx: &Sender<Action>,
+) {
+    if state.is_offline() {
+        state.ui.report_offline("change pipeline permissions");
+        return;
+    }
+    let Some(group) = state.current_group().filter(|g| g.id.is_some()).cloned() else {
+        return;
+    };
+    state.ui.search.deactivate();
+    state.ui.open(Overlay::Pipelines(PipelinesView {
+        group: group.name.clone(),
+        loading: true,
+        ..PipelinesView::default()
+    }));
+    tokio::spawn(fetch_permissions(
+        Arc::clone(client),
+        state.organization().to_string(),
+        state.project().to_string(),
+        group,
+        fetch_tx.clone(),
+    ));
+}
+
+/// Checks or unchecks the highlighted pipeline, or the first row opening the
+/// group to every pipeline.
+pub(super) fn toggle_pipeline(state: &mut State) {
+    let Some(view) = state
+        .ui
+        .opened_mut::<PipelinesView>()
+        .filter(|view| !view.loading)
+    else {
+        return;
+    };
+    let chosen = &mut view.chosen;
+    match view.selected.checked_sub(1) {
+        None => chosen.all_pipelines = !chosen.all_pipelines,
+        Some(index) => {
+            let Some(pipeline) = view.pipelines.get(index) else {
+                return;
+            };
+            if !chosen.authorized.remove(&pipeline.id) {
+                chosen.authorized.insert(pipeline.id);
+            }
+        }
+    }
+}
+
+pub(super) fn apply<C: VariableGroupsClient + 'static>(
+    state: &mut State,
+    client: &Arc<C>,
+    fetch_tx: &Sender<Action>,
+) {
+    let Some(view) = state
+        .ui
+        .opened_mut::<PipelinesView>()
+        .filter(|view| !view.loading)
+    else {
+        return;
+    };
+    let changes = view.changes();
+    if changes.is_empty() {
+        state
+            .ui
+            .set_status(StatusMessage::info("No permission changes to apply"));
+        return;
+    }
+    let name = view.group.clone();
+    let Some(group) = state.groups().iter().find(|g| g.name == name).cloned() else {
+        return;
+    };
+    if let Some(view) = state.ui.opened_mut::<PipelinesView>() {
+        view.loading = true;
+    }
+    tokio::spawn(update_permissions(
+        Arc::clone(client),
+        state.organization().to_string(),
+        state.project().to_string(),
+        group,
+        changes,
+        fetch_tx.clone(),
+    ));
+}
+
+/// Shows the pipelines allowed to use `group`, if its view is still open.
+/// Returns whether it is.
+pub(super) fn loaded(
+    state: &mut State,
+    group: &str,
+    pipelines: Vec<Pipeline>,
+    permissions: PipelinePermissions,
+) -> bool {
+    let Some(view) = state
+        .ui
+        .opened_mut::<PipelinesView>()
+        .filter(|view| view.group == group)
+    else {
+        return false;
+    };
+    view.pipelines = pipelines;
+    // Deleted pipelines can stay authorized.
+    for id in &permissions.authorized {
+        if !view.pipelines.iter().any(|pipeline| pipeline.id == *id) {
+            view.pipelines.push(Pipeline::unknown(*id));
+        }
+    }
+    view.chosen = permissions.clone();
+    view.granted = permissions;
+    view.loading = false;
+    true
+}
+
+pub(super) fn load_failed(state: &mut State, group: &str, error: ClientError) {
+    warn!("Failed to load the pipeline permissions of {group}: {error}");
+    state.ui.close::<PipelinesView>();
+    state.ui.set_status(StatusMessage::error(format!(
+        "Failed to load the pipeline permissions of {group}: {error}"
+    )));
+}
+
+pub(super) fn updated(state: &mut State, group: &str, changes: PermissionChanges) {
+    let mut done = Vec::new();
+    match changes.all_pipelines {
+        Some(true) => done.push("opened to all pipelines".to_string()),
+        Some(false) => done.push("restricted to authorized pipelines".to_string()),
+        None => {}
+    }
+    if let Some(view) = state.ui.opened_mut::<PipelinesView>()
+        && view.group == group
+    {
+        let names = |ids: &[i32]| {
+            ids.iter()
+                .map(|id| view.name_of(*id))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        if !changes.grant.is_empty() {
+            done.push(format!("granted {}", names(&changes.grant)));
+        }
+        if !changes.revoke.is_empty() {
+            done.push(format!("revoked {}", names(&changes.revoke)));
+        }
+        changes.apply(&mut view.granted);
+        view.loading = false;
+    }
+    let message = format!(
+        "Updated the pipeline permissions of {group}: {}",
+        done.join(", ")
+    );
+    info!("{message}");
+    state.ui.set_status(StatusMessage::info(message));
+}
+
+pub(super) fn update_failed(state: &mut State, group: &str, error: ClientError) {
+    warn!("Failed to update the pipeline permissions of {group}: {error}");
+    if let Some(view) = state.ui.opened_mut::<PipelinesView>() {
+        view.loading = false;
+    }
+    state.ui.set_status(StatusMessage::error(format!(
+        "Failed to update the pipeline permissions of {group}: {error}"
+    )));
+}
+
+async fn fetch_permissions<C: VariableGroupsClient>(
+    client: Arc<C>,
+    organization: String,
+    project: String,
+    group: VarGroup,
+    fetch_tx: Sender<Action>,
+) {
+    let group_id = group.id.unwrap_or_default();
+    let action = match tokio::try_join!(
+        client.get_pipelines(&organization, &project),
+        client.get_pipeline_permissions(&organization, &project, group_id),
+    ) {
+        Ok((pipelines, permissions)) => Action::PipelinesLoaded {
+            group: group.name,
+            pipelines,
+            permissions,
+        },
+        Err(error) => Action::PipelinesLoadFailed {
+            group: group.name,
+            error,
+        },
+    };
+    let _ = fetch_tx.send(action).await;
+}
+
+async fn update_permissions<C: VariableGroupsClient>(
+    client: Arc<C>,
+    organization: String,
+    project: String,
+    group: VarGroup,
+    changes: PermissionChanges,
+    fetch_tx: Sender<Action>,
+) {
+    let group_id = group.id.unwrap_or_default();
+    let action = match client
+        .set_pipeline_permissions(&organization, &project, group_id, changes.clone())
+        .await
+    {
+        Ok(()) => Action::PipelinePermissionsUpdated {
+            group: group.name,
+            changes,
+        },
+        Err(error) => Action::PipelinePermissionsUpdateFailed {
+            group: group.name,
+            error,
+        },
+    };
+    let _ = fetch_tx.send(action).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn permissions_are_read_and_changed_per_pipeline() {
+        let response = serde_json::from_str::<ResourcePipelinePermissions>(
+            r#"{
+                "resource": {"type": "variablegroup", "id": "7"},
+                "allPipelines": {"authorized": false},
+                "pipelines": [
+                    {"id": 12, "authorized": true},
+                    {"id": 15, "authorized": false},
+                    {"id": 3, "authorized": true}
+                ]
+            }"#,
+        )
+        .unwrap();
+        let current = PipelinePermissions::from(response);
+        assert!(!current.all_pipelines);
+        assert_eq!(current.authorized, BTreeSet::from([3, 12]));
+
+        let wanted = PipelinePermissions {
+            all_pipelines: true,
+            authorized: BTreeSet::from([12, 15]),
+        };
+        let changes = PermissionChanges::between(&current, &wanted);
+        assert_eq!(
+            changes,
+            PermissionChanges {
+                all_pipelines: Some(true),
+                grant: vec![15],
+                revoke: vec![3],
+            }
+        );
+        let mut applied = current.clone();
+        changes.apply(&mut applied);
+        assert_eq!(applied, wanted);
+
+        let request = serde_json::to_value(changes.to_request(7)).unwrap();
+        assert_eq!(
+            request,
+            serde_json::json!({
+                "allPipelines": {"authorized": true},
+                "pipelines": [
+                    {"id": 15, "authorized": true},
+                    {"id": 3, "authorized": false}
+                ],
+                "resource": {"id": "7", "type": "variablegroup"}
+            })
+        );
+        assert!(PermissionChanges::between(&current, &current).is_empty());
+    }
+
+    #[test]
+    fn pipeline_paths_use_slashes() {
+        let pipeline = Pipeline {
+            id: 1,
+            name: "web".into(),
+            folder: "\\deploy\\prod".into(),
+        };
+        assert_eq!(pipeline.path(), "deploy/prod/web");
+        let pipeline = Pipeline {
+            folder: "\\".into(),
+            ..pipeline
+        };
+        assert_eq!(pipeline.path(), "web");
+    }
+}
@@ -0,0 +1,155 @@
+use log::info;
+
+use super::{
+    edits::EditOp,
+    state::{Overlay, PromoteView, Prompt, PromptKind, State, StatusMessage, VarEntry},
+};
+
+/// Starts promoting variables from the group being viewed.
+pub(super) fn start(state: &mut State) {
+    if let Some(source) = state.current_group().map(|g| g.name.clone()) {
+        state.ui.search.deactivate();
+        state.ui.open(Overlay::Promote(PromoteView {
+            source,
+            ..PromoteView::default()
+        }));
+    }
+}
+
+/// Picks the highlighted group as the target. Returns its name, for its
+/// variables to be loaded, or `None` when no target is being picked.
+pub(super) fn pick_target(state: &mut State) -> Option<String> {
+    let target = state
+        .ui
+        .opened::<PromoteView>()
+        .filter(|promote| promote.target.is_none())
+        .and_then(|promote| state.promote_targets().get(promote.selected).copied())
+        .map(|group| group.name.clone())?;
+    if let Some(promote) = state.ui.opened_mut::<PromoteView>() {
+        promote.target = Some(target.clone());
+        promote.selected = 0;
+        promote.chosen.clear();
+        promote.overrides.clear();
+    }
+    Some(target)
+}
+
+/// Chooses or leaves out the highlighted key.
+pub(super) fn toggle_key(state: &mut State) {
+    let rows = state.promote_rows();
+    if let Some(promote) = state.ui.opened_mut::<PromoteView>()
+        && let Some(row) = rows.get(promote.selected)
+        && !promote.chosen.remove(&row.source.name)
+    {
+        promote.chosen.insert(row.source.name.clone());
+    }
+}
+
+/// Asks for the value the highlighted key is given in the target.
+pub(super) fn start_override(state: &mut State) {
+    let rows = state.promote_rows();
+    if let Some(promote) = state.ui.opened::<PromoteView>()
+        && let (Some(row), Some(target)) = (rows.get(promote.selected), &promote.target)
+    {
+        let name = row.source.name.clone();
+        let text = match promote.overrides.get(&name) {
+            Some(value) => value.clone(),
+            None if row.source.is_secret => String::new(),
+            None => row.source.value.clone(),
+        };
+        state.ui.prompt = Some(Prompt {
+            kind: PromptKind::OverrideValue {
+                name,
+                target: target.clone(),
+                is_secret: row.source.is_secret,
+            },
+            text,
+        });
+    }
+}
+
+/// Chooses `name`, to be copied with `value` instead of that of the source.
+pub(super) fn set_override(state: &mut State, name: &str, value: String) {
+    if let Some(promote) = state.ui.opened_mut::<PromoteView>() {
+        promote.chosen.insert(name.to_string());
+        promote.overrides.insert(name.to_string(), value);
+    }
+}
+
+/// Stages the chosen keys in the target group and opens the review, where
+/// they are saved. Secrets need a value, as theirs cannot be read.
+pub(super) fn apply(state: &mut State) {
+    let Some(promote) = state.ui.opened::<PromoteView>() else {
+        return;
+    };
+    let Some(target) = promote.target.clone() else {
+        return;
+    };
+    let chosen = state
+        .promote_rows()
+        .into_iter()
+        .filter(|row| promote.chosen.contains(&row.source.name))
+        .collect::<Vec<_>>();
+    if chosen.is_empty() {
+        state
+            .ui
+            .set_status(StatusMessage::error("No variables chosen to promote"));
+        return;
+    }
+    let missing = chosen
+        .iter()
+        .filter(|row| row.source.is_secret && !promote.overrides.contains_key(&row.source.name))
+        .map(|row| row.source.name.as_str())
+        .collect::<Vec<_>>();
+    if !missing.is_empty() {
+        let message = format!("Enter the value of {} first", missing.join(", "));
+        state.ui.set_status(StatusMessage::error(message));
+        return;
+    }
+    let ops = chosen
+        .into_iter()
+        .map(|row| {
+            let value = promote
+                .overrides
+                .get(&row.source.name)
+                .cloned()
+                .unwrap_or(row.source.value);
+            EditOp::Set(VarEntry {
+                value,
+                ..row.source
+            })
+        })
+        .collect::<Vec<_>>();
+    let (count, source) = (ops.len(), promote.source.clone());
+    let select = state.current_var().map(|var| var.name.clone());
+    if !state.stage_edits(&target, ops, select) {
+        return;
+    }
+    info!("Promoted {count} variables from {source} to {target}");
+    state.ui.close::<PromoteView>();
+    state.ui.open(Overlay::Review);
+    state.ui.set_status(StatusMessage::info(format!(
+        "Staged {count} variables from {source} in {target}, save them to apply"
+    )));
+}
+
+/// Goes back to picking the target, or ends the promotion while picking it.
+pub(super) fn exit(state: &mut State) {
+    if let Some(promote) = state.ui.opened_mut::<PromoteView>()
+        && promote.target.is_some()
+    {
+        promote.target = None;
+        promote.selected = 0;
+    } else {
+        state.ui.close::<PromoteView>();
+    }
+}
+
+/// The number of rows of the promotion in progress.
+pub(super) fn len(state: &State) -> usize {
+    match state.ui.opened::<PromoteView>() {
+        None => 0,
+        Some(PromoteView { target: None, .. }) => state.promote_targets().len(),
+        Some(_) => state.promote_rows().len(),
+    }
+}
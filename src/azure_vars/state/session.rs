@@ -0,0 +1,95 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use super::state::State;
+use crate::config::error::ConfigError;
+
+/// Where the user left off, stored by name so that it survives groups being
+/// added, removed or reordered between runs.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Session {
+    #[serde(default)]
+    pub viewing_vars: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub var: Option<String>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub groups_query: String,
+    /// Variable search queries, keyed by group name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub vars_queries: BTreeMap<String, String>,
+}
+
+impl Session {
+    pub fn capture(state: &State) -> Self {
+        // Nothing was loaded this run, so the previous session still applies.
+        if let Some(pending) = state.pending_session() {
+            return pending.clone();
+        }
+
+        let vars_queries = state
+            .ui
+            .search
+            .vars_queries()
+            .filter(|(_, query)| !query.is_empty())
+            .filter_map(|(idx, query)| {
+                let group = state.groups().get(idx)?;
+                Some((group.name.clone(), query.to_string()))
+            })
+            .collect();
+        Self {
+            viewing_vars: state.is_viewing_vars(),
+            group: state.current_group().map(|g| g.name.clone()),
+            var: state.current_var().map(|v| v.name.clone()),
+            groups_query: state.ui.search.groups_query().to_string(),
+            vars_queries,
+        }
+    }
+
+    /// Reads a saved session. Missing or unreadable sessions are ignored.
+    pub fn read(path: &Path) -> Option<Self> {
+        let text = fs::read_to_string(path).ok()?;
+        serde_yaml::from_str(&text).ok()
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), ConfigError> {
+        let io_error = |source| ConfigError::Io {
+            path: path.to_path_buf(),
+            source,
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(io_error)?;
+        }
+        let yaml = serde_yaml::to_string(self).map_err(|error| ConfigError::Parse {
+            path: path.to_path_buf(),
+            line: None,
+            column: None,
+            message: error.to_string(),
+        })?;
+        fs::write(path, yaml).map_err(io_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn session_round_trips_through_a_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sessions").join("org--project.yaml");
+        assert_eq!(Session::read(&path), None);
+
+        let session = Session {
+            viewing_vars: true,
+            group: Some("Group A".into()),
+            var: Some("API_URL".into()),
+            groups_query: "grp".into(),
+            vars_queries: BTreeMap::from([("Group A".into(), "api".into())]),
+        };
+        session.write(&path).unwrap();
+        assert_eq!(Session::read(&path), Some(session));
+    }
+}
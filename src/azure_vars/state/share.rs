@@ -0,0 +1,222 @@
+use std::sync::Arc;
+
+use log::{info, warn};
+use tokio::sync::mpsc::Sender;
+
+use super::{
+    action::Action,
+    error::ClientError,
+    state::{Overlay, ShareView, State, StatusMessage, VarGroup},
+    state_store::VariableGroupsClient,
+};
+
+/// Lists the projects of the organization, checked where the selected group
+/// is shared.
+pub(super) fn enter<C: VariableGroupsClient + 'static>(
+    state: &mut State,
+    client: &Arc<C>,
+    fetch_tx: &Sender<Action>,
+) {
+    if state.is_offline() {
+        state.ui.report_offline("share groups");
+        return;
+    }
+    let Some(group) = state.current_group().filter(|g| g.id.is_some()).cloned() else {
+        return;
+    };
+    state.ui.search.deactivate();
+    state.ui.open(Overlay::Share(ShareView {
+        group: group.name.clone(),
+        loading: true,
+        ..ShareView::default()
+    }));
+    tokio::spawn(fetch_sharing(
+        Arc::clone(client),
+        state.organization().to_string(),
+        state.project().to_string(),
+        group,
+        fetch_tx.clone(),
+    ));
+}
+
+/// Checks or unchecks the highlighted project. The group cannot be removed
+/// from the project it is viewed in.
+pub(super) fn toggle_project(state: &mut State) {
+    let own_project = state.project().to_string();
+    let Some(share) = state
+        .ui
+        .opened_mut::<ShareView>()
+        .filter(|share| !share.loading)
+    else {
+        return;
+    };
+    let Some(name) = share.projects.get(share.selected).cloned() else {
+        return;
+    };
+    if name == own_project {
+        let message = format!("{} cannot be removed from {name} here", share.group);
+        state.ui.set_status(StatusMessage::error(message));
+    } else if !share.chosen.remove(&name) {
+        share.chosen.insert(name);
+    }
+}
+
+pub(super) fn apply<C: VariableGroupsClient + 'static>(
+    state: &mut State,
+    client: &Arc<C>,
+    fetch_tx: &Sender<Action>,
+) {
+    let Some(share) = state
+        .ui
+        .opened_mut::<ShareView>()
+        .filter(|share| !share.loading)
+    else {
+        return;
+    };
+    let (add, remove) = share.changes();
+    if add.is_empty() && remove.is_empty() {
+        state
+            .ui
+            .set_status(StatusMessage::info("No sharing changes to apply"));
+        return;
+    }
+    let name = share.group.clone();
+    let Some(group) = state.groups().iter().find(|g| g.name == name).cloned() else {
+        return;
+    };
+    if let Some(share) = state.ui.opened_mut::<ShareView>() {
+        share.loading = true;
+    }
+    tokio::spawn(update_sharing(
+        Arc::clone(client),
+        state.organization().to_string(),
+        state.project().to_string(),
+        group,
+        add,
+        remove,
+        fetch_tx.clone(),
+    ));
+}
+
+/// Shows the projects `group` is shared with, if its view is still open.
+/// Returns whether it is.
+pub(super) fn loaded(
+    state: &mut State,
+    group: &str,
+    projects: Vec<String>,
+    shared: Vec<String>,
+) -> bool {
+    let Some(share) = state
+        .ui
+        .opened_mut::<ShareView>()
+        .filter(|share| share.group == group)
+    else {
+        return false;
+    };
+    share.shared = shared.into_iter().collect();
+    share.chosen = share.shared.clone();
+    // Projects hidden from the user can still be shared with.
+    share.projects = projects;
+    for name in &share.shared {
+        if !share.projects.contains(name) {
+            share.projects.push(name.clone());
+        }
+    }
+    share.projects.sort_by_key(|name| name.to_lowercase());
+    share.loading = false;
+    true
+}
+
+pub(super) fn load_failed(state: &mut State, group: &str, error: ClientError) {
+    warn!("Failed to load the projects {group} is shared with: {error}");
+    state.ui.close::<ShareView>();
+    state.ui.set_status(StatusMessage::error(format!(
+        "Failed to load the projects {group} is shared with: {error}"
+    )));
+}
+
+pub(super) fn updated(state: &mut State, group: &str, share: Vec<String>, unshare: Vec<String>) {
+    if let Some(view) = state.ui.opened_mut::<ShareView>()
+        && view.group == group
+    {
+        view.shared.extend(share.iter().cloned());
+        view.shared.retain(|name| !unshare.contains(name));
+        view.loading = false;
+    }
+    let (share, unshare) = (share.join(", "), unshare.join(", "));
+    let message = match (share.is_empty(), unshare.is_empty()) {
+        (false, true) => format!("Shared {group} with {share}"),
+        (true, false) => format!("Removed {group} from {unshare}"),
+        _ => format!("Shared {group} with {share}, removed it from {unshare}"),
+    };
+    info!("{message}");
+    state.ui.set_status(StatusMessage::info(message));
+}
+
+pub(super) fn update_failed(state: &mut State, group: &str, error: ClientError) {
+    warn!("Failed to update the sharing of {group}: {error}");
+    if let Some(view) = state.ui.opened_mut::<ShareView>() {
+        view.loading = false;
+    }
+    state.ui.set_status(StatusMessage::error(format!(
+        "Failed to update the sharing of {group}: {error}"
+    )));
+}
+
+async fn fetch_sharing<C: VariableGroupsClient>(
+    client: Arc<C>,
+    organization: String,
+    project: String,
+    group: VarGroup,
+    fetch_tx: Sender<Action>,
+) {
+    let group_id = group.id.unwrap_or_default();
+    let action = match tokio::try_join!(
+        client.get_projects(&organization),
+        client.get_shared_projects(&organization, &project, group_id),
+    ) {
+        Ok((projects, shared)) => Action::SharingLoaded {
+            group: group.name,
+            projects,
+            shared,
+        },
+        Err(error) => Action::SharingLoadFailed {
+            group: group.name,
+            error,
+        },
+    };
+    let _ = fetch_tx.send(action).await;
+}
+
+async fn update_sharing<C: VariableGroupsClient>(
+    client: Arc<C>,
+    organization: String,
+    project: String,
+    group: VarGroup,
+    share: Vec<String>,
+    unshare: Vec<String>,
+    fetch_tx: Sender<Action>,
+) {
+    let group_id = group.id.unwrap_or_default();
+    let action = match client
+        .share_variable_group(
+            &organization,
+            &project,
+            group_id,
+            share.clone(),
+            unshare.clone(),
+        )
+        .await
+    {
+        Ok(()) => Action::SharingUpdated {
+            group: group.name,
+            share,
+            unshare,
+        },
+        Err(error) => Action::SharingUpdateFailed {
+            group: group.name,
+            error,
+        },
+    };
+    let _ = fetch_tx.send(action).await;
+}
@@ -1,9 +1,26 @@
-use std::{collections::HashMap, fmt::Debug, sync::RwLock};
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fmt::Debug,
+    mem,
+    sync::{Arc, RwLock},
+    time::SystemTime,
+};
 
 use fuzzy_matcher::FuzzyMatcher;
 use fuzzy_matcher::skim::SkimMatcherV2;
 use serde::{Deserialize, Serialize};
 
+use super::audit::AuditEvent;
+use super::cache::GroupCache;
+use super::checks::Check;
+use super::diff::GroupChange;
+use super::edits::{EditOp, StagedEdits};
+use super::journal::JournalEntry;
+use super::permissions::{PermissionChanges, Pipeline, PipelinePermissions};
+use super::session::Session;
+use super::theme::{Theme, ThemeSet};
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
 pub struct VarEntry {
     pub name: String,
@@ -13,15 +30,52 @@ pub struct VarEntry {
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
 pub struct VarGroup {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<i32>,
     pub name: String,
     pub variables: Vec<VarEntry>,
+    /// Display name of whoever last modified the group.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified_by: Option<String>,
+    /// Identifies the variables as fetched, so that changes are noticed
+    /// without keeping every variable of every group.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fingerprint: Option<u64>,
+    /// Only the summary was fetched: `variables` stays empty until the group
+    /// is opened, and `variable_count` says how many there are.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub summary_only: bool,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub variable_count: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// `Vsts` for groups that hold their variables, `AzureKeyVault` for
+    /// groups linked to a vault.
+    #[serde(default, rename = "type", skip_serializing_if = "Option::is_none")]
+    pub group_type: Option<String>,
 }
 
-#[derive(Clone, Copy, Default, Debug)]
-pub enum Theme {
-    #[default]
-    Dark,
-    Light,
+fn is_zero(count: &usize) -> bool {
+    *count == 0
+}
+
+impl VarGroup {
+    /// The number of variables, whether or not they are loaded.
+    pub fn var_count(&self) -> usize {
+        if self.summary_only {
+            self.variable_count
+        } else {
+            self.variables.len()
+        }
+    }
+
+    /// The variables come from a linked source such as Azure Key Vault,
+    /// whose settings are not kept here.
+    pub fn is_linked(&self) -> bool {
+        self.group_type
+            .as_deref()
+            .is_some_and(|kind| kind != "Vsts")
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -56,7 +110,12 @@ impl StatusMessage {
 pub struct AppData {
     organization: String,
     project: String,
-    pub groups: Vec<VarGroup>,
+    pinned_groups: Vec<String>,
+    /// Shared with the copies of the state sent to the TUI, so that only
+    /// changing the groups copies them.
+    pub groups: Arc<Vec<VarGroup>>,
+    /// When the groups were fetched, if they were loaded from the cache.
+    cached_at: Option<SystemTime>,
 }
 
 impl AppData {
@@ -64,7 +123,9 @@ impl AppData {
         Self {
             organization,
             project,
-            groups: Vec::new(),
+            pinned_groups: Vec::new(),
+            groups: Arc::default(),
+            cached_at: None,
         }
     }
 }
@@ -75,6 +136,289 @@ pub struct UiState {
     pub is_fetching: bool,
     pub search: SearchState,
     pub status: Option<StatusMessage>,
+    pub show_logs: bool,
+    /// Groups received so far by the fetch in progress.
+    pub fetched_groups: usize,
+    /// Variables changed by others since the group was last viewed, by group
+    /// name.
+    pub changed_vars: HashMap<String, HashSet<String>>,
+    /// The lists open over the group list or the variables of a group, the
+    /// last one opened on top. Only that one is drawn and given the keys.
+    pub overlays: Vec<Overlay>,
+    /// Changes not saved yet, by group name.
+    pub edits: BTreeMap<String, StagedEdits>,
+    pub prompt: Option<Prompt>,
+    /// Quitting was asked for once with unsaved changes, which are discarded
+    /// when asked again.
+    pub confirm_discard: bool,
+    /// Saving was asked for once while staged changes conflict with changes
+    /// made by someone else, which are overwritten when asked again.
+    pub confirm_overwrite: bool,
+}
+
+/// A list shown in place of the groups or the variables of a group.
+#[derive(Debug, Clone)]
+pub enum Overlay {
+    /// The unsaved changes of every group, listed for saving.
+    Review,
+    /// The recorded versions of the group being viewed.
+    History(HistoryView),
+    /// The audit events of a group.
+    Audit(AuditView),
+    /// Copies variables from the group being viewed to another one.
+    Promote(PromoteView),
+    /// Picks the profile a group is copied to.
+    Copy(CopyView),
+    /// The projects of the organization a group is shared with.
+    Share(ShareView),
+    /// The pipelines of the project allowed to use a group.
+    Pipelines(PipelinesView),
+    /// The approvals and checks protecting a group.
+    Checks(ChecksView),
+}
+
+/// The view of an [`Overlay`] variant.
+pub trait OverlayView: Sized {
+    fn of(overlay: &Overlay) -> Option<&Self>;
+    fn of_mut(overlay: &mut Overlay) -> Option<&mut Self>;
+    fn from_overlay(overlay: Overlay) -> Option<Self>;
+}
+
+macro_rules! overlay_views {
+    ($($variant:ident($view:ty)),* $(,)?) => {$(
+        impl OverlayView for $view {
+            fn of(overlay: &Overlay) -> Option<&Self> {
+                match overlay {
+                    Overlay::$variant(view) => Some(view),
+                    _ => None,
+                }
+            }
+
+            fn of_mut(overlay: &mut Overlay) -> Option<&mut Self> {
+                match overlay {
+                    Overlay::$variant(view) => Some(view),
+                    _ => None,
+                }
+            }
+
+            fn from_overlay(overlay: Overlay) -> Option<Self> {
+                match overlay {
+                    Overlay::$variant(view) => Some(view),
+                    _ => None,
+                }
+            }
+        }
+    )*};
+}
+
+overlay_views!(
+    History(HistoryView),
+    Audit(AuditView),
+    Promote(PromoteView),
+    Copy(CopyView),
+    Share(ShareView),
+    Pipelines(PipelinesView),
+    Checks(ChecksView),
+);
+
+/// A line of text being typed, such as the new value of a variable.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Prompt {
+    pub kind: PromptKind,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PromptKind {
+    /// The new value of a variable.
+    EditValue { name: String, is_secret: bool },
+    /// `NAME=value` of a variable to add.
+    AddVariable,
+    /// The value a promoted variable is given in the target group.
+    OverrideValue {
+        name: String,
+        target: String,
+        is_secret: bool,
+    },
+    /// The name a group is copied under in the project of a profile.
+    CopyName { group: String, profile: String },
+    /// The users or groups who must approve runs using a group.
+    Approvers { group: String },
+}
+
+impl PromptKind {
+    pub fn title(&self) -> String {
+        match self {
+            PromptKind::EditValue { name, .. } => format!("New value of {name}"),
+            PromptKind::AddVariable => "Add variable (NAME=value)".to_string(),
+            PromptKind::OverrideValue { name, target, .. } => {
+                format!("Value of {name} in {target}")
+            }
+            PromptKind::CopyName { group, profile } => format!("Copy {group} to {profile} as"),
+            PromptKind::Approvers { group } => {
+                format!("Approvers of {group} (emails or names, comma separated)")
+            }
+        }
+    }
+
+    /// The text typed is the value of a secret, and is masked.
+    pub fn is_secret(&self) -> bool {
+        matches!(
+            self,
+            PromptKind::EditValue {
+                is_secret: true,
+                ..
+            } | PromptKind::OverrideValue {
+                is_secret: true,
+                ..
+            }
+        )
+    }
+}
+
+/// The recorded versions of the group being viewed.
+#[derive(Debug, Clone, Default)]
+pub struct HistoryView {
+    /// Newest first.
+    pub entries: Vec<JournalEntry>,
+    pub selected: usize,
+    /// Restoring the selected version was asked for once, and is done when
+    /// asked again.
+    pub confirm_restore: bool,
+}
+
+impl HistoryView {
+    pub fn selected_entry(&self) -> Option<&JournalEntry> {
+        self.entries.get(self.selected)
+    }
+
+    /// The version before the selected one, to compare it with.
+    pub fn previous_entry(&self) -> Option<&JournalEntry> {
+        self.entries.get(self.selected + 1)
+    }
+}
+
+/// The audit events of a group.
+#[derive(Debug, Clone, Default)]
+pub struct AuditView {
+    pub group: String,
+    /// Newest first.
+    pub events: Vec<AuditEvent>,
+    pub loading: bool,
+    pub selected: usize,
+}
+
+/// Variables being promoted from one group to another: the target is picked
+/// first, then the keys to copy.
+#[derive(Debug, Clone, Default)]
+pub struct PromoteView {
+    pub source: String,
+    /// `None` while the target is being picked.
+    pub target: Option<String>,
+    /// The highlighted target, or the highlighted key once a target is
+    /// picked.
+    pub selected: usize,
+    /// The keys to copy.
+    pub chosen: BTreeSet<String>,
+    /// Values to copy instead of those of the source, by key.
+    pub overrides: BTreeMap<String, String>,
+}
+
+/// A variable of the source group that the target lacks or has another
+/// value for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PromoteRow {
+    pub source: VarEntry,
+    pub target: Option<VarEntry>,
+}
+
+/// The projects of the organization, checked where a group is shared.
+#[derive(Debug, Clone, Default)]
+pub struct ShareView {
+    pub group: String,
+    /// Every project of the organization, sorted.
+    pub projects: Vec<String>,
+    /// The projects the group is shared with, as last fetched.
+    pub shared: BTreeSet<String>,
+    /// The projects it should be shared with once applied.
+    pub chosen: BTreeSet<String>,
+    pub loading: bool,
+    pub selected: usize,
+}
+
+impl ShareView {
+    /// The projects to share the group with, and to remove it from.
+    pub fn changes(&self) -> (Vec<String>, Vec<String>) {
+        let share = self.chosen.difference(&self.shared).cloned().collect();
+        let unshare = self.shared.difference(&self.chosen).cloned().collect();
+        (share, unshare)
+    }
+}
+
+/// The pipelines of the project, checked where they may use a group.
+#[derive(Debug, Clone, Default)]
+pub struct PipelinesView {
+    pub group: String,
+    /// Every pipeline of the project, sorted by path.
+    pub pipelines: Vec<Pipeline>,
+    /// The permissions as last fetched.
+    pub granted: PipelinePermissions,
+    /// The permissions once applied.
+    pub chosen: PipelinePermissions,
+    pub loading: bool,
+    /// Row 0 opens the group to every pipeline, the pipelines follow.
+    pub selected: usize,
+}
+
+impl PipelinesView {
+    pub fn changes(&self) -> PermissionChanges {
+        PermissionChanges::between(&self.granted, &self.chosen)
+    }
+
+    pub fn rows(&self) -> usize {
+        self.pipelines.len() + 1
+    }
+
+    pub fn name_of(&self, id: i32) -> String {
+        self.pipelines
+            .iter()
+            .find(|pipeline| pipeline.id == id)
+            .map_or_else(|| Pipeline::unknown(id).name, Pipeline::path)
+    }
+}
+
+/// The approvals and checks runs must pass to use a group.
+#[derive(Debug, Clone, Default)]
+pub struct ChecksView {
+    pub group: String,
+    pub checks: Vec<Check>,
+    pub loading: bool,
+    pub selected: usize,
+    /// Removing the selected check was asked for once, and is done when
+    /// asked again.
+    pub confirm_remove: bool,
+}
+
+impl ChecksView {
+    pub fn selected_check(&self) -> Option<&Check> {
+        self.checks.get(self.selected)
+    }
+}
+
+/// A group being copied to the project of a configured profile.
+#[derive(Debug, Clone, Default)]
+pub struct CopyView {
+    pub group: String,
+    /// The highlighted profile.
+    pub selected: usize,
+}
+
+/// A project groups can be copied to, configured under `profiles`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CopyProfile {
+    pub name: String,
+    pub organization: String,
+    pub project: String,
 }
 
 #[derive(Debug, Clone)]
@@ -146,6 +490,13 @@ impl SearchState {
         self.vars_queries.entry(group_idx).or_default()
     }
 
+    /// Variable search queries by group index.
+    pub fn vars_queries(&self) -> impl Iterator<Item = (usize, &str)> {
+        self.vars_queries
+            .iter()
+            .map(|(idx, query)| (*idx, query.as_str()))
+    }
+
     pub(super) fn clear_vars_query(&mut self, group_idx: usize) {
         self.vars_queries.remove(&group_idx);
     }
@@ -159,6 +510,54 @@ impl UiState {
     pub fn clear_status(&mut self) {
         self.status = None;
     }
+
+    /// The overlay drawn and given the keys, if any.
+    pub fn overlay(&self) -> Option<&Overlay> {
+        self.overlays.last()
+    }
+
+    /// Opens `overlay` on top of the others, closing any of the same kind.
+    pub fn open(&mut self, overlay: Overlay) {
+        let kind = mem::discriminant(&overlay);
+        self.overlays.retain(|open| mem::discriminant(open) != kind);
+        self.overlays.push(overlay);
+    }
+
+    /// The open overlay showing a `V`, on top or not.
+    pub fn opened<V: OverlayView>(&self) -> Option<&V> {
+        self.overlays.iter().rev().find_map(V::of)
+    }
+
+    pub fn opened_mut<V: OverlayView>(&mut self) -> Option<&mut V> {
+        self.overlays.iter_mut().rev().find_map(V::of_mut)
+    }
+
+    /// Closes the overlay showing a `V`, returning its view.
+    pub fn close<V: OverlayView>(&mut self) -> Option<V> {
+        let index = self
+            .overlays
+            .iter()
+            .position(|open| V::of(open).is_some())?;
+        V::from_overlay(self.overlays.remove(index))
+    }
+
+    pub fn is_reviewing(&self) -> bool {
+        self.overlays
+            .iter()
+            .any(|open| matches!(open, Overlay::Review))
+    }
+
+    pub fn close_review(&mut self) {
+        self.overlays
+            .retain(|open| !matches!(open, Overlay::Review));
+    }
+
+    /// Explains why an action that needs Azure DevOps did nothing.
+    pub fn report_offline(&mut self, action: &str) {
+        self.set_status(StatusMessage::error(format!(
+            "Offline mode: cannot {action}, restart without --offline"
+        )));
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
@@ -247,6 +646,10 @@ pub struct State {
     pub data: AppData,
     pub ui: UiState,
     pub theme: Theme,
+    themes: ThemeSet,
+    pending_session: Option<Session>,
+    offline: bool,
+    copy_profiles: Vec<CopyProfile>,
     pub matcher: SkimMatcherV2,
     filter_cache: RwLock<FilterCache>,
 }
@@ -257,11 +660,77 @@ impl State {
             data: AppData::new(organization, project),
             ui: UiState::default(),
             theme: Theme::default(),
+            themes: ThemeSet::default(),
+            pending_session: None,
+            offline: false,
+            copy_profiles: Vec::new(),
             matcher: SkimMatcherV2::default(),
             filter_cache: RwLock::new(FilterCache::default()),
         }
     }
 
+    /// Lists the named groups first, in the given order.
+    pub fn with_pinned_groups(mut self, pinned_groups: Vec<String>) -> Self {
+        self.data.pinned_groups = pinned_groups;
+        self.filter_cache.write().unwrap().invalidate_groups();
+        self
+    }
+
+    /// Sets the themes `ToggleTheme` cycles through and the one to start with.
+    pub fn with_themes(mut self, themes: ThemeSet, theme: Theme) -> Self {
+        self.themes = themes;
+        self.theme = theme;
+        self
+    }
+
+    pub fn themes(&self) -> &ThemeSet {
+        &self.themes
+    }
+
+    /// Restores a previous session once the first groups are loaded.
+    pub fn with_session(mut self, session: Session) -> Self {
+        self.pending_session = Some(session);
+        self
+    }
+
+    /// The session that will be restored when groups are first loaded.
+    pub fn pending_session(&self) -> Option<&Session> {
+        self.pending_session.as_ref()
+    }
+
+    /// Shows groups from a previous run until they are refetched.
+    pub fn with_cached_groups(mut self, cache: GroupCache) -> Self {
+        let fetched_at = cache.fetched_at();
+        self.set_groups(cache.groups);
+        self.data.cached_at = Some(fetched_at);
+        self
+    }
+
+    /// When the groups shown were fetched, while they come from the cache.
+    pub fn cached_at(&self) -> Option<SystemTime> {
+        self.data.cached_at
+    }
+
+    /// Never contacts Azure DevOps; only the groups already loaded are shown.
+    pub fn with_offline(mut self) -> Self {
+        self.offline = true;
+        self
+    }
+
+    pub fn is_offline(&self) -> bool {
+        self.offline
+    }
+
+    /// Offers to copy groups to these projects.
+    pub fn with_copy_profiles(mut self, profiles: Vec<CopyProfile>) -> Self {
+        self.copy_profiles = profiles;
+        self
+    }
+
+    pub fn copy_profiles(&self) -> &[CopyProfile] {
+        &self.copy_profiles
+    }
+
     pub fn organization(&self) -> &str {
         &self.data.organization
     }
@@ -274,14 +743,109 @@ impl State {
         &self.data.groups
     }
 
+    pub fn pinned_groups(&self) -> &[String] {
+        &self.data.pinned_groups
+    }
+
+    pub fn is_pinned(&self, group: &VarGroup) -> bool {
+        self.data.pinned_groups.contains(&group.name)
+    }
+
     pub(super) fn set_groups(&mut self, groups: Vec<VarGroup>) {
+        self.data.cached_at = None;
+        if !groups.is_empty()
+            && let Some(session) = self.pending_session.take()
+        {
+            self.data.groups = Arc::new(groups);
+            self.rebase_edits();
+            self.restore_session(session);
+            return;
+        }
+
+        let prev_group = self.current_group().map(|g| g.name.clone());
+        let prev_var = self.current_var().map(|v| v.name.clone());
+        self.data.groups = Arc::new(groups);
+        self.rebase_edits();
+        self.filter_cache.write().unwrap().invalidate_all();
+        self.sync_selection_with_previous(prev_group, prev_var);
+    }
+
+    /// Adds a page of groups while the first load is still in progress. The
+    /// pending session is restored once all pages are passed to `set_groups`.
+    pub(super) fn append_groups(&mut self, groups: Vec<VarGroup>) {
+        let prev_group = self.current_group().map(|g| g.name.clone());
+        let prev_var = self.current_var().map(|v| v.name.clone());
+        Arc::make_mut(&mut self.data.groups).extend(groups);
+        self.rebase_edits();
+        self.filter_cache.write().unwrap().invalidate_all();
+        self.sync_selection_with_previous(prev_group, prev_var);
+    }
+
+    /// Replaces a group's summary with the group as loaded in full, or a
+    /// group with its updated version. Groups are matched by id when known,
+    /// as an update may rename them.
+    pub(super) fn set_group_details(&mut self, group: VarGroup) {
+        let Some(idx) = self
+            .data
+            .groups
+            .iter()
+            .position(|g| match (g.id, group.id) {
+                (Some(id), Some(updated)) => id == updated,
+                _ => g.name == group.name,
+            })
+        else {
+            return;
+        };
         let prev_group = self.current_group().map(|g| g.name.clone());
         let prev_var = self.current_var().map(|v| v.name.clone());
-        self.data.groups = groups;
+        Arc::make_mut(&mut self.data.groups)[idx] = group;
+        self.rebase_edits();
         self.filter_cache.write().unwrap().invalidate_all();
         self.sync_selection_with_previous(prev_group, prev_var);
     }
 
+    fn restore_session(&mut self, session: Session) {
+        let position = |name: &str| self.data.groups.iter().position(|g| g.name == name);
+        let group_idx = session.group.as_deref().and_then(position);
+        self.ui.search.groups_query = session.groups_query;
+        self.ui.search.vars_queries = session
+            .vars_queries
+            .into_iter()
+            .filter_map(|(name, query)| Some((position(&name)?, query)))
+            .collect();
+        self.ui.view = match group_idx {
+            Some(group_idx) if session.viewing_vars => View::Vars {
+                group_idx,
+                selected_var_idx: None,
+            },
+            _ => View::Groups { selected_idx: None },
+        };
+        self.filter_cache.write().unwrap().invalidate_all();
+        self.sync_selection_with_previous(session.group, session.var);
+    }
+
+    /// Highlights the variables in `changes` until their group is viewed.
+    pub(super) fn mark_changed(&mut self, changes: &[GroupChange]) {
+        for change in changes {
+            self.ui
+                .changed_vars
+                .entry(change.group.clone())
+                .or_default()
+                .extend(change.changed_vars.iter().cloned());
+        }
+    }
+
+    pub(super) fn mark_viewed(&mut self, group: &str) {
+        self.ui.changed_vars.remove(group);
+    }
+
+    pub fn is_changed(&self, group: &str, var: &str) -> bool {
+        self.ui
+            .changed_vars
+            .get(group)
+            .is_some_and(|vars| vars.contains(var))
+    }
+
     pub(super) fn invalidate_group_cache(&mut self) {
         self.filter_cache.write().unwrap().invalidate_groups();
     }
@@ -294,6 +858,179 @@ impl State {
         self.ui.view.is_vars()
     }
 
+    /// The groups variables can be promoted to.
+    pub fn promote_targets(&self) -> Vec<&VarGroup> {
+        let source = self.ui.opened::<PromoteView>().map(|p| p.source.as_str());
+        self.data
+            .groups
+            .iter()
+            .filter(|group| Some(group.name.as_str()) != source)
+            .collect()
+    }
+
+    /// The group variables are promoted to, once picked.
+    pub fn promote_target(&self) -> Option<&VarGroup> {
+        let target = self.ui.opened::<PromoteView>()?.target.as_ref()?;
+        self.data.groups.iter().find(|group| &group.name == target)
+    }
+
+    /// What promoting could change in the target, with unsaved changes
+    /// applied to both groups. Empty until the target's variables are loaded.
+    pub fn promote_rows(&self) -> Vec<PromoteRow> {
+        let Some(promote) = self.ui.opened::<PromoteView>() else {
+            return Vec::new();
+        };
+        let source = self.data.groups.iter().find(|g| g.name == promote.source);
+        let (Some(source), Some(target)) = (source, self.promote_target()) else {
+            return Vec::new();
+        };
+        if source.summary_only || target.summary_only {
+            return Vec::new();
+        }
+        let target_vars = staged_variables(&self.ui.edits, target);
+        staged_variables(&self.ui.edits, source)
+            .iter()
+            .filter_map(|var| {
+                let existing = target_vars.iter().find(|t| t.name == var.name);
+                // Secret values are hidden on both sides, so only missing
+                // secrets are told apart.
+                let same = existing.is_some_and(|t| t == var || (t.is_secret && var.is_secret));
+                (!same).then(|| PromoteRow {
+                    source: var.clone(),
+                    target: existing.cloned(),
+                })
+            })
+            .collect()
+    }
+
+    pub fn is_reviewing(&self) -> bool {
+        self.ui.is_reviewing()
+    }
+
+    pub fn is_prompting(&self) -> bool {
+        self.ui.prompt.is_some()
+    }
+
+    /// The groups with unsaved changes, by name.
+    pub fn pending_edits(&self) -> impl Iterator<Item = (&str, &StagedEdits)> {
+        self.ui
+            .edits
+            .iter()
+            .filter(|(_, edits)| edits.has_changes())
+            .map(|(name, edits)| (name.as_str(), edits))
+    }
+
+    pub fn has_pending_edits(&self) -> bool {
+        self.pending_edits().next().is_some()
+    }
+
+    /// How many staged variables conflict with changes made by someone else.
+    pub fn conflict_count(&self) -> usize {
+        self.pending_edits()
+            .map(|(_, edits)| edits.conflicts().len())
+            .sum()
+    }
+
+    /// Keeps every staged change over the ones that conflict with it.
+    pub(super) fn accept_conflicts(&mut self) {
+        for edits in self.ui.edits.values_mut() {
+            edits.accept_conflicts();
+        }
+    }
+
+    /// The variables of a group as shown, with unsaved changes applied.
+    pub fn group_variables(&self, group_idx: usize) -> &[VarEntry] {
+        self.data
+            .groups
+            .get(group_idx)
+            .map_or(&[], |group| staged_variables(&self.ui.edits, group))
+    }
+
+    /// Stages a change to the group being viewed. The variables of the group
+    /// must be loaded.
+    pub(super) fn stage_edit(&mut self, op: EditOp) -> bool {
+        let Some(name) = self.current_group().map(|g| g.name.clone()) else {
+            return false;
+        };
+        // Select the variable set, or the one after the variable removed.
+        let select = match &op {
+            EditOp::Set(var) => Some(var.name.clone()),
+            EditOp::Remove(_) => None,
+        };
+        self.stage_edits(&name, vec![op], select)
+    }
+
+    /// Stages changes to the group named `group`, whose variables must be
+    /// loaded, then selects the variable named `select` if it is viewed.
+    pub(super) fn stage_edits(
+        &mut self,
+        group: &str,
+        ops: Vec<EditOp>,
+        select: Option<String>,
+    ) -> bool {
+        let Some(fetched) = self
+            .data
+            .groups
+            .iter()
+            .find(|g| g.name == group && !g.summary_only)
+            .map(|g| g.variables.clone())
+        else {
+            return false;
+        };
+        let edits = self
+            .ui
+            .edits
+            .entry(group.to_string())
+            .or_insert_with(|| StagedEdits::new(&fetched));
+        for op in ops {
+            edits.push(op);
+        }
+        self.edits_changed(select);
+        true
+    }
+
+    /// Takes back the last change staged to the selected group.
+    pub(super) fn undo_edit(&mut self) -> Option<EditOp> {
+        let name = self.current_group()?.name.clone();
+        let op = self.ui.edits.get_mut(&name)?.undo()?.clone();
+        let select = self.current_var().map(|v| v.name.clone());
+        self.edits_changed(select);
+        Some(op)
+    }
+
+    /// Stages the last change taken back from the selected group again.
+    pub(super) fn redo_edit(&mut self) -> Option<EditOp> {
+        let name = self.current_group()?.name.clone();
+        let op = self.ui.edits.get_mut(&name)?.redo()?.clone();
+        let select = self.current_var().map(|v| v.name.clone());
+        self.edits_changed(select);
+        Some(op)
+    }
+
+    /// Forgets the changes to a group, once saved.
+    pub(super) fn discard_edits(&mut self, group: &str) {
+        if self.ui.edits.remove(group).is_some() {
+            let select = self.current_var().map(|v| v.name.clone());
+            self.edits_changed(select);
+        }
+    }
+
+    fn edits_changed(&mut self, select: Option<String>) {
+        self.ui.confirm_discard = false;
+        self.ui.confirm_overwrite = false;
+        self.filter_cache.write().unwrap().invalidate_vars();
+        self.sync_selection_with_previous(None, select);
+    }
+
+    /// Applies the unsaved changes to the groups as last loaded.
+    fn rebase_edits(&mut self) {
+        for group in self.data.groups.iter().filter(|g| !g.summary_only) {
+            if let Some(edits) = self.ui.edits.get_mut(&group.name) {
+                edits.rebase(&group.variables);
+            }
+        }
+    }
+
     pub fn current_group_idx(&self) -> Option<usize> {
         match self.ui.view {
             View::Groups { selected_idx } => selected_idx,
@@ -326,10 +1063,7 @@ impl State {
     pub fn current_var(&self) -> Option<&VarEntry> {
         let group_idx = self.vars_group_idx()?;
         let var_idx = self.current_var_idx()?;
-        self.data
-            .groups
-            .get(group_idx)
-            .and_then(|group| group.variables.get(var_idx))
+        self.group_variables(group_idx).get(var_idx)
     }
 
     pub fn filtered_group_indices(&self) -> Vec<usize> {
@@ -343,11 +1077,23 @@ impl State {
             .groups
             .iter()
             .enumerate()
-            .map(|(idx, group)| (self.matcher.fuzzy_match(&group.name, query), idx))
-            .collect::<Vec<(Option<i64>, usize)>>();
+            .map(|(idx, group)| {
+                let pin_rank = self
+                    .data
+                    .pinned_groups
+                    .iter()
+                    .position(|name| name == &group.name)
+                    .unwrap_or(usize::MAX);
+                (self.matcher.fuzzy_match(&group.name, query), pin_rank, idx)
+            })
+            .collect::<Vec<(Option<i64>, usize, usize)>>();
 
-        ranked.sort_by(|a, b| b.0.cmp(&a.0));
-        let indices = ranked.into_iter().map(|(_, idx)| idx).collect::<Vec<_>>();
+        // Matching pinned groups come first, then everything by score.
+        ranked.sort_by_key(|(score, pin_rank, _)| (score.is_none(), *pin_rank, Reverse(*score)));
+        let indices = ranked
+            .into_iter()
+            .map(|(_, _, idx)| idx)
+            .collect::<Vec<_>>();
         self.filter_cache
             .write()
             .unwrap()
@@ -356,17 +1102,14 @@ impl State {
     }
 
     pub fn filtered_var_indices_for(&self, group_idx: usize) -> Vec<usize> {
-        let Some(group) = self.data.groups.get(group_idx) else {
-            return Vec::new();
-        };
+        let variables = self.group_variables(group_idx);
 
         let query = self.ui.search.vars_query(group_idx);
         if let Some(indices) = self.filter_cache.read().unwrap().get_vars(group_idx, query) {
             return indices;
         }
 
-        let mut vars_ranked = group
-            .variables
+        let mut vars_ranked = variables
             .iter()
             .enumerate()
             .filter_map(|(idx, var)| {
@@ -411,12 +1154,7 @@ impl State {
 
         self.filtered_var_indices_for(group_idx)
             .into_iter()
-            .filter_map(|idx| {
-                self.data
-                    .groups
-                    .get(group_idx)
-                    .and_then(|group| group.variables.get(idx))
-            })
+            .filter_map(|idx| self.group_variables(group_idx).get(idx))
             .collect()
     }
 
@@ -436,13 +1174,16 @@ impl State {
 
         let prev_group_name = prev_group.as_deref();
         let prev_var_name = prev_var.as_deref();
+        let first_listed = self.filtered_group_indices().first().copied().unwrap_or(0);
 
         match &mut self.ui.view {
             View::Groups { selected_idx } => {
                 let fallback = if prev_group_name.is_some() {
-                    0
+                    first_listed
                 } else {
-                    selected_idx.unwrap_or(0).min(self.data.groups.len() - 1)
+                    selected_idx
+                        .unwrap_or(first_listed)
+                        .min(self.data.groups.len() - 1)
                 };
                 let next = prev_group_name
                     .and_then(|name| self.data.groups.iter().position(|g| g.name == name))
@@ -455,7 +1196,7 @@ impl State {
                 selected_var_idx,
             } => {
                 let fallback_group = if prev_group_name.is_some() {
-                    0
+                    first_listed
                 } else {
                     (*group_idx).min(self.data.groups.len() - 1)
                 };
@@ -464,7 +1205,7 @@ impl State {
                     .unwrap_or(fallback_group);
                 *group_idx = next_group;
 
-                let vars = &self.data.groups[next_group].variables;
+                let vars = staged_variables(&self.ui.edits, &self.data.groups[next_group]);
                 if vars.is_empty() {
                     *selected_var_idx = None;
                     self.filter_cache.write().unwrap().invalidate_vars();
@@ -503,6 +1244,16 @@ impl State {
     }
 }
 
+fn staged_variables<'a>(
+    edits: &'a BTreeMap<String, StagedEdits>,
+    group: &'a VarGroup,
+) -> &'a [VarEntry] {
+    match edits.get(&group.name) {
+        Some(edits) if !group.summary_only => edits.variables(),
+        _ => &group.variables,
+    }
+}
+
 impl Debug for State {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("State")
@@ -511,6 +1262,7 @@ impl Debug for State {
             .field("groups", &self.data.groups.len())
             .field("view", &self.ui.view)
             .field("is_fetching", &self.ui.is_fetching)
+            .field("offline", &self.offline)
             .field("search", &self.ui.search)
             .field("theme", &self.theme)
             .finish()
@@ -522,9 +1274,15 @@ impl Clone for State {
         Self {
             data: self.data.clone(),
             ui: self.ui.clone(),
-            theme: self.theme,
+            theme: self.theme.clone(),
+            themes: self.themes.clone(),
+            pending_session: self.pending_session.clone(),
+            offline: self.offline,
+            copy_profiles: self.copy_profiles.clone(),
             matcher: SkimMatcherV2::default(),
-            filter_cache: RwLock::new(FilterCache::default()),
+            // The copy holds the same groups and query, so the filtered
+            // indices still apply.
+            filter_cache: RwLock::new(self.filter_cache.read().unwrap().clone()),
         }
     }
 }
@@ -545,6 +1303,7 @@ mod tests {
         VarGroup {
             name: name.to_string(),
             variables: vars,
+            ..VarGroup::default()
         }
     }
 
@@ -566,6 +1325,22 @@ mod tests {
         }
     }
 
+    #[test]
+    fn copies_share_the_groups_until_they_change() {
+        let mut state = State::new("org".into(), "proj".into());
+        state.set_groups(vec![group("Alpha", vec![var("a", "1")])]);
+        state.ui.search.groups_query.push_str("alp");
+        assert_eq!(state.filtered_group_indices(), [0]);
+
+        let copy = state.clone();
+        assert!(Arc::ptr_eq(&state.data.groups, &copy.data.groups));
+        assert!(copy.filter_cache.read().unwrap().groups.is_some());
+
+        state.set_group_details(group("Alpha", vec![var("a", "2")]));
+        assert!(!Arc::ptr_eq(&state.data.groups, &copy.data.groups));
+        assert_eq!(copy.groups()[0].variables[0].value, "1");
+    }
+
     #[test]
     fn filtered_vars_respect_search_query() {
         let mut state = State::new("org".into(), "proj".into());
@@ -619,6 +1394,93 @@ mod tests {
         );
     }
 
+    #[test]
+    fn pinned_groups_are_listed_first() {
+        let mut state = State::new("org".into(), "proj".into())
+            .with_pinned_groups(vec!["Gamma".into(), "Beta".into()]);
+        state.set_groups(vec![
+            group("Alpha", vec![]),
+            group("Beta", vec![]),
+            group("Gamma", vec![]),
+        ]);
+
+        let names = state
+            .filtered_groups()
+            .into_iter()
+            .map(|g| g.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["Gamma", "Beta", "Alpha"]);
+        assert_eq!(
+            state.current_group().map(|g| g.name.as_str()),
+            Some("Gamma")
+        );
+
+        state.ui.search.groups_query_mut().push_str("alp");
+        state.invalidate_group_cache();
+        assert_eq!(state.filtered_groups()[0].name, "Alpha");
+    }
+
+    #[test]
+    fn restores_a_saved_session_by_name_after_the_first_load() {
+        let session = Session {
+            viewing_vars: true,
+            group: Some("Beta".into()),
+            var: Some("b2".into()),
+            groups_query: "et".into(),
+            vars_queries: [("Beta".to_string(), "b".to_string())].into(),
+        };
+        let mut state = State::new("org".into(), "proj".into()).with_session(session.clone());
+        state.set_groups(vec![
+            group("Gamma", vec![]),
+            group("Beta", vec![var("b1", "1"), var("b2", "2")]),
+        ]);
+
+        assert!(state.pending_session().is_none());
+        assert_eq!(state.current_group().map(|g| g.name.as_str()), Some("Beta"));
+        assert_eq!(state.current_var().map(|v| v.name.as_str()), Some("b2"));
+        assert_eq!(state.ui.search.groups_query(), "et");
+        assert_eq!(state.active_vars_query(), Some("b"));
+        assert_eq!(Session::capture(&state), session);
+    }
+
+    #[test]
+    fn restoring_a_missing_group_falls_back_to_the_group_list() {
+        let session = Session {
+            viewing_vars: true,
+            group: Some("Deleted".into()),
+            ..Session::default()
+        };
+        let mut state = State::new("org".into(), "proj".into()).with_session(session.clone());
+        assert_eq!(Session::capture(&state), session);
+
+        state.set_groups(vec![group("Alpha", vec![var("a", "1")])]);
+        assert!(!state.is_viewing_vars());
+        assert_eq!(
+            state.current_group().map(|g| g.name.as_str()),
+            Some("Alpha")
+        );
+    }
+
+    #[test]
+    fn cached_groups_are_shown_until_refetched() {
+        let session = Session {
+            group: Some("Beta".into()),
+            ..Session::default()
+        };
+        let fetched_at = SystemTime::UNIX_EPOCH;
+        let cache = GroupCache::new(&[group("Alpha", vec![]), group("Beta", vec![])], fetched_at);
+        let mut state = State::new("org".into(), "proj".into())
+            .with_session(session)
+            .with_cached_groups(cache);
+
+        assert_eq!(state.cached_at(), Some(fetched_at));
+        assert_eq!(state.current_group().map(|g| g.name.as_str()), Some("Beta"));
+
+        state.set_groups(vec![group("Beta", vec![]), group("Gamma", vec![])]);
+        assert_eq!(state.cached_at(), None);
+        assert_eq!(state.current_group().map(|g| g.name.as_str()), Some("Beta"));
+    }
+
     #[test]
     fn set_groups_preserves_selected_group_by_name() {
         let mut state = State::new("org".into(), "proj".into());
@@ -714,4 +1576,32 @@ mod tests {
         assert_eq!(state.current_group().map(|g| g.name.as_str()), Some("B"));
         assert_eq!(state.current_var().map(|v| v.name.as_str()), Some("beta"));
     }
+
+    #[test]
+    fn overlays_stack_and_reopen_on_top() {
+        let mut ui = UiState::default();
+        ui.open(Overlay::History(HistoryView::default()));
+        ui.open(Overlay::Audit(AuditView {
+            group: "A".into(),
+            ..AuditView::default()
+        }));
+        assert!(matches!(ui.overlay(), Some(Overlay::Audit(_))));
+        assert!(ui.opened_mut::<HistoryView>().is_some());
+
+        ui.open(Overlay::Review);
+        ui.open(Overlay::Audit(AuditView {
+            group: "B".into(),
+            ..AuditView::default()
+        }));
+        assert_eq!(ui.overlays.len(), 3);
+        assert_eq!(
+            ui.close::<AuditView>().map(|audit| audit.group),
+            Some("B".into())
+        );
+        assert!(ui.is_reviewing());
+
+        ui.close_review();
+        assert!(matches!(ui.overlay(), Some(Overlay::History(_))));
+        assert!(ui.opened::<AuditView>().is_none());
+    }
 }
@@ -1,23 +1,72 @@
 use std::{
-    fs,
+    collections::{HashMap, HashSet},
+    fmt, fs,
     path::PathBuf,
     sync::{Arc, Mutex as StdMutex, MutexGuard},
+    time::{Duration, SystemTime},
 };
 
 use crate::azure_vars::state::state::*;
 use arboard::Clipboard;
 use async_trait::async_trait;
-use azure_devops_rust_api::distributed_task::variablegroups;
-use log::{info, warn};
+use azure_core::{
+    Bytes,
+    http::{
+        BufResponse,
+        headers::{HeaderName, Headers},
+    },
+};
+use azure_devops_rust_api::{
+    approvals_and_checks::{
+        check_configurations,
+        models::{GenericCheckConfiguration, ResourcePipelinePermissions},
+        pipeline_permissions,
+    },
+    audit::{audit_log, models::AuditLogQueryResult},
+    core::{
+        models::{TeamProject, TeamProjectReferenceList},
+        projects,
+    },
+    distributed_task::{
+        models::{
+            ProjectReference, VariableGroup, VariableGroupParameters, VariableGroupProjectReference,
+        },
+        variablegroups,
+    },
+    ims::{identities, models::IdentityList},
+    pipelines::{models::PipelineList, pipelines},
+};
+use log::{debug, info, warn};
+use serde::{
+    Deserialize, Deserializer,
+    de::{MapAccess, Visitor},
+};
 use tokio::{
     sync::{
         Mutex as AsyncMutex,
-        mpsc::{Receiver, Sender},
+        mpsc::{Receiver, Sender, channel},
     },
-    task::spawn_blocking,
+    task::{JoinHandle, spawn_blocking},
+    time::{self, Instant, Interval, MissedTickBehavior},
 };
 
-use super::action::Action;
+use super::{
+    action::Action,
+    audit::{self, AuditEvent, AuditLog},
+    cache::{GroupCache, SECRET_PLACEHOLDER, format_age},
+    checks::{self, Check, approval_check, read_checks},
+    connection::{AzureClients, RawClient},
+    copy::{self, CopyTarget},
+    diff::{ChangeKind, diff_groups, keep_loaded_details, summarize},
+    edits::{EditOp, StagedEdits},
+    error::ClientError,
+    journal::Journal,
+    paging::Pages,
+    permissions::{
+        self, PermissionChanges, Pipeline, PipelinePermissions, VARIABLE_GROUP_RESOURCE,
+    },
+    promote, share,
+};
 
 #[derive(Clone)]
 struct SharedClipboard(Arc<StdMutex<Clipboard>>);
@@ -34,111 +83,1553 @@ impl SharedClipboard {
     }
 }
 
+/// Variable groups requested per page.
+pub const PAGE_SIZE: i32 = 100;
+/// Audit events requested per page.
+const AUDIT_BATCH_SIZE: i32 = 200;
+/// Pages of audit events read at most, as the log covers the whole
+/// organization.
+const AUDIT_MAX_BATCHES: usize = 50;
+const CONTINUATION_TOKEN_HEADER: HeaderName = HeaderName::from_static("x-ms-continuationtoken");
+const RETRY_AFTER_HEADER: HeaderName = HeaderName::from_static("retry-after");
+
+/// One page of variable groups, and the token to request the next one.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VarGroupPage {
+    pub groups: Vec<VarGroup>,
+    pub continuation_token: Option<i32>,
+}
+
 #[async_trait]
-pub trait VariableGroupsClient: Send {
+pub trait VariableGroupsClient: Send + Sync {
+    /// One page of group summaries, without their variables. `group_name`
+    /// filters by name on the server; `*` is a wildcard.
+    async fn get_variable_groups_page(
+        &self,
+        organization: &str,
+        project: &str,
+        group_name: Option<String>,
+        continuation_token: Option<i32>,
+    ) -> Result<VarGroupPage, ClientError>;
+
+    /// One group with all of its variables.
+    async fn get_variable_group(
+        &self,
+        organization: &str,
+        project: &str,
+        group_id: i32,
+    ) -> Result<VarGroup, ClientError>;
+
+    /// Creates a group and returns it as stored, with its id.
+    async fn add_variable_group(
+        &self,
+        organization: &str,
+        project: &str,
+        group: VarGroup,
+    ) -> Result<VarGroup, ClientError>;
+
+    /// Replaces the name and variables of the group with `group.id`. Secret
+    /// variables whose value is still [`SECRET_PLACEHOLDER`] keep their
+    /// stored value.
+    async fn update_variable_group(
+        &self,
+        organization: &str,
+        project: &str,
+        group: VarGroup,
+    ) -> Result<VarGroup, ClientError>;
+
+    async fn delete_variable_group(
+        &self,
+        organization: &str,
+        project: &str,
+        group_id: i32,
+    ) -> Result<(), ClientError>;
+
+    /// The Library audit events of the organization from `start` to `end`,
+    /// oldest first. Events of other projects are left out.
+    async fn get_library_audit_events(
+        &self,
+        organization: &str,
+        project: &str,
+        start: SystemTime,
+        end: SystemTime,
+    ) -> Result<Vec<AuditEvent>, ClientError>;
+
+    /// The names of the projects of the organization.
+    async fn get_projects(&self, organization: &str) -> Result<Vec<String>, ClientError>;
+
+    /// The names of the projects the group is shared with, its own included.
+    async fn get_shared_projects(
+        &self,
+        organization: &str,
+        project: &str,
+        group_id: i32,
+    ) -> Result<Vec<String>, ClientError>;
+
+    /// Shares the group with the projects named in `share` and removes it
+    /// from those named in `unshare`.
+    async fn share_variable_group(
+        &self,
+        organization: &str,
+        project: &str,
+        group_id: i32,
+        share: Vec<String>,
+        unshare: Vec<String>,
+    ) -> Result<(), ClientError>;
+
+    /// The pipelines of the project.
+    async fn get_pipelines(
+        &self,
+        organization: &str,
+        project: &str,
+    ) -> Result<Vec<Pipeline>, ClientError>;
+
+    /// The pipelines allowed to use the group.
+    async fn get_pipeline_permissions(
+        &self,
+        organization: &str,
+        project: &str,
+        group_id: i32,
+    ) -> Result<PipelinePermissions, ClientError>;
+
+    /// Opens the group to every pipeline or restricts it again, and grants
+    /// or revokes the access of single pipelines.
+    async fn set_pipeline_permissions(
+        &self,
+        organization: &str,
+        project: &str,
+        group_id: i32,
+        changes: PermissionChanges,
+    ) -> Result<(), ClientError>;
+
+    /// The approvals and other checks runs must pass to use the group.
+    async fn get_checks(
+        &self,
+        organization: &str,
+        project: &str,
+        group_id: i32,
+    ) -> Result<Vec<Check>, ClientError>;
+
+    /// Adds an approval by every one of `approvers`, users or groups given
+    /// by email address or name.
+    async fn add_approval_check(
+        &self,
+        organization: &str,
+        project: &str,
+        group_id: i32,
+        approvers: Vec<String>,
+    ) -> Result<Check, ClientError>;
+
+    async fn remove_check(
+        &self,
+        organization: &str,
+        project: &str,
+        check_id: i32,
+    ) -> Result<(), ClientError>;
+
+    /// Fetches every page of group summaries.
     async fn get_variable_groups(
         &self,
         organization: &str,
         project: &str,
-    ) -> anyhow::Result<Vec<VarGroup>>;
+        group_name: Option<&str>,
+    ) -> Result<Vec<VarGroup>, ClientError> {
+        let mut groups = Vec::new();
+        let mut pages = Pages::new(format!("the variable groups of {organization}/{project}"));
+        loop {
+            let page = self
+                .get_variable_groups_page(
+                    organization,
+                    project,
+                    group_name.map(Into::into),
+                    pages.token(),
+                )
+                .await?;
+            groups.extend(page.groups);
+            if pages.advance(page.continuation_token) {
+                return Ok(groups);
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<T: VariableGroupsClient + ?Sized> VariableGroupsClient for Box<T> {
+    async fn get_variable_groups_page(
+        &self,
+        organization: &str,
+        project: &str,
+        group_name: Option<String>,
+        continuation_token: Option<i32>,
+    ) -> Result<VarGroupPage, ClientError> {
+        (**self)
+            .get_variable_groups_page(organization, project, group_name, continuation_token)
+            .await
+    }
+
+    async fn get_variable_group(
+        &self,
+        organization: &str,
+        project: &str,
+        group_id: i32,
+    ) -> Result<VarGroup, ClientError> {
+        (**self)
+            .get_variable_group(organization, project, group_id)
+            .await
+    }
+
+    async fn add_variable_group(
+        &self,
+        organization: &str,
+        project: &str,
+        group: VarGroup,
+    ) -> Result<VarGroup, ClientError> {
+        (**self)
+            .add_variable_group(organization, project, group)
+            .await
+    }
+
+    async fn update_variable_group(
+        &self,
+        organization: &str,
+        project: &str,
+        group: VarGroup,
+    ) -> Result<VarGroup, ClientError> {
+        (**self)
+            .update_variable_group(organization, project, group)
+            .await
+    }
+
+    async fn delete_variable_group(
+        &self,
+        organization: &str,
+        project: &str,
+        group_id: i32,
+    ) -> Result<(), ClientError> {
+        (**self)
+            .delete_variable_group(organization, project, group_id)
+            .await
+    }
+
+    async fn get_library_audit_events(
+        &self,
+        organization: &str,
+        project: &str,
+        start: SystemTime,
+        end: SystemTime,
+    ) -> Result<Vec<AuditEvent>, ClientError> {
+        (**self)
+            .get_library_audit_events(organization, project, start, end)
+            .await
+    }
+
+    async fn get_projects(&self, organization: &str) -> Result<Vec<String>, ClientError> {
+        (**self).get_projects(organization).await
+    }
+
+    async fn get_shared_projects(
+        &self,
+        organization: &str,
+        project: &str,
+        group_id: i32,
+    ) -> Result<Vec<String>, ClientError> {
+        (**self)
+            .get_shared_projects(organization, project, group_id)
+            .await
+    }
+
+    async fn share_variable_group(
+        &self,
+        organization: &str,
+        project: &str,
+        group_id: i32,
+        share: Vec<String>,
+        unshare: Vec<String>,
+    ) -> Result<(), ClientError> {
+        (**self)
+            .share_variable_group(organization, project, group_id, share, unshare)
+            .await
+    }
+
+    async fn get_pipelines(
+        &self,
+        organization: &str,
+        project: &str,
+    ) -> Result<Vec<Pipeline>, ClientError> {
+        (**self).get_pipelines(organization, project).await
+    }
+
+    async fn get_pipeline_permissions(
+        &self,
+        organization: &str,
+        project: &str,
+        group_id: i32,
+    ) -> Result<PipelinePermissions, ClientError> {
+        (**self)
+            .get_pipeline_permissions(organization, project, group_id)
+            .await
+    }
+
+    async fn set_pipeline_permissions(
+        &self,
+        organization: &str,
+        project: &str,
+        group_id: i32,
+        changes: PermissionChanges,
+    ) -> Result<(), ClientError> {
+        (**self)
+            .set_pipeline_permissions(organization, project, group_id, changes)
+            .await
+    }
+
+    async fn get_checks(
+        &self,
+        organization: &str,
+        project: &str,
+        group_id: i32,
+    ) -> Result<Vec<Check>, ClientError> {
+        (**self).get_checks(organization, project, group_id).await
+    }
+
+    async fn add_approval_check(
+        &self,
+        organization: &str,
+        project: &str,
+        group_id: i32,
+        approvers: Vec<String>,
+    ) -> Result<Check, ClientError> {
+        (**self)
+            .add_approval_check(organization, project, group_id, approvers)
+            .await
+    }
+
+    async fn remove_check(
+        &self,
+        organization: &str,
+        project: &str,
+        check_id: i32,
+    ) -> Result<(), ClientError> {
+        (**self).remove_check(organization, project, check_id).await
+    }
+}
+
+async fn next_tick(interval: &mut Option<Interval>) {
+    match interval {
+        Some(interval) => {
+            interval.tick().await;
+        }
+        None => std::future::pending().await,
+    }
 }
 
 pub struct AzureApiVariableGroupsClient {
     client: variablegroups::Client,
+    projects: projects::Client,
+    audit_log: audit_log::Client,
+    pipelines: pipelines::Client,
+    pipeline_permissions: pipeline_permissions::Client,
+    check_configurations: check_configurations::Client,
+    identities: identities::Client,
+    raw: RawClient,
 }
 
 impl AzureApiVariableGroupsClient {
-    pub fn new(client: variablegroups::Client) -> Self {
-        Self { client }
+    pub fn new(clients: AzureClients) -> Self {
+        Self {
+            client: clients.variable_groups,
+            projects: clients.projects,
+            audit_log: clients.audit_log,
+            pipelines: clients.pipelines,
+            pipeline_permissions: clients.pipeline_permissions,
+            check_configurations: clients.check_configurations,
+            identities: clients.identities,
+            raw: clients.raw,
+        }
+    }
+
+    async fn get_raw_group(
+        &self,
+        organization: &str,
+        project: &str,
+        group_id: i32,
+    ) -> Result<VariableGroup, ClientError> {
+        let response = self
+            .client
+            .get(organization.to_string(), project.to_string(), group_id)
+            .send()
+            .await?
+            .into_raw_response();
+        read_group(response, &format!("Variable group {group_id}")).await
+    }
+
+    /// Groups are linked to projects by id, which the variable group API
+    /// does not resolve from a name.
+    async fn project_reference(
+        &self,
+        organization: &str,
+        project: &str,
+    ) -> Result<ProjectReference, ClientError> {
+        let response = self
+            .projects
+            .get(organization.to_string(), project.to_string())
+            .send()
+            .await?
+            .into_raw_response();
+        let what = format!("Project `{organization}/{project}`");
+        let (_, body) = read_response(response, &what).await?;
+        let project = serde_json::from_slice::<TeamProject>(&body)?.team_project_reference;
+        Ok(ProjectReference {
+            id: project.id,
+            name: Some(project.name),
+        })
+    }
+
+    /// The id and name of the one user or group `name` refers to, such as an
+    /// email address.
+    async fn find_identity(
+        &self,
+        organization: &str,
+        name: &str,
+    ) -> Result<(String, String), ClientError> {
+        let response = self
+            .identities
+            .read_identities(organization.to_string())
+            .search_filter("General")
+            .filter_value(name.to_string())
+            .send()
+            .await?
+            .into_raw_response();
+        let what = format!("Identity `{name}`");
+        let (_, body) = read_response(response, &what).await?;
+        let mut found = serde_json::from_slice::<IdentityList>(&body)?
+            .value
+            .into_iter()
+            .filter_map(|identity| {
+                let identity = identity.identity_base;
+                let display_name = identity
+                    .custom_display_name
+                    .or(identity.provider_display_name)
+                    .unwrap_or_else(|| name.to_string());
+                Some((identity.id?, display_name))
+            });
+        match (found.next(), found.next()) {
+            (Some(identity), None) => Ok(identity),
+            (None, _) => Err(ClientError::NotFound { what }),
+            (Some(_), Some(_)) => Err(ClientError::Unexpected {
+                message: format!("`{name}` matches several identities, give an email address"),
+            }),
+        }
     }
 }
 
 #[async_trait]
 impl VariableGroupsClient for AzureApiVariableGroupsClient {
-    async fn get_variable_groups(
+    async fn get_variable_groups_page(
         &self,
         organization: &str,
         project: &str,
-    ) -> anyhow::Result<Vec<VarGroup>> {
-        let groups_resp = self
+        group_name: Option<String>,
+        continuation_token: Option<i32>,
+    ) -> Result<VarGroupPage, ClientError> {
+        let mut request = self
             .client
             .get_variable_groups(organization.to_string(), project.to_string())
-            .await?;
+            .top(PAGE_SIZE);
+        if let Some(group_name) = group_name {
+            request = request.group_name(group_name);
+        }
+        if let Some(token) = continuation_token {
+            request = request.continuation_token(token);
+        }
+        let response = request.send().await?.into_raw_response();
+        let what = format!("Project `{organization}/{project}`");
+        let (headers, body) = read_response(response, &what).await?;
 
-        let groups = groups_resp
-            .value
-            .into_iter()
-            .filter_map(|g| {
-                let name = g.name.clone()?;
-                let vars = g.variables.as_ref()?.as_object()?;
-                let variables = vars
-                    .iter()
-                    .map(|(k, v)| {
-                        let is_secret =
-                            v.get("isSecret").and_then(|b| b.as_bool()).unwrap_or(false);
-                        let value = if is_secret {
-                            "<secret value hidden>".to_string()
-                        } else {
-                            v.get("value")
-                                .and_then(|vv| vv.as_str())
-                                .unwrap_or("<no value>")
-                                .to_string()
-                        };
-                        VarEntry {
-                            name: k.clone(),
-                            value,
-                            is_secret,
-                        }
-                    })
-                    .collect();
-                Some(VarGroup { name, variables })
-            })
-            .collect::<Vec<_>>();
+        let continuation_token = match headers.get_optional_str(&CONTINUATION_TOKEN_HEADER) {
+            Some(token) => Some(token.trim_matches('"').parse::<i32>().map_err(|_| {
+                ClientError::Unexpected {
+                    message: format!("invalid continuation token {token:?}"),
+                }
+            })?),
+            None => None,
+        };
+        let list = serde_json::from_slice::<SummaryList>(&body)?;
+        debug!(
+            "Fetched {} variable groups, continuation token {continuation_token:?}",
+            list.value.len()
+        );
 
-        Ok(groups)
+        Ok(VarGroupPage {
+            groups: list.value.into_iter().filter_map(to_summary).collect(),
+            continuation_token,
+        })
     }
-}
 
-pub struct StateStore<C: VariableGroupsClient> {
-    var_groups_client: C,
-    state: State,
-    state_tx: Sender<State>,
-    clipboard: AsyncMutex<Option<SharedClipboard>>,
-}
+    async fn get_variable_group(
+        &self,
+        organization: &str,
+        project: &str,
+        group_id: i32,
+    ) -> Result<VarGroup, ClientError> {
+        let group = self.get_raw_group(organization, project, group_id).await?;
+        debug!("Fetched variable group {group_id}");
+        to_var_group(group).ok_or_else(|| ClientError::NotFound {
+            what: format!("Variable group {group_id}"),
+        })
+    }
 
-impl<C: VariableGroupsClient> StateStore<C> {
-    pub fn new(state: State, state_tx: Sender<State>, var_groups_client: C) -> Self {
-        Self {
-            state_tx,
-            var_groups_client,
-            state,
-            clipboard: AsyncMutex::new(None),
+    async fn add_variable_group(
+        &self,
+        organization: &str,
+        project: &str,
+        group: VarGroup,
+    ) -> Result<VarGroup, ClientError> {
+        let project_reference = self.project_reference(organization, project).await?;
+        let parameters = VariableGroupParameters {
+            name: Some(group.name.clone()),
+            description: group.description.clone(),
+            type_: Some("Vsts".to_string()),
+            variables: Some(to_variables(&group.variables)),
+            variable_group_project_references: vec![VariableGroupProjectReference {
+                name: Some(group.name.clone()),
+                project_reference: Some(project_reference),
+                ..VariableGroupProjectReference::new()
+            }],
+            ..VariableGroupParameters::new()
+        };
+        let response = self
+            .client
+            .add(organization.to_string(), parameters)
+            .send()
+            .await?
+            .into_raw_response();
+        let what = format!("Project `{organization}/{project}`");
+        let added = read_group(response, &what).await?;
+        info!("Added variable group {}", group.name);
+        to_var_group(added).ok_or(ClientError::Unexpected {
+            message: format!("the added group {} came back without variables", group.name),
+        })
+    }
+
+    async fn update_variable_group(
+        &self,
+        organization: &str,
+        project: &str,
+        group: VarGroup,
+    ) -> Result<VarGroup, ClientError> {
+        let group_id = group.id.ok_or_else(|| ClientError::NotFound {
+            what: format!("Variable group `{}`", group.name),
+        })?;
+        // The update replaces the whole group, so the parts not edited here
+        // are sent back as they are. A description in `group` is restored
+        // from a backup.
+        let current = self.get_raw_group(organization, project, group_id).await?;
+        let parameters = VariableGroupParameters {
+            description: group.description.clone().or(current.description),
+            name: Some(group.name.clone()),
+            provider_data: current.provider_data,
+            type_: current.type_,
+            variable_group_project_references: current.variable_group_project_references,
+            variables: Some(to_variables(&group.variables)),
+        };
+        let response = self
+            .client
+            .update(organization.to_string(), parameters, group_id)
+            .send()
+            .await?
+            .into_raw_response();
+        let updated = read_group(response, &format!("Variable group {group_id}")).await?;
+        info!("Updated variable group {}", group.name);
+        to_var_group(updated).ok_or(ClientError::Unexpected {
+            message: format!(
+                "the updated group {} came back without variables",
+                group.name
+            ),
+        })
+    }
+
+    async fn delete_variable_group(
+        &self,
+        organization: &str,
+        project: &str,
+        group_id: i32,
+    ) -> Result<(), ClientError> {
+        let project_id = self
+            .project_reference(organization, project)
+            .await?
+            .id
+            .unwrap_or_default();
+        let response = self
+            .client
+            .delete(organization.to_string(), group_id, project_id)
+            .send()
+            .await?
+            .into_raw_response();
+        read_response(response, &format!("Variable group {group_id}")).await?;
+        info!("Deleted variable group {group_id}");
+        Ok(())
+    }
+
+    async fn get_library_audit_events(
+        &self,
+        organization: &str,
+        project: &str,
+        start: SystemTime,
+        end: SystemTime,
+    ) -> Result<Vec<AuditEvent>, ClientError> {
+        let what = format!("The audit log of `{organization}`");
+        let mut events = Vec::new();
+        let mut pages =
+            Pages::new(format!("the audit log of {organization}")).at_most(AUDIT_MAX_BATCHES);
+        loop {
+            let mut request = self
+                .audit_log
+                .query(organization.to_string())
+                .start_time(start)
+                .end_time(end)
+                .batch_size(AUDIT_BATCH_SIZE)
+                .skip_aggregation(true);
+            if let Some(token) = pages.token() {
+                request = request.continuation_token(token);
+            }
+            let response = request.send().await?.into_raw_response();
+            let (_, body) = read_response(response, &what).await?;
+            let result = serde_json::from_slice::<AuditLogQueryResult>(&body)?;
+            events.extend(
+                result
+                    .decorated_audit_log_entries
+                    .into_iter()
+                    .map(AuditEvent::from)
+                    .filter(|event| {
+                        event.is_library()
+                            && event
+                                .project
+                                .as_deref()
+                                .is_none_or(|name| name.eq_ignore_ascii_case(project))
+                    }),
+            );
+            let next = result
+                .continuation_token
+                .filter(|_| result.has_more == Some(true));
+            if pages.advance(next) {
+                break;
+            }
         }
+        debug!("Fetched {} Library audit events", events.len());
+        events.sort_by_key(|event| event.at);
+        Ok(events)
     }
 
-    pub async fn main_loop(mut self, action_rx: Receiver<Action>) {
-        let mut action_rx = action_rx;
+    async fn get_projects(&self, organization: &str) -> Result<Vec<String>, ClientError> {
+        let what = format!("Organization `{organization}`");
+        let mut names = Vec::new();
+        let mut pages = Pages::new(format!("the projects of {organization}"));
+        loop {
+            let mut request = self.projects.list(organization.to_string()).top(PAGE_SIZE);
+            if let Some(token) = pages.token() {
+                request = request.continuation_token(token);
+            }
+            let response = request.send().await?.into_raw_response();
+            let (headers, body) = read_response(response, &what).await?;
+            let list = serde_json::from_slice::<TeamProjectReferenceList>(&body)?;
+            names.extend(list.value.into_iter().map(|project| project.name));
+            let returned = headers
+                .get_optional_str(&CONTINUATION_TOKEN_HEADER)
+                .and_then(|token| token.trim_matches('"').parse::<i32>().ok());
+            if pages.advance(returned) {
+                break;
+            }
+        }
+        names.sort_by_key(|name| name.to_lowercase());
+        Ok(names)
+    }
 
-        while let Some(action) = action_rx.recv().await {
-            info!("Received action: {action:?}");
-            match action {
-                Action::RefreshVarGroups => {
-                    self.state.ui.is_fetching = true;
-                    self.state_tx.send(self.state.clone()).await.unwrap();
-                    match self.fetch_var_groups().await {
-                        Ok(groups) => {
-                            self.state.set_groups(groups);
-                            self.state.ui.clear_status();
-                        }
-                        Err(error) => {
-                            warn!("Failed to fetch variable groups: {error}");
-                            self.state.ui.set_status(StatusMessage::error(format!(
-                                "Failed to load variable groups: {error}"
-                            )));
-                        }
-                    }
-                    self.state.ui.is_fetching = false;
-                }
+    async fn get_shared_projects(
+        &self,
+        organization: &str,
+        project: &str,
+        group_id: i32,
+    ) -> Result<Vec<String>, ClientError> {
+        let group = self.get_raw_group(organization, project, group_id).await?;
+        Ok(group
+            .variable_group_project_references
+            .into_iter()
+            .filter_map(|reference| reference.project_reference?.name)
+            .collect())
+    }
+
+    async fn share_variable_group(
+        &self,
+        organization: &str,
+        project: &str,
+        group_id: i32,
+        share: Vec<String>,
+        unshare: Vec<String>,
+    ) -> Result<(), ClientError> {
+        let what = format!("Variable group {group_id}");
+        if !share.is_empty() {
+            let group = self.get_raw_group(organization, project, group_id).await?;
+            let mut references = Vec::new();
+            for name in &share {
+                references.push(VariableGroupProjectReference {
+                    name: group.name.clone(),
+                    description: group.description.clone(),
+                    project_reference: Some(self.project_reference(organization, name).await?),
+                });
+            }
+            // Adds the references given, leaving the others in place.
+            let response = self
+                .client
+                .share_variable_group(organization.to_string(), references, group_id)
+                .send()
+                .await?
+                .into_raw_response();
+            read_response(response, &what).await?;
+            info!("Shared variable group {group_id} with {}", share.join(", "));
+        }
+        if !unshare.is_empty() {
+            let mut project_ids = Vec::new();
+            for name in &unshare {
+                let reference = self.project_reference(organization, name).await?;
+                project_ids.push(reference.id.unwrap_or_default());
+            }
+            // Deleting a group from a project only removes it there.
+            let response = self
+                .client
+                .delete(organization.to_string(), group_id, project_ids.join(","))
+                .send()
+                .await?
+                .into_raw_response();
+            read_response(response, &what).await?;
+            info!(
+                "Unshared variable group {group_id} from {}",
+                unshare.join(", ")
+            );
+        }
+        Ok(())
+    }
+
+    async fn get_pipelines(
+        &self,
+        organization: &str,
+        project: &str,
+    ) -> Result<Vec<Pipeline>, ClientError> {
+        let what = format!("The pipelines of `{organization}/{project}`");
+        let mut pipelines = Vec::new();
+        let mut pages = Pages::new(format!("the pipelines of {organization}/{project}"));
+        loop {
+            let mut request = self
+                .pipelines
+                .list(organization.to_string(), project.to_string())
+                .top(PAGE_SIZE);
+            if let Some(token) = pages.token() {
+                request = request.continuation_token(token);
+            }
+            let response = request.send().await?.into_raw_response();
+            let (headers, body) = read_response(response, &what).await?;
+            let list = serde_json::from_slice::<PipelineList>(&body)?;
+            pipelines.extend(list.value.into_iter().map(|pipeline| Pipeline {
+                id: pipeline.id,
+                name: pipeline.name,
+                folder: pipeline.folder,
+            }));
+            // Unlike other lists, pipelines are paged with opaque tokens.
+            let returned = headers
+                .get_optional_str(&CONTINUATION_TOKEN_HEADER)
+                .map(|token| token.trim_matches('"').to_string())
+                .filter(|token| !token.is_empty());
+            if pages.advance(returned) {
+                break;
+            }
+        }
+        pipelines.sort_by_key(|pipeline| pipeline.path().to_lowercase());
+        Ok(pipelines)
+    }
+
+    async fn get_pipeline_permissions(
+        &self,
+        organization: &str,
+        project: &str,
+        group_id: i32,
+    ) -> Result<PipelinePermissions, ClientError> {
+        let response = self
+            .pipeline_permissions
+            .get(
+                organization.to_string(),
+                project.to_string(),
+                VARIABLE_GROUP_RESOURCE,
+                group_id.to_string(),
+            )
+            .send()
+            .await?
+            .into_raw_response();
+        let what = format!("The pipeline permissions of variable group {group_id}");
+        let (_, body) = read_response(response, &what).await?;
+        Ok(serde_json::from_slice::<ResourcePipelinePermissions>(&body)?.into())
+    }
+
+    async fn set_pipeline_permissions(
+        &self,
+        organization: &str,
+        project: &str,
+        group_id: i32,
+        changes: PermissionChanges,
+    ) -> Result<(), ClientError> {
+        let response = self
+            .pipeline_permissions
+            .update_pipeline_permisions_for_resource(
+                organization.to_string(),
+                changes.to_request(group_id),
+                project.to_string(),
+                VARIABLE_GROUP_RESOURCE,
+                group_id.to_string(),
+            )
+            .send()
+            .await?
+            .into_raw_response();
+        let what = format!("The pipeline permissions of variable group {group_id}");
+        read_response(response, &what).await?;
+        info!("Updated the pipeline permissions of variable group {group_id}: {changes:?}");
+        Ok(())
+    }
+
+    async fn get_checks(
+        &self,
+        organization: &str,
+        project: &str,
+        group_id: i32,
+    ) -> Result<Vec<Check>, ClientError> {
+        let response = self
+            .check_configurations
+            .list(organization.to_string(), project.to_string())
+            .resource_type(VARIABLE_GROUP_RESOURCE)
+            .resource_id(group_id.to_string())
+            .expand("settings")
+            .send()
+            .await?
+            .into_raw_response();
+        let what = format!("The checks of variable group {group_id}");
+        let (_, body) = read_response(response, &what).await?;
+        Ok(read_checks(&body)?)
+    }
+
+    async fn add_approval_check(
+        &self,
+        organization: &str,
+        project: &str,
+        group_id: i32,
+        approvers: Vec<String>,
+    ) -> Result<Check, ClientError> {
+        let mut identities = Vec::new();
+        for approver in &approvers {
+            identities.push(self.find_identity(organization, approver).await?);
+        }
+        let path = format!("{organization}/{project}/_apis/pipelines/checks/configurations");
+        let response = self
+            .raw
+            .post(&path, &approval_check(group_id, &identities))
+            .await?;
+        let what = format!("The checks of variable group {group_id}");
+        let (_, body) = read_response(response, &what).await?;
+        let check = Check::from(serde_json::from_slice::<GenericCheckConfiguration>(&body)?);
+        info!(
+            "Added approval check {} to variable group {group_id}",
+            check.id
+        );
+        Ok(check)
+    }
+
+    async fn remove_check(
+        &self,
+        organization: &str,
+        project: &str,
+        check_id: i32,
+    ) -> Result<(), ClientError> {
+        let response = self
+            .check_configurations
+            .delete(organization.to_string(), project.to_string(), check_id)
+            .send()
+            .await?
+            .into_raw_response();
+        read_response(response, &format!("Check {check_id}")).await?;
+        info!("Removed check {check_id}");
+        Ok(())
+    }
+}
+
+/// Splits a response into headers and body, turning an error status into a
+/// [`ClientError`]. `what` names the resource requested, for 404s.
+async fn read_response(response: BufResponse, what: &str) -> Result<(Headers, Bytes), ClientError> {
+    let (status, headers, body) = response.deconstruct();
+    let body = body.collect().await?;
+    let status = u16::from(status);
+    if (200..300).contains(&status) && status != 203 {
+        return Ok((headers, body));
+    }
+    let error = ClientError::from_response(
+        status,
+        headers.get_optional_str(&RETRY_AFTER_HEADER),
+        &body,
+        what,
+        SystemTime::now(),
+    );
+    warn!("Azure DevOps returned status {status} for {what}: {error}");
+    Err(error)
+}
+
+/// Reads a response holding one group. A group that does not exist comes
+/// back as an empty body.
+async fn read_group(response: BufResponse, what: &str) -> Result<VariableGroup, ClientError> {
+    let (_, body) = read_response(response, what).await?;
+    let group = if body.iter().all(u8::is_ascii_whitespace) {
+        None
+    } else {
+        serde_json::from_slice::<Option<VariableGroup>>(&body)?
+    };
+    group.ok_or_else(|| ClientError::NotFound {
+        what: what.to_string(),
+    })
+}
+
+/// The variables as the API expects them. Secret values that were never
+/// read are sent as null, which keeps the stored value.
+fn to_variables(variables: &[VarEntry]) -> serde_json::Value {
+    let variables = variables
+        .iter()
+        .map(|var| {
+            let value = if var.is_secret && var.value == SECRET_PLACEHOLDER {
+                serde_json::Value::Null
+            } else {
+                serde_json::Value::String(var.value.clone())
+            };
+            let entry = serde_json::json!({ "value": value, "isSecret": var.is_secret });
+            (var.name.clone(), entry)
+        })
+        .collect();
+    serde_json::Value::Object(variables)
+}
+
+/// A page of the group list. Only the parts of a group shown in the list are
+/// read; its variables are counted and fingerprinted one at a time, so a page
+/// never holds them all.
+#[derive(Deserialize)]
+struct SummaryList {
+    value: Vec<GroupSummary>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GroupSummary {
+    id: Option<i32>,
+    name: Option<String>,
+    description: Option<String>,
+    #[serde(rename = "type")]
+    group_type: Option<String>,
+    modified_by: Option<ModifiedBy>,
+    variables: Option<VariablesDigest>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ModifiedBy {
+    display_name: Option<String>,
+}
+
+/// How many variables a group has, and their [`fingerprint`].
+struct VariablesDigest {
+    count: usize,
+    fingerprint: u64,
+}
+
+impl<'de> Deserialize<'de> for VariablesDigest {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct DigestVisitor;
+
+        impl<'de> Visitor<'de> for DigestVisitor {
+            type Value = VariablesDigest;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a map of variables")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                // Hashes the same text `serde_json::Value::to_string` writes
+                // for the whole map.
+                let mut digest = VariablesDigest {
+                    count: 0,
+                    fingerprint: FINGERPRINT_SEED,
+                };
+                let mut text = String::new();
+                while let Some((name, value)) = map.next_entry::<String, serde_json::Value>()? {
+                    text.clear();
+                    text.push(if digest.count == 0 { '{' } else { ',' });
+                    text.push_str(&serde_json::Value::String(name).to_string());
+                    text.push(':');
+                    text.push_str(&value.to_string());
+                    digest.fingerprint = hash_bytes(digest.fingerprint, text.as_bytes());
+                    digest.count += 1;
+                }
+                let end: &[u8] = if digest.count == 0 { b"{}" } else { b"}" };
+                digest.fingerprint = hash_bytes(digest.fingerprint, end);
+                Ok(digest)
+            }
+        }
+
+        deserializer.deserialize_map(DigestVisitor)
+    }
+}
+
+fn to_summary(group: GroupSummary) -> Option<VarGroup> {
+    let variables = group.variables?;
+    Some(VarGroup {
+        id: group.id,
+        name: group.name?,
+        description: group.description,
+        group_type: group.group_type,
+        fingerprint: Some(variables.fingerprint),
+        modified_by: group.modified_by.and_then(|identity| identity.display_name),
+        summary_only: true,
+        variable_count: variables.count,
+        ..VarGroup::default()
+    })
+}
+
+fn to_var_group(group: VariableGroup) -> Option<VarGroup> {
+    let vars = group.variables.as_ref()?.as_object()?;
+    let variables = vars
+        .iter()
+        .map(|(k, v)| {
+            let is_secret = v.get("isSecret").and_then(|b| b.as_bool()).unwrap_or(false);
+            let value = if is_secret {
+                SECRET_PLACEHOLDER.to_string()
+            } else {
+                v.get("value")
+                    .and_then(|vv| vv.as_str())
+                    .unwrap_or("<no value>")
+                    .to_string()
+            };
+            VarEntry {
+                name: k.clone(),
+                value,
+                is_secret,
+            }
+        })
+        .collect();
+    Some(VarGroup {
+        variables,
+        ..to_group_info(group)?
+    })
+}
+
+/// Everything but the variables.
+fn to_group_info(group: VariableGroup) -> Option<VarGroup> {
+    Some(VarGroup {
+        id: group.id,
+        name: group.name?,
+        description: group.description,
+        group_type: group.type_,
+        fingerprint: group.variables.as_ref().map(fingerprint),
+        modified_by: group
+            .modified_by
+            .and_then(|identity| identity.graph_subject_base.display_name),
+        ..VarGroup::default()
+    })
+}
+
+/// FNV-1a of the variables as returned by the server. It is stored in the
+/// cache, so it must not change between builds like `DefaultHasher` may.
+pub(super) fn fingerprint(variables: &serde_json::Value) -> u64 {
+    hash_bytes(FINGERPRINT_SEED, variables.to_string().as_bytes())
+}
+
+const FINGERPRINT_SEED: u64 = 0xcbf2_9ce4_8422_2325;
+
+fn hash_bytes(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Stands in for Azure DevOps in offline mode, where nothing is fetched.
+pub struct OfflineVariableGroupsClient;
+
+#[async_trait]
+impl VariableGroupsClient for OfflineVariableGroupsClient {
+    async fn get_variable_groups_page(
+        &self,
+        _organization: &str,
+        _project: &str,
+        _group_name: Option<String>,
+        _continuation_token: Option<i32>,
+    ) -> Result<VarGroupPage, ClientError> {
+        Err(ClientError::Offline)
+    }
+
+    async fn get_variable_group(
+        &self,
+        _organization: &str,
+        _project: &str,
+        _group_id: i32,
+    ) -> Result<VarGroup, ClientError> {
+        Err(ClientError::Offline)
+    }
+
+    async fn add_variable_group(
+        &self,
+        _organization: &str,
+        _project: &str,
+        _group: VarGroup,
+    ) -> Result<VarGroup, ClientError> {
+        Err(ClientError::Offline)
+    }
+
+    async fn update_variable_group(
+        &self,
+        _organization: &str,
+        _project: &str,
+        _group: VarGroup,
+    ) -> Result<VarGroup, ClientError> {
+        Err(ClientError::Offline)
+    }
+
+    async fn delete_variable_group(
+        &self,
+        _organization: &str,
+        _project: &str,
+        _group_id: i32,
+    ) -> Result<(), ClientError> {
+        Err(ClientError::Offline)
+    }
+
+    async fn get_library_audit_events(
+        &self,
+        _organization: &str,
+        _project: &str,
+        _start: SystemTime,
+        _end: SystemTime,
+    ) -> Result<Vec<AuditEvent>, ClientError> {
+        Err(ClientError::Offline)
+    }
+
+    async fn get_projects(&self, _organization: &str) -> Result<Vec<String>, ClientError> {
+        Err(ClientError::Offline)
+    }
+
+    async fn get_shared_projects(
+        &self,
+        _organization: &str,
+        _project: &str,
+        _group_id: i32,
+    ) -> Result<Vec<String>, ClientError> {
+        Err(ClientError::Offline)
+    }
+
+    async fn share_variable_group(
+        &self,
+        _organization: &str,
+        _project: &str,
+        _group_id: i32,
+        _share: Vec<String>,
+        _unshare: Vec<String>,
+    ) -> Result<(), ClientError> {
+        Err(ClientError::Offline)
+    }
+
+    async fn get_pipelines(
+        &self,
+        _organization: &str,
+        _project: &str,
+    ) -> Result<Vec<Pipeline>, ClientError> {
+        Err(ClientError::Offline)
+    }
+
+    async fn get_pipeline_permissions(
+        &self,
+        _organization: &str,
+        _project: &str,
+        _group_id: i32,
+    ) -> Result<PipelinePermissions, ClientError> {
+        Err(ClientError::Offline)
+    }
+
+    async fn set_pipeline_permissions(
+        &self,
+        _organization: &str,
+        _project: &str,
+        _group_id: i32,
+        _changes: PermissionChanges,
+    ) -> Result<(), ClientError> {
+        Err(ClientError::Offline)
+    }
+
+    async fn get_checks(
+        &self,
+        _organization: &str,
+        _project: &str,
+        _group_id: i32,
+    ) -> Result<Vec<Check>, ClientError> {
+        Err(ClientError::Offline)
+    }
+
+    async fn add_approval_check(
+        &self,
+        _organization: &str,
+        _project: &str,
+        _group_id: i32,
+        _approvers: Vec<String>,
+    ) -> Result<Check, ClientError> {
+        Err(ClientError::Offline)
+    }
+
+    async fn remove_check(
+        &self,
+        _organization: &str,
+        _project: &str,
+        _check_id: i32,
+    ) -> Result<(), ClientError> {
+        Err(ClientError::Offline)
+    }
+}
+
+/// A refresh in progress.
+struct Refresh {
+    generation: u64,
+    task: JoinHandle<()>,
+    /// The groups before the refresh, to report what changed.
+    previous: Vec<VarGroup>,
+    groups: Vec<VarGroup>,
+}
+
+/// Pages the refresh task may send ahead of the main loop.
+const FETCH_CHANNEL_SIZE: usize = 4;
+
+pub struct StateStore<C: VariableGroupsClient> {
+    var_groups_client: Arc<C>,
+    refresh: Option<Refresh>,
+    generation: u64,
+    state: State,
+    state_tx: Sender<State>,
+    clipboard: AsyncMutex<Option<SharedClipboard>>,
+    cache_path: Option<PathBuf>,
+    refresh_interval: Option<Duration>,
+    /// Groups whose variables are being loaded, by id.
+    detail_loads: HashSet<i32>,
+    /// The variables last loaded for groups that changed since, to highlight
+    /// what changed once they are loaded again.
+    outdated_details: HashMap<String, VarGroup>,
+    journal: Option<Journal>,
+    /// Groups whose unsaved changes are being saved, by name.
+    saving: HashSet<String>,
+    /// Groups being restored to a recorded version, by name.
+    restoring: HashSet<String>,
+    audit_log: AuditLog,
+    copy_targets: Vec<CopyTarget>,
+}
+
+impl<C: VariableGroupsClient + 'static> StateStore<C> {
+    pub fn new(state: State, state_tx: Sender<State>, var_groups_client: C) -> Self {
+        Self {
+            state_tx,
+            var_groups_client: Arc::new(var_groups_client),
+            refresh: None,
+            generation: 0,
+            state,
+            clipboard: AsyncMutex::new(None),
+            cache_path: None,
+            refresh_interval: None,
+            detail_loads: HashSet::new(),
+            outdated_details: HashMap::new(),
+            journal: None,
+            saving: HashSet::new(),
+            restoring: HashSet::new(),
+            audit_log: AuditLog::default(),
+            copy_targets: Vec::new(),
+        }
+    }
+
+    /// Saves every successful fetch to `cache_path` for the next launch.
+    pub fn with_cache_path(mut self, cache_path: PathBuf) -> Self {
+        self.cache_path = Some(cache_path);
+        self
+    }
+
+    /// Records every fetched version of the groups in `journal`.
+    pub fn with_journal(mut self, journal: Journal) -> Self {
+        self.journal = Some(journal);
+        self
+    }
+
+    /// Copies groups to these projects, which the state must offer with
+    /// [`State::with_copy_profiles`].
+    pub fn with_copy_targets(mut self, targets: Vec<CopyTarget>) -> Self {
+        self.copy_targets = targets;
+        self
+    }
+
+    /// Refetches the groups in the background every `interval`.
+    pub fn with_refresh_interval(mut self, interval: Duration) -> Self {
+        self.refresh_interval = Some(interval);
+        self
+    }
+
+    pub async fn main_loop(mut self, action_rx: Receiver<Action>) {
+        let mut action_rx = action_rx;
+        let mut auto_refresh = self.refresh_interval.map(|period| {
+            let mut interval = time::interval_at(Instant::now() + period, period);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            interval
+        });
+
+        let (fetch_tx, mut fetch_rx) = channel(FETCH_CHANNEL_SIZE);
+        let mut actions_open = true;
+
+        loop {
+            let action = tokio::select! {
+                action = action_rx.recv(), if actions_open => match action {
+                    Some(action) => action,
+                    // Let a refresh in progress finish before stopping.
+                    None if self.refresh.is_some() => {
+                        actions_open = false;
+                        continue;
+                    }
+                    None => break,
+                },
+                Some(action) = fetch_rx.recv() => action,
+                () = next_tick(&mut auto_refresh), if actions_open => Action::RefreshVarGroups,
+            };
+            match &action {
+                Action::VarGroupsPageLoaded {
+                    generation,
+                    groups,
+                    done,
+                } => debug!(
+                    "Received {} variable groups (refresh {generation}, done: {done})",
+                    groups.len()
+                ),
+                action => debug!("Received action: {action:?}"),
+            }
+            match action {
+                Action::RefreshVarGroups if self.state.is_offline() => {
+                    self.state.ui.report_offline("refresh variable groups");
+                }
+                Action::RefreshVarGroups if self.refresh.is_some() => {
+                    debug!("A refresh is already in progress");
+                    continue;
+                }
+                Action::RefreshVarGroups => {
+                    self.start_refresh(fetch_tx.clone());
+                }
+                Action::CancelRefresh => {
+                    let Some(refresh) = self.refresh.take() else {
+                        continue;
+                    };
+                    refresh.task.abort();
+                    info!("Cancelled refresh {}", refresh.generation);
+                    self.state.ui.is_fetching = false;
+                    self.state
+                        .ui
+                        .set_status(StatusMessage::info("Refresh cancelled"));
+                }
+                Action::VarGroupsPageLoaded {
+                    generation,
+                    groups,
+                    done,
+                } => {
+                    let Some(refresh) = self
+                        .refresh
+                        .as_mut()
+                        .filter(|refresh| refresh.generation == generation)
+                    else {
+                        continue;
+                    };
+                    // The first load fills the list as pages arrive.
+                    if refresh.previous.is_empty() {
+                        self.state.append_groups(groups.clone());
+                    }
+                    refresh.groups.extend(groups);
+                    self.state.ui.fetched_groups = refresh.groups.len();
+                    if done {
+                        self.finish_refresh(&fetch_tx);
+                        self.load_current_group(&fetch_tx);
+                    }
+                }
+                Action::VarGroupsFetchFailed { generation, error } => {
+                    if self
+                        .refresh
+                        .as_ref()
+                        .is_none_or(|refresh| refresh.generation != generation)
+                    {
+                        continue;
+                    }
+                    self.refresh = None;
+                    warn!("Failed to fetch variable groups: {error}");
+                    // Back off until the server accepts requests again.
+                    if let ClientError::Throttled {
+                        retry_after: Some(wait),
+                    } = error
+                        && let Some(interval) = auto_refresh.as_mut()
+                    {
+                        interval.reset_after(wait.max(interval.period()));
+                    }
+                    self.state.ui.set_status(StatusMessage::error(format!(
+                        "Failed to load variable groups: {error}"
+                    )));
+                    self.state.ui.is_fetching = false;
+                }
+                Action::VarGroupDetailsLoaded { id, group } => {
+                    self.detail_loads.remove(&id);
+                    if let Some(outdated) = self.outdated_details.remove(&group.name) {
+                        let changes = diff_groups(&[outdated], std::slice::from_ref(&group));
+                        self.state.mark_changed(&changes);
+                    }
+                    self.record_history(std::slice::from_ref(&group));
+                    self.state.set_group_details(group);
+                    self.write_cache();
+                }
+                Action::VarGroupDetailsFailed { id, error } => {
+                    self.detail_loads.remove(&id);
+                    let name = self
+                        .state
+                        .groups()
+                        .iter()
+                        .find(|group| group.id == Some(id))
+                        .map_or_else(|| format!("group {id}"), |group| group.name.clone());
+                    warn!("Failed to load variables of {name}: {error}");
+                    self.state.ui.set_status(StatusMessage::error(format!(
+                        "Failed to load variables of {name}: {error}"
+                    )));
+                }
+                Action::VarGroupUpdated { group } => {
+                    let restored = self.restoring.remove(&group.name);
+                    let saved = self.saving.remove(&group.name);
+                    if restored {
+                        self.state.ui.close::<HistoryView>();
+                    }
+                    info!("Updated variable group {}", group.name);
+                    let message = if restored {
+                        format!("Restored {}", group.name)
+                    } else if saved {
+                        format!("Saved {}", group.name)
+                    } else {
+                        format!("Updated {}", group.name)
+                    };
+                    self.record_history(std::slice::from_ref(&group));
+                    if saved {
+                        self.state.discard_edits(&group.name);
+                        if !self.state.has_pending_edits() {
+                            self.state.ui.close_review();
+                        }
+                    }
+                    self.state.set_group_details(group);
+                    self.write_cache();
+                    self.state.ui.set_status(StatusMessage::info(message));
+                }
+                Action::SaveConflicted { group } => {
+                    let name = group.name.clone();
+                    self.saving.remove(&name);
+                    self.record_history(std::slice::from_ref(&group));
+                    self.state.set_group_details(group);
+                    self.write_cache();
+                    let conflicts = self.state.conflict_count();
+                    warn!("Not saving {name}, {conflicts} staged variables changed meanwhile");
+                    self.state.ui.open(Overlay::Review);
+                    self.state.ui.confirm_overwrite = true;
+                    self.state.ui.set_status(StatusMessage::error(format!(
+                        "Not saved: someone else changed {conflicts} staged variables of {name}. Save again to overwrite their changes"
+                    )));
+                }
+                Action::VarGroupUpdateFailed { name, error } => {
+                    self.saving.remove(&name);
+                    self.restoring.remove(&name);
+                    warn!("Failed to update {name}: {error}");
+                    self.state.ui.set_status(StatusMessage::error(format!(
+                        "Failed to update {name}: {error}"
+                    )));
+                }
+                Action::AuditEventsLoaded {
+                    group,
+                    events,
+                    until,
+                } => {
+                    if !audit::loaded(&mut self.state, &mut self.audit_log, &group, events, until) {
+                        continue;
+                    }
+                }
+                Action::AuditEventsFailed { group, error } => {
+                    audit::load_failed(&mut self.state, &group, error);
+                }
+                Action::SharingLoaded {
+                    group,
+                    projects,
+                    shared,
+                } => {
+                    if !share::loaded(&mut self.state, &group, projects, shared) {
+                        continue;
+                    }
+                }
+                Action::SharingLoadFailed { group, error } => {
+                    share::load_failed(&mut self.state, &group, error);
+                }
+                Action::SharingUpdated {
+                    group,
+                    share,
+                    unshare,
+                } => {
+                    share::updated(&mut self.state, &group, share, unshare);
+                }
+                Action::SharingUpdateFailed { group, error } => {
+                    share::update_failed(&mut self.state, &group, error);
+                }
+                Action::PipelinesLoaded {
+                    group,
+                    pipelines,
+                    permissions,
+                } => {
+                    if !permissions::loaded(&mut self.state, &group, pipelines, permissions) {
+                        continue;
+                    }
+                }
+                Action::PipelinesLoadFailed { group, error } => {
+                    permissions::load_failed(&mut self.state, &group, error);
+                }
+                Action::PipelinePermissionsUpdated { group, changes } => {
+                    permissions::updated(&mut self.state, &group, changes);
+                }
+                Action::PipelinePermissionsUpdateFailed { group, error } => {
+                    permissions::update_failed(&mut self.state, &group, error);
+                }
+                Action::ChecksLoaded { group, checks } => {
+                    if !checks::loaded(&mut self.state, &group, checks) {
+                        continue;
+                    }
+                }
+                Action::ChecksLoadFailed { group, error } => {
+                    checks::load_failed(&mut self.state, &group, error);
+                }
+                Action::CheckAdded { group, check } => {
+                    checks::added(&mut self.state, &group, check);
+                }
+                Action::CheckRemoved { group, check } => {
+                    checks::removed(&mut self.state, &group, check);
+                }
+                Action::CheckUpdateFailed { group, error } => {
+                    checks::update_failed(&mut self.state, &group, error);
+                }
+                Action::GroupCopied {
+                    group,
+                    profile,
+                    copy,
+                    missing_secrets,
+                } => {
+                    copy::copied(&mut self.state, &group, &profile, &copy, &missing_secrets);
+                }
+                Action::GroupCopyFailed {
+                    group,
+                    profile,
+                    error,
+                } => {
+                    copy::failed(&mut self.state, &group, &profile, error);
+                }
                 Action::EnterSearchMode => {
                     assert!(!self.state.ui.search.is_active());
                     let target = if self.state.is_viewing_vars() {
@@ -214,7 +1705,7 @@ impl<C: VariableGroupsClient> StateStore<C> {
                     let (selected_var_idx, group_name) = {
                         let group = &self.state.groups()[index];
                         (
-                            if group.variables.is_empty() {
+                            if self.state.group_variables(index).is_empty() {
                                 None
                             } else {
                                 Some(0)
@@ -228,63 +1719,603 @@ impl<C: VariableGroupsClient> StateStore<C> {
                     };
                     info!("Entering variable group view: {group_name}");
                     self.state.ui.search.deactivate();
+                    self.load_current_group(&fetch_tx);
                 }
                 Action::ExitViewVarGroup => {
                     assert!(self.state.is_viewing_vars());
+                    if let Some(group) = self.state.current_group().map(|g| g.name.clone()) {
+                        self.state.mark_viewed(&group);
+                    }
                     let selected_idx = self.state.current_group_idx();
                     self.state.ui.view = View::Groups { selected_idx };
                     self.state.ui.search.deactivate();
                 }
-                Action::ToggleTheme => {
-                    self.toggle_theme();
-                }
-                Action::CopySelectedVar => {
-                    self.copy_selected_var().await;
+                Action::EnterHistory => {
+                    self.enter_history();
                 }
-                Action::ExportCurrentGroup => {
-                    if let Err(error) = self.export_current_group(Option::<String>::None) {
-                        eprintln!("Failed to export variable group: {error}");
-                        continue;
-                    };
+                Action::ExitHistory => {
+                    self.state.ui.close::<HistoryView>();
                 }
-                Action::MoveSelectionUp => {
-                    self.move_selection(-1);
+                Action::EnterAudit => {
+                    audit::enter(
+                        &mut self.state,
+                        &self.audit_log,
+                        &self.var_groups_client,
+                        &fetch_tx,
+                    );
                 }
-                Action::MoveSelectionDown => {
-                    self.move_selection(1);
+                Action::ExitAudit => {
+                    self.state.ui.close::<AuditView>();
                 }
-                Action::MoveSelectionTop => {
-                    let len = if self.state.is_viewing_vars() {
-                        self.state.filtered_var_indices().len()
-                    } else {
-                        self.state.filtered_group_indices().len()
-                    } as isize;
-                    self.move_selection(-len);
+                Action::RestoreSelectedVersion => {
+                    self.restore_selected_version(&fetch_tx);
                 }
-                Action::MoveSelectionBottom => {
-                    let len = if self.state.is_viewing_vars() {
-                        self.state.filtered_var_indices().len()
-                    } else {
-                        self.state.filtered_group_indices().len()
-                    } as isize;
-                    self.move_selection(len);
+                Action::RequestQuit => {
+                    let groups = self
+                        .state
+                        .pending_edits()
+                        .map(|(name, _)| name)
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    self.state.ui.confirm_discard = true;
+                    self.state.ui.set_status(StatusMessage::error(format!(
+                        "Unsaved changes to {groups}. Quit again to discard them"
+                    )));
                 }
-                Action::MoveSelectionPageUp => {
-                    self.move_selection(-10);
+                Action::StartEditVar => {
+                    if let Some(var) = self.state.current_var() {
+                        // Secret values are not known, so they are typed anew.
+                        let text = if var.is_secret {
+                            String::new()
+                        } else {
+                            var.value.clone()
+                        };
+                        self.state.ui.prompt = Some(Prompt {
+                            kind: PromptKind::EditValue {
+                                name: var.name.clone(),
+                                is_secret: var.is_secret,
+                            },
+                            text,
+                        });
+                    }
+                }
+                Action::StartAddVar => {
+                    if self.state.current_group().is_some_and(|g| !g.summary_only) {
+                        self.state.ui.prompt = Some(Prompt {
+                            kind: PromptKind::AddVariable,
+                            text: String::new(),
+                        });
+                    }
+                }
+                Action::RemoveSelectedVar => {
+                    if let Some(name) = self.state.current_var().map(|v| v.name.clone()) {
+                        self.stage(EditOp::Remove(name));
+                    }
+                }
+                Action::PromptInsertChar { ch } => {
+                    if let Some(prompt) = &mut self.state.ui.prompt {
+                        prompt.text.push(ch);
+                    }
+                }
+                Action::PromptBackspace => {
+                    if let Some(prompt) = &mut self.state.ui.prompt {
+                        prompt.text.pop();
+                    }
+                }
+                Action::SubmitPrompt => {
+                    self.submit_prompt(&fetch_tx);
+                }
+                Action::CancelPrompt => {
+                    self.state.ui.prompt = None;
+                }
+                Action::UndoEdit => {
+                    let message = match self.state.undo_edit() {
+                        Some(op) => format!("Undid: {}", op.describe()),
+                        None => "Nothing to undo".to_string(),
+                    };
+                    self.state.ui.set_status(StatusMessage::info(message));
+                }
+                Action::RedoEdit => {
+                    let message = match self.state.redo_edit() {
+                        Some(op) => format!("Redid: {}", op.describe()),
+                        None => "Nothing to redo".to_string(),
+                    };
+                    self.state.ui.set_status(StatusMessage::info(message));
+                }
+                Action::EnterReview => {
+                    if self.state.has_pending_edits() {
+                        self.state.ui.search.deactivate();
+                        self.state.ui.open(Overlay::Review);
+                    } else {
+                        self.state
+                            .ui
+                            .set_status(StatusMessage::info("No unsaved changes"));
+                    }
+                }
+                Action::ExitReview => {
+                    self.state.ui.close_review();
+                }
+                Action::SaveChanges => {
+                    self.save_changes(&fetch_tx);
+                }
+                Action::StartPromote => {
+                    promote::start(&mut self.state);
+                }
+                Action::PickPromoteTarget => {
+                    let Some(target) = promote::pick_target(&mut self.state) else {
+                        continue;
+                    };
+                    self.load_group(&target, &fetch_tx);
+                }
+                Action::TogglePromoteKey => {
+                    promote::toggle_key(&mut self.state);
+                }
+                Action::StartOverrideValue => {
+                    promote::start_override(&mut self.state);
+                }
+                Action::ApplyPromotion => {
+                    promote::apply(&mut self.state);
+                }
+                Action::ExitPromote => {
+                    promote::exit(&mut self.state);
+                }
+                Action::EnterShare => {
+                    share::enter(&mut self.state, &self.var_groups_client, &fetch_tx);
+                }
+                Action::ToggleShareProject => {
+                    share::toggle_project(&mut self.state);
+                }
+                Action::ApplySharing => {
+                    share::apply(&mut self.state, &self.var_groups_client, &fetch_tx);
+                }
+                Action::ExitShare => {
+                    self.state.ui.close::<ShareView>();
+                }
+                Action::EnterPipelines => {
+                    permissions::enter(&mut self.state, &self.var_groups_client, &fetch_tx);
+                }
+                Action::TogglePipeline => {
+                    permissions::toggle_pipeline(&mut self.state);
+                }
+                Action::ApplyPipelinePermissions => {
+                    permissions::apply(&mut self.state, &self.var_groups_client, &fetch_tx);
+                }
+                Action::ExitPipelines => {
+                    self.state.ui.close::<PipelinesView>();
+                }
+                Action::EnterChecks => {
+                    checks::enter(&mut self.state, &self.var_groups_client, &fetch_tx);
+                }
+                Action::StartAddApproval => {
+                    checks::start_add_approval(&mut self.state);
+                }
+                Action::RemoveSelectedCheck => {
+                    checks::remove_selected(&mut self.state, &self.var_groups_client, &fetch_tx);
+                }
+                Action::ExitChecks => {
+                    self.state.ui.close::<ChecksView>();
+                }
+                Action::StartCopy => {
+                    copy::start(&mut self.state, &self.copy_targets);
+                }
+                Action::PickCopyProfile => {
+                    if !copy::pick_profile(&mut self.state) {
+                        continue;
+                    }
+                }
+                Action::ExitCopy => {
+                    self.state.ui.close::<CopyView>();
+                }
+                Action::ToggleTheme => {
+                    self.toggle_theme();
+                }
+                Action::ToggleLogPane => {
+                    self.state.ui.show_logs = !self.state.ui.show_logs;
+                }
+                Action::CopySelectedVar => {
+                    self.copy_selected_var().await;
+                }
+                Action::ExportCurrentGroup => {
+                    if let Err(error) = self.export_current_group(Option::<String>::None) {
+                        warn!("Failed to export variable group: {error}");
+                        self.state.ui.set_status(StatusMessage::error(format!(
+                            "Failed to export variable group: {error}"
+                        )));
+                    };
+                }
+                Action::MoveSelectionUp => {
+                    self.move_selection(-1);
+                }
+                Action::MoveSelectionDown => {
+                    self.move_selection(1);
+                }
+                Action::MoveSelectionTop => {
+                    self.move_selection(-(self.selection_len() as isize));
+                }
+                Action::MoveSelectionBottom => {
+                    self.move_selection(self.selection_len() as isize);
+                }
+                Action::MoveSelectionPageUp => {
+                    self.move_selection(-10);
                 }
                 Action::MoveSelectionPageDown => {
                     self.move_selection(10);
                 }
             }
             self.state_tx.send(self.state.clone()).await.unwrap();
+            if !actions_open && self.refresh.is_none() {
+                break;
+            }
         }
     }
 
-    fn toggle_theme(&mut self) {
-        self.state.theme = match self.state.theme {
-            Theme::Dark => Theme::Light,
-            Theme::Light => Theme::Dark,
+    fn start_refresh(&mut self, fetch_tx: Sender<Action>) {
+        self.generation += 1;
+        let generation = self.generation;
+        let task = tokio::spawn(fetch_pages(
+            Arc::clone(&self.var_groups_client),
+            self.state.organization().to_string(),
+            self.state.project().to_string(),
+            generation,
+            fetch_tx,
+        ));
+        self.refresh = Some(Refresh {
+            generation,
+            task,
+            previous: self.state.groups().to_vec(),
+            groups: Vec::new(),
+        });
+        self.state.ui.is_fetching = true;
+        self.state.ui.fetched_groups = 0;
+    }
+
+    fn finish_refresh(&mut self, fetch_tx: &Sender<Action>) {
+        let Some(refresh) = self.refresh.take() else {
+            return;
+        };
+        let mut groups = refresh.groups;
+        keep_loaded_details(&refresh.previous, &mut groups);
+        // Everything is new on the first load.
+        let changes = if refresh.previous.is_empty() {
+            Vec::new()
+        } else {
+            diff_groups(&refresh.previous, &groups)
+        };
+        self.outdated_details.extend(
+            refresh
+                .previous
+                .into_iter()
+                .filter(|old| {
+                    !old.summary_only
+                        && groups
+                            .iter()
+                            .any(|group| group.summary_only && group.name == old.name)
+                })
+                .map(|old| (old.name.clone(), old)),
+        );
+        self.record_history(&groups);
+        // Only the summary of unopened groups is listed, so their new
+        // variables are loaded to be recorded too.
+        let unrecorded = changes
+            .iter()
+            .filter(|change| change.kind != ChangeKind::Removed)
+            .filter(|change| {
+                groups
+                    .iter()
+                    .any(|group| group.name == change.group && group.summary_only)
+            })
+            .map(|change| change.group.clone())
+            .collect::<Vec<_>>();
+        self.state.set_groups(groups);
+        self.state.ui.is_fetching = false;
+        self.write_cache();
+        if self.journal.is_some() {
+            for name in &unrecorded {
+                self.load_group(name, fetch_tx);
+            }
+        }
+        match summarize(&changes) {
+            Some(summary) => {
+                info!("Variable groups changed: {summary}");
+                self.state.mark_changed(&changes);
+                self.state.ui.set_status(StatusMessage::info(summary));
+            }
+            None => self.state.ui.clear_status(),
+        }
+    }
+
+    /// Starts loading the variables of the group being viewed, if only its
+    /// summary is known.
+    fn load_current_group(&mut self, fetch_tx: &Sender<Action>) {
+        if !self.state.is_viewing_vars() {
+            return;
+        }
+        if let Some(name) = self.state.current_group().map(|group| group.name.clone()) {
+            self.load_group(&name, fetch_tx);
+        }
+    }
+
+    /// Starts loading the variables of a group, if only its summary is known.
+    fn load_group(&mut self, name: &str, fetch_tx: &Sender<Action>) {
+        let Some(group) = self
+            .state
+            .groups()
+            .iter()
+            .find(|group| group.name == name && group.summary_only)
+        else {
+            return;
+        };
+        if self.state.is_offline() {
+            let message = format!(
+                "Offline mode: the variables of {} are not cached",
+                group.name
+            );
+            self.state.ui.set_status(StatusMessage::error(message));
+            return;
+        }
+        let Some(id) = group.id else {
+            return;
+        };
+        if !self.detail_loads.insert(id) {
+            return;
+        }
+        debug!("Loading variables of {}", group.name);
+        tokio::spawn(fetch_details(
+            Arc::clone(&self.var_groups_client),
+            self.state.organization().to_string(),
+            self.state.project().to_string(),
+            id,
+            fetch_tx.clone(),
+        ));
+    }
+
+    fn enter_history(&mut self) {
+        let Some(group) = self.state.current_group() else {
+            return;
+        };
+        let Some(journal) = &self.journal else {
+            self.state.ui.set_status(StatusMessage::error(
+                "History is not recorded when replaying responses or using a directory backend",
+            ));
+            return;
+        };
+        let entries = journal.history(group);
+        if entries.is_empty() {
+            let message = format!("No versions of {} recorded yet", group.name);
+            self.state.ui.set_status(StatusMessage::info(message));
+            return;
+        }
+        self.state.ui.search.deactivate();
+        self.state.ui.open(Overlay::History(HistoryView {
+            entries,
+            ..HistoryView::default()
+        }));
+    }
+
+    /// Asks for confirmation, then updates the group being viewed to the
+    /// selected version. Secrets keep their current values, which the
+    /// journal does not know.
+    fn restore_selected_version(&mut self, fetch_tx: &Sender<Action>) {
+        let Some(history) = self.state.ui.opened_mut::<HistoryView>() else {
+            return;
+        };
+        let Some(entry) = history.entries.get(history.selected) else {
+            return;
+        };
+        if !history.confirm_restore {
+            history.confirm_restore = true;
+            let age = SystemTime::now()
+                .duration_since(entry.recorded_at())
+                .unwrap_or_default();
+            let message = format!(
+                "Restore {} to its version from {} ago? Press restore again to confirm",
+                entry.group.name,
+                format_age(age)
+            );
+            self.state.ui.set_status(StatusMessage::info(message));
+            return;
+        }
+        history.confirm_restore = false;
+        let version = entry.group.clone();
+        if self.state.is_offline() {
+            self.state.ui.report_offline("restore a version");
+            return;
+        }
+        let Some(id) = self.state.current_group().and_then(|group| group.id) else {
+            return;
+        };
+        let group = VarGroup {
+            id: Some(id),
+            name: version.name,
+            variables: version.variables,
+            ..VarGroup::default()
+        };
+        info!("Restoring {} to a recorded version", group.name);
+        self.restoring.insert(group.name.clone());
+        tokio::spawn(update_group(
+            Arc::clone(&self.var_groups_client),
+            self.state.organization().to_string(),
+            self.state.project().to_string(),
+            group,
+            fetch_tx.clone(),
+        ));
+    }
+
+    fn stage(&mut self, op: EditOp) {
+        let description = op.describe();
+        if self.state.stage_edit(op) {
+            self.state
+                .ui
+                .set_status(StatusMessage::info(format!("Staged: {description}")));
+        }
+    }
+
+    /// Stages the change typed in the prompt. An invalid entry leaves the
+    /// prompt open to be corrected.
+    fn submit_prompt(&mut self, fetch_tx: &Sender<Action>) {
+        let Some(prompt) = self.state.ui.prompt.take() else {
+            return;
+        };
+        // Prompts for secrets start empty, so submitting nothing keeps the
+        // secret rather than emptying it.
+        if prompt.kind.is_secret() && prompt.text.is_empty() {
+            self.state
+                .ui
+                .set_status(StatusMessage::info("Secret left unchanged"));
+            return;
+        }
+        let var = match &prompt.kind {
+            PromptKind::EditValue { name, is_secret } => VarEntry {
+                name: name.clone(),
+                value: prompt.text,
+                is_secret: *is_secret,
+            },
+            PromptKind::OverrideValue { name, .. } => {
+                promote::set_override(&mut self.state, name, prompt.text);
+                return;
+            }
+            PromptKind::Approvers { group } => {
+                let added = checks::add_approval(
+                    &mut self.state,
+                    group,
+                    &prompt.text,
+                    &self.var_groups_client,
+                    fetch_tx,
+                );
+                if !added {
+                    self.state.ui.prompt = Some(prompt);
+                }
+                return;
+            }
+            PromptKind::CopyName { group, profile } => {
+                let copying = copy::copy_to(
+                    &mut self.state,
+                    &self.copy_targets,
+                    group,
+                    profile,
+                    &prompt.text,
+                    &self.var_groups_client,
+                    fetch_tx,
+                );
+                if !copying {
+                    self.state.ui.prompt = Some(prompt);
+                }
+                return;
+            }
+            PromptKind::AddVariable => {
+                let parsed = prompt
+                    .text
+                    .split_once('=')
+                    .map(|(name, value)| (name.trim(), value))
+                    .filter(|(name, _)| !name.is_empty());
+                let Some((name, value)) = parsed else {
+                    self.state
+                        .ui
+                        .set_status(StatusMessage::error("Expected NAME=value"));
+                    self.state.ui.prompt = Some(prompt);
+                    return;
+                };
+                let exists = self.state.vars_group_idx().is_some_and(|idx| {
+                    self.state
+                        .group_variables(idx)
+                        .iter()
+                        .any(|v| v.name == name)
+                });
+                if exists {
+                    self.state.ui.set_status(StatusMessage::error(format!(
+                        "{name} already exists, edit it instead"
+                    )));
+                    self.state.ui.prompt = Some(prompt);
+                    return;
+                }
+                VarEntry {
+                    name: name.to_string(),
+                    value: value.to_string(),
+                    is_secret: false,
+                }
+            }
+        };
+        if self.state.current_var() == Some(&var) {
+            return;
+        }
+        self.stage(EditOp::Set(var));
+    }
+
+    /// Updates every group with unsaved changes to its staged variables.
+    /// Staged changes that conflict with changes made by someone else are
+    /// shown on the review screen, and only overwrite them when saving is
+    /// asked for again.
+    fn save_changes(&mut self, fetch_tx: &Sender<Action>) {
+        if self.state.is_offline() {
+            self.state.ui.report_offline("save changes");
+            return;
+        }
+        let conflicts = self.state.conflict_count();
+        if conflicts > 0 {
+            if !self.state.ui.confirm_overwrite {
+                self.state.ui.confirm_overwrite = true;
+                self.state.ui.open(Overlay::Review);
+                self.state.ui.set_status(StatusMessage::error(format!(
+                    "{conflicts} staged variables were changed by someone else. Save again to overwrite their changes"
+                )));
+                return;
+            }
+            self.state.accept_conflicts();
+        }
+        self.state.ui.confirm_overwrite = false;
+        let updates = self
+            .state
+            .pending_edits()
+            .filter(|(name, _)| !self.saving.contains(*name))
+            .filter_map(|(name, edits)| {
+                let id = self
+                    .state
+                    .groups()
+                    .iter()
+                    .find(|group| group.name == name)
+                    .and_then(|group| group.id)?;
+                Some((id, name.to_string(), edits.clone()))
+            })
+            .collect::<Vec<_>>();
+        if updates.is_empty() {
+            return;
+        }
+        self.state.ui.set_status(StatusMessage::info(format!(
+            "Saving {} groups...",
+            updates.len()
+        )));
+        for (id, name, edits) in updates {
+            info!("Saving changes to {name}");
+            self.saving.insert(name.clone());
+            tokio::spawn(save_group(
+                Arc::clone(&self.var_groups_client),
+                self.state.organization().to_string(),
+                self.state.project().to_string(),
+                (id, name),
+                edits,
+                fetch_tx.clone(),
+            ));
+        }
+    }
+
+    /// Records the versions of `groups` that differ from the last ones seen.
+    fn record_history(&mut self, groups: &[VarGroup]) {
+        let Some(journal) = &mut self.journal else {
+            return;
         };
+        match journal.record(groups, SystemTime::now()) {
+            Ok(0) => {}
+            Ok(count) => debug!("Recorded {count} new group versions"),
+            Err(error) => warn!("Failed to record group history: {error}"),
+        }
+    }
+
+    fn toggle_theme(&mut self) {
+        self.state.theme = self
+            .state
+            .themes()
+            .next_after(&self.state.theme.name)
+            .clone();
     }
 
     fn export_current_group(&self, path: Option<impl Into<PathBuf>>) -> anyhow::Result<()> {
@@ -292,6 +2323,9 @@ impl<C: VariableGroupsClient> StateStore<C> {
             .state
             .current_group()
             .ok_or_else(|| anyhow::anyhow!("No variable group selected to export"))?;
+        if group.summary_only {
+            anyhow::bail!("The variables of {} are not loaded yet", group.name);
+        }
         let path = path
             .map(Into::into)
             .unwrap_or_else(|| format!("{}_variables.json", group.name.replace(' ', "_")).into());
@@ -375,22 +2409,62 @@ impl<C: VariableGroupsClient> StateStore<C> {
         guard.take();
     }
 
+    /// The number of rows of the list the selection moves in.
+    fn selection_len(&self) -> usize {
+        match self.state.ui.overlay() {
+            Some(Overlay::History(history)) => history.entries.len(),
+            Some(Overlay::Audit(audit)) => audit.events.len(),
+            Some(Overlay::Promote(_)) => promote::len(&self.state),
+            Some(Overlay::Copy(_)) => self.state.copy_profiles().len(),
+            Some(Overlay::Share(share)) => share.projects.len(),
+            Some(Overlay::Pipelines(view)) => view.rows(),
+            Some(Overlay::Checks(view)) => view.checks.len(),
+            Some(Overlay::Review) | None if self.state.is_viewing_vars() => {
+                self.state.filtered_var_indices().len()
+            }
+            Some(Overlay::Review) | None => self.state.filtered_group_indices().len(),
+        }
+    }
+
     fn move_selection(&mut self, delta: isize) {
-        if self.state.is_viewing_vars() {
-            let Some(group_idx) = self.state.vars_group_idx() else {
-                return;
-            };
-            let filtered = self.state.filtered_var_indices_for(group_idx);
-            if let View::Vars {
-                selected_var_idx, ..
-            } = &mut self.state.ui.view
-            {
-                Self::shift_selection(delta, filtered, selected_var_idx);
+        let len = self.selection_len();
+        let viewing_vars = self.state.is_viewing_vars();
+        let shift = |selected: &mut usize| {
+            if len > 0 {
+                *selected = (*selected as isize + delta).clamp(0, len as isize - 1) as usize;
             }
-        } else {
-            let filtered = self.state.filtered_group_indices();
-            if let View::Groups { selected_idx } = &mut self.state.ui.view {
-                Self::shift_selection(delta, filtered, selected_idx);
+        };
+        match self.state.ui.overlays.last_mut() {
+            Some(Overlay::History(history)) => {
+                shift(&mut history.selected);
+                history.confirm_restore = false;
+            }
+            Some(Overlay::Audit(audit)) => shift(&mut audit.selected),
+            Some(Overlay::Promote(promote)) => shift(&mut promote.selected),
+            Some(Overlay::Copy(copy)) => shift(&mut copy.selected),
+            Some(Overlay::Share(share)) => shift(&mut share.selected),
+            Some(Overlay::Pipelines(view)) => shift(&mut view.selected),
+            Some(Overlay::Checks(view)) => {
+                shift(&mut view.selected);
+                view.confirm_remove = false;
+            }
+            Some(Overlay::Review) | None if viewing_vars => {
+                let Some(group_idx) = self.state.vars_group_idx() else {
+                    return;
+                };
+                let filtered = self.state.filtered_var_indices_for(group_idx);
+                if let View::Vars {
+                    selected_var_idx, ..
+                } = &mut self.state.ui.view
+                {
+                    Self::shift_selection(delta, filtered, selected_var_idx);
+                }
+            }
+            Some(Overlay::Review) | None => {
+                let filtered = self.state.filtered_group_indices();
+                if let View::Groups { selected_idx } = &mut self.state.ui.view {
+                    Self::shift_selection(delta, filtered, selected_idx);
+                }
             }
         }
     }
@@ -449,60 +2523,282 @@ impl<C: VariableGroupsClient> StateStore<C> {
         }
     }
 
-    async fn fetch_var_groups(&mut self) -> anyhow::Result<Vec<VarGroup>> {
-        self.var_groups_client
-            .get_variable_groups(self.state.organization(), self.state.project())
+    fn write_cache(&self) {
+        let Some(path) = &self.cache_path else {
+            return;
+        };
+        let cache = GroupCache::new(self.state.groups(), SystemTime::now());
+        if let Err(error) = cache.write(path) {
+            warn!("Failed to cache variable groups: {error}");
+        }
+    }
+}
+
+/// Fetches every page of variable groups, reporting each one to the main loop.
+async fn fetch_pages<C: VariableGroupsClient>(
+    client: Arc<C>,
+    organization: String,
+    project: String,
+    generation: u64,
+    fetch_tx: Sender<Action>,
+) {
+    let mut pages = Pages::new(format!("the variable groups of {organization}/{project}"));
+    loop {
+        let page = match client
+            .get_variable_groups_page(&organization, &project, None, pages.token())
             .await
+        {
+            Ok(page) => page,
+            Err(error) => {
+                let _ = fetch_tx
+                    .send(Action::VarGroupsFetchFailed { generation, error })
+                    .await;
+                return;
+            }
+        };
+        let done = pages.advance(page.continuation_token);
+        let loaded = Action::VarGroupsPageLoaded {
+            generation,
+            groups: page.groups,
+            done,
+        };
+        if fetch_tx.send(loaded).await.is_err() || done {
+            return;
+        }
+    }
+}
+
+/// Loads one group in full and reports it to the main loop.
+async fn fetch_details<C: VariableGroupsClient>(
+    client: Arc<C>,
+    organization: String,
+    project: String,
+    id: i32,
+    fetch_tx: Sender<Action>,
+) {
+    let action = match client.get_variable_group(&organization, &project, id).await {
+        Ok(group) => Action::VarGroupDetailsLoaded { id, group },
+        Err(error) => Action::VarGroupDetailsFailed { id, error },
+    };
+    let _ = fetch_tx.send(action).await;
+}
+
+/// Updates one group and reports the result to the main loop.
+async fn update_group<C: VariableGroupsClient>(
+    client: Arc<C>,
+    organization: String,
+    project: String,
+    group: VarGroup,
+    fetch_tx: Sender<Action>,
+) {
+    let name = group.name.clone();
+    let action = match client
+        .update_variable_group(&organization, &project, group)
+        .await
+    {
+        Ok(group) => Action::VarGroupUpdated { group },
+        Err(error) => Action::VarGroupUpdateFailed { name, error },
+    };
+    let _ = fetch_tx.send(action).await;
+}
+
+/// Saves staged edits over the group as it is now, unless someone else
+/// changed the variables they change since they were staged.
+async fn save_group<C: VariableGroupsClient>(
+    client: Arc<C>,
+    organization: String,
+    project: String,
+    (id, name): (i32, String),
+    mut edits: StagedEdits,
+    fetch_tx: Sender<Action>,
+) {
+    let current = match client.get_variable_group(&organization, &project, id).await {
+        Ok(current) => current,
+        Err(error) => {
+            let _ = fetch_tx
+                .send(Action::VarGroupUpdateFailed { name, error })
+                .await;
+            return;
+        }
+    };
+    edits.rebase(&current.variables);
+    if !edits.conflicts().is_empty() {
+        let _ = fetch_tx
+            .send(Action::SaveConflicted { group: current })
+            .await;
+        return;
     }
+    let group = VarGroup {
+        id: Some(id),
+        name,
+        variables: edits.variables().to_vec(),
+        ..VarGroup::default()
+    };
+    update_group(client, organization, project, group, fetch_tx).await;
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::azure_vars::state::directory::DirectoryVariableGroupsClient;
+    use crate::azure_vars::state::{
+        checks::{APPROVAL_CHECK, CheckKind},
+        connection,
+        fixtures::FixtureMode,
+        theme::{Theme, ThemeSet},
+    };
+    use crate::config::config::ServerConfig;
+    use azure_devops_rust_api::Credential;
     use mockall::mock;
     use std::{
         collections::VecDeque,
         sync::{Arc, Mutex},
     };
     use tempfile::tempdir;
-    use tokio::task::JoinHandle;
 
     mock! {
         pub VarClient {}
 
         #[async_trait]
         impl VariableGroupsClient for VarClient {
-            async fn get_variable_groups(
+            async fn get_variable_groups_page(
                 &self,
                 organization: &str,
                 project: &str,
-            ) -> anyhow::Result<Vec<VarGroup>>;
-        }
-    }
-
-    type TestStore = StateStore<MockVarClient>;
+                group_name: Option<String>,
+                continuation_token: Option<i32>,
+            ) -> Result<VarGroupPage, ClientError>;
 
-    fn build_store_with_client(state: State, client: MockVarClient) -> TestStore {
-        let (state_tx, _rx) = tokio::sync::mpsc::channel(1);
-        StateStore::new(state, state_tx, client)
-    }
+            async fn get_variable_group(
+                &self,
+                organization: &str,
+                project: &str,
+                group_id: i32,
+            ) -> Result<VarGroup, ClientError>;
 
-    fn build_store(state: State) -> TestStore {
-        build_store_with_client(state, MockVarClient::new())
-    }
+            async fn add_variable_group(
+                &self,
+                organization: &str,
+                project: &str,
+                group: VarGroup,
+            ) -> Result<VarGroup, ClientError>;
 
-    fn sample_var(name: &str, value: &str) -> VarEntry {
-        VarEntry {
-            name: name.to_string(),
-            value: value.to_string(),
-            is_secret: false,
-        }
-    }
+            async fn update_variable_group(
+                &self,
+                organization: &str,
+                project: &str,
+                group: VarGroup,
+            ) -> Result<VarGroup, ClientError>;
 
-    fn sample_group(name: &str, vars: Vec<VarEntry>) -> VarGroup {
+            async fn delete_variable_group(
+                &self,
+                organization: &str,
+                project: &str,
+                group_id: i32,
+            ) -> Result<(), ClientError>;
+
+            async fn get_library_audit_events(
+                &self,
+                organization: &str,
+                project: &str,
+                start: SystemTime,
+                end: SystemTime,
+            ) -> Result<Vec<AuditEvent>, ClientError>;
+
+            async fn get_projects(&self, organization: &str) -> Result<Vec<String>, ClientError>;
+
+            async fn get_shared_projects(
+                &self,
+                organization: &str,
+                project: &str,
+                group_id: i32,
+            ) -> Result<Vec<String>, ClientError>;
+
+            async fn share_variable_group(
+                &self,
+                organization: &str,
+                project: &str,
+                group_id: i32,
+                share: Vec<String>,
+                unshare: Vec<String>,
+            ) -> Result<(), ClientError>;
+
+            async fn get_pipelines(
+                &self,
+                organization: &str,
+                project: &str,
+            ) -> Result<Vec<Pipeline>, ClientError>;
+
+            async fn get_pipeline_permissions(
+                &self,
+                organization: &str,
+                project: &str,
+                group_id: i32,
+            ) -> Result<PipelinePermissions, ClientError>;
+
+            async fn set_pipeline_permissions(
+                &self,
+                organization: &str,
+                project: &str,
+                group_id: i32,
+                changes: PermissionChanges,
+            ) -> Result<(), ClientError>;
+
+            async fn get_checks(
+                &self,
+                organization: &str,
+                project: &str,
+                group_id: i32,
+            ) -> Result<Vec<Check>, ClientError>;
+
+            async fn add_approval_check(
+                &self,
+                organization: &str,
+                project: &str,
+                group_id: i32,
+                approvers: Vec<String>,
+            ) -> Result<Check, ClientError>;
+
+            async fn remove_check(
+                &self,
+                organization: &str,
+                project: &str,
+                check_id: i32,
+            ) -> Result<(), ClientError>;
+        }
+    }
+
+    fn last_page(groups: Vec<VarGroup>) -> VarGroupPage {
+        VarGroupPage {
+            groups,
+            continuation_token: None,
+        }
+    }
+
+    type TestStore = StateStore<MockVarClient>;
+
+    fn build_store_with_client(state: State, client: MockVarClient) -> TestStore {
+        let (state_tx, _rx) = tokio::sync::mpsc::channel(1);
+        StateStore::new(state, state_tx, client)
+    }
+
+    fn build_store(state: State) -> TestStore {
+        build_store_with_client(state, MockVarClient::new())
+    }
+
+    fn sample_var(name: &str, value: &str) -> VarEntry {
+        VarEntry {
+            name: name.to_string(),
+            value: value.to_string(),
+            is_secret: false,
+        }
+    }
+
+    fn sample_group(name: &str, vars: Vec<VarEntry>) -> VarGroup {
         VarGroup {
             name: name.to_string(),
             variables: vars,
+            ..VarGroup::default()
         }
     }
 
@@ -554,232 +2850,1755 @@ mod tests {
             selected_var_idx: Some(0),
         };
 
-        let mut store = build_store(state);
+        let mut store = build_store(state);
+
+        let filtered = store.state.filtered_vars();
+        assert!(filtered.len() >= 3);
+        let second_expected = filtered[1].name.clone();
+        store.move_selection(1);
+        assert_eq!(
+            store.state.current_var().map(|v| &v.name),
+            Some(&second_expected)
+        );
+
+        let last_expected = store.state.filtered_vars().last().unwrap().name.clone();
+        store.move_selection(10);
+        assert_eq!(
+            store.state.current_var().map(|v| &v.name),
+            Some(&last_expected)
+        );
+
+        let first_expected = store.state.filtered_vars()[0].name.clone();
+        store.move_selection(-10);
+        assert_eq!(
+            store.state.current_var().map(|v| &v.name),
+            Some(&first_expected)
+        );
+    }
+
+    #[test]
+    fn toggle_theme_cycles_through_available_themes() {
+        let themes = ThemeSet::parse(
+            "themes:\n  solarized:\n    accent: yellow\n",
+            std::path::Path::new("themes.yaml"),
+        )
+        .unwrap();
+        let state =
+            State::new("org".to_string(), "project".to_string()).with_themes(themes, Theme::dark());
+        let mut store = build_store(state);
+
+        store.toggle_theme();
+        assert_eq!(store.state.theme.name, "light");
+
+        store.toggle_theme();
+        assert_eq!(store.state.theme.name, "solarized");
+
+        store.toggle_theme();
+        assert_eq!(store.state.theme, Theme::dark());
+    }
+
+    #[test]
+    fn export_current_group_persists_pretty_json() {
+        let mut state = State::new("org".to_string(), "project".to_string());
+        let group = sample_group(
+            "My Group",
+            vec![
+                sample_var("key", "value"),
+                VarEntry {
+                    name: "secret".to_string(),
+                    value: "<secret value hidden>".to_string(),
+                    is_secret: true,
+                },
+            ],
+        );
+        state.set_groups(vec![group.clone()]);
+        state.ui.view = View::Groups {
+            selected_idx: Some(0),
+        };
+
+        let store = build_store(state);
+        let dir = tempdir().unwrap();
+        let export_path = dir.path().join("custom_group.json");
+
+        store
+            .export_current_group(Some(export_path.clone()))
+            .expect("export should succeed");
+
+        let contents = fs::read_to_string(&export_path).expect("exported file readable");
+        let expected = serde_json::to_string_pretty(&group).unwrap();
+        assert_eq!(contents, expected);
+    }
+
+    #[tokio::test]
+    async fn main_loop_refresh_var_groups_updates_state() {
+        let initial_state = State::new("org".to_string(), "project".to_string());
+        let groups = vec![sample_group(
+            "A",
+            vec![sample_var("alpha", "1"), sample_var("beta", "2")],
+        )];
+        let mut client = MockVarClient::new();
+        let groups_clone = groups.clone();
+        client
+            .expect_get_variable_groups_page()
+            .return_once(move |_, _, _, _| Ok(last_page(groups_clone)));
+        let (state_tx, state_rx) = tokio::sync::mpsc::channel(4);
+        let store = StateStore::new(initial_state, state_tx, client);
+
+        let (action_tx, action_rx) = tokio::sync::mpsc::channel(4);
+
+        let collector: JoinHandle<Vec<State>> = tokio::spawn(async move {
+            let mut collected = Vec::new();
+            let mut rx = state_rx;
+            while let Some(state) = rx.recv().await {
+                collected.push(state);
+            }
+            collected
+        });
+
+        let main_loop = tokio::spawn(store.main_loop(action_rx));
+
+        action_tx.send(Action::RefreshVarGroups).await.unwrap();
+        drop(action_tx);
+
+        main_loop.await.unwrap();
+        let states = collector.await.unwrap();
+
+        assert!(
+            states.len() >= 2,
+            "expected at least two state broadcasts, got {}",
+            states.len()
+        );
+
+        let final_state = states.last().unwrap();
+        assert_eq!(*final_state.data.groups, groups);
+        assert_eq!(
+            final_state.current_group().map(|g| g.name.as_str()),
+            Some("A")
+        );
+        assert!(!final_state.is_viewing_vars());
+    }
+
+    #[tokio::test]
+    async fn main_loop_handles_search_actions() {
+        let mut initial_state = State::new("org".to_string(), "project".to_string());
+        initial_state.set_groups(vec![sample_group(
+            "Group",
+            vec![sample_var("alpha", "1"), sample_var("beta", "2")],
+        )]);
+        initial_state.ui.view = View::Vars {
+            group_idx: 0,
+            selected_var_idx: Some(0),
+        };
+        let client = MockVarClient::new();
+        let (state_tx, state_rx) = tokio::sync::mpsc::channel(8);
+        let store = StateStore::new(initial_state, state_tx, client);
+        let (action_tx, action_rx) = tokio::sync::mpsc::channel(8);
+
+        let collector: JoinHandle<Vec<State>> = tokio::spawn(async move {
+            let mut collected = Vec::new();
+            let mut rx = state_rx;
+            while let Some(state) = rx.recv().await {
+                collected.push(state);
+            }
+            collected
+        });
+
+        let main_loop = tokio::spawn(store.main_loop(action_rx));
+        action_tx.send(Action::EnterSearchMode).await.unwrap();
+        for ch in "alpha".chars() {
+            action_tx
+                .send(Action::SearchInsertChar { ch })
+                .await
+                .unwrap();
+        }
+        action_tx.send(Action::SubmitSearch).await.unwrap();
+        action_tx.send(Action::EnterSearchMode).await.unwrap();
+        action_tx.send(Action::ExitSearchMode).await.unwrap();
+        drop(action_tx);
+
+        main_loop.await.unwrap();
+        let states = collector.await.unwrap();
+        let final_state = states.last().cloned().expect("state updates should exist");
+        assert!(!final_state.ui.search.is_active());
+        assert_eq!(
+            final_state.current_var().map(|v| v.name.as_str()),
+            Some("alpha")
+        );
+    }
+
+    #[tokio::test]
+    async fn refresh_failures_update_status_and_success_clears() {
+        let initial_state = State::new("org".to_string(), "project".to_string());
+        let desired_groups = vec![sample_group(
+            "Recovered",
+            vec![sample_var("alpha", "1"), sample_var("beta", "2")],
+        )];
+        let responses = Arc::new(Mutex::new(VecDeque::from([
+            Err(ClientError::Network {
+                message: "connection reset".into(),
+            }),
+            Ok(last_page(desired_groups.clone())),
+        ])));
+
+        let mut client = MockVarClient::new();
+        let responses_clone = Arc::clone(&responses);
+        client
+            .expect_get_variable_groups_page()
+            .times(2)
+            .returning(move |_, _, _, _| {
+                responses_clone
+                    .lock()
+                    .unwrap()
+                    .pop_front()
+                    .expect("response available")
+            });
+
+        let (state_tx, state_rx) = tokio::sync::mpsc::channel(8);
+        let store = StateStore::new(initial_state, state_tx, client);
+        let (action_tx, action_rx) = tokio::sync::mpsc::channel(8);
+
+        let main_loop = tokio::spawn(store.main_loop(action_rx));
+        let mut state_rx = state_rx;
+        let mut states = Vec::new();
+        // A refresh requested while one is in progress is ignored, so retry
+        // only once the first one failed.
+        action_tx.send(Action::RefreshVarGroups).await.unwrap();
+        while let Some(state) = state_rx.recv().await {
+            let failed = state.ui.status.is_some();
+            states.push(state);
+            if failed {
+                break;
+            }
+        }
+        action_tx.send(Action::RefreshVarGroups).await.unwrap();
+        drop(action_tx);
+
+        main_loop.await.unwrap();
+        while let Some(state) = state_rx.recv().await {
+            states.push(state);
+        }
+
+        assert!(
+            states
+                .iter()
+                .any(|state| matches!(&state.ui.status, Some(status) if status.text.contains("Failed to load variable groups"))),
+            "expected at least one state with an error status",
+        );
+
+        let final_state = states.last().expect("state updates should exist");
+        assert_eq!(*final_state.data.groups, desired_groups);
+        assert!(
+            final_state.ui.status.is_none(),
+            "status should be cleared after successful refresh"
+        );
+    }
+
+    #[tokio::test]
+    async fn refresh_follows_continuation_tokens_and_streams_pages() {
+        let initial_state = State::new("org".to_string(), "project".to_string());
+        let mut client = MockVarClient::new();
+        client
+            .expect_get_variable_groups_page()
+            .times(3)
+            .returning(|_, _, _, token| {
+                Ok(match token {
+                    None => VarGroupPage {
+                        groups: vec![sample_group("A", vec![])],
+                        continuation_token: Some(7),
+                    },
+                    Some(7) => VarGroupPage {
+                        groups: vec![sample_group("B", vec![])],
+                        continuation_token: Some(3),
+                    },
+                    Some(3) => last_page(vec![sample_group("C", vec![])]),
+                    Some(other) => panic!("unexpected continuation token {other}"),
+                })
+            });
+
+        let (state_tx, state_rx) = tokio::sync::mpsc::channel(16);
+        let store = StateStore::new(initial_state, state_tx, client);
+        let (action_tx, action_rx) = tokio::sync::mpsc::channel(1);
+
+        let collector: JoinHandle<Vec<State>> = tokio::spawn(async move {
+            let mut collected = Vec::new();
+            let mut rx = state_rx;
+            while let Some(state) = rx.recv().await {
+                collected.push(state);
+            }
+            collected
+        });
+
+        let main_loop = tokio::spawn(store.main_loop(action_rx));
+        action_tx.send(Action::RefreshVarGroups).await.unwrap();
+        drop(action_tx);
+
+        main_loop.await.unwrap();
+        let states = collector.await.unwrap();
+
+        let progress = states
+            .iter()
+            .filter(|state| state.ui.is_fetching)
+            .map(|state| (state.groups().len(), state.ui.fetched_groups))
+            .collect::<Vec<_>>();
+        assert_eq!(progress, vec![(0, 0), (1, 1), (2, 2)]);
+
+        let final_state = states.last().unwrap();
+        assert!(!final_state.ui.is_fetching);
+        let names = final_state
+            .groups()
+            .iter()
+            .map(|g| g.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["A", "B", "C"]);
+    }
+
+    #[tokio::test]
+    async fn refresh_revalidates_cached_groups_and_saves_the_cache() {
+        let dir = tempdir().unwrap();
+        let cache_path = dir.path().join("cache.json");
+        let cached = GroupCache::new(&[sample_group("Old", vec![])], SystemTime::UNIX_EPOCH);
+        let initial_state =
+            State::new("org".to_string(), "project".to_string()).with_cached_groups(cached);
+        let fresh = vec![sample_group("New", vec![sample_var("alpha", "1")])];
+        let mut client = MockVarClient::new();
+        let fresh_clone = fresh.clone();
+        client
+            .expect_get_variable_groups_page()
+            .return_once(move |_, _, _, _| Ok(last_page(fresh_clone)));
+
+        let (state_tx, state_rx) = tokio::sync::mpsc::channel(8);
+        let store =
+            StateStore::new(initial_state, state_tx, client).with_cache_path(cache_path.clone());
+        let (action_tx, action_rx) = tokio::sync::mpsc::channel(1);
+
+        let collector: JoinHandle<Vec<State>> = tokio::spawn(async move {
+            let mut collected = Vec::new();
+            let mut rx = state_rx;
+            while let Some(state) = rx.recv().await {
+                collected.push(state);
+            }
+            collected
+        });
+
+        let main_loop = tokio::spawn(store.main_loop(action_rx));
+        action_tx.send(Action::RefreshVarGroups).await.unwrap();
+        drop(action_tx);
+
+        main_loop.await.unwrap();
+        let states = collector.await.unwrap();
+
+        let fetching = states.iter().find(|state| state.ui.is_fetching).unwrap();
+        assert_eq!(fetching.groups()[0].name, "Old");
+        assert!(fetching.cached_at().is_some());
+
+        let final_state = states.last().unwrap();
+        assert_eq!(final_state.groups(), fresh.as_slice());
+        assert_eq!(final_state.cached_at(), None);
+        assert_eq!(GroupCache::read(&cache_path).unwrap().groups, fresh);
+    }
+
+    #[tokio::test]
+    async fn refresh_is_disabled_offline() {
+        let cached = GroupCache::new(&[sample_group("Cached", vec![])], SystemTime::UNIX_EPOCH);
+        let initial_state = State::new("org".to_string(), "project".to_string())
+            .with_cached_groups(cached)
+            .with_offline();
+        let (state_tx, mut state_rx) = tokio::sync::mpsc::channel(4);
+        let store = StateStore::new(initial_state, state_tx, OfflineVariableGroupsClient);
+        let (action_tx, action_rx) = tokio::sync::mpsc::channel(1);
+
+        let main_loop = tokio::spawn(store.main_loop(action_rx));
+        action_tx.send(Action::RefreshVarGroups).await.unwrap();
+        drop(action_tx);
+        main_loop.await.unwrap();
+
+        let state = state_rx.recv().await.unwrap();
+        assert!(!state.ui.is_fetching);
+        assert_eq!(state.groups()[0].name, "Cached");
+        assert!(state.cached_at().is_some());
+        let status = state.ui.status.expect("offline status");
+        assert_eq!(status.kind, StatusKind::Error);
+        assert!(status.text.contains("Offline mode"));
+    }
+
+    #[tokio::test]
+    async fn auto_refresh_reports_and_highlights_changes() {
+        let initial_state = State::new("org".to_string(), "project".to_string());
+        let calls = Arc::new(Mutex::new(0));
+        let mut client = MockVarClient::new();
+        let calls_clone = Arc::clone(&calls);
+        client
+            .expect_get_variable_groups_page()
+            .returning(move |_, _, _, _| {
+                let mut calls = calls_clone.lock().unwrap();
+                *calls += 1;
+                let mut group = sample_group("app-prod", vec![sample_var("URL", "v1")]);
+                if *calls > 1 {
+                    group.variables[0].value = "v2".to_string();
+                    group.modified_by = Some("alice".to_string());
+                }
+                Ok(last_page(vec![group]))
+            });
+
+        let (state_tx, mut state_rx) = tokio::sync::mpsc::channel(8);
+        let store = StateStore::new(initial_state, state_tx, client)
+            .with_refresh_interval(Duration::from_millis(20));
+        let (action_tx, action_rx) = tokio::sync::mpsc::channel(1);
+        let main_loop = tokio::spawn(store.main_loop(action_rx));
+        action_tx.send(Action::RefreshVarGroups).await.unwrap();
+
+        let changed = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let state = state_rx.recv().await.unwrap();
+                if state.ui.status.is_some() {
+                    return state;
+                }
+            }
+        })
+        .await
+        .expect("auto refresh should report the change");
+
+        assert_eq!(
+            changed.ui.status.as_ref().unwrap().text,
+            "app-prod: 1 variable changed by alice"
+        );
+        assert!(changed.is_changed("app-prod", "URL"));
+        main_loop.abort();
+    }
+
+    #[tokio::test]
+    async fn leaving_a_group_clears_its_highlights() {
+        let mut state = State::new("org".to_string(), "project".to_string());
+        let old = vec![sample_group("Group", vec![sample_var("alpha", "1")])];
+        let new = vec![sample_group("Group", vec![sample_var("alpha", "2")])];
+        state.set_groups(new.clone());
+        state.mark_changed(&diff_groups(&old, &new));
+        state.ui.view = View::Vars {
+            group_idx: 0,
+            selected_var_idx: Some(0),
+        };
+        let (state_tx, mut state_rx) = tokio::sync::mpsc::channel(1);
+        let store = StateStore::new(state, state_tx, MockVarClient::new());
+        let (action_tx, action_rx) = tokio::sync::mpsc::channel(1);
+
+        let main_loop = tokio::spawn(store.main_loop(action_rx));
+        action_tx.send(Action::ExitViewVarGroup).await.unwrap();
+        drop(action_tx);
+
+        let state = state_rx.recv().await.unwrap();
+        assert!(!state.is_viewing_vars());
+        assert!(!state.is_changed("Group", "alpha"));
+        main_loop.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn entering_a_summarized_group_loads_its_variables() {
+        let summary = VarGroup {
+            id: Some(7),
+            summary_only: true,
+            variable_count: 1,
+            ..sample_group("Group", vec![])
+        };
+        let full = VarGroup {
+            id: Some(7),
+            ..sample_group("Group", vec![sample_var("alpha", "1")])
+        };
+        let mut client = MockVarClient::new();
+        client
+            .expect_get_variable_group()
+            .withf(|_, _, id| *id == 7)
+            .times(1)
+            .return_once(move |_, _, _| Ok(full));
+        let mut initial_state = State::new("org".to_string(), "project".to_string());
+        initial_state.set_groups(vec![summary]);
+        let (state_tx, mut state_rx) = tokio::sync::mpsc::channel(8);
+        let store = StateStore::new(initial_state, state_tx, client);
+        let (action_tx, action_rx) = tokio::sync::mpsc::channel(8);
+        let main_loop = tokio::spawn(store.main_loop(action_rx));
+
+        action_tx
+            .send(Action::EnterViewVarGroup { index: 0 })
+            .await
+            .unwrap();
+        let state = state_rx.recv().await.unwrap();
+        assert!(state.current_group().unwrap().summary_only);
+        assert_eq!(state.current_var(), None);
+
+        let state = state_rx.recv().await.unwrap();
+        assert!(!state.current_group().unwrap().summary_only);
+        assert_eq!(state.current_var().map(|v| v.name.as_str()), Some("alpha"));
+
+        drop(action_tx);
+        main_loop.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn changed_unopened_groups_are_loaded_to_be_recorded() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history.jsonl");
+        let summary = |fingerprint| VarGroup {
+            id: Some(7),
+            fingerprint: Some(fingerprint),
+            summary_only: true,
+            variable_count: 1,
+            ..sample_group("Group", vec![])
+        };
+        let full = VarGroup {
+            id: Some(7),
+            fingerprint: Some(2),
+            ..sample_group("Group", vec![sample_var("alpha", "2")])
+        };
+        let mut client = MockVarClient::new();
+        client
+            .expect_get_variable_groups_page()
+            .return_once(move |_, _, _, _| Ok(last_page(vec![summary(2)])));
+        let fetched = full.clone();
+        client
+            .expect_get_variable_group()
+            .withf(|_, _, id| *id == 7)
+            .times(1)
+            .return_once(move |_, _, _| Ok(fetched));
+        let mut initial_state = State::new("org".to_string(), "project".to_string());
+        initial_state.set_groups(vec![summary(1)]);
+        let (state_tx, mut state_rx) = tokio::sync::mpsc::channel(8);
+        let store = StateStore::new(initial_state, state_tx, client)
+            .with_journal(Journal::new(path.clone()));
+        let (action_tx, action_rx) = tokio::sync::mpsc::channel(8);
+        let main_loop = tokio::spawn(store.main_loop(action_rx));
+
+        action_tx.send(Action::RefreshVarGroups).await.unwrap();
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while state_rx.recv().await.unwrap().groups()[0].summary_only {}
+        })
+        .await
+        .expect("the changed group should be loaded");
+
+        let history = Journal::new(path).history(&full);
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].group.variables, full.variables);
+
+        drop(action_tx);
+        main_loop.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn restoring_a_recorded_version_updates_the_group() {
+        let dir = tempfile::tempdir().unwrap();
+        let old = VarGroup {
+            id: Some(7),
+            ..sample_group("Group", vec![sample_var("alpha", "1")])
+        };
+        let current = VarGroup {
+            id: Some(7),
+            ..sample_group("Group", vec![sample_var("alpha", "2")])
+        };
+        let mut journal = Journal::new(dir.path().join("history.jsonl"));
+        journal
+            .record(std::slice::from_ref(&old), SystemTime::UNIX_EPOCH)
+            .unwrap();
+        let mut client = MockVarClient::new();
+        client
+            .expect_update_variable_group()
+            .withf(|_, _, group| group.id == Some(7) && group.variables[0].value == "1")
+            .times(1)
+            .return_once(|_, _, group| Ok(group));
+        let mut initial_state = State::new("org".to_string(), "project".to_string());
+        initial_state.set_groups(vec![current]);
+        let (state_tx, mut state_rx) = tokio::sync::mpsc::channel(16);
+        let store = StateStore::new(initial_state, state_tx, client).with_journal(journal);
+        let (action_tx, action_rx) = tokio::sync::mpsc::channel(16);
+        let main_loop = tokio::spawn(store.main_loop(action_rx));
+
+        for action in [
+            Action::EnterViewVarGroup { index: 0 },
+            Action::EnterHistory,
+            Action::MoveSelectionBottom,
+        ] {
+            action_tx.send(action).await.unwrap();
+            state_rx.recv().await.unwrap();
+        }
+        action_tx
+            .send(Action::RestoreSelectedVersion)
+            .await
+            .unwrap();
+        let state = state_rx.recv().await.unwrap();
+        let history = state.ui.opened::<HistoryView>().unwrap();
+        assert_eq!(history.entries.len(), 1);
+        assert!(history.confirm_restore);
+
+        action_tx
+            .send(Action::RestoreSelectedVersion)
+            .await
+            .unwrap();
+        state_rx.recv().await.unwrap();
+        let state = state_rx.recv().await.unwrap();
+        assert!(state.ui.opened::<HistoryView>().is_none());
+        assert_eq!(state.current_var().map(|v| v.value.as_str()), Some("1"));
+
+        drop(action_tx);
+        main_loop.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn saving_while_the_history_is_open_is_not_a_restore() {
+        let dir = tempfile::tempdir().unwrap();
+        let group = VarGroup {
+            id: Some(7),
+            ..sample_group("Group", vec![sample_var("alpha", "1")])
+        };
+        let mut journal = Journal::new(dir.path().join("history.jsonl"));
+        journal
+            .record(std::slice::from_ref(&group), SystemTime::UNIX_EPOCH)
+            .unwrap();
+        let mut client = MockVarClient::new();
+        let fetched = group.clone();
+        client
+            .expect_get_variable_group()
+            .times(1)
+            .return_once(move |_, _, _| Ok(fetched));
+        client
+            .expect_update_variable_group()
+            .times(1)
+            .return_once(|_, _, group| Ok(group));
+        let mut initial_state = State::new("org".to_string(), "project".to_string());
+        initial_state.set_groups(vec![group]);
+        let (state_tx, mut state_rx) = tokio::sync::mpsc::channel(16);
+        let store = StateStore::new(initial_state, state_tx, client).with_journal(journal);
+        let (action_tx, action_rx) = tokio::sync::mpsc::channel(16);
+        let main_loop = tokio::spawn(store.main_loop(action_rx));
+
+        let actions = vec![
+            Action::EnterViewVarGroup { index: 0 },
+            Action::StartEditVar,
+            Action::PromptBackspace,
+            Action::PromptInsertChar { ch: '2' },
+            Action::SubmitPrompt,
+            Action::EnterHistory,
+            Action::SaveChanges,
+        ];
+        let count = actions.len();
+        for action in actions {
+            action_tx.send(action).await.unwrap();
+        }
+        for _ in 0..count {
+            state_rx.recv().await.unwrap();
+        }
+        let state = state_rx.recv().await.unwrap();
+        assert!(state.ui.opened::<HistoryView>().is_some());
+        assert!(!state.has_pending_edits());
+        assert_eq!(state.ui.status.unwrap().text, "Saved Group");
+
+        drop(action_tx);
+        main_loop.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn audit_view_lists_the_events_of_the_selected_group_newest_first() {
+        let event = |details: &str, timestamp: &str| -> AuditEvent {
+            let entry = serde_json::json!({
+                "actionId": "Library.VariableGroupModified",
+                "actorDisplayName": "Alice",
+                "area": "Library",
+                "details": details,
+                "timestamp": timestamp,
+            });
+            serde_json::from_value::<azure_devops_rust_api::audit::models::DecoratedAuditLogEntry>(
+                entry,
+            )
+            .unwrap()
+            .into()
+        };
+        let events = vec![
+            event("Modified variable group Group.", "2025-03-01T00:00:00Z"),
+            event("Modified variable group Other.", "2025-03-02T00:00:00Z"),
+            event("Shared variable group Group.", "2025-03-03T00:00:00Z"),
+        ];
+        let mut client = MockVarClient::new();
+        client
+            .expect_get_library_audit_events()
+            .times(1)
+            .return_once(move |_, _, _, _| Ok(events));
+        let mut initial_state = State::new("org".to_string(), "project".to_string());
+        initial_state.set_groups(vec![sample_group("Group", vec![])]);
+        let (state_tx, mut state_rx) = tokio::sync::mpsc::channel(16);
+        let store = StateStore::new(initial_state, state_tx, client);
+        let (action_tx, action_rx) = tokio::sync::mpsc::channel(16);
+        let main_loop = tokio::spawn(store.main_loop(action_rx));
+
+        action_tx.send(Action::EnterAudit).await.unwrap();
+        let state = state_rx.recv().await.unwrap();
+        assert!(state.ui.opened::<AuditView>().unwrap().loading);
+        let state = state_rx.recv().await.unwrap();
+        let audit = state.ui.opened::<AuditView>().unwrap();
+        assert!(!audit.loading);
+        let details = audit
+            .events
+            .iter()
+            .map(|event| event.details.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            details,
+            [
+                "Shared variable group Group.",
+                "Modified variable group Group."
+            ]
+        );
+
+        action_tx.send(Action::ExitAudit).await.unwrap();
+        assert!(
+            state_rx
+                .recv()
+                .await
+                .unwrap()
+                .ui
+                .opened::<AuditView>()
+                .is_none()
+        );
+
+        drop(action_tx);
+        main_loop.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn reopening_the_audit_view_only_loads_newer_events() {
+        let event = |details: &str, timestamp: &str| -> AuditEvent {
+            let entry = serde_json::json!({
+                "actionId": "Library.VariableGroupModified",
+                "area": "Library",
+                "details": details,
+                "timestamp": timestamp,
+            });
+            serde_json::from_value::<azure_devops_rust_api::audit::models::DecoratedAuditLogEntry>(
+                entry,
+            )
+            .unwrap()
+            .into()
+        };
+        let old = event("Modified variable group Group.", "2025-03-01T00:00:00Z");
+        let new = event("Shared variable group Group.", "2999-01-01T00:00:00Z");
+        let recent = |start: &SystemTime| {
+            SystemTime::now()
+                .duration_since(*start)
+                .is_ok_and(|age| age < Duration::from_secs(3600))
+        };
+        let mut client = MockVarClient::new();
+        client
+            .expect_get_library_audit_events()
+            .withf(move |_, _, start, _| !recent(start))
+            .times(1)
+            .return_once(move |_, _, _, _| Ok(vec![old]));
+        client
+            .expect_get_library_audit_events()
+            .withf(move |_, _, start, _| recent(start))
+            .times(1)
+            .return_once(move |_, _, _, _| Ok(vec![new]));
+        let mut initial_state = State::new("org".to_string(), "project".to_string());
+        initial_state.set_groups(vec![sample_group("Group", vec![])]);
+        let (state_tx, mut state_rx) = tokio::sync::mpsc::channel(16);
+        let store = StateStore::new(initial_state, state_tx, client);
+        let (action_tx, action_rx) = tokio::sync::mpsc::channel(16);
+        let main_loop = tokio::spawn(store.main_loop(action_rx));
+
+        action_tx.send(Action::EnterAudit).await.unwrap();
+        state_rx.recv().await.unwrap();
+        state_rx.recv().await.unwrap();
+        action_tx.send(Action::ExitAudit).await.unwrap();
+        state_rx.recv().await.unwrap();
+        action_tx.send(Action::EnterAudit).await.unwrap();
+        let state = state_rx.recv().await.unwrap();
+        assert_eq!(state.ui.opened::<AuditView>().unwrap().events.len(), 1);
+        let state = state_rx.recv().await.unwrap();
+        let details = state
+            .ui
+            .opened::<AuditView>()
+            .unwrap()
+            .events
+            .iter()
+            .map(|event| event.details.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            details,
+            [
+                "Shared variable group Group.",
+                "Modified variable group Group."
+            ]
+        );
+
+        drop(action_tx);
+        main_loop.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn sharing_is_changed_from_a_checklist_of_projects() {
+        let mut client = MockVarClient::new();
+        client
+            .expect_get_projects()
+            .times(1)
+            .return_once(|_| Ok(vec!["Api".into(), "project".into()]));
+        client
+            .expect_get_shared_projects()
+            .withf(|_, _, group_id| *group_id == 7)
+            .times(1)
+            .return_once(|_, _, _| Ok(vec!["project".into(), "Web".into()]));
+        client
+            .expect_share_variable_group()
+            .withf(|_, _, group_id, share, unshare| {
+                *group_id == 7 && share == &["Api"] && unshare == &["Web"]
+            })
+            .times(1)
+            .return_once(|_, _, _, _, _| Ok(()));
+        let mut initial_state = State::new("org".to_string(), "project".to_string());
+        initial_state.set_groups(vec![VarGroup {
+            id: Some(7),
+            ..sample_group("Group", vec![])
+        }]);
+        let (state_tx, mut state_rx) = tokio::sync::mpsc::channel(16);
+        let store = StateStore::new(initial_state, state_tx, client);
+        let (action_tx, action_rx) = tokio::sync::mpsc::channel(16);
+        let main_loop = tokio::spawn(store.main_loop(action_rx));
+
+        action_tx.send(Action::EnterShare).await.unwrap();
+        assert!(
+            state_rx
+                .recv()
+                .await
+                .unwrap()
+                .ui
+                .opened::<ShareView>()
+                .unwrap()
+                .loading
+        );
+        let share = state_rx
+            .recv()
+            .await
+            .unwrap()
+            .ui
+            .opened::<ShareView>()
+            .cloned()
+            .unwrap();
+        // Projects the group is shared with are listed even when hidden.
+        assert_eq!(share.projects, ["Api", "project", "Web"]);
+
+        let actions = [
+            Action::ToggleShareProject,
+            Action::MoveSelectionDown,
+            Action::ToggleShareProject,
+            Action::MoveSelectionDown,
+            Action::ToggleShareProject,
+        ];
+        let count = actions.len();
+        for action in actions {
+            action_tx.send(action).await.unwrap();
+        }
+        let mut state = state_rx.recv().await.unwrap();
+        for _ in 1..count {
+            state = state_rx.recv().await.unwrap();
+        }
+        let share = state.ui.opened::<ShareView>().cloned().unwrap();
+        assert_eq!(
+            share.changes(),
+            (vec!["Api".to_string()], vec!["Web".to_string()])
+        );
+
+        action_tx.send(Action::ApplySharing).await.unwrap();
+        assert!(
+            state_rx
+                .recv()
+                .await
+                .unwrap()
+                .ui
+                .opened::<ShareView>()
+                .unwrap()
+                .loading
+        );
+        let state = state_rx.recv().await.unwrap();
+        let share = state.ui.opened::<ShareView>().cloned().unwrap();
+        assert!(!share.loading);
+        assert_eq!(share.changes(), (Vec::new(), Vec::new()));
+        assert_eq!(
+            state.ui.status.unwrap().text,
+            "Shared Group with Api, removed it from Web"
+        );
+
+        drop(action_tx);
+        main_loop.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn pipelines_are_granted_and_revoked_access_to_a_group() {
+        let mut client = MockVarClient::new();
+        client.expect_get_pipelines().times(1).return_once(|_, _| {
+            Ok(vec![
+                Pipeline {
+                    id: 1,
+                    name: "api".into(),
+                    folder: "\\".into(),
+                },
+                Pipeline {
+                    id: 2,
+                    name: "web".into(),
+                    folder: "\\deploy".into(),
+                },
+            ])
+        });
+        client
+            .expect_get_pipeline_permissions()
+            .withf(|_, _, group_id| *group_id == 7)
+            .times(1)
+            .return_once(|_, _, _| {
+                Ok(PipelinePermissions {
+                    all_pipelines: false,
+                    authorized: [2, 9].into(),
+                })
+            });
+        client
+            .expect_set_pipeline_permissions()
+            .withf(|_, _, group_id, changes| {
+                *group_id == 7
+                    && changes
+                        == &PermissionChanges {
+                            all_pipelines: None,
+                            grant: vec![1],
+                            revoke: vec![2],
+                        }
+            })
+            .times(1)
+            .return_once(|_, _, _, _| Ok(()));
+        let mut initial_state = State::new("org".to_string(), "project".to_string());
+        initial_state.set_groups(vec![VarGroup {
+            id: Some(7),
+            ..sample_group("Group", vec![])
+        }]);
+        let (state_tx, mut state_rx) = tokio::sync::mpsc::channel(16);
+        let store = StateStore::new(initial_state, state_tx, client);
+        let (action_tx, action_rx) = tokio::sync::mpsc::channel(16);
+        let main_loop = tokio::spawn(store.main_loop(action_rx));
+
+        action_tx.send(Action::EnterPipelines).await.unwrap();
+        assert!(
+            state_rx
+                .recv()
+                .await
+                .unwrap()
+                .ui
+                .opened::<PipelinesView>()
+                .unwrap()
+                .loading
+        );
+        let view = state_rx
+            .recv()
+            .await
+            .unwrap()
+            .ui
+            .opened::<PipelinesView>()
+            .cloned()
+            .unwrap();
+        // A deleted pipeline that is still authorized is listed by id.
+        let paths = view
+            .pipelines
+            .iter()
+            .map(Pipeline::path)
+            .collect::<Vec<_>>();
+        assert_eq!(paths, ["api", "deploy/web", "pipeline 9"]);
+
+        let actions = [
+            Action::MoveSelectionDown,
+            Action::TogglePipeline,
+            Action::MoveSelectionDown,
+            Action::TogglePipeline,
+        ];
+        let count = actions.len();
+        for action in actions {
+            action_tx.send(action).await.unwrap();
+        }
+        for _ in 0..count {
+            state_rx.recv().await.unwrap();
+        }
+
+        action_tx
+            .send(Action::ApplyPipelinePermissions)
+            .await
+            .unwrap();
+        assert!(
+            state_rx
+                .recv()
+                .await
+                .unwrap()
+                .ui
+                .opened::<PipelinesView>()
+                .unwrap()
+                .loading
+        );
+        let state = state_rx.recv().await.unwrap();
+        let view = state.ui.opened::<PipelinesView>().cloned().unwrap();
+        assert!(!view.loading);
+        assert!(view.changes().is_empty());
+        assert_eq!(view.granted.authorized, [1, 9].into());
+        assert_eq!(
+            state.ui.status.unwrap().text,
+            "Updated the pipeline permissions of Group: granted api, revoked deploy/web"
+        );
+
+        drop(action_tx);
+        main_loop.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn approvals_are_added_to_and_removed_from_a_group() {
+        let approval = |id, approvers: &[&str]| Check {
+            id,
+            kind: CheckKind::Approval {
+                approvers: approvers.iter().map(|a| a.to_string()).collect(),
+                min_approvers: None,
+            },
+            disabled: false,
+        };
+        let branches = Check {
+            id: 5,
+            kind: CheckKind::BranchControl {
+                branches: "refs/heads/main".into(),
+                protected_only: false,
+            },
+            disabled: false,
+        };
+        let mut client = MockVarClient::new();
+        let listed = vec![branches, approval(4, &["Bo"])];
+        client
+            .expect_get_checks()
+            .withf(|_, _, group_id| *group_id == 7)
+            .times(1)
+            .return_once(move |_, _, _| Ok(listed));
+        let added = approval(8, &["Ana"]);
+        client
+            .expect_add_approval_check()
+            .withf(|_, _, group_id, approvers| *group_id == 7 && *approvers == ["ana@contoso.com"])
+            .times(1)
+            .return_once(move |_, _, _, _| Ok(added));
+        client
+            .expect_remove_check()
+            .withf(|_, _, check_id| *check_id == 4)
+            .times(1)
+            .return_once(|_, _, _| Ok(()));
+        let mut initial_state = State::new("org".to_string(), "project".to_string());
+        initial_state.set_groups(vec![VarGroup {
+            id: Some(7),
+            ..sample_group("Group", vec![])
+        }]);
+        let (state_tx, mut state_rx) = tokio::sync::mpsc::channel(16);
+        let store = StateStore::new(initial_state, state_tx, client);
+        let (action_tx, action_rx) = tokio::sync::mpsc::channel(32);
+        let main_loop = tokio::spawn(store.main_loop(action_rx));
+
+        action_tx.send(Action::EnterChecks).await.unwrap();
+        assert!(
+            state_rx
+                .recv()
+                .await
+                .unwrap()
+                .ui
+                .opened::<ChecksView>()
+                .unwrap()
+                .loading
+        );
+        let view = state_rx
+            .recv()
+            .await
+            .unwrap()
+            .ui
+            .opened::<ChecksView>()
+            .cloned()
+            .unwrap();
+        assert_eq!(view.checks.len(), 2);
+
+        // Only approvals can be removed.
+        action_tx.send(Action::RemoveSelectedCheck).await.unwrap();
+        let state = state_rx.recv().await.unwrap();
+        assert_eq!(
+            state.ui.status.unwrap().text,
+            "Only approvals can be removed here, not Branch control"
+        );
+
+        let mut actions = vec![Action::StartAddApproval];
+        actions.extend(
+            " ana@contoso.com, "
+                .chars()
+                .map(|ch| Action::PromptInsertChar { ch }),
+        );
+        let count = actions.len();
+        for action in actions {
+            action_tx.send(action).await.unwrap();
+        }
+        for _ in 0..count {
+            state_rx.recv().await.unwrap();
+        }
+        action_tx.send(Action::SubmitPrompt).await.unwrap();
+        assert!(
+            state_rx
+                .recv()
+                .await
+                .unwrap()
+                .ui
+                .opened::<ChecksView>()
+                .unwrap()
+                .loading
+        );
+        let state = state_rx.recv().await.unwrap();
+        assert_eq!(state.ui.opened::<ChecksView>().unwrap().checks.len(), 3);
+        assert_eq!(
+            state.ui.status.unwrap().text,
+            "Added an approval by Ana (all) to Group"
+        );
+
+        action_tx.send(Action::MoveSelectionUp).await.unwrap();
+        state_rx.recv().await.unwrap();
+        action_tx.send(Action::RemoveSelectedCheck).await.unwrap();
+        let state = state_rx.recv().await.unwrap();
+        assert!(state.ui.opened::<ChecksView>().unwrap().confirm_remove);
+        assert_eq!(
+            state.ui.status.unwrap().text,
+            "Remove the approval by Bo (all) from Group? Press remove again to confirm"
+        );
+        action_tx.send(Action::RemoveSelectedCheck).await.unwrap();
+        assert!(
+            state_rx
+                .recv()
+                .await
+                .unwrap()
+                .ui
+                .opened::<ChecksView>()
+                .unwrap()
+                .loading
+        );
+        let state = state_rx.recv().await.unwrap();
+        let ids = state
+            .ui
+            .opened::<ChecksView>()
+            .unwrap()
+            .checks
+            .iter()
+            .map(|check| check.id)
+            .collect::<Vec<_>>();
+        assert_eq!(ids, [5, 8]);
+        assert_eq!(
+            state.ui.status.unwrap().text,
+            "Removed the approval by Bo (all) from Group"
+        );
+
+        drop(action_tx);
+        main_loop.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn staged_edits_can_be_undone_reviewed_and_saved() {
+        let group = VarGroup {
+            id: Some(7),
+            ..sample_group(
+                "Group",
+                vec![sample_var("alpha", "1"), sample_var("beta", "2")],
+            )
+        };
+        let mut client = MockVarClient::new();
+        let current = group.clone();
+        client
+            .expect_get_variable_group()
+            .times(1)
+            .return_once(move |_, _, _| Ok(current));
+        client
+            .expect_update_variable_group()
+            .withf(|_, _, group| {
+                group.id == Some(7)
+                    && group.variables == [sample_var("alpha", "10"), sample_var("gamma", "3")]
+            })
+            .times(1)
+            .return_once(|_, _, group| Ok(group));
+        let mut initial_state = State::new("org".to_string(), "project".to_string());
+        initial_state.set_groups(vec![group]);
+        let (state_tx, mut state_rx) = tokio::sync::mpsc::channel(32);
+        let store = StateStore::new(initial_state, state_tx, client);
+        let (action_tx, action_rx) = tokio::sync::mpsc::channel(32);
+        let main_loop = tokio::spawn(store.main_loop(action_rx));
+
+        let mut actions = vec![Action::EnterViewVarGroup { index: 0 }, Action::StartEditVar];
+        actions.push(Action::PromptBackspace);
+        actions.extend("10".chars().map(|ch| Action::PromptInsertChar { ch }));
+        actions.extend([Action::SubmitPrompt, Action::StartAddVar]);
+        actions.extend("gamma=3".chars().map(|ch| Action::PromptInsertChar { ch }));
+        actions.extend([
+            Action::SubmitPrompt,
+            Action::MoveSelectionTop,
+            Action::MoveSelectionDown,
+            Action::RemoveSelectedVar,
+            Action::UndoEdit,
+            Action::RedoEdit,
+        ]);
+        let count = actions.len();
+        for action in actions {
+            action_tx.send(action).await.unwrap();
+        }
+        let mut state = state_rx.recv().await.unwrap();
+        for _ in 1..count {
+            state = state_rx.recv().await.unwrap();
+        }
+        assert!(!state.is_prompting());
+        let names = state
+            .filtered_vars()
+            .iter()
+            .map(|v| v.name.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["alpha", "gamma"]);
+        // The fetched group is left as it was until the changes are saved.
+        assert_eq!(state.groups()[0].variables[0].value, "1");
+
+        action_tx.send(Action::EnterReview).await.unwrap();
+        assert!(state_rx.recv().await.unwrap().is_reviewing());
+        action_tx.send(Action::SaveChanges).await.unwrap();
+        state_rx.recv().await.unwrap();
+        let state = state_rx.recv().await.unwrap();
+        assert!(!state.has_pending_edits());
+        assert!(!state.is_reviewing());
+        assert_eq!(state.groups()[0].variables[0].value, "10");
+
+        drop(action_tx);
+        main_loop.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn submitting_a_secret_unchanged_stages_nothing() {
+        let mut initial_state = State::new("org".to_string(), "project".to_string());
+        initial_state.set_groups(vec![sample_group(
+            "Group",
+            vec![VarEntry {
+                name: "TOKEN".into(),
+                value: SECRET_PLACEHOLDER.into(),
+                is_secret: true,
+            }],
+        )]);
+        let (state_tx, mut state_rx) = tokio::sync::mpsc::channel(16);
+        let store = StateStore::new(initial_state, state_tx, MockVarClient::new());
+        let (action_tx, action_rx) = tokio::sync::mpsc::channel(16);
+        let main_loop = tokio::spawn(store.main_loop(action_rx));
+
+        let actions = [
+            Action::EnterViewVarGroup { index: 0 },
+            Action::StartEditVar,
+            Action::SubmitPrompt,
+        ];
+        let count = actions.len();
+        for action in actions {
+            action_tx.send(action).await.unwrap();
+        }
+        let mut state = state_rx.recv().await.unwrap();
+        for _ in 1..count {
+            state = state_rx.recv().await.unwrap();
+        }
+        assert!(!state.is_prompting());
+        assert!(!state.has_pending_edits());
+        assert_eq!(state.ui.status.unwrap().text, "Secret left unchanged");
+
+        drop(action_tx);
+        main_loop.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn saving_over_changes_made_meanwhile_asks_first() {
+        let group = VarGroup {
+            id: Some(7),
+            ..sample_group(
+                "Group",
+                vec![sample_var("alpha", "1"), sample_var("beta", "2")],
+            )
+        };
+        // Someone else changed alpha after it was fetched.
+        let changed = VarGroup {
+            variables: vec![sample_var("alpha", "5"), sample_var("beta", "2")],
+            ..group.clone()
+        };
+        let mut client = MockVarClient::new();
+        client
+            .expect_get_variable_group()
+            .times(2)
+            .returning(move |_, _, _| Ok(changed.clone()));
+        client
+            .expect_update_variable_group()
+            .withf(|_, _, group| {
+                group.variables == [sample_var("alpha", "10"), sample_var("beta", "2")]
+            })
+            .times(1)
+            .return_once(|_, _, group| Ok(group));
+        let mut initial_state = State::new("org".to_string(), "project".to_string());
+        initial_state.set_groups(vec![group]);
+        let (state_tx, mut state_rx) = tokio::sync::mpsc::channel(32);
+        let store = StateStore::new(initial_state, state_tx, client);
+        let (action_tx, action_rx) = tokio::sync::mpsc::channel(32);
+        let main_loop = tokio::spawn(store.main_loop(action_rx));
 
-        let filtered = store.state.filtered_vars();
-        assert!(filtered.len() >= 3);
-        let second_expected = filtered[1].name.clone();
-        store.move_selection(1);
+        let mut actions = vec![
+            Action::EnterViewVarGroup { index: 0 },
+            Action::StartEditVar,
+            Action::PromptBackspace,
+        ];
+        actions.extend("10".chars().map(|ch| Action::PromptInsertChar { ch }));
+        actions.extend([Action::SubmitPrompt, Action::SaveChanges]);
+        let count = actions.len();
+        for action in actions {
+            action_tx.send(action).await.unwrap();
+        }
+        for _ in 0..count {
+            state_rx.recv().await.unwrap();
+        }
+        let state = state_rx.recv().await.unwrap();
+        assert!(state.is_reviewing());
+        assert_eq!(state.conflict_count(), 1);
+        assert_eq!(state.groups()[0].variables[0].value, "5");
         assert_eq!(
-            store.state.current_var().map(|v| &v.name),
-            Some(&second_expected)
+            state.ui.status.unwrap().text,
+            "Not saved: someone else changed 1 staged variables of Group. Save again to overwrite their changes"
         );
 
-        let last_expected = store.state.filtered_vars().last().unwrap().name.clone();
-        store.move_selection(10);
+        action_tx.send(Action::SaveChanges).await.unwrap();
+        state_rx.recv().await.unwrap();
+        let state = state_rx.recv().await.unwrap();
+        assert!(!state.has_pending_edits());
+        assert_eq!(state.groups()[0].variables[0].value, "10");
+
+        drop(action_tx);
+        main_loop.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn promoting_stages_the_chosen_keys_in_the_target() {
+        let mut initial_state = State::new("org".to_string(), "project".to_string());
+        initial_state.set_groups(vec![
+            sample_group(
+                "dev",
+                vec![
+                    sample_var("URL", "dev-url"),
+                    sample_var("SAME", "x"),
+                    sample_var("NEW", "1"),
+                ],
+            ),
+            sample_group(
+                "prod",
+                vec![sample_var("URL", "prod-url"), sample_var("SAME", "x")],
+            ),
+        ]);
+        let (state_tx, mut state_rx) = tokio::sync::mpsc::channel(64);
+        let store = StateStore::new(initial_state, state_tx, MockVarClient::new());
+        let (action_tx, action_rx) = tokio::sync::mpsc::channel(64);
+        let main_loop = tokio::spawn(store.main_loop(action_rx));
+
+        let mut actions = vec![
+            Action::EnterViewVarGroup { index: 0 },
+            Action::StartPromote,
+            Action::PickPromoteTarget,
+            Action::MoveSelectionDown,
+            Action::TogglePromoteKey,
+            Action::MoveSelectionUp,
+            Action::StartOverrideValue,
+        ];
+        actions.extend((0.."dev-url".len()).map(|_| Action::PromptBackspace));
+        actions.extend("p2".chars().map(|ch| Action::PromptInsertChar { ch }));
+        actions.push(Action::SubmitPrompt);
+        let count = actions.len();
+        for action in actions {
+            action_tx.send(action).await.unwrap();
+        }
+        let mut state = state_rx.recv().await.unwrap();
+        for _ in 1..count {
+            state = state_rx.recv().await.unwrap();
+        }
+        let rows = state.promote_rows();
         assert_eq!(
-            store.state.current_var().map(|v| &v.name),
-            Some(&last_expected)
+            rows.iter()
+                .map(|r| r.source.name.as_str())
+                .collect::<Vec<_>>(),
+            ["URL", "NEW"]
         );
+        assert_eq!(state.ui.opened::<PromoteView>().unwrap().chosen.len(), 2);
 
-        let first_expected = store.state.filtered_vars()[0].name.clone();
-        store.move_selection(-10);
+        action_tx.send(Action::ApplyPromotion).await.unwrap();
+        let state = state_rx.recv().await.unwrap();
+        assert!(state.ui.opened::<PromoteView>().is_none());
+        assert!(state.is_reviewing());
         assert_eq!(
-            store.state.current_var().map(|v| &v.name),
-            Some(&first_expected)
+            state.ui.edits["prod"].variables(),
+            [
+                sample_var("URL", "p2"),
+                sample_var("SAME", "x"),
+                sample_var("NEW", "1")
+            ]
         );
-    }
-
-    #[test]
-    fn toggle_theme_switches_between_variants() {
-        let mut state = State::new("org".to_string(), "project".to_string());
-        state.theme = Theme::Dark;
-        let mut store = build_store(state);
-
-        store.toggle_theme();
-        assert!(matches!(store.state.theme, Theme::Light));
 
-        store.toggle_theme();
-        assert!(matches!(store.state.theme, Theme::Dark));
+        drop(action_tx);
+        main_loop.await.unwrap();
     }
 
-    #[test]
-    fn export_current_group_persists_pretty_json() {
-        let mut state = State::new("org".to_string(), "project".to_string());
-        let group = sample_group(
-            "My Group",
+    #[tokio::test]
+    async fn groups_are_copied_to_the_project_of_a_profile() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("Legacy")).unwrap();
+        let profile = CopyProfile {
+            name: "legacy".into(),
+            organization: "org".into(),
+            project: "Legacy".into(),
+        };
+        let mut initial_state = State::new("org".to_string(), "project".to_string())
+            .with_copy_profiles(vec![profile.clone()]);
+        initial_state.set_groups(vec![sample_group(
+            "app",
             vec![
-                sample_var("key", "value"),
+                sample_var("URL", "one"),
                 VarEntry {
-                    name: "secret".to_string(),
-                    value: "<secret value hidden>".to_string(),
+                    name: "TOKEN".into(),
+                    value: SECRET_PLACEHOLDER.into(),
                     is_secret: true,
                 },
             ],
+        )]);
+        let (state_tx, mut state_rx) = tokio::sync::mpsc::channel(64);
+        let store = StateStore::new(initial_state, state_tx, MockVarClient::new())
+            .with_copy_targets(vec![CopyTarget {
+                profile,
+                client: Arc::new(DirectoryVariableGroupsClient::new(dir.path().to_path_buf())),
+            }]);
+        let (action_tx, action_rx) = tokio::sync::mpsc::channel(64);
+        let main_loop = tokio::spawn(store.main_loop(action_rx));
+
+        let mut actions = vec![Action::StartCopy, Action::PickCopyProfile];
+        actions.extend("-old".chars().map(|ch| Action::PromptInsertChar { ch }));
+        actions.push(Action::SubmitPrompt);
+        let count = actions.len();
+        for action in actions {
+            action_tx.send(action).await.unwrap();
+        }
+        for _ in 0..count {
+            state_rx.recv().await.unwrap();
+        }
+        let state = state_rx.recv().await.unwrap();
+        assert!(state.ui.opened::<CopyView>().is_none());
+        let status = state.ui.status.unwrap().text;
+        assert_eq!(
+            status,
+            "Copied app to legacy as app-old, enter its secrets there: TOKEN"
         );
-        state.set_groups(vec![group.clone()]);
-        state.ui.view = View::Groups {
-            selected_idx: Some(0),
-        };
+        assert!(dir.path().join("Legacy").join("app-old.yaml").exists());
 
-        let store = build_store(state);
-        let dir = tempdir().unwrap();
-        let export_path = dir.path().join("custom_group.json");
+        drop(action_tx);
+        main_loop.await.unwrap();
+    }
 
-        store
-            .export_current_group(Some(export_path.clone()))
-            .expect("export should succeed");
+    /// Never answers, like a server that hangs.
+    struct StalledClient;
 
-        let contents = fs::read_to_string(&export_path).expect("exported file readable");
-        let expected = serde_json::to_string_pretty(&group).unwrap();
-        assert_eq!(contents, expected);
-    }
+    #[async_trait]
+    impl VariableGroupsClient for StalledClient {
+        async fn get_variable_groups_page(
+            &self,
+            _organization: &str,
+            _project: &str,
+            _group_name: Option<String>,
+            _continuation_token: Option<i32>,
+        ) -> Result<VarGroupPage, ClientError> {
+            std::future::pending().await
+        }
 
-    #[tokio::test]
-    async fn main_loop_refresh_var_groups_updates_state() {
-        let initial_state = State::new("org".to_string(), "project".to_string());
-        let groups = vec![sample_group(
-            "A",
-            vec![sample_var("alpha", "1"), sample_var("beta", "2")],
-        )];
-        let mut client = MockVarClient::new();
-        let groups_clone = groups.clone();
-        client
-            .expect_get_variable_groups()
-            .return_once(move |_, _| Ok(groups_clone));
-        let (state_tx, state_rx) = tokio::sync::mpsc::channel(4);
-        let store = StateStore::new(initial_state, state_tx, client);
+        async fn get_variable_group(
+            &self,
+            _organization: &str,
+            _project: &str,
+            _group_id: i32,
+        ) -> Result<VarGroup, ClientError> {
+            std::future::pending().await
+        }
 
-        let (action_tx, action_rx) = tokio::sync::mpsc::channel(4);
+        async fn add_variable_group(
+            &self,
+            _organization: &str,
+            _project: &str,
+            _group: VarGroup,
+        ) -> Result<VarGroup, ClientError> {
+            std::future::pending().await
+        }
 
-        let collector: JoinHandle<Vec<State>> = tokio::spawn(async move {
-            let mut collected = Vec::new();
-            let mut rx = state_rx;
-            while let Some(state) = rx.recv().await {
-                collected.push(state);
-            }
-            collected
-        });
+        async fn update_variable_group(
+            &self,
+            _organization: &str,
+            _project: &str,
+            _group: VarGroup,
+        ) -> Result<VarGroup, ClientError> {
+            std::future::pending().await
+        }
 
-        let main_loop = tokio::spawn(store.main_loop(action_rx));
+        async fn delete_variable_group(
+            &self,
+            _organization: &str,
+            _project: &str,
+            _group_id: i32,
+        ) -> Result<(), ClientError> {
+            std::future::pending().await
+        }
 
-        action_tx.send(Action::RefreshVarGroups).await.unwrap();
-        drop(action_tx);
+        async fn get_library_audit_events(
+            &self,
+            _organization: &str,
+            _project: &str,
+            _start: SystemTime,
+            _end: SystemTime,
+        ) -> Result<Vec<AuditEvent>, ClientError> {
+            std::future::pending().await
+        }
 
-        main_loop.await.unwrap();
-        let states = collector.await.unwrap();
+        async fn get_projects(&self, _organization: &str) -> Result<Vec<String>, ClientError> {
+            std::future::pending().await
+        }
 
-        assert!(
-            states.len() >= 2,
-            "expected at least two state broadcasts, got {}",
-            states.len()
-        );
+        async fn get_shared_projects(
+            &self,
+            _organization: &str,
+            _project: &str,
+            _group_id: i32,
+        ) -> Result<Vec<String>, ClientError> {
+            std::future::pending().await
+        }
 
-        let final_state = states.last().unwrap();
-        assert_eq!(final_state.data.groups, groups);
-        assert_eq!(
-            final_state.current_group().map(|g| g.name.as_str()),
-            Some("A")
-        );
-        assert!(!final_state.is_viewing_vars());
+        async fn share_variable_group(
+            &self,
+            _organization: &str,
+            _project: &str,
+            _group_id: i32,
+            _share: Vec<String>,
+            _unshare: Vec<String>,
+        ) -> Result<(), ClientError> {
+            std::future::pending().await
+        }
+
+        async fn get_pipelines(
+            &self,
+            _organization: &str,
+            _project: &str,
+        ) -> Result<Vec<Pipeline>, ClientError> {
+            std::future::pending().await
+        }
+
+        async fn get_pipeline_permissions(
+            &self,
+            _organization: &str,
+            _project: &str,
+            _group_id: i32,
+        ) -> Result<PipelinePermissions, ClientError> {
+            std::future::pending().await
+        }
+
+        async fn set_pipeline_permissions(
+            &self,
+            _organization: &str,
+            _project: &str,
+            _group_id: i32,
+            _changes: PermissionChanges,
+        ) -> Result<(), ClientError> {
+            std::future::pending().await
+        }
+
+        async fn get_checks(
+            &self,
+            _organization: &str,
+            _project: &str,
+            _group_id: i32,
+        ) -> Result<Vec<Check>, ClientError> {
+            std::future::pending().await
+        }
+
+        async fn add_approval_check(
+            &self,
+            _organization: &str,
+            _project: &str,
+            _group_id: i32,
+            _approvers: Vec<String>,
+        ) -> Result<Check, ClientError> {
+            std::future::pending().await
+        }
+
+        async fn remove_check(
+            &self,
+            _organization: &str,
+            _project: &str,
+            _check_id: i32,
+        ) -> Result<(), ClientError> {
+            std::future::pending().await
+        }
     }
 
     #[tokio::test]
-    async fn main_loop_handles_search_actions() {
+    async fn browsing_continues_during_a_refresh_which_can_be_cancelled() {
         let mut initial_state = State::new("org".to_string(), "project".to_string());
-        initial_state.set_groups(vec![sample_group(
-            "Group",
-            vec![sample_var("alpha", "1"), sample_var("beta", "2")],
-        )]);
-        initial_state.ui.view = View::Vars {
-            group_idx: 0,
-            selected_var_idx: Some(0),
-        };
-        let client = MockVarClient::new();
-        let (state_tx, state_rx) = tokio::sync::mpsc::channel(8);
-        let store = StateStore::new(initial_state, state_tx, client);
+        initial_state.set_groups(vec![
+            sample_group("Alpha", vec![]),
+            sample_group("Beta", vec![]),
+        ]);
+        let (state_tx, mut state_rx) = tokio::sync::mpsc::channel(8);
+        let store = StateStore::new(initial_state, state_tx, StalledClient);
         let (action_tx, action_rx) = tokio::sync::mpsc::channel(8);
+        let main_loop = tokio::spawn(store.main_loop(action_rx));
 
-        let collector: JoinHandle<Vec<State>> = tokio::spawn(async move {
-            let mut collected = Vec::new();
-            let mut rx = state_rx;
-            while let Some(state) = rx.recv().await {
-                collected.push(state);
-            }
-            collected
-        });
+        action_tx.send(Action::RefreshVarGroups).await.unwrap();
+        assert!(state_rx.recv().await.unwrap().ui.is_fetching);
 
-        let main_loop = tokio::spawn(store.main_loop(action_rx));
-        action_tx.send(Action::EnterSearchMode).await.unwrap();
-        for ch in "alpha".chars() {
-            action_tx
-                .send(Action::SearchInsertChar { ch })
-                .await
-                .unwrap();
-        }
-        action_tx.send(Action::SubmitSearch).await.unwrap();
-        action_tx.send(Action::EnterSearchMode).await.unwrap();
-        action_tx.send(Action::ExitSearchMode).await.unwrap();
-        drop(action_tx);
+        action_tx.send(Action::MoveSelectionDown).await.unwrap();
+        let state = state_rx.recv().await.unwrap();
+        assert!(state.ui.is_fetching);
+        assert_eq!(state.current_group().map(|g| g.name.as_str()), Some("Beta"));
+
+        action_tx.send(Action::CancelRefresh).await.unwrap();
+        let state = state_rx.recv().await.unwrap();
+        assert!(!state.ui.is_fetching);
+        assert_eq!(state.groups().len(), 2);
+        assert_eq!(state.ui.status.unwrap().text, "Refresh cancelled");
 
+        drop(action_tx);
         main_loop.await.unwrap();
-        let states = collector.await.unwrap();
-        let final_state = states.last().cloned().expect("state updates should exist");
-        assert!(!final_state.ui.search.is_active());
-        assert_eq!(
-            final_state.current_var().map(|v| v.name.as_str()),
-            Some("alpha")
-        );
     }
 
     #[tokio::test]
-    async fn refresh_failures_update_status_and_success_clears() {
-        let initial_state = State::new("org".to_string(), "project".to_string());
-        let desired_groups = vec![sample_group(
-            "Recovered",
-            vec![sample_var("alpha", "1"), sample_var("beta", "2")],
-        )];
-        let responses = Arc::new(Mutex::new(VecDeque::from([
-            Err(anyhow::anyhow!("network error")),
-            Ok(desired_groups.clone()),
-        ])));
-
+    async fn get_variable_groups_stops_when_a_token_repeats() {
         let mut client = MockVarClient::new();
-        let responses_clone = Arc::clone(&responses);
         client
-            .expect_get_variable_groups()
+            .expect_get_variable_groups_page()
             .times(2)
-            .returning(move |_, _| {
-                responses_clone
-                    .lock()
-                    .unwrap()
-                    .pop_front()
-                    .expect("response available")
+            .returning(|_, _, _, _| {
+                Ok(VarGroupPage {
+                    groups: vec![sample_group("A", vec![])],
+                    continuation_token: Some(1),
+                })
             });
 
-        let (state_tx, state_rx) = tokio::sync::mpsc::channel(8);
-        let store = StateStore::new(initial_state, state_tx, client);
-        let (action_tx, action_rx) = tokio::sync::mpsc::channel(8);
+        let groups = client
+            .get_variable_groups("org", "project", None)
+            .await
+            .unwrap();
+        assert_eq!(groups.len(), 2);
+    }
 
-        let collector: JoinHandle<Vec<State>> = tokio::spawn(async move {
-            let mut collected = Vec::new();
-            let mut rx = state_rx;
-            while let Some(state) = rx.recv().await {
-                collected.push(state);
+    #[test]
+    fn listed_variables_are_fingerprinted_like_loaded_ones() {
+        for variables in [
+            serde_json::json!({}),
+            serde_json::json!({
+                "URL": { "value": "https://example.com/?q=\"a\"" },
+                "naïve\tname": { "value": "1", "isSecret": false },
+                "TOKEN": { "isSecret": true },
+            }),
+        ] {
+            let digest = serde_json::from_value::<VariablesDigest>(variables.clone()).unwrap();
+            assert_eq!(digest.count, variables.as_object().unwrap().len());
+            assert_eq!(digest.fingerprint, fingerprint(&variables));
+        }
+    }
+
+    #[tokio::test]
+    async fn replayed_responses_map_key_vault_groups_and_missing_values() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("fixtures.json");
+        let key_vault_group = serde_json::json!({
+            "id": 7,
+            "name": "kv-prod",
+            "type": "AzureKeyVault",
+            "providerData": { "vault": "kv-prod", "serviceEndpointId": "0c1f" },
+            "variables": {
+                "db-password": { "enabled": true, "contentType": "", "isSecret": true },
+                "EMPTY": {}
             }
-            collected
         });
+        let fixtures = serde_json::json!({ "responses": [
+            {
+                "method": "GET",
+                "request": "_apis/distributedtask/variablegroups?$top=100&api-version=7.1-preview",
+                "status": 200,
+                "body": { "count": 2, "value": [key_vault_group, { "id": 8, "name": "broken", "variables": null }] }
+            },
+            {
+                "method": "GET",
+                "request": "_apis/distributedtask/variablegroups/7?api-version=7.1-preview",
+                "status": 200,
+                "body": key_vault_group
+            },
+            {
+                "method": "GET",
+                "request": "_apis/distributedtask/variablegroups/9?api-version=7.1-preview",
+                "status": 200,
+                "body": ""
+            }
+        ]});
+        std::fs::write(&path, fixtures.to_string()).unwrap();
+        let client = AzureApiVariableGroupsClient::new(
+            connection::azure_clients(
+                Credential::Unauthenticated,
+                &ServerConfig::default(),
+                Some(&FixtureMode::Replay(path)),
+            )
+            .unwrap(),
+        );
 
-        let main_loop = tokio::spawn(store.main_loop(action_rx));
-        action_tx.send(Action::RefreshVarGroups).await.unwrap();
-        action_tx.send(Action::RefreshVarGroups).await.unwrap();
-        drop(action_tx);
-
-        main_loop.await.unwrap();
-        let states = collector.await.unwrap();
+        let groups = client
+            .get_variable_groups("org", "project", None)
+            .await
+            .unwrap();
+        assert_eq!(groups.len(), 1);
+        assert!(groups[0].summary_only);
+        assert_eq!(groups[0].var_count(), 2);
 
-        assert!(
-            states
-                .iter()
-                .any(|state| matches!(&state.ui.status, Some(status) if status.text.contains("Failed to load variable groups"))),
-            "expected at least one state with an error status",
+        let group = client
+            .get_variable_group("org", "project", 7)
+            .await
+            .unwrap();
+        assert_eq!(group.fingerprint, groups[0].fingerprint);
+        assert_eq!(group.variables[0].name, "EMPTY");
+        assert_eq!(group.variables[0].value, "<no value>");
+        assert_eq!(group.variables[1].value, SECRET_PLACEHOLDER);
+        assert_eq!(
+            client.get_variable_group("org", "project", 9).await,
+            Err(ClientError::NotFound {
+                what: "Variable group 9".into()
+            })
         );
+    }
 
-        let final_state = states.last().expect("state updates should exist");
-        assert_eq!(final_state.data.groups, desired_groups);
-        assert!(
-            final_state.ui.status.is_none(),
-            "status should be cleared after successful refresh"
+    #[tokio::test]
+    async fn approval_checks_are_posted_with_their_settings() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("fixtures.json");
+        let fixtures = serde_json::json!({ "responses": [
+            {
+                "method": "GET",
+                "request": "_apis/identities?api-version=7.1-preview&filterValue=ana@contoso.com&searchFilter=General",
+                "status": 200,
+                "body": { "count": 1, "value": [{ "id": "1f2e", "providerDisplayName": "Ana" }] }
+            },
+            {
+                "method": "POST",
+                "request": "_apis/pipelines/checks/configurations?api-version=7.1-preview",
+                "status": 200,
+                "body": {
+                    "id": 12,
+                    "type": { "id": APPROVAL_CHECK, "name": "Approval" },
+                    "settings": { "approvers": [{ "displayName": "Ana" }] }
+                }
+            }
+        ]});
+        std::fs::write(&path, fixtures.to_string()).unwrap();
+        let client = AzureApiVariableGroupsClient::new(
+            connection::azure_clients(
+                Credential::Unauthenticated,
+                &ServerConfig::default(),
+                Some(&FixtureMode::Replay(path)),
+            )
+            .unwrap(),
         );
+
+        let check = client
+            .add_approval_check("org", "project", 7, vec!["ana@contoso.com".into()])
+            .await
+            .unwrap();
+        assert_eq!(check.id, 12);
+        assert_eq!(check.summary(), "Ana (all)");
     }
 }
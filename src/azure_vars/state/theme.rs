@@ -0,0 +1,289 @@
+use std::{collections::BTreeMap, fs, path::Path, str::FromStr};
+
+use ratatui::style::Color;
+use serde::{Deserialize, Deserializer};
+
+use crate::config::error::ConfigError;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Theme {
+    pub name: String,
+    /// Selection highlight and markers.
+    pub accent: Color,
+    /// Breadcrumb, help bar and empty values.
+    pub muted: Color,
+    pub secret: Color,
+    pub value: Color,
+    pub error: Color,
+    pub info: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
+
+impl Theme {
+    pub fn dark() -> Self {
+        Self {
+            name: "dark".into(),
+            accent: Color::Yellow,
+            muted: Color::DarkGray,
+            secret: Color::Red,
+            value: Color::Cyan,
+            error: Color::Red,
+            info: Color::Green,
+        }
+    }
+
+    pub fn light() -> Self {
+        Self {
+            name: "light".into(),
+            accent: Color::Blue,
+            muted: Color::Gray,
+            secret: Color::Red,
+            value: Color::Cyan,
+            error: Color::Red,
+            info: Color::Blue,
+        }
+    }
+
+    /// Uses the terminal's own colors everywhere, for `NO_COLOR`.
+    pub fn mono() -> Self {
+        Self {
+            name: "mono".into(),
+            accent: Color::Reset,
+            muted: Color::Reset,
+            secret: Color::Reset,
+            value: Color::Reset,
+            error: Color::Reset,
+            info: Color::Reset,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Background {
+    Dark,
+    Light,
+}
+
+/// Guesses the terminal background from `COLORFGBG` (`"15;0"` is white on
+/// black), which rxvt, Konsole and several other terminals export.
+pub fn detect_background(colorfgbg: Option<&str>) -> Option<Background> {
+    let background = colorfgbg?.rsplit(';').next()?.parse::<u8>().ok()?;
+    match background {
+        0..=6 | 8 => Some(Background::Dark),
+        7 | 9..=15 => Some(Background::Light),
+        _ => None,
+    }
+}
+
+struct ColorValue(Color);
+
+impl<'de> Deserialize<'de> for ColorValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        Color::from_str(&value)
+            .map(ColorValue)
+            .map_err(|_| serde::de::Error::custom(format!("invalid color `{value}`")))
+    }
+}
+
+/// A theme in `themes.yaml`. Unset colors are taken from `base`.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ThemeSpec {
+    #[serde(default)]
+    base: Option<String>,
+    accent: Option<ColorValue>,
+    muted: Option<ColorValue>,
+    secret: Option<ColorValue>,
+    value: Option<ColorValue>,
+    error: Option<ColorValue>,
+    info: Option<ColorValue>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ThemesFile {
+    #[serde(default)]
+    themes: BTreeMap<String, ThemeSpec>,
+}
+
+/// The themes `ToggleTheme` cycles through, in order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThemeSet {
+    themes: Vec<Theme>,
+}
+
+impl Default for ThemeSet {
+    fn default() -> Self {
+        Self {
+            themes: vec![Theme::dark(), Theme::light()],
+        }
+    }
+}
+
+impl ThemeSet {
+    pub fn monochrome() -> Self {
+        Self {
+            themes: vec![Theme::mono()],
+        }
+    }
+
+    /// Reads custom themes, which follow the built-in ones. A custom theme
+    /// named like a built-in one replaces it.
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        match fs::read_to_string(path) {
+            Ok(text) => Self::parse(&text, path),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(source) => Err(ConfigError::Io {
+                path: path.to_path_buf(),
+                source,
+            }),
+        }
+    }
+
+    pub fn parse(text: &str, path: &Path) -> Result<Self, ConfigError> {
+        let mut set = Self::default();
+        if text.trim().is_empty() {
+            return Ok(set);
+        }
+        let file = serde_yaml::from_str::<ThemesFile>(text).map_err(|error| {
+            let location = error.location();
+            ConfigError::Parse {
+                path: path.to_path_buf(),
+                line: location.as_ref().map(|l| l.line()),
+                column: location.as_ref().map(|l| l.column()),
+                message: error.to_string(),
+            }
+        })?;
+
+        for (name, spec) in file.themes {
+            let base_name = spec.base.as_deref().unwrap_or("dark");
+            let base = set
+                .get(base_name)
+                .cloned()
+                .ok_or_else(|| ConfigError::Parse {
+                    path: path.to_path_buf(),
+                    line: None,
+                    column: None,
+                    message: format!("theme `{name}` is based on unknown theme `{base_name}`"),
+                })?;
+            let pick = |color: Option<ColorValue>, fallback: Color| color.map_or(fallback, |c| c.0);
+            let theme = Theme {
+                name: name.clone(),
+                accent: pick(spec.accent, base.accent),
+                muted: pick(spec.muted, base.muted),
+                secret: pick(spec.secret, base.secret),
+                value: pick(spec.value, base.value),
+                error: pick(spec.error, base.error),
+                info: pick(spec.info, base.info),
+            };
+            match set.themes.iter_mut().find(|t| t.name == name) {
+                Some(existing) => *existing = theme,
+                None => set.themes.push(theme),
+            }
+        }
+        Ok(set)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Theme> {
+        self.themes.iter().find(|theme| theme.name == name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.themes.iter().map(|theme| theme.name.as_str())
+    }
+
+    /// The theme to start with: the last one chosen if it still exists,
+    /// otherwise the built-in theme matching the terminal background.
+    pub fn initial(&self, last_choice: Option<&str>, background: Option<Background>) -> Theme {
+        let preferred = match background {
+            Some(Background::Light) => "light",
+            Some(Background::Dark) | None => "dark",
+        };
+        last_choice
+            .and_then(|name| self.get(name))
+            .or_else(|| self.get(preferred))
+            .unwrap_or(&self.themes[0])
+            .clone()
+    }
+
+    /// The theme after `name`, wrapping around.
+    pub fn next_after(&self, name: &str) -> &Theme {
+        let next = self
+            .themes
+            .iter()
+            .position(|theme| theme.name == name)
+            .map_or(0, |idx| (idx + 1) % self.themes.len());
+        &self.themes[next]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Result<ThemeSet, ConfigError> {
+        ThemeSet::parse(text, Path::new("themes.yaml"))
+    }
+
+    #[test]
+    fn detects_background_from_colorfgbg() {
+        assert_eq!(detect_background(Some("15;0")), Some(Background::Dark));
+        assert_eq!(
+            detect_background(Some("0;default;15")),
+            Some(Background::Light)
+        );
+        assert_eq!(detect_background(Some("default")), None);
+        assert_eq!(detect_background(None), None);
+    }
+
+    #[test]
+    fn custom_themes_inherit_from_their_base_and_follow_builtins() {
+        let set = parse(
+            "themes:\n  solarized:\n    base: light\n    accent: '#b58900'\n    value: cyan\n",
+        )
+        .unwrap();
+
+        let solarized = set.get("solarized").unwrap();
+        assert_eq!(solarized.accent, Color::Rgb(0xb5, 0x89, 0x00));
+        assert_eq!(solarized.muted, Theme::light().muted);
+        assert_eq!(
+            set.names().collect::<Vec<_>>(),
+            ["dark", "light", "solarized"]
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_colors_with_their_location() {
+        match parse("themes:\n  mine:\n    accent: not-a-color\n").unwrap_err() {
+            ConfigError::Parse { line, message, .. } => {
+                assert_eq!(line, Some(3));
+                assert!(message.contains("not-a-color"));
+            }
+            other => panic!("expected a parse error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn initial_theme_prefers_last_choice_then_background() {
+        let set = ThemeSet::default();
+        assert_eq!(set.initial(Some("light"), None).name, "light");
+        assert_eq!(
+            set.initial(Some("gone"), Some(Background::Light)).name,
+            "light"
+        );
+        assert_eq!(set.initial(None, None).name, "dark");
+    }
+
+    #[test]
+    fn next_after_wraps_around() {
+        let set = ThemeSet::default();
+        assert_eq!(set.next_after("dark").name, "light");
+        assert_eq!(set.next_after("light").name, "dark");
+    }
+}
@@ -1,21 +1,40 @@
+use std::collections::HashSet;
+
 use ratatui::{
     Frame,
-    layout::{Constraint, Direction, Layout},
+    layout::{Constraint, Direction, Layout, Rect},
     widgets::{Block, Borders, Paragraph},
 };
 
 use crate::azure_vars::{
-    state::state::{SearchTarget, State},
-    tui::widgets::{BreadCrumb, HelpBar, SearchBar, StatusBar, VarGroupList, VarList},
+    state::{
+        diff::VarChange,
+        state::{Overlay, SearchTarget, State},
+    },
+    tui::{
+        keymap::{Command, Context, Keymap},
+        widgets::{
+            AuditList, BreadCrumb, CheckList, HelpBar, HistoryList, LogPane, PipelineList,
+            ProfileList, PromoteList, PromptBar, ReviewList, SearchBar, ShareList, StatusBar,
+            VarGroupList, VarList,
+        },
+    },
 };
+use crate::logging::LogBuffer;
+
+const LOG_PANE_HEIGHT: u16 = 10;
 
-pub fn draw_ui(f: &mut Frame, state: &State) {
+pub fn draw_ui(f: &mut Frame, state: &State, keymap: &Keymap, logs: &LogBuffer) {
     let size = f.area();
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .margin(1)
         .constraints([
-            Constraint::Length(if state.ui.search.is_active() { 3 } else { 0 }),
+            Constraint::Length(if state.ui.search.is_active() || state.is_prompting() {
+                3
+            } else {
+                0
+            }),
             Constraint::Length(1),
             Constraint::Min(0),
             Constraint::Length(if state.ui.status.is_some() { 2 } else { 0 }),
@@ -23,16 +42,36 @@ pub fn draw_ui(f: &mut Frame, state: &State) {
         ])
         .split(size);
 
-    if state.ui.is_fetching {
+    let main_area = if state.ui.show_logs {
+        let areas = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(LOG_PANE_HEIGHT)])
+            .split(chunks[2]);
+        let visible = usize::from(LOG_PANE_HEIGHT.saturating_sub(2));
         f.render_widget(
-            Paragraph::new("Loading variable groups...")
-                .block(Block::default().borders(Borders::ALL).title("Please wait")),
-            chunks[2],
+            LogPane::new(state.theme.clone(), logs.recent(visible)),
+            areas[1],
+        );
+        areas[0]
+    } else {
+        chunks[2]
+    };
+
+    if state.ui.is_fetching && state.groups().is_empty() {
+        f.render_widget(
+            Paragraph::new(format!(
+                "Loading variable groups... {} loaded",
+                state.ui.fetched_groups
+            ))
+            .block(Block::default().borders(Borders::ALL).title("Please wait")),
+            main_area,
         );
         return;
     }
 
-    if state.ui.search.is_active() {
+    if let Some(prompt) = &state.ui.prompt {
+        f.render_widget(PromptBar::new(prompt.clone()), chunks[0]);
+    } else if state.ui.search.is_active() {
         let target = state
             .ui
             .search
@@ -55,22 +94,144 @@ pub fn draw_ui(f: &mut Frame, state: &State) {
                 .map(|g| g.name.clone())
                 .unwrap_or_default(),
             state.is_viewing_vars(),
-            state.theme,
+            state.theme.clone(),
+            state
+                .cached_at()
+                .map(|cached_at| cached_at.elapsed().unwrap_or_default()),
+            state.is_offline(),
         ),
         chunks[1],
     );
 
-    if !state.is_viewing_vars() {
-        if let Some(selected_group) = state.current_group() {
+    match state.ui.overlay() {
+        Some(Overlay::Review) => {
+            let edits = state
+                .pending_edits()
+                .map(|(name, edits)| (name.to_string(), edits.clone()))
+                .collect();
+            f.render_widget(ReviewList::new(edits, state.theme.clone()), main_area);
+        }
+        Some(Overlay::Promote(promote)) => {
+            let rows = state
+                .promote_target()
+                .filter(|target| !target.summary_only)
+                .map(|_| state.promote_rows());
+            f.render_widget(
+                PromoteList::new(
+                    promote.clone(),
+                    state
+                        .promote_targets()
+                        .into_iter()
+                        .map(|group| group.name.clone())
+                        .collect(),
+                    rows,
+                    state.theme.clone(),
+                ),
+                main_area,
+            );
+        }
+        Some(Overlay::Copy(copy)) => {
+            f.render_widget(
+                ProfileList::new(
+                    copy.clone(),
+                    state.copy_profiles().to_vec(),
+                    state.theme.clone(),
+                ),
+                main_area,
+            );
+        }
+        Some(Overlay::Share(share)) => {
+            f.render_widget(
+                ShareList::new(
+                    share.clone(),
+                    state.project().to_string(),
+                    state.theme.clone(),
+                ),
+                main_area,
+            );
+        }
+        Some(Overlay::Pipelines(view)) => {
+            f.render_widget(
+                PipelineList::new(view.clone(), state.theme.clone()),
+                main_area,
+            );
+        }
+        Some(Overlay::Checks(view)) => {
+            f.render_widget(CheckList::new(view.clone(), state.theme.clone()), main_area);
+        }
+        Some(Overlay::Audit(audit)) => {
+            f.render_widget(
+                AuditList::new(
+                    audit.group.clone(),
+                    audit.events.clone(),
+                    audit.loading,
+                    audit.selected,
+                    state.theme.clone(),
+                ),
+                main_area,
+            );
+        }
+        Some(Overlay::History(history)) => {
+            let group = state.current_group().map(|g| g.name.clone());
             f.render_widget(
-                VarGroupList::new(
-                    state.filtered_groups().into_iter().cloned().collect(),
-                    state.theme,
-                    selected_group.clone(),
+                HistoryList::new(
+                    group.unwrap_or_default(),
+                    history.entries.clone(),
+                    history.selected,
+                    state.theme.clone(),
                 ),
-                chunks[2],
+                main_area,
             );
         }
+        None if !state.is_viewing_vars() => {
+            if let Some(selected_group) = state.current_group() {
+                f.render_widget(
+                    VarGroupList::new(
+                        state.filtered_groups().into_iter().cloned().collect(),
+                        state.pinned_groups().to_vec(),
+                        state.theme.clone(),
+                        selected_group.clone(),
+                        state.ui.is_fetching.then_some(state.ui.fetched_groups),
+                    ),
+                    main_area,
+                );
+            }
+        }
+        None => draw_vars(f, state, main_area),
+    }
+
+    if let Some(status) = state.ui.status.clone() {
+        f.render_widget(StatusBar::new(state.theme.clone(), status), chunks[3]);
+    }
+
+    f.render_widget(
+        HelpBar::new(
+            state.theme.clone(),
+            state.ui.search.active_target(),
+            keymap
+                .help(Context::active(state))
+                .into_iter()
+                // Only offered while there is a refresh to cancel.
+                .filter(|(_, label)| {
+                    state.ui.is_fetching || *label != Command::CancelRefresh.label()
+                })
+                .collect(),
+        ),
+        chunks[4],
+    );
+}
+
+/// The variables of the group being viewed.
+fn draw_vars(f: &mut Frame, state: &State, area: Rect) {
+    if let Some(group) = state.current_group().filter(|g| g.summary_only) {
+        f.render_widget(
+            Paragraph::new(format!("Loading {} variables...", group.variable_count)).block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(group.name.clone()),
+            ),
+            area,
+        );
     } else if let (Some(selected_group), Some(selected_var)) =
         (state.current_group(), state.current_var())
     {
@@ -80,23 +241,30 @@ pub fn draw_ui(f: &mut Frame, state: &State) {
                 vars,
                 selected_group.name.clone(),
                 state.active_vars_query().map(str::to_string),
-                state.theme,
+                state.theme.clone(),
                 selected_var.clone(),
+                state
+                    .ui
+                    .changed_vars
+                    .get(&selected_group.name)
+                    .cloned()
+                    .unwrap_or_default(),
+                unsaved_vars(state, &selected_group.name),
             ),
-            chunks[2],
+            area,
         );
     }
+}
 
-    if let Some(status) = state.ui.status.clone() {
-        f.render_widget(StatusBar::new(state.theme, status), chunks[3]);
-    }
-
-    f.render_widget(
-        HelpBar::new(
-            state.theme,
-            state.is_viewing_vars(),
-            state.ui.search.clone(),
-        ),
-        chunks[4],
-    );
+/// The variables of `group` added or changed and not saved yet.
+fn unsaved_vars(state: &State, group: &str) -> HashSet<String> {
+    state
+        .pending_edits()
+        .filter(|(name, _)| *name == group)
+        .flat_map(|(_, edits)| edits.changes())
+        .filter_map(|change| match change {
+            VarChange::Added(var) | VarChange::Changed { new: var, .. } => Some(var.name),
+            VarChange::Removed(_) => None,
+        })
+        .collect()
 }
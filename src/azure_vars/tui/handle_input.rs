@@ -9,19 +9,24 @@ use ratatui::DefaultTerminal;
 use crate::azure_vars::state::action::Action;
 use crate::azure_vars::state::state::State;
 use crate::azure_vars::tui::draw::draw_ui;
+use crate::azure_vars::tui::keymap::{Command, Context, KeyHandler, KeyOutcome};
+use crate::logging::LogBuffer;
 
 const RENDERING_TICK_RATE: Duration = Duration::from_millis(250);
 
+/// Runs the TUI until the user quits, returning the last state shown.
 pub async fn run_app(
     terminal: &mut DefaultTerminal,
     mut action_tx: Sender<Action>,
     mut state: State,
     mut state_rx: tokio::sync::mpsc::Receiver<State>,
-) -> Result<(), Box<dyn Error>> {
+    mut keys: KeyHandler,
+    logs: LogBuffer,
+) -> Result<State, Box<dyn Error>> {
     let mut crossterm_events = EventStream::new();
     let mut ticker = tokio::time::interval(RENDERING_TICK_RATE);
     loop {
-        terminal.draw(|f| draw_ui(f, &state))?;
+        terminal.draw(|f| draw_ui(f, &state, keys.keymap(), &logs))?;
 
         tokio::select! {
             _ = ticker.tick() => {}
@@ -30,13 +35,13 @@ pub async fn run_app(
             }
             maybe_event = crossterm_events.next() => match maybe_event {
                 Some(Ok(Event::Key(key))) => {
-                    if handle_key(&state, &mut action_tx, key).await? {
-                        return Ok(());
+                    if handle_key(&state, &mut keys, &mut action_tx, key).await? {
+                        return Ok(state);
                     }
                 }
                 Some(Ok(_)) => {}
                 Some(Err(e)) => return Err(Box::new(e)),
-                None => break Ok(()), // User Interrupted
+                None => break Ok(state), // User Interrupted
             }
         }
     }
@@ -44,45 +49,95 @@ pub async fn run_app(
 
 async fn handle_key(
     state: &State,
+    keys: &mut KeyHandler,
     action_tx: &mut Sender<Action>,
     key: KeyEvent,
 ) -> anyhow::Result<bool> {
-    use crossterm::event::KeyCode::*;
-
-    if state.ui.search.is_active() {
-        let action = match key.code {
-            Esc => Action::ExitSearchMode,
-            Enter => Action::SubmitSearch,
-            Backspace => Action::SearchBackspace,
-            Char(c) => Action::SearchInsertChar { ch: c },
-            _ => return Ok(false),
-        };
-        action_tx.send(action).await?;
-        return Ok(false);
-    }
+    let contexts = Context::active(state);
+    let command = match keys.handle(contexts, key.into()) {
+        KeyOutcome::Command(command) => command,
+        KeyOutcome::Pending => return Ok(false),
+        KeyOutcome::Unbound(chord) => {
+            if let Some(ch) = chord.text() {
+                if state.is_prompting() {
+                    action_tx.send(Action::PromptInsertChar { ch }).await?;
+                } else if state.ui.search.is_active() {
+                    action_tx.send(Action::SearchInsertChar { ch }).await?;
+                }
+            }
+            return Ok(false);
+        }
+    };
 
-    assert!(!state.ui.search.is_active());
-    let action = match key.code {
-        Char('q') => return Ok(true),
-        Char('/') => Action::EnterSearchMode,
-        Char('R') => Action::RefreshVarGroups,
-        Char('T') => Action::ToggleTheme,
-        Char('C') if state.is_viewing_vars() => Action::CopySelectedVar,
-        Char('E') if state.is_viewing_vars() => Action::ExportCurrentGroup,
-        Left if state.is_viewing_vars() => Action::ExitViewVarGroup,
-        Enter if !state.is_viewing_vars() => {
+    // The list shown, which overlay commands apply to.
+    let shown = contexts[0];
+    let action = match command {
+        // Unsaved changes are only discarded when quitting twice.
+        Command::Quit if state.has_pending_edits() && !state.ui.confirm_discard => {
+            Action::RequestQuit
+        }
+        Command::Quit => return Ok(true),
+        Command::Search if state.ui.overlay().is_none() => Action::EnterSearchMode,
+        Command::Refresh => Action::RefreshVarGroups,
+        Command::CancelRefresh if state.ui.is_fetching => Action::CancelRefresh,
+        Command::ToggleTheme => Action::ToggleTheme,
+        Command::ToggleLogs => Action::ToggleLogPane,
+        Command::Copy if state.is_viewing_vars() => Action::CopySelectedVar,
+        Command::Export if state.is_viewing_vars() => Action::ExportCurrentGroup,
+        Command::Back if shown == Context::Review => Action::ExitReview,
+        Command::Save if shown == Context::Review => Action::SaveChanges,
+        Command::Review => Action::EnterReview,
+        Command::Undo => Action::UndoEdit,
+        Command::Redo => Action::RedoEdit,
+        Command::Back if shown == Context::Promote => Action::ExitPromote,
+        Command::Open if shown == Context::Promote => Action::PickPromoteTarget,
+        Command::Toggle if shown == Context::Promote => Action::TogglePromoteKey,
+        Command::Edit if shown == Context::Promote => Action::StartOverrideValue,
+        Command::Save if shown == Context::Promote => Action::ApplyPromotion,
+        Command::Promote if state.is_viewing_vars() => Action::StartPromote,
+        Command::Back if shown == Context::Profiles => Action::ExitCopy,
+        Command::Open if shown == Context::Profiles => Action::PickCopyProfile,
+        Command::CopyTo if shown != Context::Profiles => Action::StartCopy,
+        Command::Back if shown == Context::Share => Action::ExitShare,
+        Command::Toggle if shown == Context::Share => Action::ToggleShareProject,
+        Command::Save if shown == Context::Share => Action::ApplySharing,
+        Command::Share if shown != Context::Share => Action::EnterShare,
+        Command::Back if shown == Context::Pipelines => Action::ExitPipelines,
+        Command::Toggle if shown == Context::Pipelines => Action::TogglePipeline,
+        Command::Save if shown == Context::Pipelines => Action::ApplyPipelinePermissions,
+        Command::Pipelines if shown != Context::Pipelines => Action::EnterPipelines,
+        Command::Back if shown == Context::Checks => Action::ExitChecks,
+        Command::Add if shown == Context::Checks => Action::StartAddApproval,
+        Command::Remove if shown == Context::Checks => Action::RemoveSelectedCheck,
+        Command::Checks if shown != Context::Checks => Action::EnterChecks,
+        Command::Back if shown == Context::Audit => Action::ExitAudit,
+        Command::Audit if shown != Context::Audit => Action::EnterAudit,
+        Command::Back if shown == Context::History => Action::ExitHistory,
+        Command::Restore if shown == Context::History => Action::RestoreSelectedVersion,
+        Command::History if state.is_viewing_vars() => Action::EnterHistory,
+        Command::Edit if state.is_viewing_vars() => Action::StartEditVar,
+        Command::Add if state.is_viewing_vars() => Action::StartAddVar,
+        Command::Remove if state.is_viewing_vars() => Action::RemoveSelectedVar,
+        Command::Back if state.is_viewing_vars() => Action::ExitViewVarGroup,
+        Command::Open if !state.is_viewing_vars() => {
             if let Some(index) = state.current_group_idx() {
                 Action::EnterViewVarGroup { index }
             } else {
                 return Ok(false);
             }
         }
-        Up => Action::MoveSelectionUp,
-        Down => Action::MoveSelectionDown,
-        PageUp => Action::MoveSelectionPageUp,
-        PageDown => Action::MoveSelectionPageDown,
-        Home => Action::MoveSelectionTop,
-        End => Action::MoveSelectionBottom,
+        Command::Up => Action::MoveSelectionUp,
+        Command::Down => Action::MoveSelectionDown,
+        Command::PageUp => Action::MoveSelectionPageUp,
+        Command::PageDown => Action::MoveSelectionPageDown,
+        Command::Top => Action::MoveSelectionTop,
+        Command::Bottom => Action::MoveSelectionBottom,
+        Command::Cancel if state.is_prompting() => Action::CancelPrompt,
+        Command::Submit if state.is_prompting() => Action::SubmitPrompt,
+        Command::Backspace if state.is_prompting() => Action::PromptBackspace,
+        Command::Cancel => Action::ExitSearchMode,
+        Command::Submit => Action::SubmitSearch,
+        Command::Backspace => Action::SearchBackspace,
         _ => return Ok(false),
     };
 
@@ -93,6 +148,9 @@ async fn handle_key(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::azure_vars::state::edits::{EditOp, StagedEdits};
+    use crate::azure_vars::state::state::{SearchStatus, SearchTarget, VarEntry};
+    use crate::azure_vars::tui::keymap::{Keymap, Preset};
     use crossterm::event::{KeyCode, KeyModifiers};
 
     #[tokio::test]
@@ -102,10 +160,51 @@ mod tests {
         let mut tx = tx;
         let key = KeyEvent::new(KeyCode::Char('R'), KeyModifiers::NONE);
 
-        let should_quit = handle_key(&state, &mut tx, key).await.unwrap();
+        let should_quit = handle_key(&state, &mut KeyHandler::default(), &mut tx, key)
+            .await
+            .unwrap();
         assert!(!should_quit);
 
         let action = rx.recv().await.expect("action should be sent");
         assert!(matches!(action, Action::RefreshVarGroups));
     }
+
+    #[tokio::test]
+    async fn typing_in_search_mode_inserts_unbound_characters() {
+        let mut state = State::new("org".into(), "proj".into());
+        state.ui.search.status = SearchStatus::Active(SearchTarget::Groups);
+        let (mut tx, mut rx) = tokio::sync::mpsc::channel(2);
+        let mut keys = KeyHandler::new(Keymap::preset(Preset::Vim));
+
+        for key in [KeyCode::Char('j'), KeyCode::Esc] {
+            let key = KeyEvent::new(key, KeyModifiers::NONE);
+            handle_key(&state, &mut keys, &mut tx, key).await.unwrap();
+        }
+
+        assert!(matches!(
+            rx.recv().await,
+            Some(Action::SearchInsertChar { ch: 'j' })
+        ));
+        assert!(matches!(rx.recv().await, Some(Action::ExitSearchMode)));
+    }
+
+    #[tokio::test]
+    async fn quitting_with_unsaved_changes_asks_first() {
+        let mut state = State::new("org".into(), "proj".into());
+        let mut edits = StagedEdits::new(&[]);
+        edits.push(EditOp::Set(VarEntry {
+            name: "URL".into(),
+            value: "https://example.com".into(),
+            is_secret: false,
+        }));
+        state.ui.edits.insert("app".into(), edits);
+        let (mut tx, mut rx) = tokio::sync::mpsc::channel(1);
+        let mut keys = KeyHandler::default();
+        let quit = KeyEvent::new(KeyCode::Char('q'), KeyModifiers::NONE);
+
+        assert!(!handle_key(&state, &mut keys, &mut tx, quit).await.unwrap());
+        assert!(matches!(rx.recv().await, Some(Action::RequestQuit)));
+        state.ui.confirm_discard = true;
+        assert!(handle_key(&state, &mut keys, &mut tx, quit).await.unwrap());
+    }
 }
@@ -0,0 +1,833 @@
+use std::{collections::BTreeMap, fmt, fs, path::Path, str::FromStr};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::{Deserialize, Deserializer};
+
+use crate::azure_vars::state::state::{Overlay, State};
+use crate::config::error::ConfigError;
+
+/// Where a binding applies. `Common` bindings are active in both the group and
+/// the variable list, after the bindings of the list itself. `Search` bindings
+/// also apply to the other text prompts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Context {
+    Common,
+    Groups,
+    Vars,
+    History,
+    Audit,
+    Promote,
+    Profiles,
+    Share,
+    Pipelines,
+    Checks,
+    Review,
+    Search,
+}
+
+impl Context {
+    /// The contexts consulted for a key press, in priority order.
+    pub fn active(state: &State) -> &'static [Context] {
+        if state.ui.search.is_active() || state.is_prompting() {
+            return &[Context::Search];
+        }
+        match state.ui.overlay() {
+            Some(Overlay::Review) => &[Context::Review, Context::Common],
+            Some(Overlay::History(_)) => &[Context::History, Context::Common],
+            Some(Overlay::Audit(_)) => &[Context::Audit, Context::Common],
+            Some(Overlay::Promote(_)) => &[Context::Promote, Context::Common],
+            Some(Overlay::Copy(_)) => &[Context::Profiles, Context::Common],
+            Some(Overlay::Share(_)) => &[Context::Share, Context::Common],
+            Some(Overlay::Pipelines(_)) => &[Context::Pipelines, Context::Common],
+            Some(Overlay::Checks(_)) => &[Context::Checks, Context::Common],
+            None if state.is_viewing_vars() => &[Context::Vars, Context::Common],
+            None => &[Context::Groups, Context::Common],
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Context::Common => "common",
+            Context::Groups => "groups",
+            Context::Vars => "vars",
+            Context::History => "history",
+            Context::Audit => "audit",
+            Context::Promote => "promote",
+            Context::Profiles => "profiles",
+            Context::Share => "share",
+            Context::Pipelines => "pipelines",
+            Context::Checks => "checks",
+            Context::Review => "review",
+            Context::Search => "search",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Command {
+    Back,
+    Open,
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    Top,
+    Bottom,
+    Search,
+    Refresh,
+    CancelRefresh,
+    Copy,
+    Export,
+    History,
+    Restore,
+    Audit,
+    Promote,
+    CopyTo,
+    Share,
+    Pipelines,
+    Checks,
+    Toggle,
+    Edit,
+    Add,
+    Remove,
+    Undo,
+    Redo,
+    Review,
+    Save,
+    ToggleTheme,
+    ToggleLogs,
+    Quit,
+
+    // Search input
+    Cancel,
+    Submit,
+    Backspace,
+}
+
+impl Command {
+    /// Help bar order.
+    pub const ALL: [Command; 35] = [
+        Command::Back,
+        Command::Open,
+        Command::Up,
+        Command::Down,
+        Command::PageUp,
+        Command::PageDown,
+        Command::Top,
+        Command::Bottom,
+        Command::Search,
+        Command::Refresh,
+        Command::CancelRefresh,
+        Command::Copy,
+        Command::Export,
+        Command::History,
+        Command::Restore,
+        Command::Audit,
+        Command::Promote,
+        Command::CopyTo,
+        Command::Share,
+        Command::Pipelines,
+        Command::Checks,
+        Command::Toggle,
+        Command::Edit,
+        Command::Add,
+        Command::Remove,
+        Command::Undo,
+        Command::Redo,
+        Command::Review,
+        Command::Save,
+        Command::ToggleTheme,
+        Command::ToggleLogs,
+        Command::Quit,
+        Command::Submit,
+        Command::Cancel,
+        Command::Backspace,
+    ];
+
+    /// The name used in the keymap file.
+    pub fn name(self) -> &'static str {
+        match self {
+            Command::Back => "back",
+            Command::Open => "open",
+            Command::Up => "up",
+            Command::Down => "down",
+            Command::PageUp => "page-up",
+            Command::PageDown => "page-down",
+            Command::Top => "top",
+            Command::Bottom => "bottom",
+            Command::Search => "search",
+            Command::Refresh => "refresh",
+            Command::CancelRefresh => "cancel-refresh",
+            Command::Copy => "copy",
+            Command::Export => "export",
+            Command::History => "history",
+            Command::Restore => "restore",
+            Command::Audit => "audit",
+            Command::Promote => "promote",
+            Command::CopyTo => "copy-to",
+            Command::Share => "share",
+            Command::Pipelines => "pipelines",
+            Command::Checks => "checks",
+            Command::Toggle => "toggle",
+            Command::Edit => "edit",
+            Command::Add => "add",
+            Command::Remove => "remove",
+            Command::Undo => "undo",
+            Command::Redo => "redo",
+            Command::Review => "review",
+            Command::Save => "save",
+            Command::ToggleTheme => "toggle-theme",
+            Command::ToggleLogs => "toggle-logs",
+            Command::Quit => "quit",
+            Command::Cancel => "cancel",
+            Command::Submit => "submit",
+            Command::Backspace => "backspace",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Command::Back => "back",
+            Command::Open => "open",
+            Command::Up => "up",
+            Command::Down => "down",
+            Command::PageUp => "page up",
+            Command::PageDown => "page down",
+            Command::Top => "top",
+            Command::Bottom => "bottom",
+            Command::Search => "search",
+            Command::Refresh => "refresh",
+            Command::CancelRefresh => "stop refresh",
+            Command::Copy => "copy",
+            Command::Export => "export",
+            Command::History => "history",
+            Command::Restore => "restore",
+            Command::Audit => "audit",
+            Command::Promote => "promote",
+            Command::CopyTo => "copy to",
+            Command::Share => "share",
+            Command::Pipelines => "pipelines",
+            Command::Checks => "checks",
+            Command::Toggle => "toggle",
+            Command::Edit => "edit",
+            Command::Add => "add",
+            Command::Remove => "remove",
+            Command::Undo => "undo",
+            Command::Redo => "redo",
+            Command::Review => "review",
+            Command::Save => "save",
+            Command::ToggleTheme => "theme",
+            Command::ToggleLogs => "logs",
+            Command::Quit => "quit",
+            Command::Cancel => "cancel",
+            Command::Submit => "apply",
+            Command::Backspace => "delete",
+        }
+    }
+
+    fn is_search_command(self) -> bool {
+        matches!(self, Command::Cancel | Command::Submit | Command::Backspace)
+    }
+}
+
+/// A single key press, such as `Ctrl-d` or `G`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyChord {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        let mut modifiers =
+            modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT);
+        let code = match code {
+            // Terminals report `G` as Shift+G; the case of the character is enough.
+            KeyCode::Char(c) if modifiers.contains(KeyModifiers::SHIFT) => {
+                modifiers.remove(KeyModifiers::SHIFT);
+                KeyCode::Char(c.to_ascii_uppercase())
+            }
+            KeyCode::BackTab => {
+                modifiers.remove(KeyModifiers::SHIFT);
+                KeyCode::BackTab
+            }
+            code => code,
+        };
+        Self { code, modifiers }
+    }
+
+    /// The character typed by this key, if it is plain text input.
+    pub fn text(&self) -> Option<char> {
+        match self.code {
+            KeyCode::Char(c) if self.modifiers.is_empty() => Some(c),
+            _ => None,
+        }
+    }
+}
+
+impl From<KeyEvent> for KeyChord {
+    fn from(event: KeyEvent) -> Self {
+        Self::new(event.code, event.modifiers)
+    }
+}
+
+impl FromStr for KeyChord {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = s;
+        // A trailing `-` is the minus key itself, as in `Ctrl--`.
+        while let Some((prefix, key)) = rest.split_once('-')
+            && !key.is_empty()
+        {
+            modifiers |= match prefix.to_ascii_lowercase().as_str() {
+                "ctrl" | "c" => KeyModifiers::CONTROL,
+                "alt" | "meta" | "m" => KeyModifiers::ALT,
+                "shift" | "s" => KeyModifiers::SHIFT,
+                _ => return Err(format!("unknown modifier `{prefix}` in `{s}`")),
+            };
+            rest = key;
+        }
+
+        let mut chars = rest.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match rest.to_ascii_lowercase().as_str() {
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "pageup" | "pgup" => KeyCode::PageUp,
+                "pagedown" | "pgdn" => KeyCode::PageDown,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "enter" | "return" => KeyCode::Enter,
+                "esc" | "escape" => KeyCode::Esc,
+                "backspace" => KeyCode::Backspace,
+                "delete" | "del" => KeyCode::Delete,
+                "insert" => KeyCode::Insert,
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "space" => KeyCode::Char(' '),
+                "minus" => KeyCode::Char('-'),
+                name => match name.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+                    Some(n @ 1..=12) => KeyCode::F(n),
+                    _ => return Err(format!("unknown key `{rest}` in `{s}`")),
+                },
+            },
+        };
+        Ok(Self::new(code, modifiers))
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            f.write_str("Ctrl-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            f.write_str("Alt-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            f.write_str("Shift-")?;
+        }
+        match self.code {
+            KeyCode::Up => f.write_str("↑"),
+            KeyCode::Down => f.write_str("↓"),
+            KeyCode::Left => f.write_str("←"),
+            KeyCode::Right => f.write_str("→"),
+            KeyCode::PageUp => f.write_str("PgUp"),
+            KeyCode::PageDown => f.write_str("PgDn"),
+            KeyCode::Char(' ') => f.write_str("Space"),
+            KeyCode::Char(c) => write!(f, "{c}"),
+            KeyCode::F(n) => write!(f, "F{n}"),
+            code => write!(f, "{code:?}"),
+        }
+    }
+}
+
+/// One or more key presses bound to a command, written `g g` in the keymap.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeySequence(Vec<KeyChord>);
+
+impl FromStr for KeySequence {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let chords = s
+            .split_whitespace()
+            .map(str::parse)
+            .collect::<Result<Vec<KeyChord>, _>>()?;
+        if chords.is_empty() {
+            return Err("a key binding cannot be empty".into());
+        }
+        Ok(Self(chords))
+    }
+}
+
+impl fmt::Display for KeySequence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let keys = self.0.iter().map(ToString::to_string).collect::<Vec<_>>();
+        f.write_str(&keys.join(" "))
+    }
+}
+
+impl<'de> Deserialize<'de> for KeySequence {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Preset {
+    #[default]
+    Default,
+    Vim,
+    Emacs,
+}
+
+type Overrides = BTreeMap<Command, Vec<KeySequence>>;
+
+/// The contents of `keymap.yaml`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct KeymapFile {
+    #[serde(default)]
+    preset: Preset,
+    #[serde(default)]
+    common: Overrides,
+    #[serde(default)]
+    groups: Overrides,
+    #[serde(default)]
+    vars: Overrides,
+    #[serde(default)]
+    history: Overrides,
+    #[serde(default)]
+    audit: Overrides,
+    #[serde(default)]
+    promote: Overrides,
+    #[serde(default)]
+    profiles: Overrides,
+    #[serde(default)]
+    share: Overrides,
+    #[serde(default)]
+    pipelines: Overrides,
+    #[serde(default)]
+    checks: Overrides,
+    #[serde(default)]
+    review: Overrides,
+    #[serde(default)]
+    search: Overrides,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Binding {
+    context: Context,
+    command: Command,
+    keys: KeySequence,
+}
+
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: Vec<Binding>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::preset(Preset::Default)
+    }
+}
+
+impl Keymap {
+    pub fn preset(preset: Preset) -> Self {
+        use Command::*;
+        use Context::{Common, Groups, Profiles, Vars};
+
+        let mut keymap = Self { bindings: vec![] };
+        keymap.bind_all(&[
+            (Groups, Open, "Enter"),
+            (Vars, Back, "Left"),
+            (Vars, Copy, "C"),
+            (Vars, Export, "E"),
+            (Vars, Command::History, "H"),
+            (Context::History, Back, "Left"),
+            (Context::History, Restore, "r"),
+            (Groups, Command::Audit, "A"),
+            (Vars, Command::Audit, "A"),
+            (Context::Audit, Back, "Left"),
+            (Vars, Command::Promote, "P"),
+            (Context::Promote, Back, "Left"),
+            (Context::Promote, Open, "Enter"),
+            (Context::Promote, Toggle, "Space"),
+            (Context::Promote, Edit, "e"),
+            (Context::Promote, Save, "s"),
+            (Groups, CopyTo, "M"),
+            (Vars, CopyTo, "M"),
+            (Profiles, Back, "Left"),
+            (Profiles, Open, "Enter"),
+            (Groups, Command::Share, "X"),
+            (Vars, Command::Share, "X"),
+            (Context::Share, Back, "Left"),
+            (Context::Share, Toggle, "Space"),
+            (Context::Share, Save, "s"),
+            (Groups, Command::Pipelines, "I"),
+            (Vars, Command::Pipelines, "I"),
+            (Context::Pipelines, Back, "Left"),
+            (Context::Pipelines, Toggle, "Space"),
+            (Context::Pipelines, Save, "s"),
+            (Groups, Command::Checks, "K"),
+            (Vars, Command::Checks, "K"),
+            (Context::Checks, Back, "Left"),
+            (Context::Checks, Add, "a"),
+            (Context::Checks, Remove, "d"),
+            (Vars, Edit, "e"),
+            (Vars, Add, "a"),
+            (Vars, Remove, "d"),
+            (Common, Undo, "u"),
+            (Common, Redo, "Ctrl-r"),
+            (Common, Review, "S"),
+            (Context::Review, Back, "Left"),
+            (Context::Review, Save, "s"),
+            (Common, Up, "Up"),
+            (Common, Down, "Down"),
+            (Common, PageUp, "PageUp"),
+            (Common, PageDown, "PageDown"),
+            (Common, Top, "Home"),
+            (Common, Bottom, "End"),
+            (Common, Search, "/"),
+            (Common, Refresh, "R"),
+            (Common, CancelRefresh, "Esc"),
+            (Common, ToggleTheme, "T"),
+            (Common, ToggleLogs, "L"),
+            (Common, Quit, "q"),
+            (Context::Search, Submit, "Enter"),
+            (Context::Search, Cancel, "Esc"),
+            (Context::Search, Backspace, "Backspace"),
+        ]);
+        match preset {
+            Preset::Default => {}
+            Preset::Vim => keymap.bind_all(&[
+                (Groups, Open, "l"),
+                (Vars, Back, "h"),
+                (Context::History, Back, "h"),
+                (Context::Audit, Back, "h"),
+                (Context::Promote, Back, "h"),
+                (Context::Promote, Open, "l"),
+                (Profiles, Back, "h"),
+                (Profiles, Open, "l"),
+                (Context::Share, Back, "h"),
+                (Context::Pipelines, Back, "h"),
+                (Context::Checks, Back, "h"),
+                (Context::Review, Back, "h"),
+                (Common, Up, "k"),
+                (Common, Down, "j"),
+                (Common, PageUp, "Ctrl-u"),
+                (Common, PageDown, "Ctrl-d"),
+                (Common, Top, "g g"),
+                (Common, Bottom, "G"),
+            ]),
+            Preset::Emacs => keymap.bind_all(&[
+                (Groups, Open, "Ctrl-f"),
+                (Vars, Back, "Ctrl-b"),
+                (Context::History, Back, "Ctrl-b"),
+                (Context::Audit, Back, "Ctrl-b"),
+                (Context::Promote, Back, "Ctrl-b"),
+                (Context::Promote, Open, "Ctrl-f"),
+                (Profiles, Back, "Ctrl-b"),
+                (Profiles, Open, "Ctrl-f"),
+                (Context::Share, Back, "Ctrl-b"),
+                (Context::Pipelines, Back, "Ctrl-b"),
+                (Context::Checks, Back, "Ctrl-b"),
+                (Context::Review, Back, "Ctrl-b"),
+                (Common, Up, "Ctrl-p"),
+                (Common, Down, "Ctrl-n"),
+                (Common, PageUp, "Alt-v"),
+                (Common, PageDown, "Ctrl-v"),
+                (Common, Top, "Alt-<"),
+                (Common, Bottom, "Alt->"),
+                (Common, Search, "Ctrl-s"),
+                (Common, Quit, "Ctrl-x Ctrl-c"),
+                (Context::Search, Cancel, "Ctrl-g"),
+                (Context::Search, Backspace, "Ctrl-h"),
+            ]),
+        }
+        keymap
+    }
+
+    fn bind_all(&mut self, bindings: &[(Context, Command, &str)]) {
+        for (context, command, keys) in bindings {
+            self.bindings.push(Binding {
+                context: *context,
+                command: *command,
+                keys: keys.parse().expect("built-in key bindings are valid"),
+            });
+        }
+    }
+
+    /// Reads the keymap file, falling back to the default preset when it does
+    /// not exist.
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        match fs::read_to_string(path) {
+            Ok(text) => Self::parse(&text, path),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(source) => Err(ConfigError::Io {
+                path: path.to_path_buf(),
+                source,
+            }),
+        }
+    }
+
+    /// Parses a keymap file: a preset, followed by per-context overrides that
+    /// replace every preset binding of the commands they list.
+    pub fn parse(text: &str, path: &Path) -> Result<Self, ConfigError> {
+        if text.trim().is_empty() {
+            return Ok(Self::default());
+        }
+        let file = serde_yaml::from_str::<KeymapFile>(text).map_err(|error| {
+            let location = error.location();
+            ConfigError::Parse {
+                path: path.to_path_buf(),
+                line: location.as_ref().map(|l| l.line()),
+                column: location.as_ref().map(|l| l.column()),
+                message: error.to_string(),
+            }
+        })?;
+
+        let mut keymap = Self::preset(file.preset);
+        for (context, overrides) in [
+            (Context::Common, file.common),
+            (Context::Groups, file.groups),
+            (Context::Vars, file.vars),
+            (Context::History, file.history),
+            (Context::Audit, file.audit),
+            (Context::Promote, file.promote),
+            (Context::Profiles, file.profiles),
+            (Context::Share, file.share),
+            (Context::Pipelines, file.pipelines),
+            (Context::Checks, file.checks),
+            (Context::Review, file.review),
+            (Context::Search, file.search),
+        ] {
+            for (command, sequences) in overrides {
+                if command.is_search_command() != (context == Context::Search) {
+                    return Err(ConfigError::Parse {
+                        path: path.to_path_buf(),
+                        line: None,
+                        column: None,
+                        message: format!(
+                            "`{}` cannot be bound in `{}`",
+                            command.name(),
+                            context.name()
+                        ),
+                    });
+                }
+                keymap
+                    .bindings
+                    .retain(|b| !(b.context == context && b.command == command));
+                keymap
+                    .bindings
+                    .extend(sequences.into_iter().map(|keys| Binding {
+                        context,
+                        command,
+                        keys,
+                    }));
+            }
+        }
+        Ok(keymap)
+    }
+
+    /// The keys bound to `command` in the first of `contexts` that binds it.
+    pub fn keys_for(&self, contexts: &[Context], command: Command) -> Vec<&KeySequence> {
+        contexts
+            .iter()
+            .map(|context| {
+                self.bindings
+                    .iter()
+                    .filter(|b| b.context == *context && b.command == command)
+                    .map(|b| &b.keys)
+                    .collect::<Vec<_>>()
+            })
+            .find(|keys| !keys.is_empty())
+            .unwrap_or_default()
+    }
+
+    /// `(keys, label)` pairs for every command bound in `contexts`.
+    pub fn help(&self, contexts: &[Context]) -> Vec<(String, &'static str)> {
+        Command::ALL
+            .into_iter()
+            .filter_map(|command| {
+                let keys = self.keys_for(contexts, command);
+                if keys.is_empty() {
+                    return None;
+                }
+                let keys = keys
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join("/");
+                Some((keys, command.label()))
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyOutcome {
+    Command(Command),
+    /// The key starts a longer binding such as `g g`.
+    Pending,
+    Unbound(KeyChord),
+}
+
+/// Resolves key presses against a keymap, remembering the keys typed so far
+/// of a multi-key binding.
+#[derive(Debug, Clone, Default)]
+pub struct KeyHandler {
+    keymap: Keymap,
+    pending: Vec<KeyChord>,
+}
+
+impl KeyHandler {
+    pub fn new(keymap: Keymap) -> Self {
+        Self {
+            keymap,
+            pending: vec![],
+        }
+    }
+
+    pub fn keymap(&self) -> &Keymap {
+        &self.keymap
+    }
+
+    pub fn handle(&mut self, contexts: &[Context], chord: KeyChord) -> KeyOutcome {
+        self.pending.push(chord);
+        let candidates = contexts.iter().flat_map(|context| {
+            self.keymap
+                .bindings
+                .iter()
+                .filter(move |b| b.context == *context)
+        });
+
+        let mut is_prefix = false;
+        for binding in candidates {
+            if binding.keys.0 == self.pending {
+                self.pending.clear();
+                return KeyOutcome::Command(binding.command);
+            }
+            is_prefix |= binding.keys.0.starts_with(&self.pending);
+        }
+        if is_prefix {
+            return KeyOutcome::Pending;
+        }
+
+        // An unfinished sequence was abandoned; the last key may still be a
+        // binding of its own.
+        let abandoned = self.pending.len() > 1;
+        self.pending.clear();
+        if abandoned {
+            self.handle(contexts, chord)
+        } else {
+            KeyOutcome::Unbound(chord)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chord(s: &str) -> KeyChord {
+        s.parse().unwrap()
+    }
+
+    fn parse(text: &str) -> Result<Keymap, ConfigError> {
+        Keymap::parse(text, Path::new("keymap.yaml"))
+    }
+
+    #[test]
+    fn parses_key_chords() {
+        assert_eq!(
+            chord("Ctrl-d"),
+            KeyChord::new(KeyCode::Char('d'), KeyModifiers::CONTROL)
+        );
+        assert_eq!(
+            chord("pgdn"),
+            KeyChord::new(KeyCode::PageDown, KeyModifiers::NONE)
+        );
+        assert_eq!(chord("Shift-g"), chord("G"));
+        assert_eq!(chord("Ctrl--").code, KeyCode::Char('-'));
+        assert!("Hyper-x".parse::<KeyChord>().is_err());
+        assert!("Nope".parse::<KeyChord>().is_err());
+    }
+
+    #[test]
+    fn shifted_letters_from_the_terminal_match_uppercase_bindings() {
+        let event = KeyEvent::new(KeyCode::Char('G'), KeyModifiers::SHIFT);
+        assert_eq!(KeyChord::from(event), chord("G"));
+    }
+
+    #[test]
+    fn vim_preset_resolves_multi_key_sequences() {
+        let mut handler = KeyHandler::new(Keymap::preset(Preset::Vim));
+        let contexts = [Context::Groups, Context::Common];
+
+        assert_eq!(handler.handle(&contexts, chord("g")), KeyOutcome::Pending);
+        assert_eq!(
+            handler.handle(&contexts, chord("g")),
+            KeyOutcome::Command(Command::Top)
+        );
+        assert_eq!(
+            handler.handle(&contexts, chord("Ctrl-d")),
+            KeyOutcome::Command(Command::PageDown)
+        );
+        // An abandoned sequence still runs the key that broke it off.
+        assert_eq!(handler.handle(&contexts, chord("g")), KeyOutcome::Pending);
+        assert_eq!(
+            handler.handle(&contexts, chord("j")),
+            KeyOutcome::Command(Command::Down)
+        );
+    }
+
+    #[test]
+    fn view_bindings_take_priority_over_common_ones() {
+        let keymap = parse("vars:\n  copy: [q]\n").unwrap();
+        let mut handler = KeyHandler::new(keymap);
+
+        assert_eq!(
+            handler.handle(&[Context::Vars, Context::Common], chord("q")),
+            KeyOutcome::Command(Command::Copy)
+        );
+        assert_eq!(
+            handler.handle(&[Context::Groups, Context::Common], chord("q")),
+            KeyOutcome::Command(Command::Quit)
+        );
+    }
+
+    #[test]
+    fn overrides_replace_preset_bindings_and_update_help() {
+        let keymap = parse("preset: vim\ncommon:\n  refresh: [Ctrl-r, F5]\n").unwrap();
+        let help = keymap.help(&[Context::Groups, Context::Common]);
+
+        assert!(help.contains(&("Ctrl-r/F5".to_string(), "refresh")));
+        assert!(help.contains(&("↓/j".to_string(), "down")));
+        assert!(!help.iter().any(|(_, label)| *label == "copy"));
+    }
+
+    #[test]
+    fn reports_invalid_keymaps() {
+        match parse("common:\n  reload: [r]\n").unwrap_err() {
+            ConfigError::Parse { line, message, .. } => {
+                assert_eq!(line, Some(2));
+                assert!(message.contains("reload"));
+            }
+            other => panic!("expected a parse error, got {other:?}"),
+        }
+        assert!(parse("groups:\n  open: [Hyper-x]\n").is_err());
+        assert!(parse("groups:\n  submit: [Enter]\n").is_err());
+    }
+}
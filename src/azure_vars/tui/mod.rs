@@ -1,3 +1,4 @@
 pub mod draw;
 pub mod handle_input;
+pub mod keymap;
 pub mod widgets;
@@ -1,11 +1,38 @@
-use ratatui::layout::Rect;
-use ratatui::style::{Color, Modifier, Style};
+use std::{
+    collections::HashSet,
+    time::{Duration, SystemTime},
+};
+
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::style::{Modifier, Style};
 use ratatui::text::{Line, Span};
-use ratatui::widgets::{Block, Borders, List, ListItem, Paragraph, StatefulWidget, Widget};
+use ratatui::widgets::{Block, Borders, List, ListItem, Paragraph, StatefulWidget, Widget, Wrap};
 
-use crate::azure_vars::state::state::{
-    SearchState, SearchTarget, StatusKind, StatusMessage, Theme, VarEntry, VarGroup,
+use crate::azure_vars::state::{
+    audit::{AuditEvent, format_time},
+    cache::format_age,
+    diff::{VarChange, var_changes},
+    edits::StagedEdits,
+    journal::JournalEntry,
+    state::{
+        ChecksView, CopyProfile, CopyView, PipelinesView, PromoteRow, PromoteView, Prompt,
+        SearchTarget, ShareView, StatusKind, StatusMessage, VarEntry, VarGroup,
+    },
+    theme::Theme,
 };
+use crate::logging::LogRecord;
+
+/// Shown instead of the value of a secret.
+const SECRET_MASK: &str = "*****";
+
+/// The value of `var` as it may be shown on screen.
+fn shown_value(var: &VarEntry) -> &str {
+    if var.is_secret {
+        SECRET_MASK
+    } else {
+        &var.value
+    }
+}
 
 pub struct SearchBar {
     query: String,
@@ -33,12 +60,42 @@ impl Widget for SearchBar {
     }
 }
 
+pub struct PromptBar {
+    prompt: Prompt,
+}
+
+impl PromptBar {
+    pub fn new(prompt: Prompt) -> Self {
+        Self { prompt }
+    }
+}
+
+impl Widget for PromptBar {
+    fn render(self, area: Rect, buf: &mut ratatui::prelude::Buffer) {
+        let text = if self.prompt.kind.is_secret() {
+            "*".repeat(self.prompt.text.chars().count())
+        } else {
+            self.prompt.text.clone()
+        };
+        Paragraph::new(format!("{text}_"))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(self.prompt.kind.title()),
+            )
+            .render(area, buf);
+    }
+}
+
 pub struct BreadCrumb {
     organization: String,
     project: String,
     group_name: String,
     viewing_vars: bool,
     theme: Theme,
+    /// Age of the groups shown, while they come from the cache.
+    cache_age: Option<Duration>,
+    offline: bool,
 }
 
 impl BreadCrumb {
@@ -48,6 +105,8 @@ impl BreadCrumb {
         group_name: String,
         viewing_vars: bool,
         theme: Theme,
+        cache_age: Option<Duration>,
+        offline: bool,
     ) -> Self {
         Self {
             theme,
@@ -55,6 +114,8 @@ impl BreadCrumb {
             project,
             group_name,
             viewing_vars,
+            cache_age,
+            offline,
         }
     }
 }
@@ -64,10 +125,6 @@ impl Widget for BreadCrumb {
     where
         Self: Sized,
     {
-        let muted = match self.theme {
-            Theme::Dark => Color::DarkGray,
-            Theme::Light => Color::Gray,
-        };
         let breadcrumb = format!(
             "{} > {}{}",
             self.organization,
@@ -78,8 +135,21 @@ impl Widget for BreadCrumb {
                 "".into()
             }
         );
-        let header = Paragraph::new(breadcrumb)
-            .style(Style::default().fg(muted))
+        let mut spans = vec![Span::raw(breadcrumb)];
+        let freshness = match (self.offline, self.cache_age) {
+            (true, Some(age)) => Some(format!("  (offline, data from {} ago)", format_age(age))),
+            (true, None) => Some("  (offline)".to_string()),
+            (false, Some(age)) => Some(format!("  (cached {} ago)", format_age(age))),
+            (false, None) => None,
+        };
+        if let Some(freshness) = freshness {
+            spans.push(Span::styled(
+                freshness,
+                Style::default().fg(self.theme.accent),
+            ));
+        }
+        let header = Paragraph::new(Line::from(spans))
+            .style(Style::default().fg(self.theme.muted))
             .block(Block::default());
 
         header.render(area, buf);
@@ -88,43 +158,57 @@ impl Widget for BreadCrumb {
 
 pub struct VarGroupList {
     groups: Vec<VarGroup>,
+    pinned: Vec<String>,
     theme: Theme,
     selected: VarGroup,
+    /// Groups fetched so far while a refresh is in progress.
+    loading: Option<usize>,
 }
 
 impl VarGroupList {
-    pub fn new(groups: Vec<VarGroup>, theme: Theme, selected: VarGroup) -> Self {
+    pub fn new(
+        groups: Vec<VarGroup>,
+        pinned: Vec<String>,
+        theme: Theme,
+        selected: VarGroup,
+        loading: Option<usize>,
+    ) -> Self {
         Self {
             groups,
+            pinned,
             theme,
             selected,
+            loading,
         }
     }
 }
 
 impl Widget for VarGroupList {
     fn render(self, area: Rect, buf: &mut ratatui::prelude::Buffer) {
-        let accent = match self.theme {
-            Theme::Dark => Color::Yellow,
-            Theme::Light => Color::Blue,
-        };
+        let accent = self.theme.accent;
         let items: Vec<ListItem> = self
             .groups
             .iter()
             .map(|g| {
+                let marker = if self.pinned.contains(&g.name) {
+                    Span::styled("* ", Style::default().fg(accent))
+                } else {
+                    Span::raw("  ")
+                };
                 ListItem::new(Line::from(vec![
+                    marker,
                     Span::styled(&g.name, Style::default().add_modifier(Modifier::BOLD)),
-                    Span::raw(format!("  ({} vars)", g.variables.len())),
+                    Span::raw(format!("  ({} vars)", g.var_count())),
                 ]))
             })
             .collect();
 
+        let title = match self.loading {
+            Some(fetched) => format!("Variable Groups (loading, {fetched} fetched)"),
+            None => "Variable Groups".to_string(),
+        };
         let list = List::new(items)
-            .block(
-                Block::default()
-                    .borders(Borders::ALL)
-                    .title("Variable Groups"),
-            )
+            .block(Block::default().borders(Borders::ALL).title(title))
             .highlight_style(Style::default().fg(accent).add_modifier(Modifier::BOLD))
             .highlight_symbol(">> ");
 
@@ -145,6 +229,10 @@ pub struct VarList {
     search_query: Option<String>,
     theme: Theme,
     selected: VarEntry,
+    /// Names of the variables changed since the group was last viewed.
+    changed: HashSet<String>,
+    /// Names of the variables added or changed and not saved yet.
+    unsaved: HashSet<String>,
 }
 
 impl VarList {
@@ -154,6 +242,8 @@ impl VarList {
         search_query: Option<String>,
         theme: Theme,
         selected: VarEntry,
+        changed: HashSet<String>,
+        unsaved: HashSet<String>,
     ) -> Self {
         Self {
             vars,
@@ -161,49 +251,68 @@ impl VarList {
             search_query,
             theme,
             selected,
+            changed,
+            unsaved,
         }
     }
 }
 
 impl Widget for VarList {
     fn render(self, area: Rect, buf: &mut ratatui::prelude::Buffer) {
-        let accent = match self.theme {
-            Theme::Dark => Color::Yellow,
-            Theme::Light => Color::Blue,
-        };
-        let muted = match self.theme {
-            Theme::Dark => Color::DarkGray,
-            Theme::Light => Color::Gray,
-        };
+        let accent = self.theme.accent;
 
         let items: Vec<ListItem> = self
             .vars
             .iter()
             .map(|v| {
                 let val_color = if v.is_secret {
-                    Color::Red
+                    self.theme.secret
                 } else if v.value == "<no value>" {
-                    muted
+                    self.theme.muted
                 } else {
-                    Color::Cyan
+                    self.theme.value
                 };
 
-                ListItem::new(Line::from(vec![
-                    Span::styled(&v.name, Style::default().add_modifier(Modifier::BOLD)),
+                let changed = self.changed.contains(&v.name);
+                let name_style = if changed {
+                    Style::default()
+                        .fg(self.theme.info)
+                        .add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default().add_modifier(Modifier::BOLD)
+                };
+
+                let mut spans = vec![
+                    Span::styled(&v.name, name_style),
                     Span::raw(": "),
-                    Span::styled(&v.value, Style::default().fg(val_color)),
-                ]))
+                    Span::styled(shown_value(v), Style::default().fg(val_color)),
+                ];
+                if changed {
+                    spans.push(Span::styled(
+                        "  (changed)",
+                        Style::default().fg(self.theme.info),
+                    ));
+                }
+                if self.unsaved.contains(&v.name) {
+                    spans.push(Span::styled("  (unsaved)", Style::default().fg(accent)));
+                }
+                ListItem::new(Line::from(spans))
             })
             .collect();
 
         let title = format!(
-            "{} ({} vars{})",
+            "{} ({} vars{}{})",
             self.group_name,
             self.vars.len(),
             if let Some(query) = self.search_query {
                 format!(", filter: '{query}'")
             } else {
                 "".into()
+            },
+            if self.unsaved.is_empty() {
+                "".into()
+            } else {
+                format!(", {} unsaved", self.unsaved.len())
             }
         );
 
@@ -224,46 +333,745 @@ impl Widget for VarList {
     }
 }
 
-pub struct HelpBar {
+/// The recorded versions of a group, with what each one changed.
+pub struct HistoryList {
+    group_name: String,
+    /// Newest first.
+    entries: Vec<JournalEntry>,
+    selected: usize,
     theme: Theme,
-    viewing_vars: bool,
-    search: SearchState,
 }
 
-impl HelpBar {
-    pub fn new(theme: Theme, viewing_vars: bool, search: SearchState) -> Self {
+impl HistoryList {
+    pub fn new(
+        group_name: String,
+        entries: Vec<JournalEntry>,
+        selected: usize,
+        theme: Theme,
+    ) -> Self {
         Self {
+            group_name,
+            entries,
+            selected,
             theme,
-            viewing_vars,
-            search,
         }
     }
+
+    fn changes(&self, idx: usize) -> Option<Vec<VarChange>> {
+        let previous = self.entries.get(idx + 1)?;
+        Some(var_changes(
+            &previous.group.variables,
+            &self.entries[idx].group.variables,
+        ))
+    }
 }
 
-impl Widget for HelpBar {
+impl Widget for HistoryList {
+    fn render(self, area: Rect, buf: &mut ratatui::prelude::Buffer) {
+        let accent = self.theme.accent;
+        let panes = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
+            .split(area);
+
+        let now = SystemTime::now();
+        let items: Vec<ListItem> = self
+            .entries
+            .iter()
+            .enumerate()
+            .map(|(idx, entry)| {
+                let age = now.duration_since(entry.recorded_at()).unwrap_or_default();
+                let summary = match self.changes(idx) {
+                    Some(changes) => format!("{} variables changed", changes.len()),
+                    None => "first recorded version".to_string(),
+                };
+                ListItem::new(Line::from(vec![
+                    Span::styled(
+                        format!("{:>4} ago", format_age(age)),
+                        Style::default().add_modifier(Modifier::BOLD),
+                    ),
+                    Span::styled(
+                        format!(
+                            "  {}",
+                            entry.group.modified_by.as_deref().unwrap_or("unknown")
+                        ),
+                        Style::default().fg(self.theme.muted),
+                    ),
+                    Span::raw(format!("  {summary}")),
+                ]))
+            })
+            .collect();
+        let title = format!(
+            "History of {} ({} versions)",
+            self.group_name,
+            self.entries.len()
+        );
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .highlight_style(Style::default().fg(accent).add_modifier(Modifier::BOLD))
+            .highlight_symbol(">> ");
+        let selected = self.selected.min(self.entries.len().saturating_sub(1));
+        let mut state = ratatui::widgets::ListState::default().with_selected(Some(selected));
+        StatefulWidget::render(list, panes[0], buf, &mut state);
+
+        let lines: Vec<Line> = match self.changes(selected) {
+            None => vec![Line::styled(
+                "First recorded version",
+                Style::default().fg(self.theme.muted),
+            )],
+            Some(changes) if changes.is_empty() => vec![Line::styled(
+                "Only the name changed",
+                Style::default().fg(self.theme.muted),
+            )],
+            Some(changes) => changes
+                .iter()
+                .map(|change| change_line(change, &self.theme))
+                .collect(),
+        };
+        Paragraph::new(lines)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title("Changes from the previous version"),
+            )
+            .render(panes[1], buf);
+    }
+}
+
+/// The audit events of a group, with the details of the selected one.
+pub struct AuditList {
+    group_name: String,
+    /// Newest first.
+    events: Vec<AuditEvent>,
+    loading: bool,
+    selected: usize,
+    theme: Theme,
+}
+
+impl AuditList {
+    pub fn new(
+        group_name: String,
+        events: Vec<AuditEvent>,
+        loading: bool,
+        selected: usize,
+        theme: Theme,
+    ) -> Self {
+        Self {
+            group_name,
+            events,
+            loading,
+            selected,
+            theme,
+        }
+    }
+}
+
+impl Widget for AuditList {
+    fn render(self, area: Rect, buf: &mut ratatui::prelude::Buffer) {
+        let title = format!("Audit log of {}", self.group_name);
+        if self.loading || self.events.is_empty() {
+            let text = if self.loading {
+                "Loading audit events..."
+            } else {
+                "No audit events"
+            };
+            Paragraph::new(Line::styled(text, Style::default().fg(self.theme.muted)))
+                .block(Block::default().borders(Borders::ALL).title(title))
+                .render(area, buf);
+            return;
+        }
+
+        let accent = self.theme.accent;
+        let panes = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(area);
+
+        let now = SystemTime::now();
+        let items: Vec<ListItem> = self
+            .events
+            .iter()
+            .map(|event| {
+                let age = event
+                    .at
+                    .map(|at| {
+                        format!(
+                            "{:>4} ago",
+                            format_age(now.duration_since(at).unwrap_or_default())
+                        )
+                    })
+                    .unwrap_or_else(|| "       ?".to_string());
+                ListItem::new(Line::from(vec![
+                    Span::styled(age, Style::default().add_modifier(Modifier::BOLD)),
+                    Span::styled(
+                        format!("  {}", event.actor),
+                        Style::default().fg(self.theme.muted),
+                    ),
+                    Span::raw(format!("  {}", event.short_action())),
+                ]))
+            })
+            .collect();
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(format!("{title} ({} events)", self.events.len())),
+            )
+            .highlight_style(Style::default().fg(accent).add_modifier(Modifier::BOLD))
+            .highlight_symbol(">> ");
+        let selected = self.selected.min(self.events.len() - 1);
+        let mut state = ratatui::widgets::ListState::default().with_selected(Some(selected));
+        StatefulWidget::render(list, panes[0], buf, &mut state);
+
+        let event = &self.events[selected];
+        let field = |label: &str, value: String| {
+            Line::from(vec![
+                Span::styled(format!("{label}: "), Style::default().fg(self.theme.muted)),
+                Span::raw(value),
+            ])
+        };
+        let mut lines = vec![
+            field("When", event.at.map(format_time).unwrap_or_default()),
+            field("Who", event.actor.clone()),
+            field("Action", event.action.clone()),
+            field("Category", event.category.clone()),
+        ];
+        if let Some(project) = &event.project {
+            lines.push(field("Project", project.clone()));
+        }
+        lines.push(Line::raw(""));
+        lines.push(Line::raw(event.details.clone()));
+        Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .block(Block::default().borders(Borders::ALL).title("Details"))
+            .render(panes[1], buf);
+    }
+}
+
+fn change_line(change: &VarChange, theme: &Theme) -> Line<'static> {
+    match change {
+        VarChange::Added(var) => Line::styled(
+            format!("+ {}={}", var.name, shown_value(var)),
+            Style::default().fg(theme.info),
+        ),
+        VarChange::Removed(var) => Line::styled(
+            format!("- {}={}", var.name, shown_value(var)),
+            Style::default().fg(theme.error),
+        ),
+        VarChange::Changed { old, new } => Line::styled(
+            format!(
+                "~ {}: {} → {}",
+                new.name,
+                shown_value(old),
+                shown_value(new)
+            ),
+            Style::default().fg(theme.accent),
+        ),
+    }
+}
+
+/// Picking the group to promote variables to, then the variables to copy.
+pub struct PromoteList {
+    view: PromoteView,
+    targets: Vec<String>,
+    /// `None` while the variables of the target are loading.
+    rows: Option<Vec<PromoteRow>>,
+    theme: Theme,
+}
+
+impl PromoteList {
+    pub fn new(
+        view: PromoteView,
+        targets: Vec<String>,
+        rows: Option<Vec<PromoteRow>>,
+        theme: Theme,
+    ) -> Self {
+        Self {
+            view,
+            targets,
+            rows,
+            theme,
+        }
+    }
+
+    fn row_item(&self, row: &PromoteRow) -> ListItem<'static> {
+        let name = &row.source.name;
+        let mark = if self.view.chosen.contains(name) {
+            "[x]"
+        } else {
+            "[ ]"
+        };
+        let shown = |var: &VarEntry| {
+            if var.is_secret {
+                "(secret)".to_string()
+            } else {
+                var.value.clone()
+            }
+        };
+        let value = match self.view.overrides.get(name) {
+            Some(_) if row.source.is_secret => "(secret, entered)".to_string(),
+            Some(value) => format!("{value} (overridden)"),
+            None => shown(&row.source),
+        };
+        let change = match &row.target {
+            Some(target) => format!("{} → {value}", shown(target)),
+            None => format!("new: {value}"),
+        };
+        let color = if row.target.is_some() {
+            self.theme.accent
+        } else {
+            self.theme.info
+        };
+        ListItem::new(Line::from(vec![
+            Span::raw(format!("{mark} ")),
+            Span::styled(name.clone(), Style::default().add_modifier(Modifier::BOLD)),
+            Span::styled(format!("  {change}"), Style::default().fg(color)),
+        ]))
+    }
+}
+
+impl Widget for PromoteList {
+    fn render(self, area: Rect, buf: &mut ratatui::prelude::Buffer) {
+        let source = &self.view.source;
+        let (title, items) = match (&self.view.target, &self.rows) {
+            (None, _) => (
+                format!("Promote {source} to..."),
+                self.targets
+                    .iter()
+                    .map(|name| ListItem::new(name.clone()))
+                    .collect::<Vec<_>>(),
+            ),
+            (Some(target), None) => {
+                Paragraph::new(Line::styled(
+                    format!("Loading {target} variables..."),
+                    Style::default().fg(self.theme.muted),
+                ))
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .title(format!("Promote {source} → {target}")),
+                )
+                .render(area, buf);
+                return;
+            }
+            (Some(target), Some(rows)) => (
+                format!(
+                    "Promote {source} → {target} ({} of {} chosen)",
+                    rows.iter()
+                        .filter(|row| self.view.chosen.contains(&row.source.name))
+                        .count(),
+                    rows.len()
+                ),
+                rows.iter().map(|row| self.row_item(row)).collect(),
+            ),
+        };
+        if items.is_empty() {
+            let text = if self.view.target.is_some() {
+                "The target already has every variable of the source"
+            } else {
+                "No other groups"
+            };
+            Paragraph::new(Line::styled(text, Style::default().fg(self.theme.muted)))
+                .block(Block::default().borders(Borders::ALL).title(title))
+                .render(area, buf);
+            return;
+        }
+        let selected = self.view.selected.min(items.len() - 1);
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .highlight_style(
+                Style::default()
+                    .fg(self.theme.accent)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .highlight_symbol(">> ");
+        let mut state = ratatui::widgets::ListState::default().with_selected(Some(selected));
+        StatefulWidget::render(list, area, buf, &mut state);
+    }
+}
+
+/// Picking the profile a group is copied to.
+pub struct ProfileList {
+    view: CopyView,
+    profiles: Vec<CopyProfile>,
+    theme: Theme,
+}
+
+impl ProfileList {
+    pub fn new(view: CopyView, profiles: Vec<CopyProfile>, theme: Theme) -> Self {
+        Self {
+            view,
+            profiles,
+            theme,
+        }
+    }
+}
+
+impl Widget for ProfileList {
     fn render(self, area: Rect, buf: &mut ratatui::prelude::Buffer) {
-        let text: String = if let Some(target) = self.search.active_target() {
-            match target {
-                SearchTarget::Groups => {
-                    "Type to search groups | Enter=apply | Esc=cancel | Backspace=delete".into()
+        let title = format!("Copy {} to...", self.view.group);
+        let items = self
+            .profiles
+            .iter()
+            .map(|profile| {
+                ListItem::new(Line::from(vec![
+                    Span::styled(
+                        profile.name.clone(),
+                        Style::default().add_modifier(Modifier::BOLD),
+                    ),
+                    Span::styled(
+                        format!("  {}/{}", profile.organization, profile.project),
+                        Style::default().fg(self.theme.muted),
+                    ),
+                ]))
+            })
+            .collect::<Vec<_>>();
+        let selected = self.view.selected.min(items.len().saturating_sub(1));
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .highlight_style(
+                Style::default()
+                    .fg(self.theme.accent)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .highlight_symbol(">> ");
+        let mut state = ratatui::widgets::ListState::default().with_selected(Some(selected));
+        StatefulWidget::render(list, area, buf, &mut state);
+    }
+}
+
+/// The projects of the organization, checked where a group is shared.
+pub struct ShareList {
+    view: ShareView,
+    /// The project the group is viewed in.
+    project: String,
+    theme: Theme,
+}
+
+impl ShareList {
+    pub fn new(view: ShareView, project: String, theme: Theme) -> Self {
+        Self {
+            view,
+            project,
+            theme,
+        }
+    }
+}
+
+impl Widget for ShareList {
+    fn render(self, area: Rect, buf: &mut ratatui::prelude::Buffer) {
+        let group = &self.view.group;
+        if self.view.loading && self.view.projects.is_empty() {
+            Paragraph::new(Line::styled(
+                "Loading projects...",
+                Style::default().fg(self.theme.muted),
+            ))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(format!("Share {group}")),
+            )
+            .render(area, buf);
+            return;
+        }
+        let (share, unshare) = self.view.changes();
+        let title = if share.is_empty() && unshare.is_empty() {
+            format!("Share {group} ({} projects)", self.view.shared.len())
+        } else {
+            format!(
+                "Share {group} (+{} -{} unsaved)",
+                share.len(),
+                unshare.len()
+            )
+        };
+        let items = self
+            .view
+            .projects
+            .iter()
+            .map(|name| {
+                let chosen = self.view.chosen.contains(name);
+                let mark = if chosen { "[x]" } else { "[ ]" };
+                let style = if chosen != self.view.shared.contains(name) {
+                    Style::default().fg(self.theme.accent)
+                } else {
+                    Style::default()
+                };
+                let mut spans = vec![Span::styled(format!("{mark} {name}"), style)];
+                if name == &self.project {
+                    spans.push(Span::styled(
+                        "  (this project)",
+                        Style::default().fg(self.theme.muted),
+                    ));
                 }
-                SearchTarget::Vars => {
-                    "Type to search variables | Enter=apply | Esc=cancel | Backspace=delete".into()
+                ListItem::new(Line::from(spans))
+            })
+            .collect::<Vec<_>>();
+        let selected = self.view.selected.min(items.len().saturating_sub(1));
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .highlight_style(
+                Style::default()
+                    .fg(self.theme.accent)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .highlight_symbol(">> ");
+        let mut state = ratatui::widgets::ListState::default().with_selected(Some(selected));
+        StatefulWidget::render(list, area, buf, &mut state);
+    }
+}
+
+/// The pipelines of the project, checked where they may use a group.
+pub struct PipelineList {
+    view: PipelinesView,
+    theme: Theme,
+}
+
+impl PipelineList {
+    pub fn new(view: PipelinesView, theme: Theme) -> Self {
+        Self { view, theme }
+    }
+
+    fn row(&self, label: String, chosen: bool, granted: bool) -> ListItem<'static> {
+        let mark = if chosen { "[x]" } else { "[ ]" };
+        let style = if chosen != granted {
+            Style::default().fg(self.theme.accent)
+        } else {
+            Style::default()
+        };
+        ListItem::new(Line::styled(format!("{mark} {label}"), style))
+    }
+}
+
+impl Widget for PipelineList {
+    fn render(self, area: Rect, buf: &mut ratatui::prelude::Buffer) {
+        let group = &self.view.group;
+        if self.view.loading && self.view.pipelines.is_empty() {
+            Paragraph::new(Line::styled(
+                "Loading pipelines...",
+                Style::default().fg(self.theme.muted),
+            ))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(format!("Pipelines allowed to use {group}")),
+            )
+            .render(area, buf);
+            return;
+        }
+        let changes = self.view.changes();
+        let title = if changes.is_empty() {
+            format!(
+                "Pipelines allowed to use {group} ({})",
+                if self.view.granted.all_pipelines {
+                    "all".to_string()
+                } else {
+                    self.view.granted.authorized.len().to_string()
                 }
+            )
+        } else {
+            format!(
+                "Pipelines allowed to use {group} (+{} -{} unsaved)",
+                changes.grant.len(),
+                changes.revoke.len()
+            )
+        };
+        let (chosen, granted) = (&self.view.chosen, &self.view.granted);
+        let mut items = vec![self.row(
+            "All pipelines".to_string(),
+            chosen.all_pipelines,
+            granted.all_pipelines,
+        )];
+        items.extend(self.view.pipelines.iter().map(|pipeline| {
+            self.row(
+                pipeline.path(),
+                chosen.authorized.contains(&pipeline.id),
+                granted.authorized.contains(&pipeline.id),
+            )
+        }));
+        let selected = self.view.selected.min(items.len() - 1);
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .highlight_style(
+                Style::default()
+                    .fg(self.theme.accent)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .highlight_symbol(">> ");
+        let mut state = ratatui::widgets::ListState::default().with_selected(Some(selected));
+        StatefulWidget::render(list, area, buf, &mut state);
+    }
+}
+
+/// The approvals and checks runs must pass before using a group.
+pub struct CheckList {
+    view: ChecksView,
+    theme: Theme,
+}
+
+impl CheckList {
+    pub fn new(view: ChecksView, theme: Theme) -> Self {
+        Self { view, theme }
+    }
+}
+
+impl Widget for CheckList {
+    fn render(self, area: Rect, buf: &mut ratatui::prelude::Buffer) {
+        let group = &self.view.group;
+        let placeholder = if self.view.loading && self.view.checks.is_empty() {
+            Some("Loading checks...")
+        } else if self.view.checks.is_empty() {
+            Some("No approvals or checks, every authorized pipeline may use this group")
+        } else {
+            None
+        };
+        if let Some(text) = placeholder {
+            Paragraph::new(Line::styled(text, Style::default().fg(self.theme.muted)))
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .title(format!("Checks of {group}")),
+                )
+                .render(area, buf);
+            return;
+        }
+        let title = format!("Checks of {group} ({})", self.view.checks.len());
+        let width = self
+            .view
+            .checks
+            .iter()
+            .map(|check| check.name().chars().count())
+            .max()
+            .unwrap_or(0);
+        let items = self
+            .view
+            .checks
+            .iter()
+            .map(|check| {
+                let style = if check.disabled {
+                    Style::default().fg(self.theme.muted)
+                } else {
+                    Style::default()
+                };
+                ListItem::new(Line::styled(
+                    format!("{:<width$}  {}", check.name(), check.summary()),
+                    style,
+                ))
+            })
+            .collect::<Vec<_>>();
+        let selected = self.view.selected.min(items.len() - 1);
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .highlight_style(
+                Style::default()
+                    .fg(self.theme.accent)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .highlight_symbol(">> ");
+        let mut state = ratatui::widgets::ListState::default().with_selected(Some(selected));
+        StatefulWidget::render(list, area, buf, &mut state);
+    }
+}
+
+/// The unsaved changes of every group, as they will be saved.
+pub struct ReviewList {
+    edits: Vec<(String, StagedEdits)>,
+    theme: Theme,
+}
+
+impl ReviewList {
+    pub fn new(edits: Vec<(String, StagedEdits)>, theme: Theme) -> Self {
+        Self { edits, theme }
+    }
+}
+
+impl Widget for ReviewList {
+    fn render(self, area: Rect, buf: &mut ratatui::prelude::Buffer) {
+        let mut lines = Vec::new();
+        for (name, edits) in &self.edits {
+            if !lines.is_empty() {
+                lines.push(Line::raw(""));
             }
-        } else if self.viewing_vars {
-            "← back | Navigation: ↑/↓, Prev/Next Page, Home, End | / search | R refresh | C copy | E export | T theme | q quit"
-                .into()
+            lines.push(Line::styled(
+                name.clone(),
+                Style::default().add_modifier(Modifier::BOLD),
+            ));
+            lines.extend(
+                edits
+                    .changes()
+                    .iter()
+                    .map(|change| change_line(change, &self.theme)),
+            );
+            lines.extend(edits.conflicts().iter().map(|conflict| {
+                let shown = |var: &Option<VarEntry>| match var {
+                    Some(var) => shown_value(var).to_string(),
+                    None => "removed".to_string(),
+                };
+                Line::styled(
+                    format!(
+                        "! {}: now {} by someone else, {} when saved",
+                        conflict.name,
+                        shown(&conflict.theirs),
+                        shown(&conflict.ours)
+                    ),
+                    Style::default()
+                        .fg(self.theme.error)
+                        .add_modifier(Modifier::BOLD),
+                )
+            }));
+        }
+        let title = format!("Unsaved changes ({} groups)", self.edits.len());
+        Paragraph::new(lines)
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .render(area, buf);
+    }
+}
+
+pub struct HelpBar {
+    theme: Theme,
+    search_target: Option<SearchTarget>,
+    hints: Vec<(String, &'static str)>,
+}
+
+impl HelpBar {
+    /// `hints` are `(keys, label)` pairs, as produced by [`Keymap::help`].
+    ///
+    /// [`Keymap::help`]: crate::azure_vars::tui::keymap::Keymap::help
+    pub fn new(
+        theme: Theme,
+        search_target: Option<SearchTarget>,
+        hints: Vec<(String, &'static str)>,
+    ) -> Self {
+        Self {
+            theme,
+            search_target,
+            hints,
+        }
+    }
+}
+
+impl Widget for HelpBar {
+    fn render(self, area: Rect, buf: &mut ratatui::prelude::Buffer) {
+        let prompt = self.search_target.map(|target| match target {
+            SearchTarget::Groups => "Type to search groups".to_string(),
+            SearchTarget::Vars => "Type to search variables".to_string(),
+        });
+        let separator = if self.search_target.is_some() {
+            "="
         } else {
-            "← back | Navigation: ↑/↓, Prev/Next Page, Home, End | / search | R refresh | T theme | q quit"
-                .into()
+            " "
         };
+        let text = prompt
+            .into_iter()
+            .chain(
+                self.hints
+                    .into_iter()
+                    .map(|(keys, label)| format!("{keys}{separator}{label}")),
+            )
+            .collect::<Vec<_>>()
+            .join(" | ");
 
         Paragraph::new(text)
-            .style(Style::default().fg(match self.theme {
-                Theme::Dark => Color::DarkGray,
-                Theme::Light => Color::Gray,
-            }))
+            .style(Style::default().fg(self.theme.muted))
             .block(Block::default().borders(Borders::TOP))
             .render(area, buf);
     }
@@ -283,11 +1091,8 @@ impl StatusBar {
 impl Widget for StatusBar {
     fn render(self, area: Rect, buf: &mut ratatui::prelude::Buffer) {
         let color = match self.message.kind {
-            StatusKind::Info => match self.theme {
-                Theme::Dark => Color::Green,
-                Theme::Light => Color::Blue,
-            },
-            StatusKind::Error => Color::Red,
+            StatusKind::Info => self.theme.info,
+            StatusKind::Error => self.theme.error,
         };
 
         Paragraph::new(self.message.text)
@@ -296,3 +1101,104 @@ impl Widget for StatusBar {
             .render(area, buf);
     }
 }
+
+pub struct LogPane {
+    theme: Theme,
+    records: Vec<LogRecord>,
+}
+
+impl LogPane {
+    pub fn new(theme: Theme, records: Vec<LogRecord>) -> Self {
+        Self { theme, records }
+    }
+}
+
+impl Widget for LogPane {
+    fn render(self, area: Rect, buf: &mut ratatui::prelude::Buffer) {
+        let lines: Vec<Line> = self
+            .records
+            .iter()
+            .map(|record| {
+                let color = match record.level {
+                    log::Level::Error => self.theme.error,
+                    log::Level::Warn => self.theme.accent,
+                    log::Level::Info => self.theme.info,
+                    log::Level::Debug | log::Level::Trace => self.theme.muted,
+                };
+                Line::from(vec![
+                    Span::styled(format!("{:<5} ", record.level), Style::default().fg(color)),
+                    Span::styled(
+                        format!("{} ", record.target),
+                        Style::default().fg(self.theme.muted),
+                    ),
+                    Span::raw(&record.message),
+                ])
+            })
+            .collect();
+
+        Paragraph::new(lines)
+            .block(Block::default().borders(Borders::ALL).title("Logs"))
+            .render(area, buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::buffer::Buffer;
+
+    use super::*;
+    use crate::azure_vars::state::{edits::EditOp, state::PromptKind};
+
+    fn rendered(widget: impl Widget, width: u16, height: u16) -> String {
+        let mut buf = Buffer::empty(Rect::new(0, 0, width, height));
+        widget.render(buf.area, &mut buf);
+        buf.content().iter().map(|cell| cell.symbol()).collect()
+    }
+
+    #[test]
+    fn staged_secrets_are_masked() {
+        let token = |value: &str| VarEntry {
+            name: "TOKEN".into(),
+            value: value.into(),
+            is_secret: true,
+        };
+        let mut edits = StagedEdits::new(&[token("old-hunter2")]);
+        edits.push(EditOp::Set(token("new-hunter2")));
+        let theme = Theme::default();
+
+        let review = rendered(
+            ReviewList::new(vec![("app".into(), edits.clone())], theme.clone()),
+            60,
+            5,
+        );
+        assert!(review.contains("TOKEN: ***** → *****"), "{review}");
+
+        let list = rendered(
+            VarList::new(
+                edits.variables().to_vec(),
+                "app".into(),
+                None,
+                theme,
+                token("new-hunter2"),
+                HashSet::new(),
+                HashSet::from(["TOKEN".to_string()]),
+            ),
+            60,
+            4,
+        );
+        let prompt = rendered(
+            PromptBar::new(Prompt {
+                kind: PromptKind::EditValue {
+                    name: "TOKEN".into(),
+                    is_secret: true,
+                },
+                text: "new-hunter2".into(),
+            }),
+            60,
+            3,
+        );
+        for screen in [review, list, prompt] {
+            assert!(!screen.contains("hunter2"), "{screen}");
+        }
+    }
+}
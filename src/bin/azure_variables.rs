@@ -1,29 +1,168 @@
-use azure_devops_rust_api::{Credential, distributed_task::ClientBuilder};
+use azure_devops_rust_api::Credential;
 use azure_devtools::azure_vars::state::{
     action::Action,
-    state::State,
-    state_store::{AzureApiVariableGroupsClient, StateStore},
+    audit::{format_time, parse_time},
+    backup::{Backup, RestoreStep, plan_restore},
+    cache::{GroupCache, find_groups, name_matches},
+    connection,
+    copy::{CopyTarget, GroupPath, SecretValues, copy_of, secrets_without_value},
+    diff::{VarChange, keep_loaded_details},
+    directory::DirectoryVariableGroupsClient,
+    error::ClientError,
+    fixtures::FixtureMode,
+    journal::Journal,
+    permissions::{PermissionChanges, Pipeline},
+    session::Session,
+    state::{CopyProfile, State, StatusMessage, VarEntry, VarGroup},
+    state_store::{
+        AzureApiVariableGroupsClient, OfflineVariableGroupsClient, StateStore, VariableGroupsClient,
+    },
+    theme::{self, ThemeSet},
 };
-use azure_devtools::azure_vars::tui::handle_input::run_app;
+use azure_devtools::azure_vars::tui::{
+    handle_input::run_app,
+    keymap::{KeyHandler, Keymap},
+};
+use azure_devtools::config::{
+    config::{Config, ConfigKey, ConfigLayer, LayeredConfig, ServerConfig, set_value},
+    paths,
+    preferences::Preferences,
+};
+use azure_devtools::logging::{self, LogOptions};
 use azure_identity::AzureCliCredential;
 use crossterm::{
     execute,
     terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
 };
+use log::warn;
 use ratatui::Terminal;
 use ratatui::backend::CrosstermBackend;
-use serde::{Deserialize, Serialize};
 use std::env;
 use std::error::Error;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Arc;
+use std::time::SystemTime;
 use tokio::sync::mpsc::channel;
 
 use clap::{Parser, Subcommand};
+use futures::{StreamExt, TryStreamExt, stream};
 
 #[derive(Debug, Subcommand, Clone)]
 enum SubCommands {
     Init,
     Tui,
+    /// List the variable groups of the project, or those matching PATTERN (`*` is a wildcard)
+    List {
+        pattern: Option<String>,
+    },
+    /// Print the variables of a group, or the value of one variable
+    Show {
+        group: String,
+        variable: Option<String>,
+    },
+    /// Print who created, changed, shared or deleted Library items, and when
+    Audit {
+        /// Start of the range: a time such as 2025-03-31T12:00:00Z, a date, or an age such as 7d
+        #[arg(long, default_value = "7d", value_name = "TIME")]
+        from: String,
+        /// End of the range, in the same forms as --from; defaults to now
+        #[arg(long, value_name = "TIME")]
+        to: Option<String>,
+        /// Only print the events of this variable group
+        #[arg(long)]
+        group: Option<String>,
+        /// Print the events as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Save every variable group of the project to an archive, secrets by name only
+    Backup {
+        /// Archive to write; defaults to a timestamped file in the current directory
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+    /// Recreate the missing groups of a backup and reconcile the existing ones
+    Restore {
+        file: PathBuf,
+        /// Only print what would change
+        #[arg(long)]
+        dry_run: bool,
+        /// Apply the changes without asking
+        #[arg(short, long)]
+        yes: bool,
+        /// Only restore this group; repeat for more
+        #[arg(long = "group", value_name = "NAME")]
+        groups: Vec<String>,
+    },
+    /// Copy variable groups to another project, possibly in another organization
+    CopyGroup {
+        /// organization/project/group; `*` in the group name copies every matching group
+        #[arg(long, value_name = "PATH")]
+        from: GroupPath,
+        /// organization/project, or organization/project/name to copy a single group under a new name
+        #[arg(long, value_name = "PATH")]
+        to: GroupPath,
+        /// File of NAME=value lines, or group:NAME=value for one group, with the values of secrets
+        #[arg(long, value_name = "FILE")]
+        secrets: Option<PathBuf>,
+    },
+    /// Print the pipelines allowed to use a group, or grant and revoke their access
+    Permissions {
+        group: String,
+        /// Allow a pipeline, given by id, name or folder/name; repeat for more
+        #[arg(long, value_name = "PIPELINE")]
+        grant: Vec<String>,
+        /// Take the access of a pipeline away; repeat for more
+        #[arg(long, value_name = "PIPELINE")]
+        revoke: Vec<String>,
+        /// Allow every pipeline of the project
+        #[arg(long, conflicts_with = "restrict")]
+        open: bool,
+        /// Only allow the pipelines granted access one by one
+        #[arg(long)]
+        restrict: bool,
+    },
+    /// Print the approvals and checks of a group, or add and remove approvals
+    Checks {
+        group: String,
+        /// Require an approval by these comma-separated emails or names; repeat for more approvals
+        #[arg(long, value_name = "APPROVERS")]
+        add_approval: Vec<String>,
+        /// Remove the approval with this id; repeat for more
+        #[arg(long, value_name = "ID")]
+        remove: Vec<i32>,
+    },
+    /// Inspect and change the configuration
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+}
+
+#[derive(Debug, Subcommand, Clone)]
+enum ConfigCommand {
+    /// Print the effective value of a key
+    Get { key: String },
+    /// Store a value in the global config file, or the repository-local one with --local
+    Set {
+        key: String,
+        value: String,
+        #[arg(long)]
+        local: bool,
+    },
+    /// Open a config file in $VISUAL/$EDITOR and validate it before saving
+    Edit {
+        #[arg(long)]
+        local: bool,
+    },
+    /// Print the config files, or the merged configuration with --effective
+    Show {
+        #[arg(long)]
+        effective: bool,
+    },
+    /// Re-run the interactive setup, overwriting the global config file
+    Init,
 }
 
 #[derive(Parser, Debug)]
@@ -31,52 +170,994 @@ enum SubCommands {
 struct Args {
     #[command(subcommand)]
     command: SubCommands,
+
+    /// Azure DevOps organization, overriding every other configuration source
+    #[arg(long, global = true)]
+    organization: Option<String>,
+
+    /// Azure DevOps project, overriding every other configuration source
+    #[arg(long, global = true)]
+    project: Option<String>,
+
+    /// Refetch variable groups in the background this often, e.g. 30s or 5m
+    #[arg(long, global = true, value_name = "INTERVAL")]
+    refresh_interval: Option<String>,
+
+    /// Base URL of an Azure DevOps Server, e.g. https://tfs.example.com/tfs
+    #[arg(long, global = true, value_name = "URL")]
+    endpoint: Option<String>,
+
+    /// REST API version to request, e.g. 6.0 for older Azure DevOps Server releases
+    #[arg(long, global = true, value_name = "VERSION")]
+    api_version: Option<String>,
+
+    /// PEM file with extra root certificates to trust
+    #[arg(long, global = true, value_name = "FILE")]
+    ca_bundle: Option<String>,
+
+    /// Send requests through this proxy, e.g. http://proxy.example.com:8080
+    #[arg(long, global = true, value_name = "URL")]
+    proxy: Option<String>,
+
+    /// Log debug records; repeat to also log trace records and HTTP internals
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Write the log to this file instead of the default rotating log
+    #[arg(long, global = true)]
+    log_file: Option<PathBuf>,
+
+    /// Read variable groups from the local cache without contacting Azure DevOps
+    #[arg(long, global = true)]
+    offline: bool,
+
+    /// Read variable groups from a cache file or exported groups; implies --offline
+    #[arg(long, global = true, value_name = "FILE")]
+    snapshot: Option<PathBuf>,
+
+    /// Save the redacted Azure DevOps responses to this file, e.g. for a bug report
+    #[arg(long, global = true, value_name = "FILE", conflicts_with_all = ["offline", "snapshot", "replay"])]
+    record: Option<PathBuf>,
+
+    /// Answer requests from responses saved with --record instead of Azure DevOps
+    #[arg(long, global = true, value_name = "FILE", conflicts_with_all = ["offline", "snapshot"])]
+    replay: Option<PathBuf>,
+
+    /// Where variable groups are kept: `azure` (default) or `dir:<path>`, a
+    /// directory with one subdirectory per project and one file per group
+    #[arg(
+        long,
+        global = true,
+        value_name = "BACKEND",
+        value_parser = parse_backend,
+        conflicts_with_all = ["offline", "snapshot", "record", "replay"]
+    )]
+    backend: Option<Backend>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Backend {
+    Azure,
+    Dir(PathBuf),
+}
+
+fn parse_backend(value: &str) -> Result<Backend, String> {
+    match value.split_once(':') {
+        _ if value == "azure" => Ok(Backend::Azure),
+        Some(("dir", path)) if !path.is_empty() => Ok(Backend::Dir(PathBuf::from(path))),
+        _ => Err("expected `azure` or `dir:<path>`".to_string()),
+    }
+}
+
+impl Args {
+    fn flag_layer(&self) -> ConfigLayer {
+        ConfigLayer {
+            organization: self.organization.clone(),
+            project: self.project.clone(),
+            refresh_interval: self.refresh_interval.clone(),
+            endpoint: self.endpoint.clone(),
+            api_version: self.api_version.clone(),
+            ca_bundle: self.ca_bundle.clone(),
+            proxy: self.proxy.clone(),
+            ..ConfigLayer::default()
+        }
+    }
+
+    fn fixtures(&self) -> Option<FixtureMode> {
+        match (&self.record, &self.replay) {
+            (Some(path), _) => Some(FixtureMode::Record(path.clone())),
+            (None, Some(path)) => Some(FixtureMode::Replay(path.clone())),
+            (None, None) => None,
+        }
+    }
+
+    /// Replayed responses and directories are not the project's current
+    /// groups, so they neither read nor replace the cache.
+    fn uses_cache(&self) -> bool {
+        self.replay.is_none() && !matches!(self.backend, Some(Backend::Dir(_)))
+    }
+
+    fn log_options(&self) -> LogOptions {
+        LogOptions {
+            verbosity: self.verbose,
+            log_file: self.log_file.clone(),
+        }
+    }
+}
+
+fn run_init(config_path: &Path, overwrite: bool) -> Result<(), Box<dyn Error>> {
+    if config_path.exists() && !overwrite {
+        println!(
+            "Config file already exists at {}",
+            config_path.to_string_lossy()
+        );
+        println!("Run 'config init' to re-run the interactive setup.");
+        return Ok(());
+    }
+
+    let mut config = ConfigLayer::read(config_path)?.unwrap_or_default();
+    let organization = prompt_value(
+        ConfigKey::Organization,
+        "Azure DevOps Organization",
+        &config,
+    )?;
+    let project = prompt_value(ConfigKey::Project, "Azure DevOps Project", &config)?;
+    ConfigKey::Organization.set(&mut config, &organization)?;
+    ConfigKey::Project.set(&mut config, &project)?;
+    config.write(config_path)?;
+
+    println!("Config file created at {}", config_path.to_string_lossy());
+    println!("You can now run the 'tui' subcommand to manage variable groups.");
+
+    print!("Alternatively, you can set the ADO_ORGANIZATION and ADO_PROJECT environment");
+    println!(" variables to override the config values on a per-run basis if needed.\n");
+    Ok(())
+}
+
+fn prompt_value(
+    key: ConfigKey,
+    prompt: &str,
+    existing: &ConfigLayer,
+) -> Result<String, Box<dyn Error>> {
+    let mut input = dialoguer::Input::<String>::new()
+        .with_prompt(prompt)
+        .validate_with(move |value: &String| key.check(value));
+    if let Some(current) = key.get(existing) {
+        input = input.default(current);
+    }
+    Ok(input.interact_text()?)
+}
+
+fn local_config_path(cwd: &Path) -> PathBuf {
+    paths::find_local_config(cwd).unwrap_or_else(|| cwd.join(paths::LOCAL_CONFIG_FILE))
+}
+
+fn run_config_command(
+    command: &ConfigCommand,
+    args: &Args,
+    config_path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let cwd = env::current_dir()?;
+    match command {
+        ConfigCommand::Get { key } => {
+            let key = key.parse::<ConfigKey>()?;
+            let config = LayeredConfig::load(&cwd, args.flag_layer())?;
+            match config.effective(key) {
+                Some((value, _)) => println!("{value}"),
+                None => {
+                    eprintln!("`{key}` is not set");
+                    std::process::exit(1);
+                }
+            }
+        }
+        ConfigCommand::Set { key, value, local } => {
+            let key = key.parse::<ConfigKey>()?;
+            let path = if *local {
+                local_config_path(&cwd)
+            } else {
+                config_path.to_path_buf()
+            };
+            set_value(&path, key, value)?;
+            println!("Set `{key}` in {}", path.to_string_lossy());
+        }
+        ConfigCommand::Edit { local } => {
+            let path = if *local {
+                local_config_path(&cwd)
+            } else {
+                config_path.to_path_buf()
+            };
+            edit_config(&path)?;
+        }
+        ConfigCommand::Show { effective: true } => {
+            let config = LayeredConfig::load(&cwd, args.flag_layer())?;
+            for key in ConfigKey::ALL {
+                match config.effective(key) {
+                    Some((value, source)) => {
+                        println!("{key}: {value}  # from {}", source.describe(key))
+                    }
+                    None => println!("# {key} is not set"),
+                }
+            }
+        }
+        ConfigCommand::Show { effective: false } => {
+            let files = std::iter::once(config_path.to_path_buf())
+                .chain(paths::find_local_config(&cwd))
+                .collect::<Vec<_>>();
+            for path in files {
+                match std::fs::read_to_string(&path) {
+                    Ok(contents) => println!("# {}\n{}", path.display(), contents.trim_end()),
+                    Err(_) => println!("# {} (not found)", path.display()),
+                }
+            }
+        }
+        ConfigCommand::Init => run_init(config_path, true)?,
+    }
+    Ok(())
+}
+
+/// A personal access token, read by the Azure DevOps CLI extension too.
+const PAT_ENV_VAR: &str = "AZURE_DEVOPS_EXT_PAT";
+/// Groups whose variables are requested at once by `backup`, `restore` and
+/// `copy-group`.
+const DETAIL_CONCURRENCY: usize = 8;
+
+/// Where variable groups come from: Azure DevOps or a directory, or with
+/// `--offline` the snapshot if one is given and otherwise the groups cached
+/// by the last online run.
+enum GroupSource {
+    Online(Box<dyn VariableGroupsClient>),
+    Offline(GroupCache),
+}
+
+impl GroupSource {
+    fn new(args: &Args, cache_path: &Path, server: &ServerConfig) -> Result<Self, Box<dyn Error>> {
+        if let Some(snapshot) = &args.snapshot {
+            return Ok(Self::Offline(GroupCache::read_snapshot(snapshot)?));
+        }
+        if args.offline {
+            let cache = GroupCache::read(cache_path).ok_or_else(|| {
+                format!(
+                    "No cached variable groups in {}. Run once without --offline, or pass --snapshot",
+                    cache_path.display()
+                )
+            })?;
+            return Ok(Self::Offline(cache));
+        }
+        online_client(args, server).map(Self::Online)
+    }
+}
+
+/// The client of the backend chosen with `--backend`, reaching `server` for
+/// Azure DevOps.
+fn online_client(
+    args: &Args,
+    server: &ServerConfig,
+) -> Result<Box<dyn VariableGroupsClient>, Box<dyn Error>> {
+    if let Some(Backend::Dir(root)) = &args.backend {
+        return Ok(Box::new(DirectoryVariableGroupsClient::new(root.clone())));
+    }
+    let fixtures = args.fixtures();
+    // Azure DevOps Server does not accept Azure CLI tokens, only PATs.
+    let credential = match env::var(PAT_ENV_VAR) {
+        _ if matches!(fixtures, Some(FixtureMode::Replay(_))) => Credential::Unauthenticated,
+        Ok(pat) if !pat.is_empty() => Credential::from_pat(pat),
+        _ => Credential::from_token_credential(
+            AzureCliCredential::new(None).map_err(ClientError::from)?,
+        ),
+    };
+    let clients = connection::azure_clients(credential, server, fixtures.as_ref())?;
+    Ok(Box::new(AzureApiVariableGroupsClient::new(clients)))
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-struct Config {
-    organization: String,
-    project: String,
+/// The configured profiles groups can be copied to from the TUI. None
+/// offline, nor with recorded responses, which only cover one project. A
+/// profile whose client cannot be set up is left out, with the reason among
+/// the warnings returned, so that it does not keep the TUI from starting.
+fn copy_targets(
+    args: &Args,
+    config: &Config,
+    source: &GroupSource,
+) -> (Vec<CopyTarget>, Vec<String>) {
+    if matches!(source, GroupSource::Offline(_)) || args.fixtures().is_some() {
+        return (Vec::new(), Vec::new());
+    }
+    let mut warnings = Vec::new();
+    let targets = config
+        .profiles
+        .iter()
+        .filter_map(|profile| match online_client(args, &profile.server) {
+            Ok(client) => Some(CopyTarget {
+                profile: CopyProfile {
+                    name: profile.name.clone(),
+                    organization: profile.organization.clone(),
+                    project: profile.project.clone(),
+                },
+                client: Arc::from(client),
+            }),
+            Err(error) => {
+                let warning = format!("Cannot copy to profile {}: {error}", profile.name);
+                warn!("{warning}");
+                warnings.push(warning);
+                None
+            }
+        })
+        .collect();
+    (targets, warnings)
 }
 
-fn ensure_init(args: &Args, config_path: &std::path::Path) -> Result<(), Box<dyn Error>> {
-    if matches!(args.command, SubCommands::Init) {
-        if config_path.exists() {
+async fn run_read_command(args: &Args, config: Config) -> Result<(), Box<dyn Error>> {
+    let cache_path = paths::cache_path(&config.organization, &config.project)?;
+    let source = GroupSource::new(args, &cache_path, &config.server)?;
+    let (organization, project) = (&config.organization, &config.project);
+
+    match &args.command {
+        SubCommands::List { pattern } => {
+            let groups = match source {
+                GroupSource::Offline(cache) => cache.groups,
+                GroupSource::Online(client) => {
+                    let mut groups = client
+                        .get_variable_groups(organization, project, pattern.as_deref())
+                        .await?;
+                    // Only a full list can replace the cache.
+                    if pattern.is_none() && args.uses_cache() {
+                        let cached = GroupCache::read(&cache_path).map(|cache| cache.groups);
+                        keep_loaded_details(&cached.unwrap_or_default(), &mut groups);
+                        let cache = GroupCache::new(&groups, SystemTime::now());
+                        if let Err(error) = cache.write(&cache_path) {
+                            eprintln!("Failed to cache variable groups: {error}");
+                        }
+                    }
+                    groups
+                }
+            };
+            let matching = groups
+                .iter()
+                .filter(|g| pattern.as_ref().is_none_or(|p| name_matches(p, &g.name)));
+            for group in matching {
+                println!("{}", group.name);
+            }
+        }
+        SubCommands::Show { group, variable } => {
+            let group = match source {
+                GroupSource::Offline(cache) => {
+                    let found = find_group(cache.groups, group)?;
+                    if found.summary_only {
+                        return Err(format!(
+                            "The variables of `{group}` are not cached, run without --offline"
+                        )
+                        .into());
+                    }
+                    found
+                }
+                GroupSource::Online(client) => {
+                    let summaries = client
+                        .get_variable_groups(organization, project, Some(group))
+                        .await?;
+                    let id = find_group(summaries, group)?
+                        .id
+                        .ok_or_else(|| group_not_found(group))?;
+                    let group = client.get_variable_group(organization, project, id).await?;
+                    if args.uses_cache() {
+                        // Listing only caches summaries, so `show --offline`
+                        // relies on this.
+                        if let Some(mut cache) = GroupCache::read(&cache_path) {
+                            cache.store_details(&group);
+                            if let Err(error) = cache.write(&cache_path) {
+                                eprintln!("Failed to cache variable groups: {error}");
+                            }
+                        }
+                        let mut journal = Journal::new(paths::journal_path(organization, project)?);
+                        if let Err(error) =
+                            journal.record(std::slice::from_ref(&group), SystemTime::now())
+                        {
+                            eprintln!("Failed to record group history: {error}");
+                        }
+                    }
+                    group
+                }
+            };
+            match variable {
+                Some(name) => {
+                    let var = group
+                        .variables
+                        .iter()
+                        .find(|v| &v.name == name)
+                        .ok_or_else(|| ClientError::NotFound {
+                            what: format!("Variable `{name}` in `{}`", group.name),
+                        })?;
+                    println!("{}", var.value);
+                }
+                None => {
+                    for var in &group.variables {
+                        println!("{}={}", var.name, var.value);
+                    }
+                }
+            }
+        }
+        SubCommands::Audit {
+            from,
+            to,
+            group,
+            json,
+        } => {
+            let GroupSource::Online(client) = source else {
+                return Err("The audit log is not cached, run without --offline".into());
+            };
+            let now = SystemTime::now();
+            let start = parse_time(from, now).map_err(|error| format!("--from: {error}"))?;
+            let end = match to {
+                Some(to) => parse_time(to, now).map_err(|error| format!("--to: {error}"))?,
+                None => now,
+            };
+            if start > end {
+                return Err("--from must be before --to".into());
+            }
+            let mut events = client
+                .get_library_audit_events(organization, project, start, end)
+                .await?;
+            if let Some(name) = group {
+                // Deleted groups are only known by name.
+                let group = client
+                    .get_variable_groups(organization, project, Some(name))
+                    .await?
+                    .into_iter()
+                    .find(|g| &g.name == name)
+                    .unwrap_or_else(|| VarGroup {
+                        name: name.clone(),
+                        ..VarGroup::default()
+                    });
+                events.retain(|event| event.concerns(&group));
+            }
+            if *json {
+                println!("{}", serde_json::to_string_pretty(&events)?);
+            } else {
+                for event in &events {
+                    println!(
+                        "{}  {}  {}  {}",
+                        event.at.map(format_time).unwrap_or_default(),
+                        event.actor,
+                        event.short_action(),
+                        event.details
+                    );
+                }
+            }
+        }
+        SubCommands::Backup { output } => {
+            let GroupSource::Online(client) = source else {
+                return Err("The cache does not hold every variable, run without --offline".into());
+            };
+            let summaries = client
+                .get_variable_groups(organization, project, None)
+                .await?;
+            let groups = load_details(client.as_ref(), organization, project, summaries).await?;
+            let now = SystemTime::now();
+            let backup = Backup::new(organization, project, &groups, now);
+            let path = output.clone().unwrap_or_else(|| {
+                // Colons are not allowed in Windows file names.
+                let stamp = format_time(now).replace(':', "");
+                PathBuf::from(format!("{organization}-{project}-library-{stamp}.json"))
+            });
+            backup.write(&path)?;
             println!(
-                "Config file already exists at {}",
-                config_path.to_string_lossy()
+                "Backed up {} variable groups of {organization}/{project} to {}",
+                groups.len(),
+                path.display()
             );
-            std::process::exit(0);
+        }
+        _ => unreachable!("not a read command"),
+    }
+    Ok(())
+}
+
+/// Edits a copy of the config file so that an invalid edit never replaces a
+/// working configuration.
+fn edit_config(path: &Path) -> Result<(), Box<dyn Error>> {
+    let original = std::fs::read_to_string(path).unwrap_or_default();
+    let draft = Draft(path.with_extension("edit.yaml"));
+    std::fs::write(&draft.0, &original)?;
+
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| if cfg!(windows) { "notepad" } else { "vi" }.to_string());
+    let mut parts = editor.split_whitespace();
+    let program = parts.next().ok_or("Editor command is empty")?;
+    let editor_args = parts.collect::<Vec<_>>();
+
+    loop {
+        let status = Command::new(program)
+            .args(&editor_args)
+            .arg(&draft.0)
+            .status()?;
+        if !status.success() {
+            break Err(format!("Editor exited with {status}").into());
         }
 
-        let organization = dialoguer::Input::<String>::new()
-            .with_prompt("Azure DevOps Organization")
-            .interact_text()?;
-        let project = dialoguer::Input::<String>::new()
-            .with_prompt("Azure DevOps Project")
-            .interact_text()?;
+        let edited = std::fs::read_to_string(&draft.0)?;
+        match ConfigLayer::parse(&edited, path) {
+            Ok(_) => {
+                std::fs::write(path, edited)?;
+                println!("Saved {}", path.to_string_lossy());
+                break Ok(());
+            }
+            Err(error) => {
+                eprintln!("{error}");
+                let retry = dialoguer::Confirm::new()
+                    .with_prompt("Re-open the editor?")
+                    .default(true)
+                    .interact()?;
+                if !retry {
+                    println!("Changes discarded");
+                    break Ok(());
+                }
+            }
+        }
+    }
+}
+
+/// The file a config is edited in, removed however editing ends.
+struct Draft(PathBuf);
+
+impl Drop for Draft {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// Shows what restoring `file` changes, then applies it once confirmed.
+async fn run_restore(
+    args: &Args,
+    config: Config,
+    file: &Path,
+    dry_run: bool,
+    yes: bool,
+    only: &[String],
+) -> Result<(), Box<dyn Error>> {
+    let cache_path = paths::cache_path(&config.organization, &config.project)?;
+    let GroupSource::Online(client) = GroupSource::new(args, &cache_path, &config.server)? else {
+        return Err("Groups cannot be restored offline".into());
+    };
+    let (organization, project) = (&config.organization, &config.project);
+    let mut backup = Backup::read(file)?;
+    if let Some(missing) = only
+        .iter()
+        .find(|name| !backup.groups.iter().any(|g| &g.name == *name))
+    {
+        return Err(format!("`{missing}` is not in the backup").into());
+    }
+    if !only.is_empty() {
+        backup.groups.retain(|group| only.contains(&group.name));
+    }
+    println!(
+        "Backup of {}/{} from {}, {} groups",
+        backup.organization,
+        backup.project,
+        backup.created_at,
+        backup.groups.len()
+    );
+    if (&backup.organization, &backup.project) != (organization, project) {
+        println!("Restoring into {organization}/{project}");
+    }
 
-        let config = Config {
-            organization,
-            project,
+    let existing = client
+        .get_variable_groups(organization, project, None)
+        .await?
+        .into_iter()
+        .filter(|group| backup.groups.iter().any(|g| g.name == group.name))
+        .collect();
+    let current = load_details(client.as_ref(), organization, project, existing).await?;
+    let steps = plan_restore(&backup.groups, &current)?;
+    if steps.is_empty() {
+        println!("Nothing to restore, every group matches the backup");
+        return Ok(());
+    }
+    for step in &steps {
+        print_restore_step(step);
+    }
+    if dry_run {
+        return Ok(());
+    }
+    if !yes
+        && !dialoguer::Confirm::new()
+            .with_prompt(format!("Restore {} groups?", steps.len()))
+            .default(false)
+            .interact()?
+    {
+        println!("Nothing restored");
+        return Ok(());
+    }
+
+    let mut reenter = Vec::new();
+    for step in &steps {
+        let secrets = step.secrets_to_reenter().join(", ");
+        let restored = match step {
+            RestoreStep::Create(group) => {
+                client
+                    .add_variable_group(organization, project, group.clone())
+                    .await?
+            }
+            RestoreStep::Update { group, .. } => {
+                client
+                    .update_variable_group(organization, project, group.clone())
+                    .await?
+            }
         };
-        let config_yaml = serde_yaml::to_string(&config)?;
-        std::fs::write(config_path, config_yaml)?;
+        println!("Restored {}", restored.name);
+        if !secrets.is_empty() {
+            reenter.push(format!("{}: {secrets}", restored.name));
+        }
+    }
+    if !reenter.is_empty() {
+        println!("Secrets restored without a value, enter them again:");
+        for line in reenter {
+            println!("  {line}");
+        }
+    }
+    Ok(())
+}
+
+/// Grants and revokes the access of pipelines to `group`, then prints the
+/// pipelines allowed to use it.
+async fn run_permissions(
+    args: &Args,
+    config: Config,
+    group: &str,
+    grant: &[String],
+    revoke: &[String],
+    all_pipelines: Option<bool>,
+) -> Result<(), Box<dyn Error>> {
+    let cache_path = paths::cache_path(&config.organization, &config.project)?;
+    let GroupSource::Online(client) = GroupSource::new(args, &cache_path, &config.server)? else {
+        return Err("Pipeline permissions are not cached, run without --offline".into());
+    };
+    let (organization, project) = (&config.organization, &config.project);
+    let summaries = client
+        .get_variable_groups(organization, project, Some(group))
+        .await?;
+    let id = find_group(summaries, group)?
+        .id
+        .ok_or_else(|| group_not_found(group))?;
+    let (pipelines, mut permissions) = tokio::try_join!(
+        client.get_pipelines(organization, project),
+        client.get_pipeline_permissions(organization, project, id),
+    )?;
+
+    let mut wanted = permissions.clone();
+    if let Some(all_pipelines) = all_pipelines {
+        wanted.all_pipelines = all_pipelines;
+    }
+    for name in grant {
+        wanted.authorized.insert(find_pipeline(&pipelines, name)?);
+    }
+    for name in revoke {
+        wanted.authorized.remove(&find_pipeline(&pipelines, name)?);
+    }
+    let changes = PermissionChanges::between(&permissions, &wanted);
+    if !changes.is_empty() {
+        client
+            .set_pipeline_permissions(organization, project, id, changes.clone())
+            .await?;
+        changes.apply(&mut permissions);
+        println!("Updated the pipeline permissions of {group}");
+    }
+
+    if permissions.all_pipelines {
+        println!("Every pipeline of {organization}/{project} may use {group}");
+    } else if permissions.authorized.is_empty() {
+        println!("No pipeline may use {group}");
+    }
+    for id in &permissions.authorized {
+        let pipeline = pipelines
+            .iter()
+            .find(|pipeline| pipeline.id == *id)
+            .cloned()
+            .unwrap_or_else(|| Pipeline::unknown(*id));
+        println!("{id:>6}  {}", pipeline.path());
+    }
+    Ok(())
+}
 
-        println!("Config file created at {}", config_path.to_string_lossy());
-        println!("You can now run the 'tui' subcommand to manage variable groups.");
+/// Removes and adds approvals on `group`, then prints its checks.
+async fn run_checks(
+    args: &Args,
+    config: Config,
+    group: &str,
+    add_approval: &[String],
+    remove: &[i32],
+) -> Result<(), Box<dyn Error>> {
+    let cache_path = paths::cache_path(&config.organization, &config.project)?;
+    let GroupSource::Online(client) = GroupSource::new(args, &cache_path, &config.server)? else {
+        return Err("Approvals and checks are not cached, run without --offline".into());
+    };
+    let (organization, project) = (&config.organization, &config.project);
+    let summaries = client
+        .get_variable_groups(organization, project, Some(group))
+        .await?;
+    let id = find_group(summaries, group)?
+        .id
+        .ok_or_else(|| group_not_found(group))?;
+    let mut checks = client.get_checks(organization, project, id).await?;
+
+    for check_id in remove {
+        let Some(index) = checks.iter().position(|check| check.id == *check_id) else {
+            return Err(format!("{group} has no check {check_id}").into());
+        };
+        if !checks[index].is_approval() {
+            return Err(format!(
+                "Only approvals can be removed, check {check_id} is a {} check",
+                checks[index].name().to_lowercase()
+            )
+            .into());
+        }
+        client
+            .remove_check(organization, project, *check_id)
+            .await?;
+        let check = checks.remove(index);
+        println!("Removed the approval by {} from {group}", check.summary());
+    }
+    for approvers in add_approval {
+        let approvers = approvers
+            .split(',')
+            .map(str::trim)
+            .filter(|approver| !approver.is_empty())
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        if approvers.is_empty() {
+            return Err("Name at least one approver".into());
+        }
+        let check = client
+            .add_approval_check(organization, project, id, approvers)
+            .await?;
+        println!("Added an approval by {} to {group}", check.summary());
+        checks.push(check);
+    }
 
-        print!("Alternatively, you can set the ADO_ORGANIZATION and ADO_PROJECT environment");
-        println!(" variables to override the config values on a per-run basis if needed.\n");
-        std::process::exit(0);
-    } else if !config_path.exists() {
+    if checks.is_empty() {
+        println!("No approvals or checks protect {group}");
+    }
+    let width = checks
+        .iter()
+        .map(|check| check.name().chars().count())
+        .max()
+        .unwrap_or(0);
+    for check in &checks {
         println!(
-            "Config file not found at {}. Please run 'init' command first.",
-            config_path.to_string_lossy()
+            "{:>6}  {:<width$}  {}",
+            check.id,
+            check.name(),
+            check.summary()
         );
-        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// The id of the pipeline `name` refers to: an id, a name, or a path such
+/// as `deploy/web`.
+fn find_pipeline(pipelines: &[Pipeline], name: &str) -> Result<i32, String> {
+    if let Ok(id) = name.parse() {
+        return Ok(id);
+    }
+    let matches = |pipeline: &&Pipeline| {
+        pipeline.path().eq_ignore_ascii_case(name.trim_matches('/'))
+            || pipeline.name.eq_ignore_ascii_case(name)
+    };
+    match pipelines.iter().filter(matches).collect::<Vec<_>>()[..] {
+        [pipeline] => Ok(pipeline.id),
+        [] => Err(format!("No pipeline named `{name}`")),
+        ref found => Err(format!(
+            "`{name}` names {} pipelines, give its folder or id: {}",
+            found.len(),
+            found
+                .iter()
+                .map(|pipeline| format!("{} ({})", pipeline.path(), pipeline.id))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
+
+/// Copies the groups matching `from` to `to`, after checking that none of
+/// them exists there yet.
+async fn run_copy_group(
+    args: &Args,
+    config: Config,
+    from: &GroupPath,
+    to: &GroupPath,
+    secrets: Option<&Path>,
+) -> Result<(), Box<dyn Error>> {
+    if args.offline || args.snapshot.is_some() {
+        return Err("Groups cannot be copied offline".into());
+    }
+    let pattern = from
+        .group
+        .as_deref()
+        .ok_or("--from must name a group, e.g. organization/project/group")?;
+    let secrets = match secrets {
+        Some(path) => SecretValues::parse(&std::fs::read_to_string(path)?)
+            .map_err(|error| format!("{}: {error}", path.display()))?,
+        None => SecretValues::default(),
+    };
+    let source_server = config.server_for(&from.organization);
+    let target_server = config.server_for(&to.organization);
+    let source = online_client(args, &source_server)?;
+    let other = if target_server == source_server {
+        None
+    } else if args.fixtures().is_some() {
+        return Err("--record and --replay cover a single server".into());
     } else {
-        Ok(())
+        Some(online_client(args, &target_server)?)
+    };
+    let target = other.as_deref().unwrap_or(source.as_ref());
+
+    let summaries = source
+        .get_variable_groups(&from.organization, &from.project, Some(pattern))
+        .await?
+        .into_iter()
+        .filter(|group| name_matches(pattern, &group.name))
+        .collect::<Vec<_>>();
+    if summaries.is_empty() {
+        return Err(group_not_found(pattern).into());
+    }
+    if to.group.is_some() && summaries.len() > 1 {
+        return Err(format!(
+            "`{pattern}` matches {} groups, a new name only applies to one",
+            summaries.len()
+        )
+        .into());
+    }
+    let groups = load_details(
+        source.as_ref(),
+        &from.organization,
+        &from.project,
+        summaries,
+    )
+    .await?;
+    let copies = groups
+        .iter()
+        .map(|group| copy_of(group, to.group.as_deref().unwrap_or(&group.name), &secrets))
+        .collect::<Result<Vec<_>, _>>()?;
+    let existing = target
+        .get_variable_groups(&to.organization, &to.project, None)
+        .await?;
+    let taken = copies
+        .iter()
+        .filter(|copy| existing.iter().any(|group| group.name == copy.name))
+        .map(|copy| format!("`{}`", copy.name))
+        .collect::<Vec<_>>();
+    if !taken.is_empty() {
+        return Err(format!(
+            "{} already exist in {}/{}, nothing was copied",
+            taken.join(", "),
+            to.organization,
+            to.project
+        )
+        .into());
+    }
+
+    let mut reenter = Vec::new();
+    for (source, copy) in groups.iter().zip(copies) {
+        let missing = secrets_without_value(&copy).join(", ");
+        let created = target
+            .add_variable_group(&to.organization, &to.project, copy)
+            .await?;
+        println!(
+            "Copied {} to {}/{}/{}",
+            source.name, to.organization, to.project, created.name
+        );
+        if !missing.is_empty() {
+            reenter.push(format!("{}: {missing}", created.name));
+        }
+    }
+    if !reenter.is_empty() {
+        println!("Secrets copied without a value, enter them again:");
+        for line in reenter {
+            println!("  {line}");
+        }
+    }
+    Ok(())
+}
+
+fn print_restore_step(step: &RestoreStep) {
+    match step {
+        RestoreStep::Create(group) => {
+            println!("+ {} (recreated)", group.name);
+            for var in &group.variables {
+                println!("    + {}", describe_var(var));
+            }
+        }
+        RestoreStep::Update {
+            group,
+            changes,
+            old_description,
+        } => {
+            println!("~ {}", group.name);
+            if let Some(old) = old_description {
+                println!(
+                    "    ~ description {:?} (was {:?})",
+                    group.description.as_deref().unwrap_or_default(),
+                    old.as_deref().unwrap_or_default()
+                );
+            }
+            for change in changes {
+                match change {
+                    VarChange::Added(var) => println!("    + {}", describe_var(var)),
+                    VarChange::Removed(var) => println!("    - {}", var.name),
+                    VarChange::Changed { old, new } => {
+                        println!("    ~ {} (was {})", describe_var(new), describe_var(old))
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn describe_var(var: &VarEntry) -> String {
+    if var.is_secret {
+        format!("{} (secret)", var.name)
+    } else {
+        format!("{}={}", var.name, var.value)
+    }
+}
+
+/// Loads the variables of the groups that only have a summary.
+async fn load_details(
+    client: &dyn VariableGroupsClient,
+    organization: &str,
+    project: &str,
+    groups: Vec<VarGroup>,
+) -> Result<Vec<VarGroup>, ClientError> {
+    stream::iter(groups)
+        .map(|group| async move {
+            match group.id.filter(|_| group.summary_only) {
+                Some(id) => client.get_variable_group(organization, project, id).await,
+                None => Ok(group),
+            }
+        })
+        .buffered(DETAIL_CONCURRENCY)
+        .try_collect()
+        .await
+}
+
+fn group_not_found(group: &str) -> ClientError {
+    ClientError::NotFound {
+        what: format!("Variable group `{group}`"),
+    }
+}
+
+/// The one group `name` refers to, ignoring case like `copy-group` and the
+/// TUI search.
+fn find_group(groups: Vec<VarGroup>, name: &str) -> Result<VarGroup, Box<dyn Error>> {
+    let found = find_groups(&groups, name);
+    match found.as_slice() {
+        [] => Err(group_not_found(name).into()),
+        [group] => Ok((*group).clone()),
+        several => {
+            let names = several
+                .iter()
+                .map(|group| format!("`{}`", group.name))
+                .collect::<Vec<_>>()
+                .join(", ");
+            Err(format!("`{name}` matches several groups ({names}), give the exact name").into())
+        }
+    }
+}
+
+/// Prints a fatal error and exits, with the code of a [`ClientError`] if it
+/// is one.
+fn exit_with(error: &(dyn Error + 'static)) -> ! {
+    eprintln!("{error}");
+    let code = error
+        .downcast_ref::<ClientError>()
+        .map_or(1, ClientError::exit_code);
+    std::process::exit(code);
+}
+
+fn resolve_config(args: &Args, cwd: &Path) -> Config {
+    match LayeredConfig::load(cwd, args.flag_layer()).and_then(|layers| layers.resolve()) {
+        Ok(config) => config,
+        Err(error) => {
+            eprintln!("{error}");
+            std::process::exit(1);
+        }
     }
 }
 
@@ -84,23 +1165,116 @@ fn ensure_init(args: &Args, config_path: &std::path::Path) -> Result<(), Box<dyn
 async fn main() -> Result<(), Box<dyn Error>> {
     let args = Args::parse();
 
-    let config_dir = dirs::config_dir()
-        .ok_or("Could not determine config directory")?
-        .join("azure_devtools");
-    std::fs::create_dir_all(&config_dir)?;
-    let config_path = config_dir.join("config.yaml");
-
-    ensure_init(&args, &config_path)?;
+    let config_path = paths::global_config_path()?;
+    std::fs::create_dir_all(paths::config_dir()?)?;
+    // Every command logs, so that --verbose and --log-file apply to all.
+    let logs = match logging::init(&args.log_options(), &paths::log_path()?) {
+        Ok(logs) => logs,
+        Err(error) => {
+            eprintln!("Failed to set up logging: {error:#}");
+            std::process::exit(1);
+        }
+    };
 
-    if PathBuf::from("logging.yaml").exists() {
-        println!("Using logging configuration from logging.yaml");
-        log4rs::init_file("logging.yaml", Default::default()).unwrap();
+    match &args.command {
+        SubCommands::Init => return run_init(&config_path, false),
+        SubCommands::Config { command } => {
+            return run_config_command(command, &args, &config_path);
+        }
+        SubCommands::List { .. }
+        | SubCommands::Show { .. }
+        | SubCommands::Audit { .. }
+        | SubCommands::Backup { .. } => {
+            let config = resolve_config(&args, &env::current_dir()?);
+            if let Err(error) = run_read_command(&args, config).await {
+                exit_with(error.as_ref());
+            }
+            return Ok(());
+        }
+        SubCommands::Restore {
+            file,
+            dry_run,
+            yes,
+            groups,
+        } => {
+            let config = resolve_config(&args, &env::current_dir()?);
+            if let Err(error) = run_restore(&args, config, file, *dry_run, *yes, groups).await {
+                exit_with(error.as_ref());
+            }
+            return Ok(());
+        }
+        SubCommands::Permissions {
+            group,
+            grant,
+            revoke,
+            open,
+            restrict,
+        } => {
+            let config = resolve_config(&args, &env::current_dir()?);
+            let all_pipelines = (*open || *restrict).then_some(*open);
+            if let Err(error) =
+                run_permissions(&args, config, group, grant, revoke, all_pipelines).await
+            {
+                exit_with(error.as_ref());
+            }
+            return Ok(());
+        }
+        SubCommands::Checks {
+            group,
+            add_approval,
+            remove,
+        } => {
+            let config = resolve_config(&args, &env::current_dir()?);
+            if let Err(error) = run_checks(&args, config, group, add_approval, remove).await {
+                exit_with(error.as_ref());
+            }
+            return Ok(());
+        }
+        SubCommands::CopyGroup { from, to, secrets } => {
+            let config = resolve_config(&args, &env::current_dir()?);
+            if let Err(error) = run_copy_group(&args, config, from, to, secrets.as_deref()).await {
+                exit_with(error.as_ref());
+            }
+            return Ok(());
+        }
+        SubCommands::Tui => {}
     }
-    let config = serde_yaml::from_str::<Config>(&std::fs::read_to_string(&config_path)?)?;
-    let azure_cli_credential = AzureCliCredential::new(None)?;
-    let credential = Credential::from_token_credential(azure_cli_credential);
-    let organization = env::var("ADO_ORGANIZATION").unwrap_or(config.organization);
-    let project = env::var("ADO_PROJECT").unwrap_or(config.project);
+
+    let config = resolve_config(&args, &env::current_dir()?);
+    let keymap = match Keymap::load(&paths::keymap_path()?) {
+        Ok(keymap) => keymap,
+        Err(error) => {
+            eprintln!("{error}");
+            std::process::exit(1);
+        }
+    };
+    // https://no-color.org: any non-empty value disables colors.
+    let no_color = env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+    let themes = if no_color {
+        ThemeSet::monochrome()
+    } else {
+        match ThemeSet::load(&paths::themes_path()?) {
+            Ok(themes) => themes,
+            Err(error) => {
+                eprintln!("{error}");
+                std::process::exit(1);
+            }
+        }
+    };
+    let preferences_path = paths::preferences_path()?;
+    let mut preferences = Preferences::read(&preferences_path);
+    let initial_theme = themes.initial(
+        preferences.theme.as_deref(),
+        theme::detect_background(env::var("COLORFGBG").ok().as_deref()),
+    );
+    let session_path = paths::session_path(&config.organization, &config.project)?;
+    let cache_path = paths::cache_path(&config.organization, &config.project)?;
+    let journal_path = paths::journal_path(&config.organization, &config.project)?;
+    let group_source = match GroupSource::new(&args, &cache_path, &config.server) {
+        Ok(group_source) => group_source,
+        Err(error) => exit_with(error.as_ref()),
+    };
+    let (copy_targets, copy_warnings) = copy_targets(&args, &config, &group_source);
 
     enable_raw_mode()?;
     let mut stdout = std::io::stdout();
@@ -110,19 +1284,66 @@ async fn main() -> Result<(), Box<dyn Error>> {
 
     let (action_tx, action_rx) = channel(10);
     let (state_tx, state_rx) = channel(10);
-    let state = State::new(organization, project);
-    let client = ClientBuilder::new(credential).build();
-    let var_groups_client = AzureApiVariableGroupsClient::new(client.variablegroups_client());
-    let state_store = StateStore::new(state.clone(), state_tx, var_groups_client);
-
-    let state_task = state_store.main_loop(action_rx);
-    action_tx.send(Action::RefreshVarGroups).await?;
-    tokio::spawn(state_task);
-    run_app(&mut terminal, action_tx, state, state_rx).await?;
+    let mut state = State::new(config.organization, config.project)
+        .with_pinned_groups(config.groups)
+        .with_themes(themes, initial_theme)
+        .with_copy_profiles(copy_targets.iter().map(|t| t.profile.clone()).collect());
+    if let Some(session) = Session::read(&session_path) {
+        state = state.with_session(session);
+    }
+    if let Some(warning) = copy_warnings.first() {
+        state.ui.set_status(StatusMessage::error(warning.clone()));
+    }
+    match group_source {
+        GroupSource::Online(var_groups_client) => {
+            // Cached groups are shown at once; the refresh below revalidates them.
+            if let Some(cache) = GroupCache::read(&cache_path).filter(|_| args.uses_cache()) {
+                state = state.with_cached_groups(cache);
+            }
+            let mut state_store = StateStore::new(state.clone(), state_tx, var_groups_client)
+                .with_copy_targets(copy_targets);
+            if args.uses_cache() {
+                state_store = state_store
+                    .with_cache_path(cache_path)
+                    .with_journal(Journal::new(journal_path));
+            }
+            if let Some(interval) = config.refresh_interval {
+                state_store = state_store.with_refresh_interval(interval);
+            }
+            action_tx.send(Action::RefreshVarGroups).await?;
+            tokio::spawn(state_store.main_loop(action_rx));
+        }
+        GroupSource::Offline(cache) => {
+            state = state.with_cached_groups(cache).with_offline();
+            // Past versions can be browsed offline, though not restored.
+            let state_store = StateStore::new(state.clone(), state_tx, OfflineVariableGroupsClient)
+                .with_journal(Journal::new(journal_path));
+            tokio::spawn(state_store.main_loop(action_rx));
+        }
+    }
+    let final_state = run_app(
+        &mut terminal,
+        action_tx,
+        state,
+        state_rx,
+        KeyHandler::new(keymap),
+        logs,
+    )
+    .await?;
 
     disable_raw_mode()?;
     execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
     terminal.show_cursor()?;
 
+    if let Err(error) = Session::capture(&final_state).write(&session_path) {
+        eprintln!("Failed to save session: {error}");
+    }
+    if !no_color {
+        preferences.theme = Some(final_state.theme.name);
+        if let Err(error) = preferences.write(&preferences_path) {
+            eprintln!("Failed to save preferences: {error}");
+        }
+    }
+
     Ok(())
 }
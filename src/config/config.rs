@@ -0,0 +1,867 @@
+use std::{
+    collections::BTreeMap,
+    fmt, fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    duration::{CLOCK_UNITS, DurationError, parse_duration},
+    error::ConfigError,
+    git_remote::{self, AzureRemote},
+    paths,
+};
+
+/// A single source of configuration. Every key is optional so that unset keys
+/// fall through to the layers below it.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigLayer {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub organization: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+    /// Variable groups listed first in the group list, in this order.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub groups: Option<Vec<String>>,
+    /// How often the TUI refetches groups in the background, e.g. `5m`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refresh_interval: Option<String>,
+    /// Base URL of an Azure DevOps Server collection host, e.g.
+    /// `https://tfs.example.com/tfs`. Defaults to `https://dev.azure.com`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endpoint: Option<String>,
+    /// REST API version sent with every request, e.g. `6.0` for older servers.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_version: Option<String>,
+    /// PEM file with extra root certificates to trust.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ca_bundle: Option<String>,
+    /// Proxy for all requests, e.g. `http://proxy.example.com:8080`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+    /// Other projects, possibly in other organizations or on other servers,
+    /// that groups can be copied to. Keyed by a name of the user's choosing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profiles: Option<BTreeMap<String, ProfileLayer>>,
+}
+
+/// A named project. Unset keys are those of the effective configuration.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProfileLayer {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub organization: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endpoint: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ca_bundle: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+}
+
+impl ProfileLayer {
+    /// The profile as a layer, so that its values are checked like the
+    /// top-level keys.
+    fn as_layer(&self) -> ConfigLayer {
+        ConfigLayer {
+            organization: self.organization.clone(),
+            project: self.project.clone(),
+            endpoint: self.endpoint.clone(),
+            api_version: self.api_version.clone(),
+            ca_bundle: self.ca_bundle.clone(),
+            proxy: self.proxy.clone(),
+            ..ConfigLayer::default()
+        }
+    }
+}
+
+impl ConfigLayer {
+    pub fn defaults() -> Self {
+        Self::default()
+    }
+
+    fn from_remote(remote: &AzureRemote) -> Self {
+        Self {
+            organization: Some(remote.organization.clone()),
+            project: Some(remote.project.clone()),
+            ..Self::default()
+        }
+    }
+
+    pub fn from_env(lookup: impl Fn(&str) -> Option<String>) -> Self {
+        let mut layer = Self::default();
+        for key in ConfigKey::ALL {
+            let Some(var) = key.env_var() else {
+                continue;
+            };
+            if let Some(value) = lookup(var).filter(|value| !value.is_empty()) {
+                key.assign(&mut layer, value);
+            }
+        }
+        layer
+    }
+
+    /// Reads a config file, returning `None` when it does not exist.
+    pub fn read(path: &Path) -> Result<Option<Self>, ConfigError> {
+        match fs::read_to_string(path) {
+            Ok(text) => Self::parse(&text, path).map(Some),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(source) => Err(ConfigError::Io {
+                path: path.to_path_buf(),
+                source,
+            }),
+        }
+    }
+
+    pub fn parse(text: &str, path: &Path) -> Result<Self, ConfigError> {
+        if text.trim().is_empty() {
+            return Ok(Self::default());
+        }
+        let layer = serde_yaml::from_str::<Self>(text).map_err(|error| {
+            let location = error.location();
+            ConfigError::Parse {
+                path: path.to_path_buf(),
+                line: location.as_ref().map(|l| l.line()),
+                column: location.as_ref().map(|l| l.column()),
+                message: error.to_string(),
+            }
+        })?;
+        layer.validate(&path.display().to_string())?;
+        Ok(layer)
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), ConfigError> {
+        let io_error = |source| ConfigError::Io {
+            path: path.to_path_buf(),
+            source,
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(io_error)?;
+        }
+        let yaml = serde_yaml::to_string(self).map_err(|error| ConfigError::Parse {
+            path: path.to_path_buf(),
+            line: None,
+            column: None,
+            message: error.to_string(),
+        })?;
+        fs::write(path, yaml).map_err(io_error)
+    }
+
+    pub fn validate(&self, origin: &str) -> Result<(), ConfigError> {
+        for key in ConfigKey::ALL {
+            for value in key.values(self) {
+                key.check(&value)
+                    .map_err(|reason| ConfigError::InvalidValue {
+                        key: key.name(),
+                        value,
+                        reason,
+                        origin: origin.to_string(),
+                    })?;
+            }
+        }
+        for (name, profile) in self.profiles.iter().flatten() {
+            profile
+                .as_layer()
+                .validate(&format!("profile `{name}` in {origin}"))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigKey {
+    Organization,
+    Project,
+    Groups,
+    RefreshInterval,
+    Endpoint,
+    ApiVersion,
+    CaBundle,
+    Proxy,
+}
+
+impl ConfigKey {
+    pub const ALL: [ConfigKey; 8] = [
+        ConfigKey::Organization,
+        ConfigKey::Project,
+        ConfigKey::Groups,
+        ConfigKey::RefreshInterval,
+        ConfigKey::Endpoint,
+        ConfigKey::ApiVersion,
+        ConfigKey::CaBundle,
+        ConfigKey::Proxy,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ConfigKey::Organization => "organization",
+            ConfigKey::Project => "project",
+            ConfigKey::Groups => "groups",
+            ConfigKey::RefreshInterval => "refresh_interval",
+            ConfigKey::Endpoint => "endpoint",
+            ConfigKey::ApiVersion => "api_version",
+            ConfigKey::CaBundle => "ca_bundle",
+            ConfigKey::Proxy => "proxy",
+        }
+    }
+
+    pub fn env_var(self) -> Option<&'static str> {
+        match self {
+            ConfigKey::Organization => Some("ADO_ORGANIZATION"),
+            ConfigKey::Project => Some("ADO_PROJECT"),
+            ConfigKey::Groups => None,
+            ConfigKey::RefreshInterval => Some("ADO_REFRESH_INTERVAL"),
+            ConfigKey::Endpoint => Some("ADO_ENDPOINT"),
+            ConfigKey::ApiVersion => Some("ADO_API_VERSION"),
+            ConfigKey::CaBundle => Some("ADO_CA_BUNDLE"),
+            ConfigKey::Proxy => Some("ADO_PROXY"),
+        }
+    }
+
+    pub fn flag(self) -> Option<&'static str> {
+        match self {
+            ConfigKey::Organization => Some("--organization"),
+            ConfigKey::Project => Some("--project"),
+            ConfigKey::Groups => None,
+            ConfigKey::RefreshInterval => Some("--refresh-interval"),
+            ConfigKey::Endpoint => Some("--endpoint"),
+            ConfigKey::ApiVersion => Some("--api-version"),
+            ConfigKey::CaBundle => Some("--ca-bundle"),
+            ConfigKey::Proxy => Some("--proxy"),
+        }
+    }
+
+    pub fn get(self, layer: &ConfigLayer) -> Option<String> {
+        match self {
+            ConfigKey::Organization => layer.organization.clone(),
+            ConfigKey::Project => layer.project.clone(),
+            ConfigKey::Groups => layer.groups.as_ref().map(|groups| groups.join(", ")),
+            ConfigKey::RefreshInterval => layer.refresh_interval.clone(),
+            ConfigKey::Endpoint => layer.endpoint.clone(),
+            ConfigKey::ApiVersion => layer.api_version.clone(),
+            ConfigKey::CaBundle => layer.ca_bundle.clone(),
+            ConfigKey::Proxy => layer.proxy.clone(),
+        }
+    }
+
+    /// The individual values stored for `self`, one per list entry.
+    fn values(self, layer: &ConfigLayer) -> Vec<String> {
+        match self {
+            ConfigKey::Groups => layer.groups.clone().unwrap_or_default(),
+            _ => self.get(layer).into_iter().collect(),
+        }
+    }
+
+    /// Validates `value` and stores it in `layer`. List keys take a
+    /// comma-separated value.
+    pub fn set(self, layer: &mut ConfigLayer, value: &str) -> Result<(), ConfigError> {
+        self.assign(layer, value.to_string());
+        layer.validate("the command line")
+    }
+
+    pub fn check(self, value: &str) -> Result<(), String> {
+        if value.trim().is_empty() {
+            return Err("value must not be empty".into());
+        }
+        match self {
+            ConfigKey::Organization => {
+                if value.contains("://") || value.contains('/') {
+                    Err("expected the organization name, not a URL".into())
+                } else if value.chars().any(char::is_whitespace) {
+                    Err("organization names cannot contain whitespace".into())
+                } else {
+                    Ok(())
+                }
+            }
+            ConfigKey::Project => {
+                if value.contains('/') || value.contains('\\') {
+                    Err("expected the project name, not a path or URL".into())
+                } else {
+                    Ok(())
+                }
+            }
+            ConfigKey::Groups => Ok(()),
+            ConfigKey::RefreshInterval => parse_interval(value).map(|_| ()),
+            ConfigKey::Endpoint => check_url(value, &["http", "https"]),
+            ConfigKey::ApiVersion => check_api_version(value),
+            ConfigKey::CaBundle => Ok(()),
+            ConfigKey::Proxy => check_url(value, &["http", "https", "socks5", "socks5h"]),
+        }
+    }
+
+    fn assign(self, layer: &mut ConfigLayer, value: String) {
+        match self {
+            ConfigKey::Organization => layer.organization = Some(value),
+            ConfigKey::Project => layer.project = Some(value),
+            ConfigKey::Groups => {
+                layer.groups = Some(value.split(',').map(|g| g.trim().to_string()).collect());
+            }
+            ConfigKey::RefreshInterval => layer.refresh_interval = Some(value),
+            ConfigKey::Endpoint => layer.endpoint = Some(value),
+            ConfigKey::ApiVersion => layer.api_version = Some(value),
+            ConfigKey::CaBundle => layer.ca_bundle = Some(value),
+            ConfigKey::Proxy => layer.proxy = Some(value),
+        }
+    }
+}
+
+fn check_url(value: &str, schemes: &[&str]) -> Result<(), String> {
+    let scheme = value.split_once("://").map(|(scheme, _)| scheme);
+    match scheme {
+        Some(scheme) if schemes.contains(&scheme.to_ascii_lowercase().as_str()) => Ok(()),
+        _ => {
+            let schemes = schemes.iter().map(|scheme| format!("{scheme}://"));
+            Err(format!(
+                "expected a URL starting with {}",
+                schemes.collect::<Vec<_>>().join(" or ")
+            ))
+        }
+    }
+}
+
+/// Accepts versions such as `6.0`, `7.1` and `7.1-preview.1`.
+fn check_api_version(value: &str) -> Result<(), String> {
+    let (version, preview) = match value.split_once("-preview") {
+        Some((version, rest)) => (version, Some(rest)),
+        None => (value, None),
+    };
+    let is_number = |part: &str| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit());
+    let valid_version = version
+        .split_once('.')
+        .is_some_and(|(major, minor)| is_number(major) && is_number(minor));
+    let valid_preview =
+        preview.is_none_or(|rest| rest.is_empty() || rest.strip_prefix('.').is_some_and(is_number));
+    if valid_version && valid_preview {
+        Ok(())
+    } else {
+        Err("expected a version such as 6.0 or 7.1-preview.1".into())
+    }
+}
+
+/// Refreshing more often than this would mostly load the server.
+const MIN_REFRESH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Parses an interval such as `90`, `90s`, `5m` or `1h`.
+pub fn parse_interval(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let interval = if value.bytes().all(|b| b.is_ascii_digit()) {
+        parse_duration(&format!("{value}s"), CLOCK_UNITS)
+    } else {
+        parse_duration(value, CLOCK_UNITS)
+    }
+    .map_err(|error| match error {
+        DurationError::NotANumber => {
+            "expected a number of seconds or a value like 30s, 5m or 1h".to_string()
+        }
+        error => error.to_string(),
+    })?;
+    if interval < MIN_REFRESH_INTERVAL {
+        return Err(format!(
+            "must be at least {}s",
+            MIN_REFRESH_INTERVAL.as_secs()
+        ));
+    }
+    Ok(interval)
+}
+
+impl fmt::Display for ConfigKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for ConfigKey {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ConfigKey::ALL
+            .into_iter()
+            .find(|key| key.name() == s)
+            .ok_or_else(|| ConfigError::UnknownKey {
+                key: s.to_string(),
+                known: ConfigKey::ALL.map(ConfigKey::name).join(", "),
+            })
+    }
+}
+
+/// Where a layer came from, from lowest to highest precedence.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigSource {
+    Default,
+    GlobalFile(PathBuf),
+    GitRemote(String),
+    LocalFile(PathBuf),
+    Environment,
+    Flags,
+}
+
+impl ConfigSource {
+    pub fn describe(&self, key: ConfigKey) -> String {
+        match self {
+            ConfigSource::Environment => key.env_var().unwrap_or("environment").to_string(),
+            ConfigSource::Flags => key.flag().unwrap_or("command line").to_string(),
+            other => other.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigSource::Default => f.write_str("default"),
+            ConfigSource::GlobalFile(path) => write!(f, "{}", path.display()),
+            ConfigSource::GitRemote(url) => write!(f, "git remote {url}"),
+            ConfigSource::LocalFile(path) => write!(f, "{}", path.display()),
+            ConfigSource::Environment => f.write_str("environment"),
+            ConfigSource::Flags => f.write_str("command line"),
+        }
+    }
+}
+
+/// The fully resolved configuration used by the application.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Config {
+    pub organization: String,
+    pub project: String,
+    pub groups: Vec<String>,
+    pub refresh_interval: Option<Duration>,
+    pub server: ServerConfig,
+    /// Sorted by name.
+    pub profiles: Vec<Profile>,
+}
+
+impl Config {
+    /// How to reach `organization`: the configured server if it is the
+    /// configured organization, the server of the first profile in it, or
+    /// else the configured server.
+    pub fn server_for(&self, organization: &str) -> ServerConfig {
+        self.profiles
+            .iter()
+            .filter(|_| organization != self.organization)
+            .find(|profile| profile.organization == organization)
+            .map_or_else(|| self.server.clone(), |profile| profile.server.clone())
+    }
+}
+
+/// A project configured under `profiles`, with unset keys filled in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Profile {
+    pub name: String,
+    pub organization: String,
+    pub project: String,
+    pub server: ServerConfig,
+}
+
+/// How to reach Azure DevOps. Every field is unset for dev.azure.com.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ServerConfig {
+    pub endpoint: Option<String>,
+    pub api_version: Option<String>,
+    pub ca_bundle: Option<PathBuf>,
+    pub proxy: Option<String>,
+}
+
+/// All configuration layers, merged with the precedence
+/// defaults < global file < git remote < repository-local file < environment < flags.
+#[derive(Debug, Clone)]
+pub struct LayeredConfig {
+    layers: Vec<(ConfigSource, ConfigLayer)>,
+}
+
+impl LayeredConfig {
+    pub fn load(cwd: &Path, flags: ConfigLayer) -> Result<Self, ConfigError> {
+        Self::load_from(
+            &paths::global_config_path()?,
+            cwd,
+            git_remote::detect(cwd),
+            |name| std::env::var(name).ok(),
+            flags,
+        )
+    }
+
+    pub fn load_from(
+        global_path: &Path,
+        cwd: &Path,
+        remote: Option<AzureRemote>,
+        env: impl Fn(&str) -> Option<String>,
+        flags: ConfigLayer,
+    ) -> Result<Self, ConfigError> {
+        let mut layers = vec![(ConfigSource::Default, ConfigLayer::defaults())];
+
+        if let Some(layer) = ConfigLayer::read(global_path)? {
+            layers.push((ConfigSource::GlobalFile(global_path.to_path_buf()), layer));
+        }
+        if let Some(remote) = remote {
+            let layer = ConfigLayer::from_remote(&remote);
+            layers.push((ConfigSource::GitRemote(remote.url), layer));
+        }
+        if let Some(local_path) = paths::find_local_config(cwd)
+            && let Some(layer) = ConfigLayer::read(&local_path)?
+        {
+            layers.push((ConfigSource::LocalFile(local_path), layer));
+        }
+
+        let env_layer = ConfigLayer::from_env(env);
+        env_layer.validate("the environment")?;
+        layers.push((ConfigSource::Environment, env_layer));
+
+        flags.validate("the command line")?;
+        layers.push((ConfigSource::Flags, flags));
+
+        Ok(Self { layers })
+    }
+
+    pub fn layers(&self) -> &[(ConfigSource, ConfigLayer)] {
+        &self.layers
+    }
+
+    /// Returns the effective value of `key` together with the layer it came from.
+    pub fn effective(&self, key: ConfigKey) -> Option<(String, &ConfigSource)> {
+        self.layers
+            .iter()
+            .rev()
+            .find_map(|(source, layer)| key.get(layer).map(|value| (value, source)))
+    }
+
+    pub fn resolve(&self) -> Result<Config, ConfigError> {
+        let organization = self.required(ConfigKey::Organization)?;
+        let project = self.required(ConfigKey::Project)?;
+        let server = ServerConfig {
+            endpoint: self.value(ConfigKey::Endpoint),
+            api_version: self.value(ConfigKey::ApiVersion),
+            ca_bundle: self.value(ConfigKey::CaBundle).map(PathBuf::from),
+            proxy: self.value(ConfigKey::Proxy),
+        };
+        // Higher layers replace the profiles of the same name.
+        let mut profiles = BTreeMap::new();
+        for (_, layer) in &self.layers {
+            profiles.extend(layer.profiles.clone().unwrap_or_default());
+        }
+        let profiles = profiles
+            .into_iter()
+            .map(|(name, profile)| Profile {
+                name,
+                organization: profile.organization.unwrap_or_else(|| organization.clone()),
+                project: profile.project.unwrap_or_else(|| project.clone()),
+                server: ServerConfig {
+                    endpoint: profile.endpoint.or_else(|| server.endpoint.clone()),
+                    api_version: profile.api_version.or_else(|| server.api_version.clone()),
+                    ca_bundle: profile
+                        .ca_bundle
+                        .map(PathBuf::from)
+                        .or_else(|| server.ca_bundle.clone()),
+                    proxy: profile.proxy.or_else(|| server.proxy.clone()),
+                },
+            })
+            .collect();
+        Ok(Config {
+            organization,
+            project,
+            groups: self
+                .layers
+                .iter()
+                .rev()
+                .find_map(|(_, layer)| layer.groups.clone())
+                .unwrap_or_default(),
+            // Every layer was validated when it was loaded.
+            refresh_interval: self
+                .effective(ConfigKey::RefreshInterval)
+                .and_then(|(value, _)| parse_interval(&value).ok()),
+            server,
+            profiles,
+        })
+    }
+
+    fn value(&self, key: ConfigKey) -> Option<String> {
+        self.effective(key).map(|(value, _)| value)
+    }
+
+    fn required(&self, key: ConfigKey) -> Result<String, ConfigError> {
+        self.effective(key)
+            .map(|(value, _)| value)
+            .ok_or(ConfigError::Missing {
+                key: key.name(),
+                env: key.env_var().unwrap_or_default(),
+                flag: key.flag().unwrap_or_default(),
+            })
+    }
+}
+
+/// Stores `value` for `key` in the config file at `path`, keeping other keys.
+pub fn set_value(path: &Path, key: ConfigKey, value: &str) -> Result<(), ConfigError> {
+    let mut layer = ConfigLayer::read(path)?.unwrap_or_default();
+    key.set(&mut layer, value)?;
+    layer.write(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use tempfile::tempdir;
+
+    use super::*;
+
+    fn no_env(_: &str) -> Option<String> {
+        None
+    }
+
+    #[test]
+    fn layers_follow_precedence() {
+        let dir = tempdir().unwrap();
+        let global = dir.path().join("config.yaml");
+        fs::write(
+            &global,
+            "organization: global-org\nproject: global-project\n",
+        )
+        .unwrap();
+        let repo = dir.path().join("repo");
+        fs::create_dir_all(repo.join("nested")).unwrap();
+        fs::write(
+            repo.join(paths::LOCAL_CONFIG_FILE),
+            "project: local-project\n",
+        )
+        .unwrap();
+
+        let env = HashMap::from([("ADO_ORGANIZATION", "env-org".to_string())]);
+        let config = LayeredConfig::load_from(
+            &global,
+            &repo.join("nested"),
+            None,
+            |name| env.get(name).cloned(),
+            ConfigLayer::default(),
+        )
+        .unwrap();
+
+        let (org, org_source) = config.effective(ConfigKey::Organization).unwrap();
+        assert_eq!(org, "env-org");
+        assert_eq!(org_source, &ConfigSource::Environment);
+        let (project, project_source) = config.effective(ConfigKey::Project).unwrap();
+        assert_eq!(project, "local-project");
+        assert!(matches!(project_source, ConfigSource::LocalFile(_)));
+
+        let flags = ConfigLayer {
+            project: Some("flag-project".into()),
+            ..ConfigLayer::default()
+        };
+        let config =
+            LayeredConfig::load_from(&global, &repo, None, |name| env.get(name).cloned(), flags)
+                .unwrap();
+        assert_eq!(
+            config.resolve().unwrap(),
+            Config {
+                organization: "env-org".into(),
+                project: "flag-project".into(),
+                groups: Vec::new(),
+                refresh_interval: None,
+                server: ServerConfig::default(),
+                profiles: Vec::new(),
+            }
+        );
+    }
+
+    #[test]
+    fn git_remote_sits_between_global_and_local_files() {
+        let dir = tempdir().unwrap();
+        let global = dir.path().join("config.yaml");
+        fs::write(
+            &global,
+            "organization: global-org\nproject: global-project\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join(paths::LOCAL_CONFIG_FILE),
+            "groups:\n  - app-prod\n  - app-dev\n",
+        )
+        .unwrap();
+        let remote = git_remote::parse_remote_url("https://dev.azure.com/contoso/Web/_git/site");
+
+        let config =
+            LayeredConfig::load_from(&global, dir.path(), remote, no_env, ConfigLayer::default())
+                .unwrap();
+
+        let (_, source) = config.effective(ConfigKey::Project).unwrap();
+        assert!(matches!(source, ConfigSource::GitRemote(_)));
+        assert_eq!(
+            config.resolve().unwrap(),
+            Config {
+                organization: "contoso".into(),
+                project: "Web".into(),
+                groups: vec!["app-prod".into(), "app-dev".into()],
+                refresh_interval: None,
+                server: ServerConfig::default(),
+                profiles: Vec::new(),
+            }
+        );
+    }
+
+    #[test]
+    fn missing_keys_report_how_to_set_them() {
+        let dir = tempdir().unwrap();
+        let config = LayeredConfig::load_from(
+            &dir.path().join("config.yaml"),
+            dir.path(),
+            None,
+            no_env,
+            ConfigLayer::default(),
+        )
+        .unwrap();
+
+        let error = config.resolve().unwrap_err();
+        assert!(matches!(
+            error,
+            ConfigError::Missing {
+                key: "organization",
+                ..
+            }
+        ));
+        assert!(error.to_string().contains("ADO_ORGANIZATION"));
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected_with_location() {
+        let error = ConfigLayer::parse(
+            "organization: org\norganisation: typo\n",
+            Path::new("config.yaml"),
+        )
+        .unwrap_err();
+
+        match error {
+            ConfigError::Parse { line, message, .. } => {
+                assert_eq!(line, Some(2));
+                assert!(message.contains("organisation"));
+            }
+            other => panic!("expected a parse error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn invalid_values_are_rejected() {
+        let error = ConfigLayer::parse(
+            "organization: https://dev.azure.com/org\n",
+            Path::new("config.yaml"),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            error,
+            ConfigError::InvalidValue {
+                key: "organization",
+                ..
+            }
+        ));
+
+        assert!(
+            "organisation".parse::<ConfigKey>().is_err(),
+            "unknown keys should not parse"
+        );
+    }
+
+    #[test]
+    fn refresh_intervals_accept_units() {
+        assert_eq!(parse_interval("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_interval("30s"), Ok(Duration::from_secs(30)));
+        assert_eq!(parse_interval("5m"), Ok(Duration::from_secs(300)));
+        assert_eq!(parse_interval("1h"), Ok(Duration::from_secs(3600)));
+        assert!(parse_interval("5").is_err());
+        assert!(parse_interval("5d").is_err());
+        assert!(parse_interval("soon").is_err());
+        assert!(parse_interval("99999999999999999h").is_err());
+
+        let error =
+            ConfigLayer::parse("refresh_interval: 2s\n", Path::new("config.yaml")).unwrap_err();
+        assert!(matches!(
+            error,
+            ConfigError::InvalidValue {
+                key: "refresh_interval",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn server_settings_are_validated() {
+        let layer = ConfigLayer::parse(
+            "endpoint: https://tfs.example.com/tfs\napi_version: 7.1-preview.1\nproxy: http://proxy:8080\n",
+            Path::new("config.yaml"),
+        )
+        .unwrap();
+        assert_eq!(layer.api_version.as_deref(), Some("7.1-preview.1"));
+
+        assert!(ConfigKey::ApiVersion.check("6.0").is_ok());
+        assert!(ConfigKey::ApiVersion.check("7.1-preview").is_ok());
+        assert!(ConfigKey::ApiVersion.check("7").is_err());
+        assert!(ConfigKey::ApiVersion.check("7.1-beta").is_err());
+        assert!(ConfigKey::Endpoint.check("tfs.example.com").is_err());
+        assert!(ConfigKey::Proxy.check("socks5://proxy:1080").is_ok());
+        assert!(ConfigKey::Proxy.check("ftp://proxy").is_err());
+    }
+
+    #[test]
+    fn profiles_fall_back_to_the_effective_configuration() {
+        let dir = tempdir().unwrap();
+        let global = dir.path().join("config.yaml");
+        fs::write(
+            &global,
+            "organization: contoso\nproject: Web\nendpoint: https://tfs.example.com/tfs\n\
+             profiles:\n  legacy:\n    project: Legacy\n  fabrikam:\n    organization: fabrikam\n    project: Old\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join(paths::LOCAL_CONFIG_FILE),
+            "profiles:\n  fabrikam:\n    organization: fabrikam\n    project: New\n    endpoint: https://dev.azure.com\n",
+        )
+        .unwrap();
+
+        let config =
+            LayeredConfig::load_from(&global, dir.path(), None, no_env, ConfigLayer::default())
+                .unwrap()
+                .resolve()
+                .unwrap();
+        let [fabrikam, legacy] = &config.profiles[..] else {
+            panic!("expected two profiles, got {:?}", config.profiles);
+        };
+        assert_eq!(
+            (fabrikam.name.as_str(), fabrikam.project.as_str()),
+            ("fabrikam", "New")
+        );
+        assert_eq!(
+            config.server_for("fabrikam").endpoint.as_deref(),
+            Some("https://dev.azure.com")
+        );
+        assert_eq!(legacy.organization, "contoso");
+        assert_eq!(legacy.server, config.server);
+
+        let error = ConfigLayer::parse(
+            "profiles:\n  broken:\n    organization: https://dev.azure.com/org\n",
+            Path::new("config.yaml"),
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("profile `broken`"));
+    }
+
+    #[test]
+    fn set_value_preserves_other_keys() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("config.yaml");
+        fs::write(&path, "organization: org\nproject: old\n").unwrap();
+
+        set_value(&path, ConfigKey::Project, "new").unwrap();
+        set_value(&path, ConfigKey::Groups, "app-prod, app-dev").unwrap();
+
+        let layer = ConfigLayer::read(&path).unwrap().unwrap();
+        assert_eq!(layer.organization.as_deref(), Some("org"));
+        assert_eq!(layer.project.as_deref(), Some("new"));
+        assert_eq!(
+            layer.groups,
+            Some(vec!["app-prod".to_string(), "app-dev".to_string()])
+        );
+    }
+}
@@ -0,0 +1,92 @@
+use std::time::Duration;
+
+/// Seconds, minutes and hours, as in `90s`, `5m` or `1h`.
+pub const CLOCK_UNITS: &[(&str, u64)] = &[("s", 1), ("m", 60), ("h", 3600)];
+
+/// [`CLOCK_UNITS`] with days and weeks, as in `7d` or `2w`.
+pub const CALENDAR_UNITS: &[(&str, u64)] = &[
+    ("s", 1),
+    ("m", 60),
+    ("h", 3600),
+    ("d", 86400),
+    ("w", 7 * 86400),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum DurationError {
+    #[error("expected a number followed by a unit")]
+    NotANumber,
+
+    #[error("unknown unit `{unit}`, expected {}", expected(.units))]
+    UnknownUnit {
+        unit: String,
+        units: &'static [(&'static str, u64)],
+    },
+
+    #[error("`{0}` is too long")]
+    TooLong(String),
+}
+
+fn expected(units: &[(&str, u64)]) -> String {
+    let names = units.iter().map(|(name, _)| *name).collect::<Vec<_>>();
+    match names.split_last() {
+        Some((last, [])) => last.to_string(),
+        Some((last, rest)) => format!("{} or {last}", rest.join(", ")),
+        None => String::new(),
+    }
+}
+
+/// Parses a number followed by one of `units`, such as `5m`.
+pub fn parse_duration(
+    value: &str,
+    units: &'static [(&'static str, u64)],
+) -> Result<Duration, DurationError> {
+    let value = value.trim();
+    let idx = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let number = value[..idx]
+        .parse::<u64>()
+        .map_err(|_| DurationError::NotANumber)?;
+    let unit = &value[idx..];
+    let (_, seconds) = units
+        .iter()
+        .find(|(name, _)| *name == unit)
+        .ok_or_else(|| DurationError::UnknownUnit {
+            unit: unit.to_string(),
+            units,
+        })?;
+    number
+        .checked_mul(*seconds)
+        .map(Duration::from_secs)
+        .ok_or_else(|| DurationError::TooLong(value.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations_are_checked_for_units_and_overflow() {
+        assert_eq!(
+            parse_duration("5m", CLOCK_UNITS),
+            Ok(Duration::from_secs(300))
+        );
+        assert_eq!(
+            parse_duration(" 2w ", CALENDAR_UNITS),
+            Ok(Duration::from_secs(14 * 86400))
+        );
+        assert_eq!(
+            parse_duration("5d", CLOCK_UNITS).unwrap_err().to_string(),
+            "unknown unit `d`, expected s, m or h"
+        );
+        assert_eq!(
+            parse_duration("soon", CLOCK_UNITS),
+            Err(DurationError::NotANumber)
+        );
+        assert_eq!(
+            parse_duration("99999999999999999h", CLOCK_UNITS),
+            Err(DurationError::TooLong("99999999999999999h".into()))
+        );
+    }
+}
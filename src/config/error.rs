@@ -0,0 +1,54 @@
+use std::path::PathBuf;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("Could not determine the user configuration directory")]
+    NoConfigDir,
+
+    #[error("Could not determine the user state directory")]
+    NoStateDir,
+
+    #[error("Could not determine the user cache directory")]
+    NoCacheDir,
+
+    #[error("Failed to access {}: {source}", path.display())]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("Invalid configuration in {}{}: {message}", path.display(), location(*line, *column))]
+    Parse {
+        path: PathBuf,
+        line: Option<usize>,
+        column: Option<usize>,
+        message: String,
+    },
+
+    #[error("Unknown config key `{key}` (known keys: {known})")]
+    UnknownKey { key: String, known: String },
+
+    #[error("Invalid value {value:?} for `{key}` from {origin}: {reason}")]
+    InvalidValue {
+        key: &'static str,
+        value: String,
+        reason: String,
+        origin: String,
+    },
+
+    #[error("`{key}` is not configured. Run the 'init' command, set {env} or pass {flag}")]
+    Missing {
+        key: &'static str,
+        env: &'static str,
+        flag: &'static str,
+    },
+}
+
+fn location(line: Option<usize>, column: Option<usize>) -> String {
+    match (line, column) {
+        (Some(line), Some(column)) => format!(" (line {line}, column {column})"),
+        (Some(line), None) => format!(" (line {line})"),
+        _ => String::new(),
+    }
+}
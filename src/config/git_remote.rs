@@ -0,0 +1,170 @@
+use std::{path::Path, process::Command};
+
+/// An Azure Repos remote, as found in a git checkout.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AzureRemote {
+    pub url: String,
+    pub organization: String,
+    pub project: String,
+    pub repository: String,
+}
+
+/// Finds the first Azure Repos remote of the checkout containing `cwd`,
+/// preferring `origin`. Returns `None` outside a checkout or when git is not
+/// installed.
+pub fn detect(cwd: &Path) -> Option<AzureRemote> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(cwd)
+        .args(["config", "--get-regexp", r"^remote\..*\.url$"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut remotes = stdout
+        .lines()
+        .filter_map(|line| line.split_once(' '))
+        .collect::<Vec<_>>();
+    remotes.sort_by_key(|(name, _)| *name != "remote.origin.url");
+    remotes
+        .into_iter()
+        .find_map(|(_, url)| parse_remote_url(url))
+}
+
+/// Parses the HTTPS and SSH remote formats of both `dev.azure.com` and the
+/// legacy `*.visualstudio.com` hosts.
+pub fn parse_remote_url(url: &str) -> Option<AzureRemote> {
+    let trimmed = url.trim().trim_end_matches('/');
+    let (host, path) = split_host_and_path(trimmed)?;
+    let segments = path
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .map(percent_decode)
+        .collect::<Vec<_>>();
+
+    let (organization, project, repository) = match host.as_str() {
+        "ssh.dev.azure.com" | "vs-ssh.visualstudio.com" => match segments.as_slice() {
+            [version, organization, project, repository] if version == "v3" => {
+                (organization.clone(), project.clone(), repository.clone())
+            }
+            _ => return None,
+        },
+        "dev.azure.com" => {
+            let (organization, rest) = segments.split_first()?;
+            let (project, repository) = project_and_repository(rest)?;
+            (organization.clone(), project, repository)
+        }
+        host if host.ends_with(".visualstudio.com") => {
+            let organization = host.trim_end_matches(".visualstudio.com").to_string();
+            let rest = match segments.split_first() {
+                Some((collection, rest))
+                    if collection.eq_ignore_ascii_case("DefaultCollection") =>
+                {
+                    rest
+                }
+                _ => segments.as_slice(),
+            };
+            let (project, repository) = project_and_repository(rest)?;
+            (organization, project, repository)
+        }
+        _ => return None,
+    };
+
+    Some(AzureRemote {
+        url: trimmed.to_string(),
+        organization,
+        project,
+        repository: repository.trim_end_matches(".git").to_string(),
+    })
+}
+
+fn project_and_repository(segments: &[String]) -> Option<(String, String)> {
+    match segments {
+        [project, git, repository] if git == "_git" => Some((project.clone(), repository.clone())),
+        // A repository named after its project may omit the project segment.
+        [git, repository] if git == "_git" => Some((repository.clone(), repository.clone())),
+        _ => None,
+    }
+}
+
+fn split_host_and_path(url: &str) -> Option<(String, &str)> {
+    let (authority, path) = match url.split_once("://") {
+        Some((_, rest)) => rest.split_once('/').unwrap_or((rest, "")),
+        // scp-like syntax: user@host:path
+        None => url.split_once(':')?,
+    };
+    let host = authority.rsplit('@').next()?;
+    let host = host.split(':').next()?;
+    Some((host.to_ascii_lowercase(), path))
+}
+
+fn percent_decode(segment: &str) -> String {
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && let Some(byte) = segment
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        {
+            decoded.push(byte);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parsed(url: &str) -> (String, String, String) {
+        let remote = parse_remote_url(url).unwrap_or_else(|| panic!("{url} should parse"));
+        (remote.organization, remote.project, remote.repository)
+    }
+
+    fn expected(org: &str, project: &str, repo: &str) -> (String, String, String) {
+        (org.into(), project.into(), repo.into())
+    }
+
+    #[test]
+    fn parses_dev_azure_com_remotes() {
+        assert_eq!(
+            parsed("https://contoso@dev.azure.com/contoso/My%20Project/_git/api"),
+            expected("contoso", "My Project", "api")
+        );
+        assert_eq!(
+            parsed("git@ssh.dev.azure.com:v3/contoso/My%20Project/api"),
+            expected("contoso", "My Project", "api")
+        );
+        assert_eq!(
+            parsed("https://dev.azure.com/contoso/_git/Platform"),
+            expected("contoso", "Platform", "Platform")
+        );
+    }
+
+    #[test]
+    fn parses_visualstudio_com_remotes() {
+        assert_eq!(
+            parsed("https://contoso.visualstudio.com/DefaultCollection/Web/_git/site.git"),
+            expected("contoso", "Web", "site")
+        );
+        assert_eq!(
+            parsed("contoso@vs-ssh.visualstudio.com:v3/contoso/Web/site"),
+            expected("contoso", "Web", "site")
+        );
+    }
+
+    #[test]
+    fn ignores_other_hosts() {
+        assert!(parse_remote_url("git@github.com:contoso/site.git").is_none());
+        assert!(parse_remote_url("https://dev.azure.com/contoso/Web/_wiki").is_none());
+    }
+}
@@ -0,0 +1,6 @@
+pub mod config;
+pub mod duration;
+pub mod error;
+pub mod git_remote;
+pub mod paths;
+pub mod preferences;
@@ -0,0 +1,105 @@
+use std::path::{Path, PathBuf};
+
+use super::error::ConfigError;
+
+pub const APP_DIR_NAME: &str = "azure_devtools";
+pub const GLOBAL_CONFIG_FILE: &str = "config.yaml";
+pub const LOCAL_CONFIG_FILE: &str = ".azure-devtools.yaml";
+pub const KEYMAP_FILE: &str = "keymap.yaml";
+pub const THEMES_FILE: &str = "themes.yaml";
+pub const PREFERENCES_FILE: &str = "preferences.yaml";
+pub const LOG_FILE: &str = "azure_devtools.log";
+
+pub fn config_dir() -> Result<PathBuf, ConfigError> {
+    dirs::config_dir()
+        .map(|dir| dir.join(APP_DIR_NAME))
+        .ok_or(ConfigError::NoConfigDir)
+}
+
+/// Where the TUI remembers state between runs. Falls back to the local data
+/// directory on platforms without a separate state directory.
+pub fn state_dir() -> Result<PathBuf, ConfigError> {
+    dirs::state_dir()
+        .or_else(dirs::data_local_dir)
+        .map(|dir| dir.join(APP_DIR_NAME))
+        .ok_or(ConfigError::NoStateDir)
+}
+
+pub fn cache_dir() -> Result<PathBuf, ConfigError> {
+    dirs::cache_dir()
+        .map(|dir| dir.join(APP_DIR_NAME))
+        .ok_or(ConfigError::NoCacheDir)
+}
+
+pub fn global_config_path() -> Result<PathBuf, ConfigError> {
+    config_dir().map(|dir| dir.join(GLOBAL_CONFIG_FILE))
+}
+
+pub fn keymap_path() -> Result<PathBuf, ConfigError> {
+    config_dir().map(|dir| dir.join(KEYMAP_FILE))
+}
+
+pub fn themes_path() -> Result<PathBuf, ConfigError> {
+    config_dir().map(|dir| dir.join(THEMES_FILE))
+}
+
+/// The default log file. Rotated copies are kept next to it.
+pub fn log_path() -> Result<PathBuf, ConfigError> {
+    config_dir().map(|dir| dir.join("logs").join(LOG_FILE))
+}
+
+pub fn preferences_path() -> Result<PathBuf, ConfigError> {
+    state_dir().map(|dir| dir.join(PREFERENCES_FILE))
+}
+
+/// The saved TUI session of one organization and project.
+pub fn session_path(organization: &str, project: &str) -> Result<PathBuf, ConfigError> {
+    let file_name = format!(
+        "{}--{}.yaml",
+        file_name_component(organization),
+        file_name_component(project)
+    );
+    state_dir().map(|dir| dir.join("sessions").join(file_name))
+}
+
+/// The cached variable groups of one organization and project.
+pub fn cache_path(organization: &str, project: &str) -> Result<PathBuf, ConfigError> {
+    let file_name = format!(
+        "{}--{}.json",
+        file_name_component(organization),
+        file_name_component(project)
+    );
+    cache_dir().map(|dir| dir.join("groups").join(file_name))
+}
+
+/// The journal of every version fetched of the groups of one organization
+/// and project.
+pub fn journal_path(organization: &str, project: &str) -> Result<PathBuf, ConfigError> {
+    let file_name = format!(
+        "{}--{}.jsonl",
+        file_name_component(organization),
+        file_name_component(project)
+    );
+    state_dir().map(|dir| dir.join("history").join(file_name))
+}
+
+/// Replaces characters that are not safe in file names on every platform.
+fn file_name_component(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Walks up from `start` looking for a repository-local config file.
+pub fn find_local_config(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .map(|dir| dir.join(LOCAL_CONFIG_FILE))
+        .find(|candidate| candidate.is_file())
+}
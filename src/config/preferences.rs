@@ -0,0 +1,61 @@
+use std::{fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use super::error::ConfigError;
+
+/// Choices made inside the TUI that are remembered between runs.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Preferences {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub theme: Option<String>,
+}
+
+impl Preferences {
+    /// Reads the preferences file. A missing or unreadable file is not an
+    /// error; the preferences are simply forgotten.
+    pub fn read(path: &Path) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|text| serde_yaml::from_str(&text).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), ConfigError> {
+        let io_error = |source| ConfigError::Io {
+            path: path.to_path_buf(),
+            source,
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(io_error)?;
+        }
+        let yaml = serde_yaml::to_string(self).map_err(|error| ConfigError::Parse {
+            path: path.to_path_buf(),
+            line: None,
+            column: None,
+            message: error.to_string(),
+        })?;
+        fs::write(path, yaml).map_err(io_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_and_tolerates_broken_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state").join("preferences.yaml");
+        assert_eq!(Preferences::read(&path), Preferences::default());
+
+        let preferences = Preferences {
+            theme: Some("light".into()),
+        };
+        preferences.write(&path).unwrap();
+        assert_eq!(Preferences::read(&path), preferences);
+
+        fs::write(&path, "theme: [").unwrap();
+        assert_eq!(Preferences::read(&path), Preferences::default());
+    }
+}
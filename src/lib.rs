@@ -12,3 +12,5 @@
 extern crate test;
 
 pub mod azure_vars;
+pub mod config;
+pub mod logging;
@@ -0,0 +1,261 @@
+use std::{
+    collections::VecDeque,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use anyhow::Context;
+use log::{Level, LevelFilter, Record};
+use log4rs::{
+    append::{
+        Append,
+        rolling_file::{
+            RollingFileAppender,
+            policy::compound::{
+                CompoundPolicy, roll::fixed_window::FixedWindowRoller, trigger::size::SizeTrigger,
+            },
+        },
+    },
+    config::{Appender, Config, Deserializers, Logger, RawConfig, Root},
+    encode::pattern::PatternEncoder,
+};
+
+/// A `logging.yaml` in the working directory replaces the default log file.
+pub const LOG_CONFIG_FILE: &str = "logging.yaml";
+const LOG_PATTERN: &str = "{d(%Y-%m-%d %H:%M:%S)} {l:<5} {t} - {m}{n}";
+const MAX_LOG_SIZE: u64 = 5 * 1024 * 1024;
+const ARCHIVED_LOGS: u32 = 3;
+const BUFFER_CAPACITY: usize = 500;
+const BUFFER_APPENDER: &str = "tui";
+const FILE_APPENDER: &str = "file";
+
+/// Dependencies that log every request at debug level; they are only shown
+/// at the highest verbosity.
+const NOISY_TARGETS: [&str; 6] = [
+    "azure_core",
+    "azure_identity",
+    "reqwest",
+    "hyper",
+    "hyper_util",
+    "rustls",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogRecord {
+    pub level: Level,
+    pub target: String,
+    pub message: String,
+}
+
+/// The most recent log records, shared with the TUI log pane.
+#[derive(Debug, Clone)]
+pub struct LogBuffer {
+    records: Arc<Mutex<VecDeque<LogRecord>>>,
+    capacity: usize,
+}
+
+impl Default for LogBuffer {
+    fn default() -> Self {
+        Self::new(BUFFER_CAPACITY)
+    }
+}
+
+impl LogBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            records: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+            capacity,
+        }
+    }
+
+    pub fn push(&self, record: LogRecord) {
+        let mut records = self.records.lock().unwrap_or_else(|e| e.into_inner());
+        if records.len() == self.capacity {
+            records.pop_front();
+        }
+        records.push_back(record);
+    }
+
+    /// The last `count` records, oldest first.
+    pub fn recent(&self, count: usize) -> Vec<LogRecord> {
+        let records = self.records.lock().unwrap_or_else(|e| e.into_inner());
+        records
+            .iter()
+            .skip(records.len().saturating_sub(count))
+            .cloned()
+            .collect()
+    }
+}
+
+#[derive(Debug)]
+struct BufferAppender(LogBuffer);
+
+impl Append for BufferAppender {
+    fn append(&self, record: &Record) -> anyhow::Result<()> {
+        self.0.push(LogRecord {
+            level: record.level(),
+            target: record.target().to_string(),
+            message: record.args().to_string(),
+        });
+        Ok(())
+    }
+
+    fn flush(&self) {}
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LogOptions {
+    /// `-v` logs debug records, `-vv` also trace records and HTTP internals.
+    pub verbosity: u8,
+    /// Overrides the default log file, and `logging.yaml`.
+    pub log_file: Option<PathBuf>,
+}
+
+impl LogOptions {
+    fn level(&self) -> LevelFilter {
+        match self.verbosity {
+            0 => LevelFilter::Info,
+            1 => LevelFilter::Debug,
+            _ => LevelFilter::Trace,
+        }
+    }
+}
+
+/// Installs the global logger of every command: a rotating log file (or the
+/// appenders of `logging.yaml`) plus an in-memory buffer for the TUI log
+/// pane.
+pub fn init(options: &LogOptions, default_log_file: &Path) -> anyhow::Result<LogBuffer> {
+    let buffer = LogBuffer::default();
+    let config_file = Path::new(LOG_CONFIG_FILE);
+    let config = if options.log_file.is_none() && config_file.exists() {
+        // Commands print their results on stdout.
+        eprintln!("Using logging configuration from {LOG_CONFIG_FILE}");
+        config_from_file(config_file, options, &buffer)?
+    } else {
+        let path = options.log_file.as_deref().unwrap_or(default_log_file);
+        default_config(path, options, &buffer)?
+    };
+    log4rs::init_config(config)?;
+    Ok(buffer)
+}
+
+fn buffer_appender(buffer: &LogBuffer) -> Appender {
+    Appender::builder().build(BUFFER_APPENDER, Box::new(BufferAppender(buffer.clone())))
+}
+
+fn default_config(path: &Path, options: &LogOptions, buffer: &LogBuffer) -> anyhow::Result<Config> {
+    let archive_pattern = format!("{}.{{}}", path.display());
+    let roller = FixedWindowRoller::builder().build(&archive_pattern, ARCHIVED_LOGS)?;
+    let policy = CompoundPolicy::new(Box::new(SizeTrigger::new(MAX_LOG_SIZE)), Box::new(roller));
+    let file = RollingFileAppender::builder()
+        .encoder(Box::new(PatternEncoder::new(LOG_PATTERN)))
+        .build(path, Box::new(policy))
+        .with_context(|| format!("Failed to open log file {}", path.display()))?;
+
+    let level = options.level();
+    let noisy_level = if options.verbosity >= 2 {
+        LevelFilter::Trace
+    } else {
+        LevelFilter::Warn
+    };
+    let config = Config::builder()
+        .appender(Appender::builder().build(FILE_APPENDER, Box::new(file)))
+        .appender(buffer_appender(buffer))
+        .loggers(
+            NOISY_TARGETS
+                .iter()
+                .map(|target| Logger::builder().build(*target, noisy_level)),
+        )
+        .build(
+            Root::builder()
+                .appenders([FILE_APPENDER, BUFFER_APPENDER])
+                .build(level),
+        )?;
+    Ok(config)
+}
+
+fn config_from_file(
+    path: &Path,
+    options: &LogOptions,
+    buffer: &LogBuffer,
+) -> anyhow::Result<Config> {
+    let text = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read logging configuration {}", path.display()))?;
+    let raw = serde_yaml::from_str::<RawConfig>(&text)
+        .with_context(|| format!("Invalid logging configuration in {}", path.display()))?;
+    let (appenders, errors) = raw.appenders_lossy(&Deserializers::default());
+    if !errors.is_empty() {
+        return Err(anyhow::Error::new(errors).context(format!(
+            "Invalid logging configuration in {}",
+            path.display()
+        )));
+    }
+
+    let root = raw.root();
+    let level = if options.verbosity > 0 {
+        options.level()
+    } else {
+        root.level()
+    };
+    let root_appenders = root
+        .appenders()
+        .iter()
+        .cloned()
+        .chain([BUFFER_APPENDER.to_string()]);
+    let config = Config::builder()
+        .appenders(appenders)
+        .appender(buffer_appender(buffer))
+        .loggers(raw.loggers())
+        .build(Root::builder().appenders(root_appenders).build(level))?;
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(message: &str) -> LogRecord {
+        LogRecord {
+            level: Level::Info,
+            target: "test".into(),
+            message: message.into(),
+        }
+    }
+
+    #[test]
+    fn buffer_keeps_only_the_most_recent_records() {
+        let buffer = LogBuffer::new(2);
+        for message in ["one", "two", "three"] {
+            buffer.push(record(message));
+        }
+
+        assert_eq!(buffer.recent(10), vec![record("two"), record("three")]);
+        assert_eq!(buffer.recent(1), vec![record("three")]);
+    }
+
+    #[test]
+    fn logging_yaml_appenders_are_kept_next_to_the_buffer() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(LOG_CONFIG_FILE);
+        fs::write(
+            &path,
+            "appenders:\n  stdout:\n    kind: console\nroot:\n  level: warn\n  appenders: [stdout]\n",
+        )
+        .unwrap();
+
+        let config =
+            config_from_file(&path, &LogOptions::default(), &LogBuffer::default()).unwrap();
+        assert_eq!(config.root().level(), LevelFilter::Warn);
+        assert_eq!(config.root().appenders(), ["stdout", BUFFER_APPENDER]);
+    }
+
+    #[test]
+    fn invalid_logging_yaml_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(LOG_CONFIG_FILE);
+        fs::write(&path, "appenders:\n  stdout:\n    kind: carrier-pigeon\n").unwrap();
+
+        assert!(config_from_file(&path, &LogOptions::default(), &LogBuffer::default()).is_err());
+    }
+}